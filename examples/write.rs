@@ -2,8 +2,7 @@ use rawzip::ZipArchiveWriter;
 use std::env;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
-use std::time::UNIX_EPOCH;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
@@ -44,15 +43,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     for input_path in input_paths {
         let path = Path::new(input_path);
-        if path.is_file() {
+        if path.is_symlink() {
+            add_symlink_to_archive(&mut archive, path, Path::new(path.file_name().unwrap()))?;
+        } else if path.is_file() {
             add_file_to_archive(
                 &mut archive,
                 path,
-                path.file_name().unwrap().to_str().unwrap(),
+                Path::new(path.file_name().unwrap()),
                 compression_method,
             )?;
         } else if path.is_dir() {
-            add_directory_to_archive(&mut archive, path, "", compression_method)?;
+            add_directory_to_archive(&mut archive, path, Path::new(""), compression_method)?;
         } else {
             eprintln!(
                 "Warning: '{}' does not exist or is not a regular file/directory",
@@ -66,33 +67,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn get_modification_time(
-    metadata: &fs::Metadata,
-) -> Result<rawzip::time::UtcDateTime, Box<dyn std::error::Error>> {
-    let modified = metadata.modified()?;
-
-    // Convert system time to UTC DateTime
-    let unix_seconds = modified.duration_since(UNIX_EPOCH)?.as_secs() as i64;
-    Ok(rawzip::time::UtcDateTime::from_unix(unix_seconds))
-}
-
 fn add_file_to_archive<W: Write>(
     archive: &mut ZipArchiveWriter<W>,
     file_path: &Path,
-    archive_path: &str,
+    archive_path: &Path,
     compression_method: rawzip::CompressionMethod,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let metadata = fs::metadata(file_path)?;
-    let modification_time = get_modification_time(&metadata)?;
-
-    let mut builder = archive
-        .new_file(archive_path)
+    let builder = archive
+        .new_file_path(archive_path)?
         .compression_method(compression_method)
-        .last_modified(modification_time);
-
-    if let Some(permissions) = get_unix_permissions(&metadata) {
-        builder = builder.unix_permissions(permissions);
-    }
+        .metadata(&metadata);
 
     // Read and compress the file content
     let mut file = fs::File::open(file_path)?;
@@ -120,14 +105,36 @@ fn add_file_to_archive<W: Write>(
         }
     }
 
-    println!("  adding: {}", archive_path);
+    println!("  adding: {}", archive_path.display());
+    Ok(())
+}
+
+fn add_symlink_to_archive<W: Write>(
+    archive: &mut ZipArchiveWriter<W>,
+    link_path: &Path,
+    archive_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let archive_path = archive_path
+        .to_str()
+        .ok_or("symlink path is not valid UTF-8")?;
+    // The link itself is archived, rather than what it points to
+    let metadata = fs::symlink_metadata(link_path)?;
+    let target = fs::read_link(link_path)?;
+    let target = target.to_str().ok_or("symlink target is not valid UTF-8")?;
+
+    archive
+        .new_symlink(archive_path, target)
+        .metadata(&metadata)
+        .create()?;
+
+    println!("  adding: {} -> {}", archive_path, target);
     Ok(())
 }
 
 fn add_directory_to_archive<W: Write>(
     archive: &mut ZipArchiveWriter<W>,
     dir_path: &Path,
-    base_path: &str,
+    base_path: &Path,
     compression_method: rawzip::CompressionMethod,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let entries = fs::read_dir(dir_path)?;
@@ -135,33 +142,20 @@ fn add_directory_to_archive<W: Write>(
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
-        let name = entry.file_name();
-        let name_str = name.to_str().unwrap();
-
-        let archive_path = if base_path.is_empty() {
-            name_str.to_string()
-        } else {
-            format!("{}/{}", base_path, name_str)
-        };
+        let archive_path: PathBuf = base_path.join(entry.file_name());
 
-        if path.is_file() {
+        if entry.file_type()?.is_symlink() {
+            add_symlink_to_archive(archive, &path, &archive_path)?;
+        } else if path.is_file() {
             add_file_to_archive(archive, &path, &archive_path, compression_method)?;
         } else if path.is_dir() {
             // Add directory entry
             let metadata = fs::metadata(&path)?;
-            let modification_time = get_modification_time(&metadata)?;
-
-            let dir_archive_path = format!("{}/", archive_path);
-            let mut builder = archive
-                .new_dir(&dir_archive_path)
-                .last_modified(modification_time);
-
-            if let Some(permissions) = get_unix_permissions(&metadata) {
-                builder = builder.unix_permissions(permissions);
-            }
-
-            builder.create()?;
-            println!("  adding: {}", dir_archive_path);
+            archive
+                .new_dir_path(&archive_path)?
+                .metadata(&metadata)
+                .create()?;
+            println!("  adding: {}/", archive_path.display());
 
             // Recursively add directory contents
             add_directory_to_archive(archive, &path, &archive_path, compression_method)?;
@@ -170,14 +164,3 @@ fn add_directory_to_archive<W: Write>(
 
     Ok(())
 }
-
-#[cfg(unix)]
-fn get_unix_permissions(metadata: &fs::Metadata) -> Option<u32> {
-    use std::os::unix::fs::PermissionsExt;
-    Some(metadata.permissions().mode())
-}
-
-#[cfg(not(unix))]
-fn get_unix_permissions(_metadata: &fs::Metadata) -> Option<u32> {
-    None
-}
@@ -0,0 +1,117 @@
+//! WinZip AES encryption (AE-2).
+//!
+//! An encrypted entry's data is laid out as a random salt, a password
+//! verification value, the compressed data encrypted with AES in counter
+//! mode, and an authentication code: the first 10 bytes of an HMAC-SHA1 over
+//! the encrypted data. The keys are derived from the password and salt with
+//! PBKDF2-HMAC-SHA1.
+
+use crate::{encryption::Password, CompressionMethod, Error};
+use aes::cipher::{BlockEncrypt, KeyInit};
+use hmac::Mac;
+use std::io;
+
+/// AES-256 keys are 32 bytes long.
+const KEY_LEN: usize = 32;
+
+/// The salt is half the length of the key.
+const SALT_LEN: usize = KEY_LEN / 2;
+
+/// The password verification value follows the salt.
+const VERIFIER_LEN: usize = 2;
+
+/// The authentication code is a truncated HMAC-SHA1.
+pub(crate) const AUTH_CODE_LEN: usize = 10;
+
+/// The number of PBKDF2 iterations that WinZip settled on.
+const ITERATIONS: u32 = 1000;
+
+/// AE-2 entries don't record a CRC32, as the authentication code already
+/// detects corruption, and a CRC32 of small files would leak their contents.
+const VENDOR_VERSION: u16 = 2;
+
+/// The strength byte for 256-bit keys.
+const STRENGTH_256: u8 = 3;
+
+/// Returns the AES extra field for an entry that is compressed with
+/// `method` before it's encrypted.
+pub(crate) fn extra_field(method: CompressionMethod) -> [u8; 7] {
+    let mut data = [0u8; 7];
+    data[0..2].copy_from_slice(&VENDOR_VERSION.to_le_bytes());
+    data[2..4].copy_from_slice(b"AE");
+    data[4] = STRENGTH_256;
+    data[5..7].copy_from_slice(&method.as_id().as_u16().to_le_bytes());
+    data
+}
+
+/// Encrypts an entry's data and computes its authentication code.
+pub(crate) struct AesEncryptor {
+    cipher: aes::Aes256,
+    mac: hmac::Hmac<sha1::Sha1>,
+    counter: u128,
+    keystream: [u8; 16],
+    used: usize,
+}
+
+impl AesEncryptor {
+    /// Derives the keys from the password and a fresh salt. Returns the
+    /// encryptor along with the salt and password verification value, which
+    /// precede the encrypted data.
+    pub(crate) fn new(password: &Password) -> Result<(Self, Vec<u8>), Error> {
+        let mut salt = [0u8; SALT_LEN];
+        getrandom::getrandom(&mut salt).map_err(io::Error::from)?;
+
+        let mut keys = [0u8; 2 * KEY_LEN + VERIFIER_LEN];
+        pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password.as_bytes(), &salt, ITERATIONS, &mut keys);
+        let (encryption_key, rest) = keys.split_at(KEY_LEN);
+        let (mac_key, verifier) = rest.split_at(KEY_LEN);
+
+        let encryptor = AesEncryptor {
+            cipher: aes::Aes256::new(encryption_key.into()),
+            mac: <hmac::Hmac<sha1::Sha1> as Mac>::new_from_slice(mac_key)
+                .expect("HMAC accepts keys of any length"),
+            counter: 0,
+            keystream: [0u8; 16],
+            used: 16,
+        };
+
+        let mut header = Vec::with_capacity(SALT_LEN + VERIFIER_LEN);
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(verifier);
+        Ok((encryptor, header))
+    }
+
+    /// Encrypts the data in place.
+    ///
+    /// The counter is little endian and starts at one, unlike the usual
+    /// counter mode.
+    pub(crate) fn encrypt(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            if self.used == self.keystream.len() {
+                self.counter = self.counter.wrapping_add(1);
+                self.keystream = self.counter.to_le_bytes();
+                self.cipher.encrypt_block((&mut self.keystream).into());
+                self.used = 0;
+            }
+
+            *byte ^= self.keystream[self.used];
+            self.used += 1;
+        }
+
+        self.mac.update(data);
+    }
+
+    /// Returns the authentication code that follows the encrypted data.
+    pub(crate) fn finish(self) -> [u8; AUTH_CODE_LEN] {
+        let mac = self.mac.finalize().into_bytes();
+        let mut code = [0u8; AUTH_CODE_LEN];
+        code.copy_from_slice(&mac[..AUTH_CODE_LEN]);
+        code
+    }
+}
+
+impl std::fmt::Debug for AesEncryptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AesEncryptor").finish_non_exhaustive()
+    }
+}
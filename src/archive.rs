@@ -7,7 +7,7 @@ use crate::mode::{
 };
 use crate::path::{RawPath, ZipFilePath};
 use crate::reader_at::{FileReader, MutexReader, RangeReader, ReaderAt, ReaderAtExt};
-use crate::time::{extract_best_timestamp, ZipDateTimeKind};
+use crate::time::{extract_best_timestamp, DosDateTime, ZipDateTimeKind};
 use crate::utils::{le_u16, le_u32, le_u64};
 use crate::{EndOfCentralDirectory, EndOfCentralDirectoryRecordFixed, ZipLocator};
 use std::io::{Read, Seek, Write};
@@ -15,6 +15,15 @@ use std::io::{Read, Seek, Write};
 pub(crate) const END_OF_CENTRAL_DIR_SIGNATURE64: u32 = 0x06064b50;
 pub(crate) const END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE: u32 = 0x07064b50;
 pub(crate) const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+pub(crate) const ARCHIVE_EXTRA_DATA_SIGNATURE: u32 = 0x08064b50;
+pub(crate) const DIGITAL_SIGNATURE_SIGNATURE: u32 = 0x05054b50;
+
+/// The name of the entry that holds the dictionary that zstd entries are
+/// compressed against.
+///
+/// See [`ZipArchiveWriter::set_zstd_dictionary`](crate::ZipArchiveWriter::set_zstd_dictionary).
+pub const ZSTD_DICTIONARY_NAME: &str = ".zstd-dictionary";
+
 /// The recommended buffer size to use when reading from a zip file.
 ///
 /// This buffer size was chosen as it can hold an entire central directory
@@ -46,7 +55,7 @@ pub const RECOMMENDED_BUFFER_SIZE: usize = 1 << 16;
 /// ```
 #[derive(Debug, Clone)]
 pub struct ZipSliceArchive<T> {
-    data: T,
+    pub(crate) data: T,
     eocd: EndOfCentralDirectory,
 }
 
@@ -60,11 +69,7 @@ impl<T: AsRef<[u8]>> ZipSliceArchive<T> {
         let data = self.data.as_ref();
         let directory_start = self.eocd.directory_offset();
         let entry_data = &data[(directory_start as usize)..self.eocd.head_eocd_offset() as usize];
-        ZipSliceEntries {
-            entry_data,
-            base_offset: self.eocd.base_offset(),
-            current_offset: directory_start,
-        }
+        ZipSliceEntries::new(entry_data, self.eocd.base_offset(), directory_start)
     }
 
     /// Returns the byte slice that represents the zip file.
@@ -113,6 +118,66 @@ impl<T: AsRef<[u8]>> ZipSliceArchive<T> {
         ZipStr::new(&data[comment_start..comment_start + comment_len])
     }
 
+    /// Returns the data of the archive extra data record (4.3.11), which
+    /// sits between the last entry and the central directory, if there is
+    /// one.
+    ///
+    /// See [`ZipArchive::archive_extra_data`] for more details.
+    pub fn archive_extra_data(&self) -> Result<Option<&[u8]>, Error> {
+        let data = self.data.as_ref();
+        let mut last = None;
+        for record in self.entries() {
+            let record = record?;
+            let offset = record.local_header_offset();
+            if last.map_or(true, |(last, _)| offset >= last) {
+                last = Some((offset, record.compressed_size_hint()));
+            }
+        }
+
+        let data_end = match last {
+            Some((offset, compressed_size)) => {
+                let header = data.get(offset as usize..).ok_or(ErrorKind::Eof)?;
+                let header = ZipLocalFileHeaderFixed::parse(header)?;
+                local_data_end(offset, &header, compressed_size)?
+            }
+            None => self.eocd.base_offset(),
+        };
+
+        let directory_offset = self.directory_offset();
+        let start = find_archive_extra_data(data_end, directory_offset, |offset, buf| {
+            let src = data
+                .get(offset as usize..offset as usize + buf.len())
+                .ok_or(ErrorKind::Eof)?;
+            buf.copy_from_slice(src);
+            Ok(())
+        })?;
+        Ok(start.map(|start| &data[start as usize + 8..directory_offset as usize]))
+    }
+
+    /// Returns the dictionary that zstd entries are compressed against, if
+    /// the archive has one.
+    ///
+    /// See [`ZipArchive::zstd_dictionary`] for more details.
+    pub fn zstd_dictionary(&self) -> Result<Option<&[u8]>, Error> {
+        for record in self.entries() {
+            let record = record?;
+            if record.file_path().as_ref() != ZSTD_DICTIONARY_NAME.as_bytes() {
+                continue;
+            }
+
+            check_zstd_dictionary(&record)?;
+            let entry = self.get_entry(record.wayfinder())?;
+            let data = entry.data();
+            entry.claim_verifier().valid(ZipVerification {
+                crc: crc32_chunk(data, 0),
+                uncompressed_size: data.len() as u64,
+            })?;
+            return Ok(Some(data));
+        }
+
+        Ok(None)
+    }
+
     /// Converts the [`ZipSliceArchive`] into a general [`ZipArchive`].
     ///
     /// This is useful for unifying code that might handle both slice-based
@@ -176,6 +241,49 @@ impl<T: AsRef<[u8]>> ZipSliceArchive<T> {
     }
 }
 
+/// Returns the offset where the data of the entry whose local header is at
+/// `offset` ends.
+fn local_data_end(
+    offset: u64,
+    header: &ZipLocalFileHeaderFixed,
+    compressed_size: u64,
+) -> Result<u64, Error> {
+    offset
+        .checked_add(ZipLocalFileHeaderFixed::SIZE as u64 + header.variable_length() as u64)
+        .and_then(|x| x.checked_add(compressed_size))
+        .ok_or_else(|| Error::from(ErrorKind::Eof))
+}
+
+/// Looks for the archive extra data record after the data of the last entry,
+/// which ends at `data_end`.
+///
+/// The entry may be followed by a data descriptor, with or without a
+/// signature and with 4 or 8 byte sizes, so each of those lengths is tried.
+/// The record must end where the central directory starts.
+fn find_archive_extra_data(
+    data_end: u64,
+    directory_offset: u64,
+    mut read: impl FnMut(u64, &mut [u8; 8]) -> Result<(), Error>,
+) -> Result<Option<u64>, Error> {
+    for descriptor_len in [0, 12, 16, 20, 24] {
+        let start = data_end.saturating_add(descriptor_len);
+        if start.saturating_add(8) > directory_offset {
+            break;
+        }
+
+        let mut header = [0u8; 8];
+        read(start, &mut header)?;
+        let len = u64::from(le_u32(&header[4..8]));
+        if le_u32(&header[0..4]) == ARCHIVE_EXTRA_DATA_SIGNATURE
+            && start + 8 + len == directory_offset
+        {
+            return Ok(Some(start));
+        }
+    }
+
+    Ok(None)
+}
+
 /// Represents a single entry (file or directory) within a `ZipSliceArchive`.
 ///
 /// It provides access to the raw compressed data of the entry.
@@ -228,29 +336,52 @@ impl<'a> ZipSliceEntry<'a> {
         (compressed_data_start, compressed_data_end)
     }
 
-    /// Returns an iterator over the extra fields from the local file header.
+    /// Returns the local file header, borrowed from the archive's data.
     ///
-    /// See [`ZipLocalFileHeader`] for more details.
-    pub fn extra_fields(&self) -> ExtraFields<'_> {
+    /// Unlike [`ZipEntry::local_header`], no buffer is needed, as the header
+    /// is already in memory.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::ZipArchive;
+    /// let data = include_bytes!("../assets/test.zip");
+    /// let archive = ZipArchive::from_slice(data)?;
+    /// let record = archive.entries().next_entry()?.unwrap();
+    /// let name = {
+    ///     let entry = archive.get_entry(record.wayfinder())?;
+    ///     entry.local_header().file_path()
+    /// };
+    ///
+    /// // The name outlives the entry, as it points into the archive's data
+    /// assert_eq!(name.as_ref(), b"test.txt");
+    /// assert!(data.as_ptr_range().contains(&name.as_ref().as_ptr()));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn local_header(&self) -> ZipLocalFileHeader<'a> {
         let header =
             ZipLocalFileHeaderFixed::parse(self.data).expect("header has already been parsed");
-        let file_name_len = header.file_name_len as usize;
-        let extra_field_len = header.extra_field_len as usize;
-        let extra_field_start = ZipLocalFileHeaderFixed::SIZE + file_name_len;
-        let extra_field_end = extra_field_start + extra_field_len;
-        ExtraFields::new(&self.data[extra_field_start..extra_field_end])
+        let (file_name, extra_fields) = self.data[ZipLocalFileHeaderFixed::SIZE..]
+            [..header.variable_length()]
+            .split_at(header.file_name_len as usize);
+        ZipLocalFileHeader {
+            file_path: ZipFilePath::from_bytes(file_name),
+            extra_fields: ExtraFields::new(extra_fields),
+        }
+    }
+
+    /// Returns an iterator over the extra fields from the local file header.
+    ///
+    /// See [`ZipLocalFileHeader`] for more details.
+    pub fn extra_fields(&self) -> ExtraFields<'a> {
+        self.local_header().extra_fields()
     }
 
     /// Returns the file path from the local file header.
     ///
     /// See [`ZipLocalFileHeader`] for more details.
-    pub fn file_path(&self) -> ZipFilePath<RawPath<'_>> {
-        let header =
-            ZipLocalFileHeaderFixed::parse(self.data).expect("header has already been parsed");
-        let file_name_len = header.file_name_len as usize;
-        let filename_start = ZipLocalFileHeaderFixed::SIZE;
-        let filename_end = filename_start + file_name_len;
-        ZipFilePath::from_bytes(&self.data[filename_start..filename_end])
+    pub fn file_path(&self) -> ZipFilePath<RawPath<'a>> {
+        self.local_header().file_path()
     }
 }
 
@@ -303,10 +434,21 @@ pub struct ZipSliceEntries<'data> {
 }
 
 impl<'data> ZipSliceEntries<'data> {
+    /// Creates an iterator over the central directory in `entry_data`, which
+    /// starts at `directory_offset` in the archive.
+    pub(crate) fn new(entry_data: &'data [u8], base_offset: u64, directory_offset: u64) -> Self {
+        ZipSliceEntries {
+            entry_data,
+            base_offset,
+            current_offset: directory_offset,
+        }
+    }
+
     /// Yield the next zip file entry in the central directory if there is any
     #[inline]
     pub fn next_entry(&mut self) -> Result<Option<ZipFileHeaderRecord<'data>>, Error> {
-        if self.entry_data.is_empty() {
+        if self.entry_data.is_empty() || is_digital_signature(self.entry_data) {
+            self.entry_data = &[];
             return Ok(None);
         }
 
@@ -363,7 +505,7 @@ impl<'data> Iterator for ZipSliceEntries<'data> {
 #[derive(Debug, Clone)]
 pub struct ZipArchive<R> {
     reader: R,
-    eocd: EndOfCentralDirectory,
+    pub(crate) eocd: EndOfCentralDirectory,
 }
 
 impl ZipArchive<()> {
@@ -576,6 +718,101 @@ impl<R> ZipArchive<R>
 where
     R: ReaderAt,
 {
+    /// Returns a reader for the data of the archive extra data record
+    /// (4.3.11), which sits between the last entry and the central
+    /// directory, if there is one.
+    ///
+    /// The record isn't referenced from anywhere, so the central directory
+    /// is read with the given buffer to find where the last entry ends. A
+    /// data descriptor may follow the entry. The record is only recognized
+    /// when it ends exactly where the central directory starts.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use rawzip::{ZipArchive, ZipArchiveWriter, RECOMMENDED_BUFFER_SIZE};
+    /// # use std::io::Read;
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::new(&mut output);
+    /// archive.new_dir("dir/").create()?;
+    /// archive.set_archive_extra_data(b"manifest")?;
+    /// archive.finish()?;
+    ///
+    /// let mut buffer = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+    /// let archive = ZipArchive::from_seekable(std::io::Cursor::new(output), &mut buffer)?;
+    /// let mut data = Vec::new();
+    /// let mut reader = archive.archive_extra_data(&mut buffer)?.unwrap();
+    /// reader.read_to_end(&mut data)?;
+    /// assert_eq!(data, b"manifest");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn archive_extra_data(&self, buffer: &mut [u8]) -> Result<Option<RangeReader<&R>>, Error> {
+        let start = self.archive_extra_data_offset(buffer)?;
+        let end = self.directory_offset();
+        Ok(start.map(|start| RangeReader::new(&self.reader, start + 8..end)))
+    }
+
+    /// Returns the dictionary that zstd entries are compressed against, if
+    /// the archive has one.
+    ///
+    /// The dictionary is the stored entry named [`ZSTD_DICTIONARY_NAME`],
+    /// which is written by
+    /// [`ZipArchiveWriter::set_zstd_dictionary`](crate::ZipArchiveWriter::set_zstd_dictionary).
+    /// Pass it to the decoder of the archive's zstd entries, such as
+    /// `zstd::Decoder::with_dictionary`. Returns an error if the entry is
+    /// compressed or fails its CRC check.
+    pub fn zstd_dictionary(&self, buffer: &mut [u8]) -> Result<Option<Vec<u8>>, Error> {
+        let mut entries = self.entries(buffer);
+        let mut wayfinder = None;
+        while let Some(record) = entries.next_entry()? {
+            if record.file_path().as_ref() == ZSTD_DICTIONARY_NAME.as_bytes() {
+                check_zstd_dictionary(&record)?;
+                wayfinder = Some(record.wayfinder());
+                break;
+            }
+        }
+
+        let Some(wayfinder) = wayfinder else {
+            return Ok(None);
+        };
+
+        let entry = self.get_entry(wayfinder)?;
+        let mut dictionary = Vec::new();
+        entry
+            .verifying_reader(entry.reader())
+            .read_to_end(&mut dictionary)?;
+        Ok(Some(dictionary))
+    }
+
+    /// Returns the offset of the archive extra data record, if there is one.
+    pub(crate) fn archive_extra_data_offset(
+        &self,
+        buffer: &mut [u8],
+    ) -> Result<Option<u64>, Error> {
+        let mut last = None;
+        let mut entries = self.entries(buffer);
+        while let Some(record) = entries.next_entry()? {
+            let offset = record.local_header_offset();
+            if last.map_or(true, |(last, _)| offset >= last) {
+                last = Some((offset, record.compressed_size_hint()));
+            }
+        }
+
+        let data_end = match last {
+            Some((offset, compressed_size)) => {
+                let mut header = [0u8; ZipLocalFileHeaderFixed::SIZE];
+                self.reader.read_exact_at(&mut header, offset)?;
+                let header = ZipLocalFileHeaderFixed::parse(&header)?;
+                local_data_end(offset, &header, compressed_size)?
+            }
+            None => self.eocd.base_offset(),
+        };
+
+        find_archive_extra_data(data_end, self.directory_offset(), |offset, buf| {
+            Ok(self.reader.read_exact_at(buf, offset)?)
+        })
+    }
+
     /// Seeks to the given file entry in the zip archive.
     pub fn get_entry(&self, entry: ZipArchiveEntryWayfinder) -> Result<ZipEntry<'_, R>, Error> {
         let mut buffer = [0u8; ZipLocalFileHeaderFixed::SIZE];
@@ -949,14 +1186,14 @@ impl<'a> ZipLocalFileHeader<'a> {
 
 #[derive(Debug, Clone)]
 pub(crate) struct DataDescriptor {
-    crc: u32,
+    pub(crate) crc: u32,
 }
 
 impl DataDescriptor {
-    const SIZE: usize = 8;
+    pub(crate) const SIZE: usize = 8;
     pub const SIGNATURE: u32 = 0x08074b50;
 
-    fn parse(data: &[u8]) -> Result<DataDescriptor, Error> {
+    pub(crate) fn parse(data: &[u8]) -> Result<DataDescriptor, Error> {
         if data.len() < Self::SIZE {
             return Err(Error::from(ErrorKind::Eof));
         }
@@ -1020,7 +1257,7 @@ where
                 .min(self.buffer.len() - remaining);
             let read = self.archive.reader.read_at_least_at(
                 &mut self.buffer[remaining..][..max_read],
-                ZipFileHeaderFixed::SIZE,
+                ZipFileHeaderFixed::SIZE.min(max_read),
                 self.offset,
             )?;
             self.offset += read as u64;
@@ -1030,6 +1267,12 @@ where
 
         let central_directory_offset = self.offset - (self.end - self.pos) as u64;
         let data = &self.buffer[self.pos..self.end];
+        if is_digital_signature(data) {
+            self.pos = self.end;
+            self.offset = self.central_dir_end_pos;
+            return Ok(None);
+        }
+
         let file_header = ZipFileHeaderFixed::parse(data)?;
         self.pos += ZipFileHeaderFixed::SIZE;
 
@@ -1067,6 +1310,25 @@ where
     }
 }
 
+/// Checks that the entry holding the zstd dictionary is stored, as the
+/// dictionary is read from it as is.
+fn check_zstd_dictionary(record: &ZipFileHeaderRecord<'_>) -> Result<(), Error> {
+    if record.compression_method() != CompressionMethod::Store {
+        return Err(Error::from(ErrorKind::InvalidInput {
+            msg: "zstd dictionary entry is compressed".to_string(),
+        }));
+    }
+
+    Ok(())
+}
+
+/// Returns true if the data starts with the digital signature record, which
+/// follows the last central directory header (4.3.13).
+#[inline]
+fn is_digital_signature(data: &[u8]) -> bool {
+    data.len() >= 4 && le_u32(&data[0..4]) == DIGITAL_SIGNATURE_SIGNATURE
+}
+
 /// 4.4.2
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct VersionMadeBy(u16);
@@ -1353,21 +1615,21 @@ impl ZipString {
 #[allow(dead_code)]
 pub struct ZipFileHeaderRecord<'a> {
     signature: u32,
-    version_made_by: u16,
-    version_needed: u16,
-    flags: u16,
-    compression_method: CompressionMethodId,
-    last_mod_time: u16,
-    last_mod_date: u16,
-    crc32: u32,
-    compressed_size: u64,
-    uncompressed_size: u64,
+    pub(crate) version_made_by: u16,
+    pub(crate) version_needed: u16,
+    pub(crate) flags: u16,
+    pub(crate) compression_method: CompressionMethodId,
+    pub(crate) last_mod_time: u16,
+    pub(crate) last_mod_date: u16,
+    pub(crate) crc32: u32,
+    pub(crate) compressed_size: u64,
+    pub(crate) uncompressed_size: u64,
     file_name_len: u16,
     extra_field_len: u16,
     file_comment_len: u16,
     disk_number_start: u32,
-    internal_file_attrs: u16,
-    external_file_attrs: u32,
+    pub(crate) internal_file_attrs: u16,
+    pub(crate) external_file_attrs: u32,
     local_header_offset: u64,
     central_directory_offset: u64,
     file_name: ZipFilePath<RawPath<'a>>,
@@ -1530,6 +1792,13 @@ impl<'a> ZipFileHeaderRecord<'a> {
         self.local_header_offset
     }
 
+    /// The number of the disk that the entry's local header is on, which is
+    /// 0 unless the archive was split.
+    #[inline]
+    pub fn disk_number_start(&self) -> u32 {
+        self.disk_number_start
+    }
+
     /// The compression method used to compress the data
     #[inline]
     pub fn compression_method(&self) -> CompressionMethod {
@@ -1572,6 +1841,12 @@ impl<'a> ZipFileHeaderRecord<'a> {
         self.file_name
     }
 
+    /// The comment of the entry.
+    #[inline]
+    pub fn comment(&self) -> ZipStr<'a> {
+        self.file_comment
+    }
+
     /// Returns the last modification date and time.
     ///
     /// This method parses the extra field data to locate more accurate timestamps.
@@ -1580,6 +1855,31 @@ impl<'a> ZipFileHeaderRecord<'a> {
         extract_best_timestamp(self.extra_fields(), self.last_mod_time, self.last_mod_date)
     }
 
+    /// Returns the MS-DOS date and time stored in the header, without
+    /// consulting the extra fields.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rawzip::{time::UtcDateTime, ZipArchive, ZipArchiveWriter};
+    /// let mtime = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 0).unwrap();
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::new(&mut output);
+    /// archive.new_dir("dir/").last_modified(mtime).create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// let dos = entry.dos_last_modified();
+    /// assert_eq!((dos.year(), dos.second()), (2024, 8));
+    /// assert_eq!(dos.packed_date(), (44 << 9) | (5 << 5) | 6);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn dos_last_modified(&self) -> DosDateTime {
+        DosDateTime::new(self.last_mod_time, self.last_mod_date)
+    }
+
     /// Returns the file mode information extracted from the external file attributes.
     #[inline]
     pub fn mode(&self) -> EntryMode {
@@ -1667,10 +1967,10 @@ impl<'a> ZipFileHeaderRecord<'a> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ZipArchiveEntryWayfinder {
     uncompressed_size: u64,
-    compressed_size: u64,
-    local_header_offset: u64,
-    crc: u32,
-    has_data_descriptor: bool,
+    pub(crate) compressed_size: u64,
+    pub(crate) local_header_offset: u64,
+    pub(crate) crc: u32,
+    pub(crate) has_data_descriptor: bool,
 }
 
 impl ZipArchiveEntryWayfinder {
@@ -1709,7 +2009,7 @@ pub(crate) struct ZipLocalFileHeaderFixed {
 }
 
 impl ZipLocalFileHeaderFixed {
-    const SIZE: usize = 30;
+    pub(crate) const SIZE: usize = 30;
     pub const SIGNATURE: u32 = 0x04034b50;
 
     pub fn parse(data: &[u8]) -> Result<ZipLocalFileHeaderFixed, Error> {
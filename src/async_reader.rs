@@ -0,0 +1,601 @@
+//! Reading archives from async readers, either tokio's `AsyncRead +
+//! AsyncSeek` or those of futures-io.
+//!
+//! The end of central directory is located by the same code that backs
+//! [`ZipArchive`](crate::ZipArchive), run against the end of the input once
+//! it has been read into memory. Should the locator need bytes that haven't
+//! been read, they are read and the search starts over. The central
+//! directory is then read into memory whole, and entries are read on demand.
+//!
+//! Neither the locating nor the verification know of the runtime, which only
+//! comes in to read and seek, through [`AsyncSource`].
+
+use crate::archive::{DataDescriptor, ZipLocalFileHeaderFixed};
+use crate::crc::crc32_chunk;
+use crate::errors::{Error, ErrorKind};
+use crate::locator::{EndOfCentralDirectory, EndOfCentralDirectoryRecordFixed};
+use crate::{
+    ReaderAt, ZipArchiveEntryWayfinder, ZipLocator, ZipSliceEntries, ZipStr, ZipVerification,
+    RECOMMENDED_BUFFER_SIZE,
+};
+use std::cell::Cell;
+use std::future::poll_fn;
+use std::io::{self, SeekFrom};
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+mod private {
+    use std::io::{self, SeekFrom};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// The operations needed of an async reader, regardless of the runtime.
+    pub trait Source {
+        fn poll_read_source(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>>;
+
+        fn start_seek_source(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()>;
+
+        fn poll_seek_source(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>>;
+    }
+}
+
+/// An async reader that an [`AsyncZipArchive`] can read from.
+///
+/// This is implemented for tokio's `AsyncRead + AsyncSeek` with the `tokio`
+/// feature and for futures-io's wrapped in [`FuturesAsyncReader`] with the
+/// `futures-io` feature. It is sealed.
+pub trait AsyncSource: private::Source + Unpin {}
+
+impl<T: private::Source + Unpin> AsyncSource for T {}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin> private::Source for R {
+    fn poll_read_source(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        ready!(self.poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+
+    fn start_seek_source(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        self.start_seek(position)
+    }
+
+    fn poll_seek_source(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        self.poll_complete(cx)
+    }
+}
+
+/// Adapts a futures-io `AsyncRead + AsyncSeek` so that an
+/// [`AsyncZipArchive`] can read from it.
+///
+/// A wrapper is needed, as a reader may implement both tokio's and
+/// futures-io's `AsyncRead`.
+///
+/// # Example
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// use futures::io::AsyncReadExt;
+/// use rawzip::{AsyncZipArchive, FuturesAsyncReader};
+///
+/// let data = std::fs::read("assets/test.zip")?;
+/// let input = FuturesAsyncReader::new(futures::io::Cursor::new(data));
+/// let mut archive = AsyncZipArchive::from_reader(input).await?;
+/// let wayfinder = archive.entries().next_entry()?.unwrap().wayfinder();
+/// let mut entry = archive.get_entry(wayfinder).await?;
+/// let mut compressed = Vec::new();
+/// entry.read_to_end(&mut compressed).await?;
+/// assert_eq!(compressed.len() as u64, wayfinder.compressed_size_hint());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap();
+/// ```
+#[cfg(feature = "futures-io")]
+#[derive(Debug)]
+pub struct FuturesAsyncReader<R> {
+    reader: R,
+    seek: Option<SeekFrom>,
+}
+
+#[cfg(feature = "futures-io")]
+impl<R> FuturesAsyncReader<R> {
+    /// Wraps a futures-io `AsyncRead + AsyncSeek`.
+    pub fn new(reader: R) -> Self {
+        FuturesAsyncReader { reader, seek: None }
+    }
+
+    /// Returns a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: futures_io::AsyncRead + futures_io::AsyncSeek + Unpin> private::Source
+    for FuturesAsyncReader<R>
+{
+    fn poll_read_source(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+    }
+
+    fn start_seek_source(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        self.get_mut().seek = Some(position);
+        Ok(())
+    }
+
+    // futures-io seeks in a single call, which is repeated with the same
+    // position until it completes
+    fn poll_seek_source(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let position = this.seek.unwrap_or(SeekFrom::Current(0));
+        let result = ready!(Pin::new(&mut this.reader).poll_seek(cx, position));
+        this.seek = None;
+        Poll::Ready(result)
+    }
+}
+
+/// A Zip archive read from an async reader, the async counterpart to
+/// [`ZipArchive`](crate::ZipArchive).
+///
+/// Any tokio `AsyncRead + AsyncSeek` can be read from, as can a futures-io
+/// one wrapped in [`FuturesAsyncReader`].
+///
+/// The central directory is held in memory, so that entries can be listed
+/// without awaiting. Reading an entry's data needs exclusive access to the
+/// reader, as it seeks, so only one entry is read at a time.
+///
+/// Entry data is read compressed, as with the other readers. Decompress it
+/// with an async decompressor, like those of the async-compression crate,
+/// and verify the result with [`AsyncZipEntry::verifying_reader`].
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// use rawzip::{AsyncZipArchive, ZipArchiveWriter};
+/// use tokio::io::AsyncReadExt;
+///
+/// let mut output = Vec::new();
+/// let mut archive = ZipArchiveWriter::new(&mut output);
+/// let (mut entry, config) = archive.new_file("file.txt").start()?;
+/// let mut writer = config.wrap(&mut entry);
+/// std::io::Write::write_all(&mut writer, b"Hello, world!")?;
+/// let (_, descriptor) = writer.finish()?;
+/// entry.finish(descriptor)?;
+/// archive.finish()?;
+///
+/// let mut archive = AsyncZipArchive::from_reader(std::io::Cursor::new(output)).await?;
+/// let wayfinder = {
+///     let entry = archive.entries().next_entry()?.unwrap();
+///     assert_eq!(entry.file_path().as_ref(), b"file.txt");
+///     entry.wayfinder()
+/// };
+///
+/// // The entry is stored, so there's nothing to decompress
+/// let entry = archive.get_entry(wayfinder).await?;
+/// let mut contents = Vec::new();
+/// entry.verifying_reader(|data| data).read_to_end(&mut contents).await?;
+/// assert_eq!(contents, b"Hello, world!");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct AsyncZipArchive<R> {
+    reader: R,
+    eocd: EndOfCentralDirectory,
+    directory: Vec<u8>,
+    comment: Vec<u8>,
+}
+
+impl<R: AsyncSource> AsyncZipArchive<R> {
+    /// Locates the archive at the end of `reader` and reads its central
+    /// directory.
+    pub async fn from_reader(reader: R) -> Result<Self, Error> {
+        Self::with_locator(&ZipLocator::new(), reader).await
+    }
+
+    /// Like [`from_reader`](AsyncZipArchive::from_reader), but with a
+    /// locator that has been configured, such as with a maximum search
+    /// space.
+    pub async fn with_locator(locator: &ZipLocator, mut reader: R) -> Result<Self, Error> {
+        let len = seek(&mut reader, SeekFrom::End(0)).await?;
+        let mut input = Prefetched {
+            len,
+            chunks: Vec::new(),
+            missing: Cell::new(None),
+        };
+
+        let tail = len.saturating_sub(RECOMMENDED_BUFFER_SIZE as u64)..len;
+        input.fetch(&mut reader, tail).await?;
+
+        let mut buffer = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+        let eocd = loop {
+            let result = locator
+                .locate_in_reader(&input, &mut buffer, len)
+                .map(|archive| archive.eocd)
+                .map_err(|(_, e)| e);
+
+            // The locator may have done without bytes that weren't read,
+            // so it's run again with them
+            match input.missing.take() {
+                Some(missing) => input.fetch(&mut reader, missing).await?,
+                None => break result?,
+            }
+        };
+
+        let directory_range = eocd.directory_offset()..eocd.head_eocd_offset();
+        let directory = input.read(&mut reader, directory_range).await?;
+
+        let comment_start = eocd.tail_eocd_offset() + EndOfCentralDirectoryRecordFixed::SIZE as u64;
+        let comment_range = comment_start..comment_start + eocd.comment_len() as u64;
+        let comment = input.read(&mut reader, comment_range).await?;
+
+        Ok(AsyncZipArchive {
+            reader,
+            eocd,
+            directory,
+            comment,
+        })
+    }
+
+    /// Reads the local file header of the entry, and its data descriptor if
+    /// it has one, and seeks to the start of its data.
+    ///
+    /// See [`ZipArchive::get_entry`](crate::ZipArchive::get_entry) for more
+    /// details.
+    pub async fn get_entry(
+        &mut self,
+        entry: ZipArchiveEntryWayfinder,
+    ) -> Result<AsyncZipEntry<'_, R>, Error> {
+        let mut buffer = [0u8; ZipLocalFileHeaderFixed::SIZE];
+        seek(&mut self.reader, SeekFrom::Start(entry.local_header_offset)).await?;
+        read_exact(&mut self.reader, &mut buffer).await?;
+        let file_header = ZipLocalFileHeaderFixed::parse(&buffer)?;
+
+        let body_offset = entry
+            .local_header_offset
+            .checked_add(ZipLocalFileHeaderFixed::SIZE as u64)
+            .and_then(|x| x.checked_add(file_header.variable_length() as u64))
+            .ok_or_else(|| Error::from(ErrorKind::Eof))?;
+        let body_end_offset = body_offset
+            .checked_add(entry.compressed_size)
+            .ok_or_else(|| Error::from(ErrorKind::Eof))?;
+
+        let crc = if entry.has_data_descriptor {
+            let mut descriptor = [0u8; DataDescriptor::SIZE];
+            seek(&mut self.reader, SeekFrom::Start(body_end_offset)).await?;
+            read_exact(&mut self.reader, &mut descriptor).await?;
+            DataDescriptor::parse(&descriptor)?.crc
+        } else {
+            entry.crc
+        };
+
+        seek(&mut self.reader, SeekFrom::Start(body_offset)).await?;
+        Ok(AsyncZipEntry {
+            reader: &mut self.reader,
+            remaining: entry.compressed_size,
+            body_offset,
+            body_end_offset,
+            verification: ZipVerification {
+                crc,
+                uncompressed_size: entry.uncompressed_size_hint(),
+            },
+        })
+    }
+}
+
+impl<R> AsyncZipArchive<R> {
+    /// Returns an iterator over the entries in the central directory, which
+    /// has already been read.
+    pub fn entries(&self) -> ZipSliceEntries<'_> {
+        ZipSliceEntries::new(
+            &self.directory,
+            self.eocd.base_offset(),
+            self.eocd.directory_offset(),
+        )
+    }
+
+    /// Returns the number of entries that the archive claims to hold.
+    ///
+    /// See [`ZipArchive::entries_hint`](crate::ZipArchive::entries_hint)
+    /// for more details.
+    pub fn entries_hint(&self) -> u64 {
+        self.eocd.entries()
+    }
+
+    /// Returns the comment of the archive.
+    pub fn comment(&self) -> ZipStr<'_> {
+        ZipStr::new(&self.comment)
+    }
+
+    /// Returns the offset of the start of the central directory.
+    pub fn directory_offset(&self) -> u64 {
+        self.eocd.directory_offset()
+    }
+
+    /// Returns the offset of the End of Central Directory signature.
+    pub fn eocd_offset(&self) -> u64 {
+        self.eocd.tail_eocd_offset()
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Consumes the archive, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// An entry of an [`AsyncZipArchive`], which reads the entry's compressed
+/// data.
+#[derive(Debug)]
+pub struct AsyncZipEntry<'archive, R> {
+    reader: &'archive mut R,
+    remaining: u64,
+    body_offset: u64,
+    body_end_offset: u64,
+    verification: ZipVerification,
+}
+
+impl<'archive, R: AsyncSource> AsyncZipEntry<'archive, R> {
+    /// Returns the expected CRC32 and uncompressed size of the entry.
+    ///
+    /// The data descriptor, if any, has already been read.
+    pub fn claim_verifier(&self) -> ZipVerification {
+        self.verification
+    }
+
+    /// Returns a tuple of start and end byte offsets for the compressed data
+    /// within the underlying reader.
+    ///
+    /// See [`ZipEntry::compressed_data_range`](crate::ZipEntry::compressed_data_range)
+    /// for more details.
+    pub fn compressed_data_range(&self) -> (u64, u64) {
+        (self.body_offset, self.body_end_offset)
+    }
+
+    /// Returns a reader that wraps a decompressor and verifies the size and
+    /// CRC of the decompressed data once finished.
+    ///
+    /// The decompressor is created by `decoder` from the entry, as it reads
+    /// the entry's data. Pass the entry through as is for stored entries.
+    pub fn verifying_reader<D, F>(self, decoder: F) -> AsyncZipVerifier<D>
+    where
+        F: FnOnce(Self) -> D,
+    {
+        let verification = self.verification;
+        AsyncZipVerifier {
+            reader: decoder(self),
+            verification,
+            crc: 0,
+            size: 0,
+        }
+    }
+}
+
+impl<R: AsyncSource> AsyncZipEntry<'_, R> {
+    fn poll_read_entry(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let len = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        if len == 0 {
+            return Poll::Ready(Ok(0));
+        }
+
+        let read = ready!(Pin::new(&mut *self.reader).poll_read_source(cx, &mut buf[..len]))?;
+        if read == 0 {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        self.remaining -= read as u64;
+        Poll::Ready(Ok(read))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncSource> tokio::io::AsyncRead for AsyncZipEntry<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = ready!(self
+            .get_mut()
+            .poll_read_entry(cx, buf.initialize_unfilled()))?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: AsyncSource> futures_io::AsyncRead for AsyncZipEntry<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_entry(cx, buf)
+    }
+}
+
+/// Verifies the size and CRC of the data an async decompressor reads,
+/// returned by [`AsyncZipEntry::verifying_reader`].
+///
+/// A mismatch is returned as an [`io::ErrorKind::InvalidData`] error from
+/// the read that reaches the end of the data.
+#[derive(Debug)]
+pub struct AsyncZipVerifier<D> {
+    reader: D,
+    verification: ZipVerification,
+    crc: u32,
+    size: u64,
+}
+
+impl<D> AsyncZipVerifier<D> {
+    /// Consumes the verifier, returning the underlying decompressor.
+    pub fn into_inner(self) -> D {
+        self.reader
+    }
+
+    /// Accounts for data that has been read, validating it once the end
+    /// has been reached.
+    fn verify(&mut self, data: &[u8]) -> io::Result<()> {
+        self.crc = crc32_chunk(data, self.crc);
+        self.size += data.len() as u64;
+
+        if data.is_empty() || self.size >= self.verification.size() {
+            self.verification
+                .valid(ZipVerification {
+                    crc: self.crc,
+                    uncompressed_size: self.size,
+                })
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<D> tokio::io::AsyncRead for AsyncZipVerifier<D>
+where
+    D: tokio::io::AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.reader).poll_read(cx, buf))?;
+
+        // An async read that fails must not have filled the buffer
+        if let Err(e) = this.verify(&buf.filled()[before..]) {
+            buf.set_filled(before);
+            return Poll::Ready(Err(e));
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<D> futures_io::AsyncRead for AsyncZipVerifier<D>
+where
+    D: futures_io::AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let read = ready!(Pin::new(&mut this.reader).poll_read(cx, buf))?;
+        this.verify(&buf[..read])?;
+        Poll::Ready(Ok(read))
+    }
+}
+
+/// The parts of the input that have been read into memory, which the
+/// locator reads from.
+///
+/// A read of a part that hasn't been read fails and is recorded, so that it
+/// can be read before the locator is run again.
+struct Prefetched {
+    len: u64,
+    chunks: Vec<(u64, Vec<u8>)>,
+    missing: Cell<Option<Range<u64>>>,
+}
+
+impl Prefetched {
+    async fn fetch<R: AsyncSource>(
+        &mut self,
+        reader: &mut R,
+        range: Range<u64>,
+    ) -> Result<(), Error> {
+        let mut data = vec![0u8; (range.end - range.start) as usize];
+        seek(reader, SeekFrom::Start(range.start)).await?;
+        read_exact(reader, &mut data).await?;
+        self.chunks.push((range.start, data));
+        Ok(())
+    }
+
+    /// Returns the bytes in `range`, reading them if they haven't been.
+    async fn read<R: AsyncSource>(
+        &mut self,
+        reader: &mut R,
+        range: Range<u64>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut data = vec![0u8; (range.end - range.start) as usize];
+        if self.read_exact_at(&mut data, range.start).is_err() {
+            self.missing.take();
+            seek(reader, SeekFrom::Start(range.start)).await?;
+            read_exact(reader, &mut data).await?;
+        }
+        Ok(data)
+    }
+}
+
+impl ReaderAt for Prefetched {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if offset >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let chunk = self
+            .chunks
+            .iter()
+            .find(|(start, data)| offset >= *start && offset - *start < data.len() as u64);
+        if let Some((start, data)) = chunk {
+            let data = &data[(offset - start) as usize..];
+            let len = data.len().min(buf.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            return Ok(len);
+        }
+
+        let end = offset.saturating_add(buf.len() as u64).min(self.len);
+        self.missing.set(Some(offset..end));
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the input hasn't been read this far",
+        ))
+    }
+}
+
+async fn seek<R: AsyncSource>(reader: &mut R, position: SeekFrom) -> io::Result<u64> {
+    Pin::new(&mut *reader).start_seek_source(position)?;
+    poll_fn(|cx| Pin::new(&mut *reader).poll_seek_source(cx)).await
+}
+
+async fn read_exact<R: AsyncSource>(reader: &mut R, mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        let read = poll_fn(|cx| Pin::new(&mut *reader).poll_read_source(cx, buf)).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf = &mut buf[read..];
+    }
+    Ok(())
+}
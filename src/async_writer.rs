@@ -0,0 +1,507 @@
+//! Writing archives to async writers, either tokio's `AsyncWrite` or the
+//! `AsyncWrite` of futures-io.
+//!
+//! The ZIP records are serialized into an in-memory buffer by the same code
+//! that backs [`ZipArchiveWriter`], and the buffer is drained into the async
+//! writer before more is added to it. The buffer only ever holds the records
+//! written since the last drain and the latest chunk of entry data.
+
+use crate::{
+    DataDescriptorOutput, DataTransform, Error, ZipArchiveWriter, ZipArchiveWriterBuilder,
+    ZipDataWriter, ZipDataWriterConfig, ZipDirBuilder, ZipEntrySummary, ZipEntryWriter,
+    ZipFileBuilder, ZipHeaderFields, ZipProgress,
+};
+use std::future::poll_fn;
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+mod private {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// The operations needed of an async writer, regardless of the runtime.
+    pub trait Sink {
+        fn poll_write_sink(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>>;
+
+        fn poll_flush_sink(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+    }
+}
+
+/// An async writer that an [`AsyncZipArchiveWriter`] can write to.
+///
+/// This is implemented for tokio's `AsyncWrite` with the `tokio` feature and
+/// for futures-io's `AsyncWrite` wrapped in [`FuturesAsyncWriter`] with the
+/// `futures-io` feature. It is sealed.
+pub trait AsyncSink: private::Sink + Unpin {}
+
+impl<T: private::Sink + Unpin> AsyncSink for T {}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> private::Sink for W {
+    fn poll_write_sink(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write(cx, buf)
+    }
+
+    fn poll_flush_sink(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Adapts a futures-io `AsyncWrite` so that an [`AsyncZipArchiveWriter`] can
+/// write to it.
+///
+/// A wrapper is needed, as a writer may implement both tokio's and futures-io's
+/// `AsyncWrite`.
+///
+/// # Example
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// use futures::io::AsyncWriteExt;
+/// use rawzip::{AsyncZipArchiveWriter, FuturesAsyncWriter};
+///
+/// let output = futures::io::Cursor::new(Vec::new());
+/// let mut archive = AsyncZipArchiveWriter::new(FuturesAsyncWriter::new(output));
+/// let (mut entry, config) = archive.new_file("file.txt").start_async()?;
+/// let mut writer = config.wrap(&mut entry);
+/// writer.write_all(b"Hello, world!").await?;
+/// let (_, descriptor) = writer.into_parts();
+/// entry.finish(descriptor).await?;
+/// let output = archive.finish().await?.into_inner().into_inner();
+///
+/// let archive = rawzip::ZipArchive::from_slice(&output)?;
+/// assert_eq!(archive.entries_hint(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap();
+/// ```
+#[cfg(feature = "futures-io")]
+#[derive(Debug)]
+pub struct FuturesAsyncWriter<W>(W);
+
+#[cfg(feature = "futures-io")]
+impl<W> FuturesAsyncWriter<W> {
+    /// Wraps a futures-io `AsyncWrite`.
+    pub fn new(writer: W) -> Self {
+        FuturesAsyncWriter(writer)
+    }
+
+    /// Returns a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.0
+    }
+
+    /// Returns the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: futures_io::AsyncWrite + Unpin> private::Sink for FuturesAsyncWriter<W> {
+    fn poll_write_sink(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush_sink(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+}
+
+/// The buffer that an [`AsyncZipArchiveWriter`] serializes into before the
+/// data is written to the async writer.
+///
+/// It appears in the builders returned by the async writer but otherwise
+/// isn't used directly.
+#[derive(Debug)]
+pub struct AsyncWriteBuffer<W> {
+    buffer: Vec<u8>,
+    written: usize,
+    writer: W,
+}
+
+impl<W> Write for AsyncWriteBuffer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: AsyncSink> AsyncWriteBuffer<W> {
+    /// Writes out everything that has been buffered.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.buffer.len() {
+            let buf = &self.buffer[self.written..];
+            let n = ready!(Pin::new(&mut self.writer).poll_write_sink(cx, buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+
+        self.buffer.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Writes out everything that has been buffered and flushes the writer.
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        Pin::new(&mut self.writer).poll_flush_sink(cx)
+    }
+
+    async fn drain(&mut self) -> io::Result<()> {
+        poll_fn(|cx| self.poll_drain(cx)).await
+    }
+}
+
+impl ZipArchiveWriterBuilder {
+    /// Builds an [`AsyncZipArchiveWriter`] that writes to an async writer.
+    pub fn build_async<W>(&self, writer: W) -> AsyncZipArchiveWriter<W> {
+        let buffer = AsyncWriteBuffer {
+            buffer: Vec::new(),
+            written: 0,
+            writer,
+        };
+
+        AsyncZipArchiveWriter {
+            archive: self.build(buffer).without_write_buffer(),
+        }
+    }
+}
+
+/// Writes a Zip archive to an async writer.
+///
+/// Any tokio `AsyncWrite` can be written to, as can a futures-io
+/// `AsyncWrite` wrapped in a [`FuturesAsyncWriter`], depending on the enabled
+/// features.
+///
+/// Entries are added with the same builders as [`ZipArchiveWriter`], with
+/// [`ZipFileBuilder::start_async`] returning an entry writer that implements
+/// `AsyncWrite`. Records of entries that are created in one go, like
+/// directories, are written to the async writer along with the next entry
+/// or when the archive is flushed or finished.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// use tokio::io::AsyncWriteExt;
+///
+/// let mut archive = rawzip::AsyncZipArchiveWriter::new(Vec::new());
+/// archive.new_dir("dir/").create()?;
+/// let (mut entry, config) = archive.new_file("dir/file.txt").start_async()?;
+/// let mut writer = config.wrap(&mut entry);
+/// writer.write_all(b"Hello, world!").await?;
+/// let (_, descriptor) = writer.into_parts();
+/// entry.finish(descriptor).await?;
+/// let output = archive.finish().await?;
+///
+/// let archive = rawzip::ZipArchive::from_slice(&output)?;
+/// assert_eq!(archive.entries_hint(), 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct AsyncZipArchiveWriter<W> {
+    archive: ZipArchiveWriter<AsyncWriteBuffer<W>>,
+}
+
+impl<W> AsyncZipArchiveWriter<W> {
+    /// Creates a new `AsyncZipArchiveWriter` that writes to `writer`.
+    pub fn new(writer: W) -> Self {
+        ZipArchiveWriterBuilder::new().build_async(writer)
+    }
+
+    /// Returns the current offset in the output stream, including data that
+    /// is still buffered.
+    pub fn stream_offset(&self) -> u64 {
+        self.archive.stream_offset()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.archive.get_ref().writer
+    }
+
+    /// Sets the archive comment. See [`ZipArchiveWriter::set_comment`].
+    pub fn set_comment(&mut self, comment: &[u8]) -> Result<(), Error> {
+        self.archive.set_comment(comment)
+    }
+
+    /// Sets the data of the archive extra data record. See
+    /// [`ZipArchiveWriter::set_archive_extra_data`].
+    pub fn set_archive_extra_data(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.archive.set_archive_extra_data(data)
+    }
+
+    /// Removes all entries with the given name from the central directory.
+    /// See [`ZipArchiveWriter::remove`].
+    pub fn remove(&mut self, name: impl AsRef<[u8]>) -> bool {
+        self.archive.remove(name)
+    }
+
+    /// Sets a callback that is updated as entries are written. See
+    /// [`ZipArchiveWriter::on_progress`].
+    ///
+    /// Updates are made as data is buffered, ahead of it reaching the
+    /// async writer.
+    pub fn on_progress(&mut self, callback: impl FnMut(&ZipProgress) + Send + 'static) {
+        self.archive.on_progress(callback)
+    }
+
+    /// Sets a callback that is handed the header fields of every entry right
+    /// before they're written. See [`ZipArchiveWriter::on_header`].
+    pub fn on_header(
+        &mut self,
+        callback: impl FnMut(&mut ZipHeaderFields<'_, '_>) -> Result<(), Error> + Send + 'static,
+    ) {
+        self.archive.on_header(callback)
+    }
+
+    /// Sets a callback that signs the central directory when the archive is
+    /// finished. See [`ZipArchiveWriter::sign_directory`].
+    pub fn sign_directory(
+        &mut self,
+        signer: impl FnMut(&[u8]) -> Result<Vec<u8>, Error> + Send + 'static,
+    ) {
+        self.archive.sign_directory(signer)
+    }
+}
+
+impl<W: AsyncSink> AsyncZipArchiveWriter<W> {
+    /// Creates a builder for adding a new file to the archive.
+    ///
+    /// Start the entry with [`ZipFileBuilder::start_async`].
+    #[must_use]
+    pub fn new_file<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> ZipFileBuilder<'a, 'a, AsyncWriteBuffer<W>> {
+        self.archive.new_file(name)
+    }
+
+    /// Creates a builder for adding a new file whose name is written
+    /// verbatim. See [`ZipArchiveWriter::new_file_bytes`].
+    #[must_use]
+    pub fn new_file_bytes<'a>(
+        &'a mut self,
+        name: &'a [u8],
+    ) -> ZipFileBuilder<'a, 'a, AsyncWriteBuffer<W>> {
+        self.archive.new_file_bytes(name)
+    }
+
+    /// Creates a builder for adding a new directory to the archive.
+    #[must_use]
+    pub fn new_dir<'a>(&'a mut self, name: &'a str) -> ZipDirBuilder<'a, AsyncWriteBuffer<W>> {
+        self.archive.new_dir(name)
+    }
+
+    /// Returns the number of bytes that have been serialized but not yet
+    /// written to the async writer.
+    pub fn buffered_len(&self) -> usize {
+        let buffer = self.archive.get_ref();
+        buffer.buffer.len() - buffer.written
+    }
+
+    /// Writes everything that is buffered and flushes the async writer.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        let buffer = self.archive.get_mut();
+        poll_fn(|cx| buffer.poll_flush(cx)).await?;
+        Ok(())
+    }
+
+    /// Finishes writing the archive and returns the async writer.
+    ///
+    /// See [`ZipArchiveWriter::finish`]. The writer is flushed but not shut
+    /// down.
+    pub async fn finish(self) -> Result<W, Error> {
+        let mut buffer = self.archive.finish()?;
+        poll_fn(|cx| buffer.poll_flush(cx)).await?;
+        Ok(buffer.writer)
+    }
+}
+
+impl<'archive, W> ZipFileBuilder<'archive, '_, AsyncWriteBuffer<W>>
+where
+    W: AsyncSink,
+{
+    /// Mark the start of file data for an [`AsyncZipArchiveWriter`].
+    ///
+    /// Like [`start`](ZipFileBuilder::start), but the entry writer implements
+    /// `AsyncWrite`, as does the data writer that the `config` wraps it with. Take the data descriptor from the data writer with
+    /// [`ZipDataWriter::into_parts`], after shutting down any async
+    /// compressor in between.
+    pub fn start_async(
+        self,
+    ) -> Result<(AsyncZipEntryWriter<'archive, W>, ZipDataWriterConfig), Error> {
+        let (entry, config) = self.start()?;
+        Ok((AsyncZipEntryWriter { entry }, config))
+    }
+}
+
+/// An async writer for a file in a Zip archive.
+///
+/// Created by [`ZipFileBuilder::start_async`]. After writing all data, call
+/// `finish` to complete the entry.
+#[derive(Debug)]
+pub struct AsyncZipEntryWriter<'archive, W> {
+    entry: ZipEntryWriter<'archive, AsyncWriteBuffer<W>>,
+}
+
+impl<W: AsyncSink> AsyncZipEntryWriter<'_, W> {
+    /// Returns the total number of bytes successfully written (bytes out).
+    pub fn compressed_bytes(&self) -> u64 {
+        self.entry.compressed_bytes()
+    }
+
+    /// Transforms the data written from here on before it reaches the
+    /// archive. See [`ZipEntryWriter::set_transform`].
+    pub fn set_transform(
+        &mut self,
+        transform: impl DataTransform + Send + 'static,
+    ) -> Result<(), Error> {
+        self.entry.set_transform(transform)
+    }
+
+    /// Finishes writing the file entry. See [`ZipEntryWriter::finish`].
+    pub async fn finish(mut self, output: DataDescriptorOutput) -> Result<u64, Error> {
+        self.entry.output_mut().drain().await?;
+        self.entry.finish(output)
+    }
+
+    /// Finishes writing the file entry and returns where it was written. See
+    /// [`ZipEntryWriter::finish_with_summary`].
+    pub async fn finish_with_summary(
+        mut self,
+        output: DataDescriptorOutput,
+    ) -> Result<ZipEntrySummary, Error> {
+        self.entry.output_mut().drain().await?;
+        self.entry.finish_with_summary(output)
+    }
+
+    /// Abandons the entry. See [`ZipEntryWriter::abort`].
+    pub async fn abort(mut self) -> Result<(), Error> {
+        self.entry.output_mut().drain().await?;
+        self.entry.abort()
+    }
+}
+
+impl<W: AsyncSink> AsyncZipEntryWriter<'_, W> {
+    fn poll_write_entry(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.entry.output_mut().poll_drain(cx))?;
+        Poll::Ready(self.entry.write(buf))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: AsyncSink> tokio::io::AsyncWrite for AsyncZipEntryWriter<'_, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_entry(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().entry.output_mut().poll_flush(cx)
+    }
+
+    /// Flushes the entry, but doesn't shut down the async writer, as the
+    /// archive isn't finished.
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().entry.output_mut().poll_flush(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: AsyncSink> futures_io::AsyncWrite for AsyncZipEntryWriter<'_, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_entry(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().entry.output_mut().poll_flush(cx)
+    }
+
+    /// Flushes the entry, but doesn't close the async writer, as the archive
+    /// isn't finished.
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().entry.output_mut().poll_flush(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for ZipDataWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().write_with(
+            buf,
+            |inner, buf| Pin::new(inner).poll_write(cx, buf),
+            |result| match result {
+                Poll::Ready(Ok(written)) => *written,
+                _ => 0,
+            },
+        )
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().get_mut()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().get_mut()).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: futures_io::AsyncWrite + Unpin> futures_io::AsyncWrite for ZipDataWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().write_with(
+            buf,
+            |inner, buf| Pin::new(inner).poll_write(cx, buf),
+            |result| match result {
+                Poll::Ready(Ok(written)) => *written,
+                _ => 0,
+            },
+        )
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().get_mut()).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.get_mut().get_mut()).poll_close(cx)
+    }
+}
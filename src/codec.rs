@@ -0,0 +1,450 @@
+//! Built-in compressors that are available behind cargo features.
+//!
+//! Rawzip is bring-your-own-compression at heart, so everything in here is
+//! optional sugar layered on top of the raw `ZipEntryWriter` API.
+
+use crate::{errors::ErrorKind, CompressionMethod, Error};
+use std::io::{self, Read, Write};
+
+/// A compressor that writes into `S`.
+///
+/// `Store` passes data through untouched so that stored entries can share the
+/// same convenience writer.
+pub(crate) enum Encoder<S: Write> {
+    Store(S),
+    #[cfg(feature = "deflate")]
+    Deflate(flate2::write::DeflateEncoder<S>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, S>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<S>),
+    #[cfg(feature = "lzma")]
+    Lzma(xz2::write::XzEncoder<LzmaHeader<S>>),
+    #[cfg(feature = "xz")]
+    Xz(xz2::write::XzEncoder<S>),
+}
+
+impl<S: Write> Encoder<S> {
+    /// Returns true if there is a built-in codec for the compression method.
+    pub(crate) fn supports(method: CompressionMethod) -> bool {
+        match method {
+            CompressionMethod::Store => true,
+            #[cfg(feature = "deflate")]
+            CompressionMethod::Deflate => true,
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => true,
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => true,
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => true,
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => true,
+            _ => false,
+        }
+    }
+
+    /// Creates the compressor for the compression method.
+    ///
+    /// When `level` is absent, the codec's default level is used.
+    pub(crate) fn new(
+        sink: S,
+        method: CompressionMethod,
+        level: Option<i32>,
+    ) -> Result<Self, Error> {
+        Self::with_dictionary(sink, method, level, None)
+    }
+
+    /// Like [`new`](Encoder::new), but zstd compresses against the
+    /// `dictionary`, if there is one. Other methods ignore it.
+    #[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+    pub(crate) fn with_dictionary(
+        sink: S,
+        method: CompressionMethod,
+        level: Option<i32>,
+        dictionary: Option<&[u8]>,
+    ) -> Result<Self, Error> {
+        match method {
+            CompressionMethod::Store => Ok(Encoder::Store(sink)),
+            #[cfg(feature = "deflate")]
+            CompressionMethod::Deflate => {
+                let level = level.map_or(6, |x| x.clamp(0, 9) as u32);
+                let level = flate2::Compression::new(level);
+                Ok(Encoder::Deflate(flate2::write::DeflateEncoder::new(
+                    sink, level,
+                )))
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let level = level.unwrap_or(0);
+                let encoder = match dictionary {
+                    Some(dictionary) => {
+                        zstd::stream::write::Encoder::with_dictionary(sink, level, dictionary)?
+                    }
+                    None => zstd::stream::write::Encoder::new(sink, level)?,
+                };
+                Ok(Encoder::Zstd(encoder))
+            }
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => {
+                let level = level.map_or(9, |x| x.clamp(1, 9) as u32);
+                let level = bzip2::Compression::new(level);
+                Ok(Encoder::Bzip2(bzip2::write::BzEncoder::new(sink, level)))
+            }
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => {
+                let level = level.map_or(6, |x| x.clamp(0, 9) as u32);
+                let options =
+                    xz2::stream::LzmaOptions::new_preset(level).map_err(io::Error::from)?;
+                let stream =
+                    xz2::stream::Stream::new_lzma_encoder(&options).map_err(io::Error::from)?;
+                let sink = LzmaHeader {
+                    sink,
+                    header: Vec::with_capacity(LZMA_ALONE_HEADER_LEN),
+                };
+                Ok(Encoder::Lzma(xz2::write::XzEncoder::new_stream(
+                    sink, stream,
+                )))
+            }
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => {
+                let level = level.map_or(6, |x| x.clamp(0, 9) as u32);
+                Ok(Encoder::Xz(xz2::write::XzEncoder::new(sink, level)))
+            }
+            _ => Err(Error::from(ErrorKind::InvalidInput {
+                msg: format!("no built-in codec for compression method {:?}", method),
+            })),
+        }
+    }
+
+    /// Flushes any buffered compressor state and returns the sink.
+    pub(crate) fn finish(self) -> io::Result<S> {
+        match self {
+            Encoder::Store(sink) => Ok(sink),
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(encoder) => encoder.finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.finish(),
+            #[cfg(feature = "lzma")]
+            Encoder::Lzma(encoder) => encoder.finish().map(|x| x.sink),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// Compresses an entry's data in one go.
+///
+/// With `store_if_larger`, the data is returned as is when compression
+/// doesn't make it any smaller. Returns the payload and the compression method
+/// it was written with.
+pub(crate) fn compress(
+    data: Vec<u8>,
+    method: CompressionMethod,
+    level: Option<i32>,
+    store_if_larger: bool,
+    dictionary: Option<&[u8]>,
+) -> Result<(Vec<u8>, CompressionMethod), Error> {
+    let mut encoder = Encoder::with_dictionary(Vec::new(), method, level, dictionary)?;
+    encoder.write_all(&data)?;
+    let compressed = encoder.finish()?;
+    if store_if_larger && method != CompressionMethod::Store && compressed.len() >= data.len() {
+        Ok((data, CompressionMethod::Store))
+    } else {
+        Ok((compressed, method))
+    }
+}
+
+/// Compresses an entry's data with each of the candidate methods and returns
+/// the smallest payload along with the method it was written with.
+///
+/// The candidates are storing the data, deflate and zstd when their features
+/// are enabled, and `method`, which is the only one given the `level`. The
+/// earlier candidate wins a tie.
+pub(crate) fn compress_smallest(
+    data: Vec<u8>,
+    method: CompressionMethod,
+    level: Option<i32>,
+    dictionary: Option<&[u8]>,
+) -> Result<(Vec<u8>, CompressionMethod), Error> {
+    let mut candidates = Vec::new();
+    #[cfg(feature = "deflate")]
+    candidates.push(CompressionMethod::Deflate);
+    #[cfg(feature = "zstd")]
+    candidates.push(CompressionMethod::Zstd);
+    if method != CompressionMethod::Store && !candidates.contains(&method) {
+        candidates.push(method);
+    }
+
+    let mut smallest: Option<(Vec<u8>, CompressionMethod)> = None;
+    for candidate in candidates {
+        let level = if candidate == method { level } else { None };
+        let mut encoder = Encoder::with_dictionary(Vec::new(), candidate, level, dictionary)?;
+        encoder.write_all(&data)?;
+        let compressed = encoder.finish()?;
+        let len = smallest
+            .as_ref()
+            .map_or(data.len(), |(payload, _)| payload.len());
+        if compressed.len() < len {
+            smallest = Some((compressed, candidate));
+        }
+    }
+
+    Ok(smallest.unwrap_or((data, CompressionMethod::Store)))
+}
+
+/// Trains a zstd dictionary of up to `max_size` bytes on samples of the
+/// entries that are to be compressed against it.
+///
+/// Dictionaries pay off for many small entries that share content, such as
+/// JSON documents of the same shape, where there is too little data in each
+/// entry for zstd to pick up on the repetition. A hundred or so samples and a
+/// dictionary of around 100 KiB are customary. The dictionary is then written
+/// with [`ZipArchiveWriter::set_zstd_dictionary`](crate::ZipArchiveWriter::set_zstd_dictionary).
+///
+/// Returns an error if zstd can't train a dictionary on the samples, such as
+/// when there are too few of them.
+#[cfg(feature = "zstd")]
+pub fn train_zstd_dictionary<S: AsRef<[u8]>>(
+    samples: &[S],
+    max_size: usize,
+) -> Result<Vec<u8>, Error> {
+    Ok(zstd::dict::from_samples(samples, max_size)?)
+}
+
+/// Deflates one block of a larger stream on its own, so that blocks can be
+/// compressed in parallel.
+///
+/// All but the `last` block end with a sync flush, which byte aligns the
+/// output without marking the end of the stream, so that the compressed
+/// blocks concatenate into a single valid deflate stream. Each block starts
+/// with an empty window, which costs a little compression ratio.
+#[cfg(feature = "deflate")]
+pub(crate) fn deflate_block(data: &[u8], level: u32, last: bool) -> Result<Vec<u8>, Error> {
+    let mut compress = flate2::Compress::new(flate2::Compression::new(level.min(9)), false);
+    let flush = if last {
+        flate2::FlushCompress::Finish
+    } else {
+        flate2::FlushCompress::Sync
+    };
+
+    let mut output = Vec::with_capacity(data.len() / 2 + 1024);
+    loop {
+        let consumed = compress.total_in() as usize;
+        let status = compress
+            .compress_vec(&data[consumed..], &mut output, flush)
+            .map_err(io::Error::from)?;
+
+        // A flush is complete once it no longer fills the output
+        let done = if last {
+            status == flate2::Status::StreamEnd
+        } else {
+            compress.total_in() as usize == data.len() && output.len() < output.capacity()
+        };
+
+        if done {
+            return Ok(output);
+        }
+
+        output.reserve(output.capacity());
+    }
+}
+
+impl<S: Write> Write for Encoder<S> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Store(sink) => sink.write(buf),
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+            #[cfg(feature = "lzma")]
+            Encoder::Lzma(encoder) => encoder.write(buf),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(encoder) => encoder.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Store(sink) => sink.flush(),
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(encoder) => encoder.flush(),
+            // LZMA1 has no way to flush mid stream, which liblzma reports
+            // as a programming error, so only the sink is flushed
+            #[cfg(feature = "lzma")]
+            Encoder::Lzma(encoder) => encoder.get_mut().flush(),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl<S: Write> std::fmt::Debug for Encoder<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Encoder::Store(_) => "Store",
+            #[cfg(feature = "deflate")]
+            Encoder::Deflate(_) => "Deflate",
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(_) => "Zstd",
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(_) => "Bzip2",
+            #[cfg(feature = "lzma")]
+            Encoder::Lzma(_) => "Lzma",
+            #[cfg(feature = "xz")]
+            Encoder::Xz(_) => "Xz",
+        };
+        f.debug_tuple("Encoder").field(&name).finish()
+    }
+}
+
+/// A decompressor that reads from `R`, the counterpart to [`Encoder`].
+pub(crate) enum Decoder<R: Read> {
+    Store(R),
+    #[cfg(feature = "deflate")]
+    Deflate(flate2::read::DeflateDecoder<R>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, io::BufReader<R>>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::read::BzDecoder<R>),
+    #[cfg(feature = "lzma")]
+    // Boxed, as the chained header makes it much larger than the others
+    Lzma(Box<xz2::read::XzDecoder<io::Chain<io::Cursor<Vec<u8>>, R>>>),
+    #[cfg(feature = "xz")]
+    Xz(xz2::read::XzDecoder<R>),
+}
+
+impl<R: Read> Decoder<R> {
+    /// Creates the decompressor for the compression method. Zstd
+    /// decompresses against the `dictionary`, if there is one, which other
+    /// methods ignore.
+    #[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+    pub(crate) fn new(
+        source: R,
+        method: CompressionMethod,
+        dictionary: Option<&[u8]>,
+    ) -> Result<Self, Error> {
+        match method {
+            CompressionMethod::Store => Ok(Decoder::Store(source)),
+            #[cfg(feature = "deflate")]
+            CompressionMethod::Deflate => {
+                Ok(Decoder::Deflate(flate2::read::DeflateDecoder::new(source)))
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let source = io::BufReader::new(source);
+                let dictionary = dictionary.unwrap_or_default();
+                let decoder = zstd::stream::read::Decoder::with_dictionary(source, dictionary)?;
+                Ok(Decoder::Zstd(decoder))
+            }
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => Ok(Decoder::Bzip2(bzip2::read::BzDecoder::new(source))),
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => {
+                // Restore the header of the `.lzma` format, which leaves the
+                // uncompressed size unknown and relies on the end marker
+                let mut source = source;
+                let mut header = [0u8; ZIP_LZMA_HEADER_LEN];
+                source.read_exact(&mut header)?;
+                let mut alone = header[4..].to_vec();
+                alone.extend_from_slice(&u64::MAX.to_le_bytes());
+                let stream =
+                    xz2::stream::Stream::new_lzma_decoder(u64::MAX).map_err(io::Error::from)?;
+                let source = io::Cursor::new(alone).chain(source);
+                Ok(Decoder::Lzma(Box::new(xz2::read::XzDecoder::new_stream(
+                    source, stream,
+                ))))
+            }
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => Ok(Decoder::Xz(xz2::read::XzDecoder::new(source))),
+            _ => Err(Error::from(ErrorKind::InvalidInput {
+                msg: format!("no built-in codec for compression method {:?}", method),
+            })),
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoder::Store(source) => source.read(buf),
+            #[cfg(feature = "deflate")]
+            Decoder::Deflate(decoder) => decoder.read(buf),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(decoder) => decoder.read(buf),
+            #[cfg(feature = "bzip2")]
+            Decoder::Bzip2(decoder) => decoder.read(buf),
+            #[cfg(feature = "lzma")]
+            Decoder::Lzma(decoder) => decoder.read(buf),
+            #[cfg(feature = "xz")]
+            Decoder::Xz(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+/// The LZMA SDK version recorded in the header of LZMA entries. Readers
+/// ignore it, so it's the version that other writers customarily record.
+#[cfg(feature = "lzma")]
+const LZMA_VERSION: [u8; 2] = [9, 4];
+
+/// The size of the LZMA properties: the literal and position bits followed
+/// by the dictionary size.
+#[cfg(feature = "lzma")]
+const LZMA_PROPS_LEN: usize = 5;
+
+/// The `.lzma` header is the properties followed by the uncompressed size.
+#[cfg(feature = "lzma")]
+const LZMA_ALONE_HEADER_LEN: usize = LZMA_PROPS_LEN + 8;
+
+/// The zip header is the version and size of the properties followed by the
+/// properties (5.8.8).
+#[cfg(feature = "lzma")]
+const ZIP_LZMA_HEADER_LEN: usize = 4 + LZMA_PROPS_LEN;
+
+/// Rewrites the header that liblzma writes for the `.lzma` format into the
+/// one that zip expects, passing the compressed stream through as is.
+///
+/// The `.lzma` header ends with the uncompressed size, which zip records in
+/// the entry's headers instead.
+#[cfg(feature = "lzma")]
+pub(crate) struct LzmaHeader<S> {
+    sink: S,
+    header: Vec<u8>,
+}
+
+#[cfg(feature = "lzma")]
+impl<S: Write> Write for LzmaHeader<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let missing = LZMA_ALONE_HEADER_LEN - self.header.len();
+        if missing == 0 {
+            return self.sink.write(buf);
+        }
+
+        let len = missing.min(buf.len());
+        self.header.extend_from_slice(&buf[..len]);
+        if self.header.len() == LZMA_ALONE_HEADER_LEN {
+            let mut header = [0u8; ZIP_LZMA_HEADER_LEN];
+            header[..2].copy_from_slice(&LZMA_VERSION);
+            header[2..4].copy_from_slice(&(LZMA_PROPS_LEN as u16).to_le_bytes());
+            header[4..].copy_from_slice(&self.header[..LZMA_PROPS_LEN]);
+            self.sink.write_all(&header)?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sink.flush()
+    }
+}
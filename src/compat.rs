@@ -0,0 +1,169 @@
+//! Checks of the archive being written against what extractors can read.
+
+use crate::{extra_fields::ExtraFieldId, CompressionMethod};
+
+const FLAG_ENCRYPTED: u16 = 0x01;
+const FLAG_DATA_DESCRIPTOR: u16 = 0x08;
+const FLAG_UTF8_ENCODING: u16 = 0x800;
+
+/// An extractor that the archive is checked against, set with
+/// [`ZipArchiveWriterBuilder::with_compat_target`](crate::ZipArchiveWriterBuilder::with_compat_target).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompatTarget {
+    /// The compressed folders of Windows Explorer, which read stored and
+    /// deflated entries, and ZipCrypto but not AES. An archive that needs
+    /// ZIP64 may be shown as empty, and names that aren't flagged as UTF-8
+    /// are read in the OEM code page.
+    WindowsExplorer,
+    /// `java.util.zip` as of Java 8, which reads stored and deflated
+    /// entries that aren't encrypted, and decodes names as UTF-8. Its
+    /// `ZipInputStream` can't find the end of a stored entry that has a
+    /// data descriptor.
+    Java8,
+}
+
+impl std::fmt::Display for CompatTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            CompatTarget::WindowsExplorer => write!(f, "Windows Explorer"),
+            CompatTarget::Java8 => write!(f, "Java 8"),
+        }
+    }
+}
+
+/// What to do when the archive uses a feature that the [`CompatTarget`]
+/// can't read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompatPolicy {
+    /// Record the issue, to be returned by
+    /// [`ZipArchiveSummary::compat_issues`](crate::ZipArchiveSummary::compat_issues).
+    #[default]
+    Warn,
+    /// Fail with [`ErrorKind::Incompatible`](crate::ErrorKind::Incompatible).
+    /// Entries are checked before anything is written for them.
+    Error,
+}
+
+/// A feature of the archive that the [`CompatTarget`] can't read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompatIssue {
+    /// The entry is compressed with a method the target can't decompress.
+    CompressionMethod {
+        name: String,
+        method: CompressionMethod,
+    },
+    /// The entry is encrypted in a way the target can't decrypt.
+    Encryption { name: String },
+    /// The entry is stored with a data descriptor, so the target can't tell
+    /// where its data ends.
+    StoredDataDescriptor { name: String },
+    /// The target would misread the entry's name.
+    NameEncoding { name: String },
+    /// The archive needs ZIP64.
+    Zip64,
+}
+
+impl std::fmt::Display for CompatIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            CompatIssue::CompressionMethod {
+                ref name,
+                ref method,
+            } => {
+                write!(f, "entry {:?} uses compression method {:?}", name, method)
+            }
+            CompatIssue::Encryption { ref name } => {
+                write!(f, "entry {:?} is encrypted", name)
+            }
+            CompatIssue::StoredDataDescriptor { ref name } => {
+                write!(f, "stored entry {:?} has a data descriptor", name)
+            }
+            CompatIssue::NameEncoding { ref name } => {
+                write!(f, "entry {:?} has a name that isn't flagged as UTF-8", name)
+            }
+            CompatIssue::Zip64 => write!(f, "archive needs zip64"),
+        }
+    }
+}
+
+impl CompatTarget {
+    /// Returns the issues with an entry, given its name and header fields as
+    /// they are to be written.
+    pub(crate) fn entry_issues(
+        self,
+        name: &[u8],
+        method: CompressionMethod,
+        flags: u16,
+        mut extra_fields: impl Iterator<Item = ExtraFieldId>,
+    ) -> Vec<CompatIssue> {
+        let display_name = || String::from_utf8_lossy(name).into_owned();
+        let mut issues = Vec::new();
+
+        let encrypted = flags & FLAG_ENCRYPTED != 0;
+        let decrypts = match self {
+            CompatTarget::WindowsExplorer => method != CompressionMethod::Aes,
+            CompatTarget::Java8 => !encrypted,
+        };
+        if !decrypts {
+            issues.push(CompatIssue::Encryption {
+                name: display_name(),
+            });
+        }
+
+        let decompresses = match self {
+            CompatTarget::WindowsExplorer => matches!(
+                method,
+                CompressionMethod::Store
+                    | CompressionMethod::Deflate
+                    | CompressionMethod::Deflate64
+            ),
+            CompatTarget::Java8 => {
+                matches!(
+                    method,
+                    CompressionMethod::Store | CompressionMethod::Deflate
+                )
+            }
+        };
+        if !decompresses && decrypts {
+            issues.push(CompatIssue::CompressionMethod {
+                name: display_name(),
+                method,
+            });
+        }
+
+        if self == CompatTarget::Java8
+            && method == CompressionMethod::Store
+            && flags & FLAG_DATA_DESCRIPTOR != 0
+        {
+            issues.push(CompatIssue::StoredDataDescriptor {
+                name: display_name(),
+            });
+        }
+
+        let misread = match self {
+            CompatTarget::WindowsExplorer => {
+                !name.is_ascii()
+                    && flags & FLAG_UTF8_ENCODING == 0
+                    && !extra_fields.any(|id| id == ExtraFieldId::INFO_ZIP_UNICODE_PATH)
+            }
+            CompatTarget::Java8 => std::str::from_utf8(name).is_err(),
+        };
+        if misread {
+            issues.push(CompatIssue::NameEncoding {
+                name: display_name(),
+            });
+        }
+
+        issues
+    }
+
+    /// Returns true if the target reads archives that need ZIP64.
+    pub(crate) fn supports_zip64(self) -> bool {
+        match self {
+            CompatTarget::WindowsExplorer => false,
+            CompatTarget::Java8 => true,
+        }
+    }
+}
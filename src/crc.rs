@@ -78,6 +78,85 @@ pub fn crc32_chunk(data: &[u8], prev: u32) -> u32 {
     !crc
 }
 
+/// Advances a CRC32 register by a byte, without the inversions that
+/// [`crc32`] applies before and after, as traditional PKWARE encryption keeps
+/// its keys in raw registers.
+#[cfg(feature = "zipcrypto")]
+pub(crate) fn crc32_update(crc: u32, byte: u8) -> u32 {
+    (crc >> 8) ^ CRC_TABLE[0][((crc ^ u32::from(byte)) & 0xFF) as usize]
+}
+
+/// Combines the CRC32 of two consecutive blocks of data, given the CRC32 of
+/// each and the length of the second, without revisiting the data.
+///
+/// Port of zlib's `crc32_combine`: appending `len2` zero bits to the first
+/// block is a linear operation over GF(2), applied by repeatedly squaring the
+/// matrix that appends a single zero bit.
+#[cfg(feature = "deflate")]
+pub(crate) fn crc32_combine(mut crc1: u32, crc2: u32, mut len2: u64) -> u32 {
+    if len2 == 0 {
+        return crc1;
+    }
+
+    // The operator for one zero bit
+    let mut odd = [0u32; 32];
+    odd[0] = 0xEDB88320;
+    for (n, row) in odd.iter_mut().enumerate().skip(1) {
+        *row = 1 << (n - 1);
+    }
+
+    // The operators for two and then four zero bits
+    let mut even = gf2_matrix_square(&odd);
+    odd = gf2_matrix_square(&even);
+
+    // Apply len2 zero bytes to crc1. The first square in the loop yields the
+    // operator for one zero byte and each one after doubles it.
+    loop {
+        even = gf2_matrix_square(&odd);
+        if len2 & 1 != 0 {
+            crc1 = gf2_matrix_times(&even, crc1);
+        }
+        len2 >>= 1;
+        if len2 == 0 {
+            break;
+        }
+
+        odd = gf2_matrix_square(&even);
+        if len2 & 1 != 0 {
+            crc1 = gf2_matrix_times(&odd, crc1);
+        }
+        len2 >>= 1;
+        if len2 == 0 {
+            break;
+        }
+    }
+
+    crc1 ^ crc2
+}
+
+#[cfg(feature = "deflate")]
+fn gf2_matrix_times(mat: &[u32; 32], mut vec: u32) -> u32 {
+    let mut sum = 0;
+    let mut rows = mat.iter();
+    while vec != 0 {
+        let row = rows.next().copied().unwrap_or(0);
+        if vec & 1 != 0 {
+            sum ^= row;
+        }
+        vec >>= 1;
+    }
+    sum
+}
+
+#[cfg(feature = "deflate")]
+fn gf2_matrix_square(mat: &[u32; 32]) -> [u32; 32] {
+    let mut square = [0u32; 32];
+    for (row, &x) in square.iter_mut().zip(mat) {
+        *row = gf2_matrix_times(mat, x);
+    }
+    square
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,4 +173,15 @@ mod tests {
         let abc = b"EU4txt\nchecksum=\"ced5411e2d4a5ec724595c2c4f1b7347\"";
         assert_eq!(crc32(abc), 1702863696);
     }
+
+    #[test]
+    #[cfg(feature = "deflate")]
+    fn test_crc_combine() {
+        let data = b"The quick brown fox jumps over the lazy dog".repeat(50);
+        for split in [0, 1, 7, 16, 100, data.len()] {
+            let (a, b) = data.split_at(split);
+            let combined = crc32_combine(crc32(a), crc32(b), b.len() as u64);
+            assert_eq!(combined, crc32(&data));
+        }
+    }
 }
@@ -0,0 +1,127 @@
+//! The encryption schemes that entries can be written with.
+
+#[cfg(feature = "aes")]
+use crate::aes::{self, AesEncryptor};
+#[cfg(feature = "zipcrypto")]
+use crate::zipcrypto::ZipCryptoEncryptor;
+use crate::Error;
+
+/// A password that entries are encrypted with.
+///
+/// It's kept out of debug output.
+#[derive(Clone)]
+pub(crate) struct Password(Vec<u8>);
+
+impl Password {
+    pub(crate) fn new(password: &[u8]) -> Self {
+        Password(password.to_vec())
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Password {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Password(..)")
+    }
+}
+
+/// How an entry is to be encrypted.
+#[derive(Debug, Clone)]
+pub(crate) enum Encryption {
+    #[cfg(feature = "aes")]
+    Aes(Password),
+    #[cfg(feature = "zipcrypto")]
+    ZipCrypto(Password),
+}
+
+impl Encryption {
+    /// Returns true for ZipCrypto, whose entries are written with a data
+    /// descriptor, as the header would otherwise be checked against the
+    /// CRC32 that isn't known until the data has been written.
+    pub(crate) fn is_zipcrypto(&self) -> bool {
+        match self {
+            #[cfg(feature = "aes")]
+            Encryption::Aes(_) => false,
+            #[cfg(feature = "zipcrypto")]
+            Encryption::ZipCrypto(_) => true,
+        }
+    }
+}
+
+/// Encrypts an entry's data as it's written.
+#[derive(Debug)]
+pub(crate) enum Encryptor {
+    #[cfg(feature = "aes")]
+    Aes(Box<AesEncryptor>),
+    #[cfg(feature = "zipcrypto")]
+    ZipCrypto(ZipCryptoEncryptor),
+}
+
+impl Encryptor {
+    /// Creates the encryptor along with the header that precedes the
+    /// encrypted data.
+    ///
+    /// The `check` byte is what ZipCrypto readers verify the password with.
+    #[cfg_attr(not(feature = "zipcrypto"), allow(unused_variables))]
+    pub(crate) fn new(encryption: &Encryption, check: u8) -> Result<(Self, Vec<u8>), Error> {
+        match encryption {
+            #[cfg(feature = "aes")]
+            Encryption::Aes(password) => {
+                let (encryptor, header) = AesEncryptor::new(password)?;
+                Ok((Encryptor::Aes(Box::new(encryptor)), header))
+            }
+            #[cfg(feature = "zipcrypto")]
+            Encryption::ZipCrypto(password) => {
+                let (encryptor, header) = ZipCryptoEncryptor::new(password, check)?;
+                Ok((Encryptor::ZipCrypto(encryptor), header))
+            }
+        }
+    }
+
+    /// Encrypts the data in place.
+    pub(crate) fn encrypt(&mut self, data: &mut [u8]) {
+        match self {
+            #[cfg(feature = "aes")]
+            Encryptor::Aes(encryptor) => encryptor.encrypt(data),
+            #[cfg(feature = "zipcrypto")]
+            Encryptor::ZipCrypto(encryptor) => encryptor.encrypt(data),
+        }
+    }
+
+    /// Returns true if the entry's CRC32 is recorded. AE-2 leaves it out, as
+    /// the authentication code covers it.
+    pub(crate) fn records_crc(&self) -> bool {
+        match self {
+            #[cfg(feature = "aes")]
+            Encryptor::Aes(_) => false,
+            #[cfg(feature = "zipcrypto")]
+            Encryptor::ZipCrypto(_) => true,
+        }
+    }
+
+    /// Returns the trailer that follows the encrypted data.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "aes")]
+            Encryptor::Aes(encryptor) => encryptor.finish().to_vec(),
+            #[cfg(feature = "zipcrypto")]
+            Encryptor::ZipCrypto(_) => Vec::new(),
+        }
+    }
+}
+
+/// Returns the AES extra field for an entry, if it's encrypted with AES.
+#[cfg(feature = "aes")]
+pub(crate) fn aes_extra_field(
+    encryption: &Encryption,
+    method: crate::CompressionMethod,
+) -> Option<[u8; 7]> {
+    match encryption {
+        Encryption::Aes(_) => Some(aes::extra_field(method)),
+        #[cfg(feature = "zipcrypto")]
+        Encryption::ZipCrypto(_) => None,
+    }
+}
@@ -72,9 +72,25 @@ pub enum ErrorKind {
     /// An invalid input error with associated message
     InvalidInput { msg: String },
 
+    /// An entry name that was rejected by name validation, with the reason
+    InvalidPath { path: String, msg: String },
+
+    /// An entry name that appears more than once when merging archives, or
+    /// that was already written when the writer rejects duplicates
+    DuplicateName { name: String },
+
     /// Could not construct an archive with the given end of central directory
     InvalidEndOfCentralDirectory,
 
+    /// A limit of the original zip format was hit while zip64 is disabled
+    Zip64Required { limit: Zip64Limit },
+
+    /// The archive uses a feature that the compatibility target can't read
+    Incompatible {
+        target: crate::CompatTarget,
+        issue: crate::CompatIssue,
+    },
+
     /// An IO error
     IO(std::io::Error),
 
@@ -82,6 +98,30 @@ pub enum ErrorKind {
     Eof,
 }
 
+/// A limit of the original zip format, past which zip64 is needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zip64Limit {
+    /// An entry's sizes are 4 GiB or more
+    EntrySize,
+
+    /// An offset into the archive is 4 GiB or more, or a disk number is
+    /// 65,535 or more
+    ArchiveSize,
+
+    /// The archive has 65,535 entries or more
+    EntryCount,
+}
+
+impl std::fmt::Display for Zip64Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Zip64Limit::EntrySize => write!(f, "entry size"),
+            Zip64Limit::ArchiveSize => write!(f, "archive size"),
+            Zip64Limit::EntryCount => write!(f, "entry count"),
+        }
+    }
+}
+
 impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
@@ -130,9 +170,24 @@ impl std::fmt::Display for ErrorKind {
             ErrorKind::InvalidInput { ref msg } => {
                 write!(f, "Invalid input: {}", msg)
             }
+            ErrorKind::InvalidPath { ref path, ref msg } => {
+                write!(f, "Invalid path {:?}: {}", path, msg)
+            }
+            ErrorKind::DuplicateName { ref name } => {
+                write!(f, "Duplicate entry name {:?}", name)
+            }
             ErrorKind::InvalidEndOfCentralDirectory => {
                 write!(f, "Invalid end of central directory")
             }
+            ErrorKind::Zip64Required { limit } => {
+                write!(f, "Zip64 required but disabled: {} limit exceeded", limit)
+            }
+            ErrorKind::Incompatible {
+                ref target,
+                ref issue,
+            } => {
+                write!(f, "Incompatible with {}: {}", target, issue)
+            }
         }
     }
 }
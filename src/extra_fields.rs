@@ -1,4 +1,9 @@
-use crate::{utils::le_u16, Error, ErrorKind, Header};
+use crate::{
+    crc32,
+    time::UtcDateTime,
+    utils::{le_u16, le_u32, le_u64},
+    CompressionMethod, Error, ErrorKind, Header,
+};
 use std::io::Write;
 
 /// A numeric identifier for an extra field in a Zip archive.
@@ -46,8 +51,9 @@ impl ExtraFieldId {
     pub const INFO_ZIP_UNICODE_PATH: Self = Self(0x7075);
     pub const DATA_STREAM_ALIGNMENT: Self = Self(0xa11e);
     pub const MICROSOFT_OPEN_PACKAGING_GROWTH_HINT: Self = Self(0xa220);
+    pub const WINZIP_AES: Self = Self(0x9901);
 
-    /// Returns the raw `u16` value of the extra field ID.
+    /// Creates an extra field ID from its raw `u16` value.
     #[inline]
     pub const fn new(id: u16) -> Self {
         Self(id)
@@ -60,6 +66,13 @@ impl ExtraFieldId {
     }
 }
 
+impl From<u16> for ExtraFieldId {
+    #[inline]
+    fn from(id: u16) -> Self {
+        Self(id)
+    }
+}
+
 /// An iterator over extra field entries in a Zip archive.
 ///
 /// This follows zip spec section 4.5 defines extensible data fields:
@@ -122,6 +135,282 @@ impl<'a> Iterator for ExtraFields<'a> {
     }
 }
 
+/// The OS/2 extended attributes extra field (`0x0009`).
+///
+/// The attributes are kept in the compressed form that OS/2 archivers wrote
+/// them in, preceded by their uncompressed size, compression method, and
+/// CRC32 (section 4.5.8 of the Zip spec). Write the field with
+/// [`ZipFileBuilder::extra_field`](crate::ZipFileBuilder::extra_field) and
+/// [`ExtraFieldId::OS2`].
+///
+/// # Example
+///
+/// ```rust
+/// # use rawzip::extra_fields::Os2ExtendedAttributes;
+/// let field = Os2ExtendedAttributes::stored(b"\x00\x00\x00\x00");
+/// let bytes = field.to_bytes();
+/// let parsed = Os2ExtendedAttributes::parse(&bytes).unwrap();
+/// assert_eq!(parsed.attributes(), Some(&b"\x00\x00\x00\x00"[..]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Os2ExtendedAttributes<'a> {
+    uncompressed_size: u32,
+    compression_method: CompressionMethod,
+    crc32: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Os2ExtendedAttributes<'a> {
+    /// Creates the field for attributes that are stored without compression.
+    pub fn stored(attributes: &'a [u8]) -> Self {
+        Os2ExtendedAttributes {
+            uncompressed_size: attributes.len() as u32,
+            compression_method: CompressionMethod::Store,
+            crc32: crc32(attributes),
+            data: attributes,
+        }
+    }
+
+    /// Parses the data of the field, returning `None` if it's truncated.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 10 {
+            return None;
+        }
+
+        Some(Os2ExtendedAttributes {
+            uncompressed_size: le_u32(&data[0..4]),
+            compression_method: CompressionMethod::from(le_u16(&data[4..6])),
+            crc32: le_u32(&data[6..10]),
+            data: &data[10..],
+        })
+    }
+
+    /// Returns the size of the attributes once decompressed.
+    pub fn uncompressed_size(&self) -> u32 {
+        self.uncompressed_size
+    }
+
+    /// Returns the method the attributes are compressed with.
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    /// Returns the CRC32 of the uncompressed attributes.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Returns the attributes as they are compressed.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the attributes if they are stored without compression and
+    /// match their size and CRC32.
+    pub fn attributes(&self) -> Option<&'a [u8]> {
+        let valid = self.compression_method == CompressionMethod::Store
+            && self.data.len() as u64 == u64::from(self.uncompressed_size)
+            && crc32(self.data) == self.crc32;
+        valid.then_some(self.data)
+    }
+
+    /// Serializes the field's data, without the header ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(10 + self.data.len());
+        data.extend_from_slice(&self.uncompressed_size.to_le_bytes());
+        data.extend_from_slice(&self.compression_method.as_id().as_u16().to_le_bytes());
+        data.extend_from_slice(&self.crc32.to_le_bytes());
+        data.extend_from_slice(self.data);
+        data
+    }
+}
+
+/// The timestamps of the PKWARE Win32 (NTFS) extra field (`0x000a`).
+///
+/// The modification, access, and creation times are kept at the 100ns
+/// resolution of Windows file times (section 4.5.5 of the Zip spec). The
+/// writer adds the field with
+/// [`ZipFileBuilder::ntfs_times`](crate::ZipFileBuilder::ntfs_times).
+///
+/// # Example
+///
+/// ```rust
+/// # use rawzip::{extra_fields::NtfsTimestamps, time::UtcDateTime};
+/// let modified = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 123_456_700).unwrap();
+/// let created = UtcDateTime::from_unix(1_600_000_000);
+/// let field = NtfsTimestamps::new(modified, modified, created);
+/// let parsed = NtfsTimestamps::parse(&field.to_bytes()).unwrap();
+/// assert_eq!(parsed.modified(), modified);
+/// assert_eq!(parsed.created(), created);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NtfsTimestamps {
+    modified: UtcDateTime,
+    accessed: UtcDateTime,
+    created: UtcDateTime,
+}
+
+impl NtfsTimestamps {
+    /// Creates the field from the modification, access, and creation times.
+    pub fn new(modified: UtcDateTime, accessed: UtcDateTime, created: UtcDateTime) -> Self {
+        NtfsTimestamps {
+            modified,
+            accessed,
+            created,
+        }
+    }
+
+    /// Parses the data of the field, returning `None` if it has no
+    /// timestamp attribute.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        // 4 reserved bytes, then attributes of a 2 byte tag and size each
+        let mut attributes = data.get(4..)?;
+        while attributes.len() >= 4 {
+            let tag = le_u16(&attributes[0..2]);
+            let size = le_u16(&attributes[2..4]) as usize;
+            let value = attributes.get(4..4 + size)?;
+            if tag == 0x0001 && size >= 24 {
+                return Some(NtfsTimestamps {
+                    modified: UtcDateTime::from_ntfs(le_u64(&value[0..8])),
+                    accessed: UtcDateTime::from_ntfs(le_u64(&value[8..16])),
+                    created: UtcDateTime::from_ntfs(le_u64(&value[16..24])),
+                });
+            }
+            attributes = &attributes[4 + size..];
+        }
+
+        None
+    }
+
+    /// Returns the last modification time.
+    pub fn modified(&self) -> UtcDateTime {
+        self.modified
+    }
+
+    /// Returns the last access time.
+    pub fn accessed(&self) -> UtcDateTime {
+        self.accessed
+    }
+
+    /// Returns the creation time.
+    pub fn created(&self) -> UtcDateTime {
+        self.created
+    }
+
+    /// Serializes the field's data, without the header ID and size.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut data = [0u8; 32];
+        data[4..6].copy_from_slice(&1u16.to_le_bytes());
+        data[6..8].copy_from_slice(&24u16.to_le_bytes());
+        data[8..16].copy_from_slice(&self.modified.to_ntfs().to_le_bytes());
+        data[16..24].copy_from_slice(&self.accessed.to_ntfs().to_le_bytes());
+        data[24..32].copy_from_slice(&self.created.to_ntfs().to_le_bytes());
+        data
+    }
+}
+
+/// The PKWARE Unix extra field (`0x000d`).
+///
+/// It holds the access and modification times in seconds, the user and
+/// group IDs, and data that depends on the type of the file, like the
+/// target of a hard or symbolic link, or the major and minor numbers of a
+/// device (section 4.5.7 of the Zip spec). Write the field with
+/// [`ZipFileBuilder::extra_field`](crate::ZipFileBuilder::extra_field) and
+/// [`ExtraFieldId::UNIX`].
+///
+/// # Example
+///
+/// ```rust
+/// # use rawzip::{extra_fields::PkwareUnix, time::UtcDateTime};
+/// let modified = UtcDateTime::from_unix(1_700_000_000);
+/// let field = PkwareUnix::new(modified, modified, 1000, 100);
+/// let bytes = field.to_bytes();
+/// let parsed = PkwareUnix::parse(&bytes).unwrap();
+/// assert_eq!(parsed.modified(), modified);
+/// assert_eq!((parsed.uid(), parsed.gid()), (1000, 100));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PkwareUnix<'a> {
+    accessed: u32,
+    modified: u32,
+    uid: u16,
+    gid: u16,
+    data: &'a [u8],
+}
+
+impl<'a> PkwareUnix<'a> {
+    /// Creates the field from the modification and access times and the
+    /// owner. Times before 1970 are written as 1970.
+    pub fn new(modified: UtcDateTime, accessed: UtcDateTime, uid: u16, gid: u16) -> Self {
+        let seconds = |time: UtcDateTime| time.to_unix().clamp(0, i64::from(u32::MAX)) as u32;
+        PkwareUnix {
+            accessed: seconds(accessed),
+            modified: seconds(modified),
+            uid,
+            gid,
+            data: &[],
+        }
+    }
+
+    /// Sets the data that depends on the type of the file.
+    #[must_use]
+    pub fn with_data(mut self, data: &'a [u8]) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Parses the data of the field, returning `None` if it's truncated.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 12 {
+            return None;
+        }
+
+        Some(PkwareUnix {
+            accessed: le_u32(&data[0..4]),
+            modified: le_u32(&data[4..8]),
+            uid: le_u16(&data[8..10]),
+            gid: le_u16(&data[10..12]),
+            data: &data[12..],
+        })
+    }
+
+    /// Returns the last access time.
+    pub fn accessed(&self) -> UtcDateTime {
+        UtcDateTime::from_unix(i64::from(self.accessed))
+    }
+
+    /// Returns the last modification time.
+    pub fn modified(&self) -> UtcDateTime {
+        UtcDateTime::from_unix(i64::from(self.modified))
+    }
+
+    /// Returns the user ID.
+    pub fn uid(&self) -> u16 {
+        self.uid
+    }
+
+    /// Returns the group ID.
+    pub fn gid(&self) -> u16 {
+        self.gid
+    }
+
+    /// Returns the data that depends on the type of the file.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Serializes the field's data, without the header ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(12 + self.data.len());
+        data.extend_from_slice(&self.accessed.to_le_bytes());
+        data.extend_from_slice(&self.modified.to_le_bytes());
+        data.extend_from_slice(&self.uid.to_le_bytes());
+        data.extend_from_slice(&self.gid.to_le_bytes());
+        data.extend_from_slice(self.data);
+        data
+    }
+}
+
 /// Container for extra fields with a shared data buffer and cached sizes.
 #[derive(Debug, Clone)]
 pub(crate) struct ExtraFieldsContainer {
@@ -178,6 +467,79 @@ impl ExtraFieldsContainer {
         Ok(())
     }
 
+    /// Adds a field ahead of the others, for readers that look for it at a
+    /// fixed position.
+    pub fn prepend_field(
+        &mut self,
+        id: ExtraFieldId,
+        data: &[u8],
+        location: Header,
+    ) -> Result<(), Error> {
+        let mut fields = ExtraFieldsContainer::new();
+        fields.add_field(id, data, location)?;
+        let mut extra_fields = ExtraFields::new(self.data_buffer.as_slice());
+        for &entry in self.entries.as_slice() {
+            let field = extra_fields.next_data().expect("Entry should have data");
+            let field_id = ExtraFieldId(le_u16(&field[0..2]));
+            fields.add_field(field_id, &field[4..], entry)?;
+        }
+
+        *self = fields;
+        Ok(())
+    }
+
+    /// Returns the fields, regardless of which headers they're written to.
+    pub fn iter(&self) -> ExtraFields<'_> {
+        ExtraFields::new(self.data_buffer.as_slice())
+    }
+
+    /// Keeps only the fields for which the predicate returns true.
+    pub fn retain_fields(&mut self, mut keep: impl FnMut(ExtraFieldId, &[u8]) -> bool) {
+        let mut fields = ExtraFieldsContainer::new();
+        let mut extra_fields = ExtraFields::new(self.data_buffer.as_slice());
+        for &entry in self.entries.as_slice() {
+            let field = extra_fields.next_data().expect("Entry should have data");
+            let field_id = ExtraFieldId(le_u16(&field[0..2]));
+            if keep(field_id, &field[4..]) {
+                fields
+                    .add_field(field_id, &field[4..], entry)
+                    .expect("Fewer fields should fit");
+            }
+        }
+
+        *self = fields;
+    }
+
+    /// Overwrites the data of the first field with the given id and location.
+    ///
+    /// Returns false if there is no such field or its data is a different
+    /// length, as the sizes of written headers must not change.
+    pub fn replace_field(&mut self, id: ExtraFieldId, data: &[u8], location: Header) -> bool {
+        let mut pos = 0;
+        let buffer = self.data_buffer.as_mut_slice();
+        for entry in self.entries.as_slice() {
+            let field_id = le_u16(&buffer[pos..pos + 2]);
+            let size = le_u16(&buffer[pos + 2..pos + 4]) as usize;
+            let body = pos + 4;
+            pos = body + size;
+            if field_id == id.as_u16() && *entry == location {
+                if size != data.len() {
+                    return false;
+                }
+
+                buffer[body..pos].copy_from_slice(data);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Hands the data of each field to `f` to be modified in place.
+    pub fn for_each_field_mut(&mut self, f: impl FnMut(ExtraFieldId, &mut [u8])) {
+        for_each_field_mut(self.data_buffer.as_mut_slice(), f);
+    }
+
     fn write_extra_fields_iter(
         &self,
         writer: &mut impl Write,
@@ -214,6 +576,25 @@ impl ExtraFieldsContainer {
     }
 }
 
+/// Hands the data of each field in the serialized `fields` to `f` to be
+/// modified in place, stopping at a truncated field.
+pub(crate) fn for_each_field_mut(
+    mut fields: &mut [u8],
+    mut f: impl FnMut(ExtraFieldId, &mut [u8]),
+) {
+    while fields.len() >= 4 {
+        let id = ExtraFieldId(le_u16(&fields[0..2]));
+        let size = le_u16(&fields[2..4]) as usize;
+        if fields.len() < 4 + size {
+            return;
+        }
+
+        let (field, rest) = std::mem::take(&mut fields).split_at_mut(4 + size);
+        f(id, &mut field[4..]);
+        fields = rest;
+    }
+}
+
 /// A stack-first vector that avoids heap allocation for small amounts of data.
 ///
 /// A poor man's `smallvec` as we aren't able to store as many elements inline
@@ -269,6 +650,13 @@ where
             Self::Large(vec) => vec.as_slice(),
         }
     }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match self {
+            Self::Small { data, len } => &mut data[..*len as usize],
+            Self::Large(vec) => vec.as_mut_slice(),
+        }
+    }
 }
 
 // Specialized methods for StackVec<u8, N> (byte buffers)
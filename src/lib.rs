@@ -1,24 +1,70 @@
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "aes")]
+mod aes;
 mod archive;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+mod async_reader;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+mod async_writer;
+#[cfg(feature = "_codec")]
+mod codec;
+mod compat;
 mod crc;
+#[cfg(any(feature = "aes", feature = "zipcrypto"))]
+mod encryption;
 mod errors;
 pub mod extra_fields;
 mod headers;
 mod locator;
+mod merge;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod mode;
+#[cfg(feature = "_codec")]
+mod parallel;
 pub mod path;
+mod plan;
 mod reader_at;
+mod rewrite;
+mod sink;
+mod split;
+mod stream;
 pub mod time;
+#[cfg(feature = "_codec")]
+mod transcode;
 mod utils;
 mod writer;
+#[cfg(feature = "zipcrypto")]
+mod zipcrypto;
 
 pub use archive::*;
+#[cfg(feature = "futures-io")]
+pub use async_reader::FuturesAsyncReader;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub use async_reader::{AsyncSource, AsyncZipArchive, AsyncZipEntry, AsyncZipVerifier};
+#[cfg(feature = "futures-io")]
+pub use async_writer::FuturesAsyncWriter;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub use async_writer::{AsyncSink, AsyncWriteBuffer, AsyncZipArchiveWriter, AsyncZipEntryWriter};
+#[cfg(feature = "zstd")]
+pub use codec::train_zstd_dictionary;
+pub use compat::{CompatIssue, CompatPolicy, CompatTarget};
 pub use crc::crc32;
-pub use errors::{Error, ErrorKind};
+pub use errors::{Error, ErrorKind, Zip64Limit};
 pub use headers::Header;
 pub use locator::*;
-pub use mode::EntryMode;
+pub use merge::{merge, CollisionPolicy};
+#[cfg(feature = "mmap")]
+pub use mmap::MmapAdvice;
+pub use mode::{DosAttributes, EntryMode, HostSystem};
+pub use plan::{ZipPlan, ZipPlanLayout, ZipPlannedEntry};
 pub use reader_at::{FileReader, RangeReader, ReaderAt};
+pub use rewrite::{rewrite, EntryRewrite};
+pub use sink::{SinkOrder, ZipSink, ZipSinkConsumer, ZipSinkSlot};
+pub use split::SplitZipWriter;
+pub use stream::{ZipStreamEntry, ZipStreamReader};
+#[cfg(feature = "_codec")]
+pub use transcode::transcode;
 pub use writer::*;
@@ -0,0 +1,102 @@
+use crate::{
+    errors::ErrorKind, Error, ReaderAt, ZipArchive, ZipArchiveWriter, RECOMMENDED_BUFFER_SIZE,
+};
+use std::collections::HashSet;
+use std::io::Write;
+
+/// What [`merge`] does with an entry whose name was already written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    /// Fail with [`ErrorKind::DuplicateName`].
+    #[default]
+    Error,
+
+    /// Keep the entry that was written first and skip the later ones.
+    Skip,
+
+    /// Replace the entry that was written first with the later one.
+    ///
+    /// The earlier entry is [removed](ZipArchiveWriter::remove) from the
+    /// central directory, but its data can't be taken back from a streaming
+    /// output and is left in place as dead space.
+    LastWins,
+}
+
+/// Copies the entries of each of the `sources`, in order, to `dest`.
+///
+/// Entries are copied raw with [`ZipArchiveWriter::copy_entry`], without
+/// decompressing them, and `dest` writes a single central directory for all
+/// of them once it's finished. Names are compared byte for byte, and the
+/// entries already in `dest` are taken into account. When a name was already
+/// written, `policy` decides the outcome, except for directories, which are
+/// only written once.
+///
+/// # Example
+///
+/// ```rust
+/// use rawzip::{CollisionPolicy, ZipArchive, ZipArchiveWriter};
+///
+/// let mut sources = Vec::new();
+/// for dirs in [["shared/", "first/"], ["shared/", "second/"]] {
+///     let mut output = Vec::new();
+///     let mut archive = ZipArchiveWriter::new(&mut output);
+///     for dir in dirs {
+///         archive.new_dir(dir).create()?;
+///     }
+///     archive.finish()?;
+///     sources.push(ZipArchive::from_slice(output)?.into_zip_archive());
+/// }
+///
+/// let mut output = Vec::new();
+/// let mut archive = ZipArchiveWriter::new(&mut output);
+/// rawzip::merge(&sources, &mut archive, CollisionPolicy::Error)?;
+/// archive.finish()?;
+///
+/// let archive = ZipArchive::from_slice(&output)?;
+/// assert_eq!(archive.entries_hint(), 3);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn merge<'a, R, W, I>(
+    sources: I,
+    dest: &mut ZipArchiveWriter<W>,
+    policy: CollisionPolicy,
+) -> Result<(), Error>
+where
+    R: ReaderAt + 'a,
+    W: Write,
+    I: IntoIterator<Item = &'a ZipArchive<R>>,
+{
+    let mut names = dest
+        .entry_names()
+        .map(<[u8]>::to_vec)
+        .collect::<HashSet<_>>();
+    let mut buffer = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+    for source in sources {
+        let mut entries = source.entries(&mut buffer);
+        while let Some(record) = entries.next_entry()? {
+            let name = record.file_path().as_bytes();
+            if !names.insert(name.to_vec()) {
+                if record.is_dir() {
+                    continue;
+                }
+
+                match policy {
+                    CollisionPolicy::Error => {
+                        return Err(Error::from(ErrorKind::DuplicateName {
+                            name: String::from_utf8_lossy(name).into_owned(),
+                        }));
+                    }
+                    CollisionPolicy::Skip => continue,
+                    CollisionPolicy::LastWins => {
+                        dest.remove(name);
+                    }
+                }
+            }
+
+            let entry = source.get_entry(record.wayfinder())?;
+            dest.copy_entry(&record, entry.reader())?;
+        }
+    }
+
+    Ok(())
+}
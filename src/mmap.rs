@@ -0,0 +1,75 @@
+use crate::{Error, ZipArchive, ZipSliceArchive};
+use memmap2::Mmap;
+
+/// How a memory-mapped archive is expected to be read, passed to
+/// [`ZipSliceArchive::advise`] so that the operating system can page it in
+/// accordingly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MmapAdvice {
+    /// No particular order, which is how the archive is mapped.
+    #[default]
+    Normal,
+    /// From start to end, like when every entry is extracted. Pages are read
+    /// ahead aggressively and may be dropped soon after they are read.
+    Sequential,
+    /// A few entries here and there, like when serving files out of a large
+    /// archive. Pages aren't read ahead.
+    Random,
+}
+
+impl ZipArchive<()> {
+    /// Parses an archive from a file that has been mapped into memory, so
+    /// that the [slice API](ZipSliceArchive) can be used without reading the
+    /// file in.
+    ///
+    /// Entries are borrowed from the mapping, and pages of the file are only
+    /// read as they are touched, which suits large archives that are read in
+    /// part.
+    ///
+    /// The mapping is created by the caller, as creating it is `unsafe`: the
+    /// file mustn't be modified or truncated while it's mapped, by this
+    /// process or any other, as the mapping reflects such changes and
+    /// reading beyond the end of a truncated file crashes the process.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{MmapAdvice, ZipArchive};
+    /// let file = std::fs::File::open("assets/test.zip")?;
+    /// // SAFETY: the file isn't modified while it's mapped
+    /// let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    /// let archive = ZipArchive::from_mmap(mmap)?;
+    /// archive.advise(MmapAdvice::Sequential)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.file_path().as_ref(), b"test.txt");
+    /// let data = archive.get_entry(entry.wayfinder())?.data();
+    /// assert_eq!(data.len() as u64, entry.compressed_size_hint());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_mmap(mmap: Mmap) -> Result<ZipSliceArchive<Mmap>, Error> {
+        ZipArchive::from_slice(mmap)
+    }
+}
+
+impl ZipSliceArchive<Mmap> {
+    /// Tells the operating system how the mapped archive is going to be
+    /// read.
+    ///
+    /// This is only a hint, and does nothing on platforms other than Unix.
+    pub fn advise(&self, advice: MmapAdvice) -> Result<(), Error> {
+        #[cfg(unix)]
+        {
+            let advice = match advice {
+                MmapAdvice::Normal => memmap2::Advice::Normal,
+                MmapAdvice::Sequential => memmap2::Advice::Sequential,
+                MmapAdvice::Random => memmap2::Advice::Random,
+            };
+            self.data.advise(advice)?;
+        }
+
+        #[cfg(not(unix))]
+        let _ = advice;
+
+        Ok(())
+    }
+}
@@ -5,6 +5,90 @@ pub(crate) const CREATOR_NTFS: u16 = 11;
 pub(crate) const CREATOR_VFAT: u16 = 14;
 pub(crate) const CREATOR_FAT: u16 = 0;
 
+/// The host system that produced an entry, stored in the upper byte of the
+/// "version made by" field.
+///
+/// The host system determines how readers interpret the external file
+/// attributes. Constants are provided for the systems that are commonly
+/// encountered, see section 4.4.2.2 of the Zip spec for the full list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HostSystem(u8);
+
+impl HostSystem {
+    pub const MSDOS: Self = Self(CREATOR_FAT as u8);
+    pub const UNIX: Self = Self(CREATOR_UNIX as u8);
+    pub const NTFS: Self = Self(CREATOR_NTFS as u8);
+    pub const VFAT: Self = Self(CREATOR_VFAT as u8);
+    pub const MACOS: Self = Self(CREATOR_MACOS as u8);
+
+    /// Creates a host system from its raw value.
+    #[inline]
+    pub const fn new(value: u8) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw value of the host system.
+    #[inline]
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+
+/// MS-DOS file attributes, stored in the low byte of the external file
+/// attributes.
+///
+/// Attributes can be combined with `|`:
+///
+/// ```rust
+/// use rawzip::DosAttributes;
+/// let attributes = DosAttributes::READ_ONLY | DosAttributes::HIDDEN;
+/// assert_eq!(attributes.as_u8(), 0x03);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DosAttributes(u8);
+
+impl DosAttributes {
+    pub const READ_ONLY: Self = Self(MSDOS_READONLY as u8);
+    pub const HIDDEN: Self = Self(0x02);
+    pub const SYSTEM: Self = Self(0x04);
+    pub const DIRECTORY: Self = Self(MSDOS_DIR as u8);
+    pub const ARCHIVE: Self = Self(0x20);
+
+    /// Creates attributes from their raw value.
+    #[inline]
+    pub const fn new(value: u8) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw value of the attributes.
+    #[inline]
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if all of the given attributes are set.
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for DosAttributes {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for DosAttributes {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
 /// File mode information for a given zip file entry.
 ///
 /// This represents Unix-style file permissions and type information.
@@ -40,7 +124,7 @@ impl EntryMode {
 /// Unix file type and permission constants
 const S_IFMT: u32 = 0o170000; // File type mask
 const S_IFSOCK: u32 = 0o140000; // Socket
-const S_IFLNK: u32 = 0o120000; // Symbolic link
+pub(crate) const S_IFLNK: u32 = 0o120000; // Symbolic link
 const S_IFREG: u32 = 0o100000; // Regular file
 const S_IFBLK: u32 = 0o060000; // Block device
 const S_IFDIR: u32 = 0o040000; // Directory
@@ -83,6 +167,19 @@ pub(crate) fn unix_mode_to_file_mode(m: u32) -> u32 {
     mode
 }
 
+/// Derives the MSDOS attributes that Info-ZIP records alongside a Unix mode:
+/// the directory bit, and the read-only bit when the owner can't write.
+pub(crate) fn unix_mode_to_msdos_attributes(m: u32, is_dir: bool) -> DosAttributes {
+    let mut attributes = DosAttributes::default();
+    if is_dir || m & S_IFMT == S_IFDIR {
+        attributes |= DosAttributes::DIRECTORY;
+    }
+    if m & 0o200 == 0 {
+        attributes |= DosAttributes::READ_ONLY;
+    }
+    attributes
+}
+
 /// Converts MSDOS attributes to file mode, following Go's zip reader logic
 pub(crate) fn msdos_mode_to_file_mode(m: u32) -> u32 {
     if m & MSDOS_DIR != 0 {
@@ -0,0 +1,200 @@
+//! A pool of threads that compresses entries in the background.
+//!
+//! Entries are compressed in memory on the workers and handed back in the
+//! order they were queued, so that the archive is laid out the same as if
+//! they had been compressed one after another.
+
+use crate::codec;
+use crate::{crc, CompressionMethod, Crc32Option, Error};
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// Data to be compressed.
+pub(crate) enum Job {
+    /// An entry's data in full.
+    Entry {
+        data: Vec<u8>,
+        method: CompressionMethod,
+        level: Option<i32>,
+        store_if_larger: bool,
+        crc32_option: Crc32Option,
+        dictionary: Option<Arc<[u8]>>,
+    },
+
+    /// A block of a deflate stream that is split across workers.
+    #[cfg(feature = "deflate")]
+    DeflateBlock {
+        data: Vec<u8>,
+        level: u32,
+        last: bool,
+    },
+}
+
+/// Data after compression.
+#[derive(Debug)]
+pub(crate) struct Compressed {
+    pub(crate) payload: Vec<u8>,
+    pub(crate) method: CompressionMethod,
+    pub(crate) crc: u32,
+    pub(crate) uncompressed_size: u64,
+}
+
+impl Job {
+    fn run(self) -> Result<Compressed, Error> {
+        match self {
+            Job::Entry {
+                data,
+                method,
+                level,
+                store_if_larger,
+                crc32_option,
+                dictionary,
+            } => {
+                let crc = match crc32_option {
+                    Crc32Option::Calculate => crc::crc32(&data),
+                    Crc32Option::Custom(crc) => crc,
+                    Crc32Option::Skip => 0,
+                };
+
+                let uncompressed_size = data.len() as u64;
+                let (payload, method) =
+                    codec::compress(data, method, level, store_if_larger, dictionary.as_deref())?;
+                Ok(Compressed {
+                    payload,
+                    method,
+                    crc,
+                    uncompressed_size,
+                })
+            }
+            #[cfg(feature = "deflate")]
+            Job::DeflateBlock { data, level, last } => Ok(Compressed {
+                payload: codec::deflate_block(&data, level, last)?,
+                method: CompressionMethod::Deflate,
+                crc: crc::crc32(&data),
+                uncompressed_size: data.len() as u64,
+            }),
+        }
+    }
+}
+
+type JobResult = (u64, Result<Compressed, Error>);
+
+/// Compresses jobs on worker threads and returns the results in the order
+/// that the jobs were submitted.
+///
+/// The workers exit once the pool is dropped and they've run out of jobs.
+#[derive(Debug)]
+pub(crate) struct CompressorPool {
+    threads: usize,
+    jobs: mpsc::Sender<(u64, Job)>,
+    results: mpsc::Receiver<JobResult>,
+    finished: BTreeMap<u64, Result<Compressed, Error>>,
+    submitted: u64,
+    returned: u64,
+}
+
+impl CompressorPool {
+    /// Spawns `threads` workers, or one per available core when zero.
+    pub(crate) fn new(threads: usize) -> Result<Self, Error> {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |x| x.get()),
+            n => n,
+        };
+
+        let (jobs, job_receiver) = mpsc::channel::<(u64, Job)>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..threads {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            thread::Builder::new()
+                .name(String::from("rawzip-compress"))
+                .spawn(move || loop {
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+
+                    let Ok((seq, job)) = job else {
+                        return;
+                    };
+
+                    if result_sender.send((seq, job.run())).is_err() {
+                        return;
+                    }
+                })?;
+        }
+
+        Ok(CompressorPool {
+            threads,
+            jobs,
+            results,
+            finished: BTreeMap::new(),
+            submitted: 0,
+            returned: 0,
+        })
+    }
+
+    /// Returns the number of jobs that have been submitted but not returned.
+    pub(crate) fn pending(&self) -> u64 {
+        self.submitted - self.returned
+    }
+
+    /// Returns the number of jobs in flight that keeps every worker busy.
+    /// Any more would only buffer data in memory.
+    pub(crate) fn capacity(&self) -> u64 {
+        2 * self.threads as u64
+    }
+
+    pub(crate) fn submit(&mut self, job: Job) -> Result<(), Error> {
+        self.jobs
+            .send((self.submitted, job))
+            .map_err(|_| worker_error())?;
+        self.submitted += 1;
+        Ok(())
+    }
+
+    /// Returns the result of the oldest job that has not been returned yet.
+    ///
+    /// When `block` is false, `None` is returned if the job is still being
+    /// compressed.
+    pub(crate) fn next(&mut self, block: bool) -> Option<Result<Compressed, Error>> {
+        if self.pending() == 0 {
+            return None;
+        }
+
+        while !self.finished.contains_key(&self.returned) {
+            let received = if block {
+                self.results.recv().map_err(|_| worker_error())
+            } else {
+                match self.results.try_recv() {
+                    Ok(result) => Ok(result),
+                    Err(mpsc::TryRecvError::Empty) => return None,
+                    Err(mpsc::TryRecvError::Disconnected) => Err(worker_error()),
+                }
+            };
+
+            match received {
+                Ok((seq, result)) => {
+                    self.finished.insert(seq, result);
+                }
+                Err(e) => {
+                    self.returned += 1;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let result = self.finished.remove(&self.returned)?;
+        self.returned += 1;
+        Some(result)
+    }
+}
+
+fn worker_error() -> Error {
+    Error::from(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "compression worker exited unexpectedly",
+    ))
+}
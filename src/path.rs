@@ -229,6 +229,11 @@ impl ZipFilePath<()> {
             result.push_str(split);
         }
 
+        // Keep directories as such
+        if s.ends_with('/') && !result.is_empty() {
+            result.push('/');
+        }
+
         result
     }
 }
@@ -390,6 +395,10 @@ mod tests {
     #[case(b"C:\\hello\\test.txt", "hello/test.txt")]
     #[case(b"C:/hello\\test.txt", "hello/test.txt")]
     #[case(b"C:/hello/test.txt", "hello/test.txt")]
+    #[case(b"dir\\", "dir/")]
+    #[case(b".\\dir\\sub\\", "dir/sub/")]
+    #[case(b"dir//sub//", "dir/sub/")]
+    #[case(b"../", "")]
     fn test_zip_path_normalized(#[case] input: &[u8], #[case] expected: &str) {
         assert_eq!(
             ZipFilePath::from_bytes(input)
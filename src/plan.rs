@@ -0,0 +1,368 @@
+use crate::{
+    crc::crc32_chunk, errors::ErrorKind, time::UtcDateTime, CompressionMethod, Error, SizePlanner,
+    ZipArchiveWriter, ZipArchiveWriterBuilder, ZipFileBuilder,
+};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+/// An entry of a [`ZipPlan`], whose CRC32 and sizes are known up front.
+#[derive(Debug, Clone)]
+pub struct ZipPlannedEntry {
+    name: String,
+    method: CompressionMethod,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    last_modified: Option<UtcDateTime>,
+    permissions: Option<u32>,
+}
+
+impl ZipPlannedEntry {
+    /// Creates an entry whose data is stored without compression.
+    pub fn stored(name: impl Into<String>, crc32: u32, size: u64) -> Self {
+        Self::precompressed(name, CompressionMethod::Store, crc32, size, size)
+    }
+
+    /// Creates an entry whose data has already been compressed with
+    /// `method`. The CRC32 and `uncompressed_size` are those of the data
+    /// before it was compressed.
+    pub fn precompressed(
+        name: impl Into<String>,
+        method: CompressionMethod,
+        crc32: u32,
+        compressed_size: u64,
+        uncompressed_size: u64,
+    ) -> Self {
+        ZipPlannedEntry {
+            name: name.into(),
+            method,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            last_modified: None,
+            permissions: None,
+        }
+    }
+
+    /// Sets the last modified time. See [`ZipFileBuilder::last_modified`].
+    #[must_use]
+    #[inline]
+    pub fn last_modified(mut self, modification_time: UtcDateTime) -> Self {
+        self.last_modified = Some(modification_time);
+        self
+    }
+
+    /// Sets the Unix permissions. See [`ZipFileBuilder::unix_permissions`].
+    #[must_use]
+    #[inline]
+    pub fn unix_permissions(mut self, permissions: u32) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// Returns the name of the entry.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the number of bytes of data that the entry is written with.
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Configures the builder the same way whether the entry is planned or
+    /// written, so that both are laid out alike.
+    fn configure<'archive, 'name, W: Write>(
+        &self,
+        builder: ZipFileBuilder<'archive, 'name, W>,
+    ) -> ZipFileBuilder<'archive, 'name, W> {
+        let mut builder = builder.compression_method(self.method).predeclare(
+            self.crc32,
+            self.compressed_size,
+            self.uncompressed_size,
+        );
+        if let Some(time) = self.last_modified {
+            builder = builder.last_modified(time);
+        }
+        if let Some(permissions) = self.permissions {
+            builder = builder.unix_permissions(permissions);
+        }
+        builder
+    }
+}
+
+/// An archive whose length is known before the first byte is written, in the
+/// manner of nginx's mod_zip.
+///
+/// The entries are listed up front with their CRC32 and sizes, so that the
+/// headers don't depend on the data, which is only read as the archive is
+/// written. This suits web servers that stream a "download all" archive
+/// with a correct `Content-Length`, from files that are stored or were
+/// compressed ahead of time.
+///
+/// # Example
+///
+/// ```rust
+/// use rawzip::{ZipPlan, ZipPlannedEntry};
+///
+/// let files: [(&str, &[u8]); 2] = [("a.txt", b"Hello"), ("b.txt", b"world!")];
+/// let mut plan = ZipPlan::new();
+/// for (name, data) in files {
+///     plan.push(ZipPlannedEntry::stored(name, rawzip::crc32(data), data.len() as u64));
+/// }
+///
+/// let content_length = plan.size()?;
+/// let output = plan.write(Vec::new(), |entry| {
+///     let (_, data) = files.iter().find(|(name, _)| *name == entry.name()).unwrap();
+///     Ok(*data)
+/// })?;
+/// assert_eq!(output.len() as u64, content_length);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ZipPlan {
+    builder: ZipArchiveWriterBuilder,
+    entries: Vec<ZipPlannedEntry>,
+}
+
+impl ZipPlan {
+    /// Creates an empty plan for an archive written with the default
+    /// options.
+    pub fn new() -> Self {
+        ZipPlan::default()
+    }
+
+    /// Creates an empty plan for an archive written with the options of the
+    /// builder.
+    pub fn with_builder(builder: ZipArchiveWriterBuilder) -> Self {
+        ZipPlan {
+            builder,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds an entry to the end of the archive.
+    pub fn push(&mut self, entry: ZipPlannedEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Returns the entries in the order they are written.
+    pub fn entries(&self) -> &[ZipPlannedEntry] {
+        &self.entries
+    }
+
+    /// Returns the exact number of bytes that [`write`](ZipPlan::write)
+    /// writes.
+    pub fn size(&self) -> Result<u64, Error> {
+        let mut planner = self.builder.build(SizePlanner::new());
+        for entry in &self.entries {
+            entry
+                .configure(planner.new_file(&entry.name))
+                .plan(entry.compressed_size, entry.uncompressed_size)?;
+        }
+        Ok(planner.finish()?.size())
+    }
+
+    /// Lays out the archive, working out where each entry's data goes and
+    /// serializing everything else, so that the archive can be written a
+    /// range at a time with [`ZipPlanLayout::write_range`].
+    ///
+    /// The entries are copied into the layout, so later changes to the plan
+    /// don't affect it.
+    pub fn layout(&self) -> Result<ZipPlanLayout, Error> {
+        let mut planner = self.builder.build(SizePlanner::recording());
+        let mut data_ranges = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let start = planner.get_ref().size();
+            let summary = entry
+                .configure(planner.new_file(&entry.name))
+                .plan(entry.compressed_size, entry.uncompressed_size)?;
+            let data_start = start + (summary.data_range().start - summary.local_header_offset());
+            data_ranges.push(data_start..data_start + entry.compressed_size);
+        }
+
+        let planner = planner.finish()?;
+        let size = planner.size();
+        let headers = planner.into_recorded();
+        let crc = headers
+            .iter()
+            .fold(0, |crc, (_, bytes)| crc32_chunk(bytes, crc));
+        Ok(ZipPlanLayout {
+            entries: self.entries.clone(),
+            data_ranges,
+            headers,
+            size,
+            etag: format!("\"{:x}-{:08x}\"", size, crc),
+        })
+    }
+
+    /// Writes the archive to `writer`, reading the data of each entry from
+    /// the reader that `data` returns for it.
+    ///
+    /// The data is written verbatim, as it's expected to be compressed
+    /// already, and its CRC32 is taken on trust. Returns an error if a reader
+    /// doesn't have the entry's compressed size worth of data, by which point
+    /// the archive is only partially written. Excess data is left unread.
+    pub fn write<W, R>(
+        &self,
+        writer: W,
+        mut data: impl FnMut(&ZipPlannedEntry) -> Result<R, Error>,
+    ) -> Result<W, Error>
+    where
+        W: Write,
+        R: Read,
+    {
+        let mut archive = self.builder.build(writer);
+        for entry in &self.entries {
+            let reader = data(entry)?;
+            write_entry(&mut archive, entry, reader)?;
+        }
+        archive.finish()
+    }
+}
+
+pub(crate) fn write_entry<W: Write>(
+    archive: &mut ZipArchiveWriter<W>,
+    entry: &ZipPlannedEntry,
+    reader: impl Read,
+) -> Result<(), Error> {
+    let mut writer = entry
+        .configure(archive.new_file(&entry.name))
+        .start_raw(entry.crc32, entry.uncompressed_size)?;
+    let copied = io::copy(&mut reader.take(entry.compressed_size), &mut writer)?;
+    if copied != entry.compressed_size {
+        return Err(Error::from(ErrorKind::InvalidSize {
+            expected: entry.compressed_size,
+            actual: copied,
+        }));
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// A [`ZipPlan`] that has been laid out, returned by [`ZipPlan::layout`].
+///
+/// Everything but the data of the entries is held in memory, so any range
+/// of the archive can be written, reading only the data that falls within
+/// it. An HTTP service can send the size and ETag up front and then answer
+/// range requests, and an interrupted upload can pick up from the last byte
+/// that was received.
+///
+/// # Example
+///
+/// ```rust
+/// use rawzip::{ZipPlan, ZipPlannedEntry};
+///
+/// let files: [(&str, &[u8]); 2] = [("a.txt", b"Hello"), ("b.txt", b"world!")];
+/// let mut plan = ZipPlan::new();
+/// for (name, data) in files {
+///     plan.push(ZipPlannedEntry::stored(name, rawzip::crc32(data), data.len() as u64));
+/// }
+/// let layout = plan.layout()?;
+/// let data = |entry: &ZipPlannedEntry, skip: u64| {
+///     let (_, data) = files.iter().find(|(name, _)| *name == entry.name()).unwrap();
+///     Ok(&data[skip as usize..])
+/// };
+///
+/// // Written in two parts, as if the transfer had been interrupted
+/// let middle = layout.size() / 2;
+/// let mut output = layout.write_range(0..middle, Vec::new(), data)?;
+/// output = layout.write_range(middle..layout.size(), output, data)?;
+/// assert_eq!(output, plan.write(Vec::new(), |entry| data(entry, 0))?);
+/// assert!(layout.etag().starts_with('"'));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZipPlanLayout {
+    entries: Vec<ZipPlannedEntry>,
+    data_ranges: Vec<Range<u64>>,
+    /// The bytes between the entries' data, by their offset.
+    headers: Vec<(u64, Vec<u8>)>,
+    size: u64,
+    etag: String,
+}
+
+impl ZipPlanLayout {
+    /// Returns the exact number of bytes in the archive.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns a strong ETag for the archive, quoted as HTTP expects.
+    ///
+    /// It's derived from the bytes of the headers, which cover the entries'
+    /// names, metadata, CRC32s, and sizes, so it changes whenever the
+    /// contents of the archive do.
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    /// Returns the entries in the order they are written.
+    pub fn entries(&self) -> &[ZipPlannedEntry] {
+        &self.entries
+    }
+
+    /// Returns the range of the archive that holds the data of the entry at
+    /// `index`.
+    pub fn data_range(&self, index: usize) -> Range<u64> {
+        self.data_ranges[index].clone()
+    }
+
+    /// Writes the bytes of the archive within `range` to `writer`.
+    ///
+    /// The data of an entry that overlaps the range is read from the reader
+    /// that `data` returns for it, given the number of bytes of the entry's
+    /// data that precede the range, which the reader is to start after.
+    /// Entries outside of the range aren't asked for. Returns an error if a
+    /// reader runs out of data early.
+    pub fn write_range<W, R>(
+        &self,
+        range: Range<u64>,
+        mut writer: W,
+        mut data: impl FnMut(&ZipPlannedEntry, u64) -> Result<R, Error>,
+    ) -> Result<W, Error>
+    where
+        W: Write,
+        R: Read,
+    {
+        let end = range.end.min(self.size);
+        let mut position = range.start.min(end);
+        let mut headers = self.headers.iter().peekable();
+        let mut entries = self.entries.iter().zip(&self.data_ranges).peekable();
+        while position < end {
+            // The bytes at `position` are either headers or an entry's data
+            while headers
+                .next_if(|(offset, bytes)| offset + bytes.len() as u64 <= position)
+                .is_some()
+            {}
+            while entries.next_if(|(_, data)| data.end <= position).is_some() {}
+
+            if let Some((offset, bytes)) = headers.peek().filter(|(offset, _)| *offset <= position)
+            {
+                let start = (position - offset) as usize;
+                let len = bytes.len().min((end - offset) as usize);
+                writer.write_all(&bytes[start..len])?;
+                position = offset + len as u64;
+                continue;
+            }
+
+            let (entry, range) = entries
+                .next_if(|(_, data)| data.start <= position)
+                .expect("bytes outside of the headers belong to an entry");
+            let skip = position - range.start;
+            let len = range.end.min(end) - position;
+            let reader = data(entry, skip)?;
+            let copied = io::copy(&mut reader.take(len), &mut writer)?;
+            if copied != len {
+                return Err(Error::from(ErrorKind::InvalidSize {
+                    expected: len,
+                    actual: copied,
+                }));
+            }
+            position += len;
+        }
+
+        Ok(writer)
+    }
+}
@@ -26,7 +26,11 @@ pub trait ReaderAt {
     fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
         let mut read = 0;
         while read < buf.len() {
-            let latest = self.read_at(&mut buf[read..], offset + (read as u64))?;
+            let latest = match self.read_at(&mut buf[read..], offset + (read as u64)) {
+                Ok(latest) => latest,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
             if latest == 0 {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::UnexpectedEof,
@@ -61,7 +65,11 @@ impl<T: ReaderAt> ReaderAtExt for T {
         size = size.min(buffer.len());
         let mut pos = 0;
         while pos < size {
-            let read = self.read_at(&mut buffer[pos..], offset + pos as u64)?;
+            let read = match self.read_at(&mut buffer[pos..], offset + pos as u64) {
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
             if read == 0 {
                 return Ok(pos);
             }
@@ -90,11 +98,23 @@ impl<T: ReaderAt> ReaderAtExt for T {
     }
 }
 
+/// A file wrapper that implements [`ReaderAt`] across platforms.
+///
+/// On this platform, the file is behind a mutex, and each read seeks to its
+/// offset and back.
 #[cfg(not(any(unix, windows)))]
 #[derive(Debug)]
 pub struct FileReader(MutexReader<std::fs::File>);
 
 /// A file wrapper that implements [`ReaderAt`] across platforms.
+///
+/// On Unix and Windows, reads are positional, with `pread` and `seek_read`
+/// respectively, so threads reading entries at once don't wait on each
+/// other. Elsewhere, the file is behind a mutex, and each read seeks to its
+/// offset and back.
+///
+/// On Windows, a positional read also moves the file's position, so seek
+/// before reading with [`std::io::Read`] after [`ReaderAt::read_at`].
 #[cfg(any(unix, windows))]
 #[derive(Debug)]
 pub struct FileReader(std::fs::File);
@@ -111,12 +131,7 @@ impl FileReader {
 impl ReaderAt for FileReader {
     #[inline]
     fn read_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
-        #[cfg(unix)]
-        return self.0.read_at(buf, offset);
-        #[cfg(windows)]
-        return self.0.seek_read(buf, offset);
-        #[cfg(not(any(unix, windows)))]
-        return self.0.read_at(buf, offset);
+        ReaderAt::read_at(&self.0, buf, offset)
     }
 }
 
@@ -139,6 +154,21 @@ impl From<std::fs::File> for FileReader {
     }
 }
 
+/// Reads with `pread` on Unix and `seek_read` on Windows, so that reads
+/// from many threads don't contend over the file's position.
+///
+/// See [`FileReader`] for the file's position on Windows.
+#[cfg(any(unix, windows))]
+impl ReaderAt for std::fs::File {
+    #[inline]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+        #[cfg(unix)]
+        return FileExt::read_at(self, buf, offset);
+        #[cfg(windows)]
+        return self.seek_read(buf, offset);
+    }
+}
+
 /// A reader that is wrapped in a mutex to allow for concurrent reads.
 #[derive(Debug)]
 pub struct MutexReader<R>(std::sync::Mutex<R>);
@@ -442,6 +472,40 @@ mod tests {
         test_reader_at_impl(&data, data.len());
     }
 
+    /// Reads different parts of the file from many threads at once.
+    #[cfg(any(unix, windows))]
+    fn test_concurrent_reads<R: ReaderAt + Sync>(reader: &R) {
+        std::thread::scope(|scope| {
+            for offset in 0..TEST_DATA.len() - 8 {
+                scope.spawn(move || {
+                    for _ in 0..100 {
+                        let mut buf = [0u8; 8];
+                        reader.read_exact_at(&mut buf, offset as u64).unwrap();
+                        assert_eq!(&buf, &TEST_DATA[offset..offset + 8]);
+                    }
+                });
+            }
+        });
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_file_implementation() {
+        let path = std::env::temp_dir().join(format!("rawzip-reader-at-{}", std::process::id()));
+        std::fs::write(&path, TEST_DATA).unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        test_reader_at_impl(&file, TEST_DATA.len());
+        test_concurrent_reads(&file);
+
+        let reader = FileReader::from(file);
+        test_reader_at_impl(&reader, TEST_DATA.len());
+        test_concurrent_reads(&reader);
+
+        drop(reader);
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_range_reader_basic() {
         let data = b"Hello, World! This is test data.";
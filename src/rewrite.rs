@@ -0,0 +1,133 @@
+#[cfg(feature = "_codec")]
+use crate::CompressionMethod;
+use crate::{
+    Error, ReaderAt, ZipArchive, ZipArchiveWriter, ZipFileHeaderRecord, RECOMMENDED_BUFFER_SIZE,
+};
+use std::io::Write;
+
+/// What [`rewrite`] does with an entry of the source archive.
+///
+/// Entries are kept as is unless told otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct EntryRewrite {
+    remove: bool,
+    name: Option<String>,
+    #[cfg(feature = "_codec")]
+    compression_method: Option<CompressionMethod>,
+}
+
+impl EntryRewrite {
+    /// Copies the entry without changes.
+    pub fn keep() -> Self {
+        EntryRewrite::default()
+    }
+
+    /// Leaves the entry out of the rewritten archive.
+    pub fn remove() -> Self {
+        EntryRewrite {
+            remove: true,
+            ..EntryRewrite::default()
+        }
+    }
+
+    /// Copies the entry under a new name. See [`ZipCopyBuilder::name`](crate::ZipCopyBuilder::name).
+    #[must_use]
+    #[inline]
+    pub fn rename(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Recompresses the entry with a different compression method. See
+    /// [`ZipCopyBuilder::compression_method`](crate::ZipCopyBuilder::compression_method).
+    #[cfg(feature = "_codec")]
+    #[must_use]
+    #[inline]
+    pub fn compression_method(mut self, compression_method: CompressionMethod) -> Self {
+        self.compression_method = Some(compression_method);
+        self
+    }
+}
+
+/// Rewrites the entries of `source` to `dest`, with `decide` choosing
+/// whether each entry is kept, removed, renamed, or recompressed.
+///
+/// This is the backbone of post-processing tools for formats built on ZIP,
+/// like stripping signatures from a JAR or moving the contents of an
+/// archive under a new prefix. Entries are streamed one at a time and, unless
+/// they are recompressed, copied without decompressing them. Their
+/// timestamps, permissions, comments, and extra fields are carried over, as
+/// with [`ZipArchiveWriter::copy_entry`].
+///
+/// The archive comment isn't copied, and `dest` isn't finished, so that
+/// more entries can be added.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::io::Write;
+/// use rawzip::EntryRewrite;
+///
+/// # let mut source = Vec::new();
+/// # let mut archive = rawzip::ZipArchiveWriter::new(&mut source);
+/// # for name in ["META-INF/CERT.SF", "com/example/Main.class"] {
+/// #     let (mut entry, config) = archive.new_file(name).start()?;
+/// #     let mut writer = config.wrap(&mut entry);
+/// #     writer.write_all(b"contents")?;
+/// #     let (_, output) = writer.finish()?;
+/// #     entry.finish(output)?;
+/// # }
+/// # archive.finish()?;
+/// let source = rawzip::ZipArchive::from_slice(&source)?.into_zip_archive();
+/// let mut output = Vec::new();
+/// let mut dest = rawzip::ZipArchiveWriter::new(&mut output);
+/// rawzip::rewrite(&source, &mut dest, |record| {
+///     let name = record.file_path().try_normalize().unwrap();
+///     if name.as_ref().starts_with("META-INF/") {
+///         EntryRewrite::remove()
+///     } else {
+///         EntryRewrite::keep().rename(format!("shaded/{}", name.as_ref()))
+///     }
+/// })?;
+/// dest.finish()?;
+///
+/// let archive = rawzip::ZipArchive::from_slice(&output)?;
+/// let entry = archive.entries().next_entry()?.unwrap();
+/// assert_eq!(entry.file_path().as_ref(), b"shaded/com/example/Main.class");
+/// assert_eq!(archive.entries_hint(), 1);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn rewrite<R, W, F>(
+    source: &ZipArchive<R>,
+    dest: &mut ZipArchiveWriter<W>,
+    mut decide: F,
+) -> Result<(), Error>
+where
+    R: ReaderAt,
+    W: Write,
+    F: FnMut(&ZipFileHeaderRecord<'_>) -> EntryRewrite,
+{
+    let mut buffer = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+    let mut entries = source.entries(&mut buffer);
+    while let Some(record) = entries.next_entry()? {
+        let rewrite = decide(&record);
+        if rewrite.remove {
+            continue;
+        }
+
+        let entry = source.get_entry(record.wayfinder())?;
+        let mut builder = dest.copy_entry_with(&record);
+        if let Some(name) = rewrite.name.as_deref() {
+            builder = builder.name(name);
+        }
+
+        #[cfg(feature = "_codec")]
+        if let Some(method) = rewrite.compression_method {
+            builder = builder.compression_method(method);
+        }
+
+        builder.copy(entry.reader())?;
+    }
+
+    Ok(())
+}
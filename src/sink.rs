@@ -0,0 +1,221 @@
+use crate::{errors::ErrorKind, plan::write_entry, Error, ZipArchiveWriter, ZipPlannedEntry};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+
+/// The order in which the consumer of a [`ZipSink`] writes entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkOrder {
+    /// Entries are written as they arrive.
+    #[default]
+    Arrival,
+    /// Entries are written in the order they were submitted or
+    /// [reserved](ZipSink::reserve), holding back those that arrive early.
+    Submission,
+}
+
+enum Message {
+    Entry {
+        index: u64,
+        entry: ZipPlannedEntry,
+        data: Vec<u8>,
+    },
+    /// A reserved slot that was dropped without an entry.
+    Skip(u64),
+}
+
+/// A handle for submitting entries to an archive from many threads, which a
+/// single consumer thread writes out.
+///
+/// Producers submit an entry's name and metadata as a [`ZipPlannedEntry`]
+/// along with its data, compressed already, so that the work of compressing
+/// is spread across the producers while the archive is written in one
+/// place. The handle is cloned for each producer.
+///
+/// The consumer finishes once every handle has been dropped, at which point
+/// [`ZipSinkConsumer::finish`] writes the central directory. Should writing
+/// an entry fail, the consumer stops, later submissions fail, and the error
+/// is returned when the consumer is joined.
+///
+/// # Example
+///
+/// ```rust
+/// use rawzip::{SinkOrder, ZipArchiveWriter, ZipPlannedEntry, ZipSink};
+///
+/// let archive = ZipArchiveWriter::new(Vec::new());
+/// let (sink, consumer) = ZipSink::spawn(archive, SinkOrder::Arrival);
+/// let producers = (0..4)
+///     .map(|i| {
+///         let sink = sink.clone();
+///         std::thread::spawn(move || {
+///             let data = format!("page {}", i).into_bytes();
+///             let entry = ZipPlannedEntry::stored(
+///                 format!("page-{}.txt", i),
+///                 rawzip::crc32(&data),
+///                 data.len() as u64,
+///             );
+///             sink.submit(entry, data)
+///         })
+///     })
+///     .collect::<Vec<_>>();
+/// drop(sink);
+///
+/// for producer in producers {
+///     producer.join().unwrap()?;
+/// }
+/// let output = consumer.finish()?;
+///
+/// let archive = rawzip::ZipArchive::from_slice(&output)?;
+/// assert_eq!(archive.entries_hint(), 4);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZipSink {
+    sender: mpsc::Sender<Message>,
+    next: Arc<AtomicU64>,
+}
+
+impl ZipSink {
+    /// Starts the consumer thread that writes submitted entries to
+    /// `archive`, returning the handle to submit them with and the consumer
+    /// to finish the archive with.
+    pub fn spawn<W>(archive: ZipArchiveWriter<W>, order: SinkOrder) -> (ZipSink, ZipSinkConsumer<W>)
+    where
+        W: Write + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let handle = std::thread::spawn(move || consume(archive, receiver, order));
+        let sink = ZipSink {
+            sender,
+            next: Arc::new(AtomicU64::new(0)),
+        };
+        (sink, ZipSinkConsumer { handle })
+    }
+
+    /// Submits an entry along with its data, which is written verbatim.
+    ///
+    /// Returns an error if the data isn't the entry's compressed size, or if
+    /// the consumer has stopped.
+    pub fn submit(&self, entry: ZipPlannedEntry, data: Vec<u8>) -> Result<(), Error> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        self.send(index, entry, data)
+    }
+
+    /// Reserves the position of an entry that is yet to be produced, when
+    /// entries are written in [submission](SinkOrder::Submission) order.
+    ///
+    /// The entries submitted after the reservation are held back until the
+    /// slot is filled or dropped.
+    pub fn reserve(&self) -> ZipSinkSlot {
+        ZipSinkSlot {
+            sink: self.clone(),
+            index: self.next.fetch_add(1, Ordering::Relaxed),
+            filled: false,
+        }
+    }
+
+    fn send(&self, index: u64, entry: ZipPlannedEntry, data: Vec<u8>) -> Result<(), Error> {
+        if data.len() as u64 != entry.compressed_size() {
+            // The position is given up, so that later entries aren't held
+            // back waiting for it
+            let _ = self.sender.send(Message::Skip(index));
+            return Err(Error::from(ErrorKind::InvalidSize {
+                expected: entry.compressed_size(),
+                actual: data.len() as u64,
+            }));
+        }
+
+        let message = Message::Entry { index, entry, data };
+        self.sender.send(message).map_err(|_| stopped())
+    }
+}
+
+/// A position in the archive reserved with [`ZipSink::reserve`].
+#[derive(Debug)]
+pub struct ZipSinkSlot {
+    sink: ZipSink,
+    index: u64,
+    filled: bool,
+}
+
+impl ZipSinkSlot {
+    /// Submits the entry that takes the reserved position.
+    ///
+    /// See [`ZipSink::submit`] for details.
+    pub fn submit(mut self, entry: ZipPlannedEntry, data: Vec<u8>) -> Result<(), Error> {
+        self.filled = true;
+        self.sink.send(self.index, entry, data)
+    }
+}
+
+impl Drop for ZipSinkSlot {
+    fn drop(&mut self) {
+        if !self.filled {
+            let _ = self.sink.sender.send(Message::Skip(self.index));
+        }
+    }
+}
+
+/// The consumer thread started by [`ZipSink::spawn`].
+#[derive(Debug)]
+pub struct ZipSinkConsumer<W> {
+    handle: JoinHandle<Result<ZipArchiveWriter<W>, Error>>,
+}
+
+impl<W: Write> ZipSinkConsumer<W> {
+    /// Waits for every [`ZipSink`] to be dropped and the entries to be
+    /// written, returning the archive so that more can be added.
+    pub fn join(self) -> Result<ZipArchiveWriter<W>, Error> {
+        match self.handle.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    /// Like [`join`](ZipSinkConsumer::join), but finishes the archive.
+    pub fn finish(self) -> Result<W, Error> {
+        self.join()?.finish()
+    }
+}
+
+fn consume<W: Write>(
+    mut archive: ZipArchiveWriter<W>,
+    receiver: mpsc::Receiver<Message>,
+    order: SinkOrder,
+) -> Result<ZipArchiveWriter<W>, Error> {
+    // Entries that arrived ahead of their turn, or `None` for skipped slots
+    let mut pending = BTreeMap::new();
+    let mut next = 0;
+    for message in receiver {
+        let (index, item) = match message {
+            Message::Entry { index, entry, data } => (index, Some((entry, data))),
+            Message::Skip(index) => (index, None),
+        };
+
+        if order == SinkOrder::Arrival {
+            if let Some((entry, data)) = item {
+                write_entry(&mut archive, &entry, data.as_slice())?;
+            }
+            continue;
+        }
+
+        pending.insert(index, item);
+        while let Some(item) = pending.remove(&next) {
+            next += 1;
+            if let Some((entry, data)) = item {
+                write_entry(&mut archive, &entry, data.as_slice())?;
+            }
+        }
+    }
+
+    Ok(archive)
+}
+
+fn stopped() -> Error {
+    Error::from(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "the sink's consumer has stopped",
+    ))
+}
@@ -0,0 +1,167 @@
+use crate::{errors::ErrorKind, DataDescriptor, Error};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The smallest volume allowed by the spec (8.5.5).
+const MIN_VOLUME_SIZE: u64 = 64 * 1024;
+
+/// The most volumes that can be numbered without ZIP64 records.
+const MAX_VOLUMES: u32 = u16::MAX as u32;
+
+/// Writes a split (multi-volume) archive to the file system.
+///
+/// Each volume is capped at a maximum size. Given a path of `archive.zip`,
+/// the volumes are named `archive.z01`, `archive.z02`, and so on, with the
+/// last volume named `archive.zip`, which holds the end of central directory.
+/// Headers are not split across volumes, so a volume may be cut short of the
+/// maximum size.
+///
+/// Pass it to [`ZipArchiveWriterBuilder::build_split`](crate::ZipArchiveWriterBuilder::build_split),
+/// so that the disk numbers and offsets written to the central directory
+/// refer to the volumes.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::io::Write;
+/// # let dir = std::env::temp_dir().join("rawzip-split-doc");
+/// # std::fs::create_dir_all(&dir)?;
+/// let path = dir.join("archive.zip");
+/// let volumes = rawzip::SplitZipWriter::create(&path, 64 * 1024)?;
+/// let mut archive = rawzip::ZipArchiveWriter::builder().build_split(volumes);
+/// let (mut entry, config) = archive.new_file("large.bin").start()?;
+/// let mut writer = config.wrap(&mut entry);
+/// writer.write_all(&vec![0u8; 100_000])?;
+/// let (_, descriptor) = writer.finish()?;
+/// entry.finish(descriptor)?;
+/// let volumes = archive.finish()?;
+///
+/// assert_eq!(volumes.volume_count(), 2);
+/// assert!(dir.join("archive.z01").exists());
+/// assert!(path.exists());
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct SplitZipWriter {
+    path: PathBuf,
+    file: Option<File>,
+    volume_size: u64,
+    disk: u32,
+    disk_offset: u64,
+    written: u64,
+}
+
+impl SplitZipWriter {
+    /// Creates the first volume at `path`, which is renamed once the volume
+    /// is full.
+    ///
+    /// The volume size must be at least 64 KiB.
+    pub fn create(path: impl AsRef<Path>, volume_size: u64) -> Result<Self, Error> {
+        if volume_size < MIN_VOLUME_SIZE {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "volume size must be at least 64 KiB".to_string(),
+            }));
+        }
+
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::create(&path)?;
+
+        // Split archives begin with the data descriptor signature (8.5.3)
+        file.write_all(&DataDescriptor::SIGNATURE.to_le_bytes())?;
+        Ok(SplitZipWriter {
+            path,
+            file: Some(file),
+            volume_size,
+            disk: 0,
+            disk_offset: 4,
+            written: 4,
+        })
+    }
+
+    /// Returns the maximum size of a volume.
+    pub fn volume_size(&self) -> u64 {
+        self.volume_size
+    }
+
+    /// Returns the number of volumes written so far.
+    pub fn volume_count(&self) -> u32 {
+        self.disk + 1
+    }
+
+    /// Returns the total number of bytes written across all volumes.
+    pub(crate) fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Returns the zero based number of the current volume and the offset
+    /// within it.
+    pub(crate) fn position(&self) -> (u32, u64) {
+        (self.disk, self.disk_offset)
+    }
+
+    /// Starts a new volume if the next `len` bytes don't fit in the current
+    /// one, so that they aren't split.
+    pub(crate) fn reserve(&mut self, len: u64) -> io::Result<()> {
+        if self.disk_offset > 0 && self.disk_offset + len > self.volume_size {
+            self.next_volume()?;
+        }
+
+        Ok(())
+    }
+
+    /// Renames the full volume to its numbered name and starts the next one.
+    fn next_volume(&mut self) -> io::Result<()> {
+        if self.disk + 1 >= MAX_VOLUMES {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "too many volumes in split archive",
+            ));
+        }
+
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        self.disk += 1;
+        std::fs::rename(
+            &self.path,
+            self.path.with_extension(format!("z{:02}", self.disk)),
+        )?;
+        self.file = Some(File::create(&self.path)?);
+        self.disk_offset = 0;
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "split volume is not open"))
+    }
+}
+
+impl Write for SplitZipWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.disk_offset >= self.volume_size {
+            self.next_volume()?;
+        }
+
+        let remaining = self.volume_size - self.disk_offset;
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let written = self.file()?.write(&buf[..len])?;
+        self.disk_offset += written as u64;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
@@ -0,0 +1,635 @@
+use crate::archive::{
+    DataDescriptor, ZipLocalFileHeaderFixed, ARCHIVE_EXTRA_DATA_SIGNATURE,
+    CENTRAL_HEADER_SIGNATURE, DIGITAL_SIGNATURE_SIGNATURE, END_OF_CENTRAL_DIR_SIGNATURE64,
+};
+use crate::errors::{Error, ErrorKind};
+use crate::extra_fields::{ExtraFieldId, ExtraFields};
+use crate::path::{RawPath, ZipFilePath};
+use crate::time::{extract_best_timestamp, DosDateTime, ZipDateTimeKind};
+use crate::utils::{le_u32, le_u64};
+use crate::{CompressionMethod, ZipVerification, RECOMMENDED_BUFFER_SIZE};
+use std::io::{self, Read};
+
+const END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x06054b50;
+
+/// Marks the start of a split archive, and of an archive that could have been
+/// split but fit in one segment (spec 8.5.3 and 8.5.4).
+const SPLIT_ARCHIVE_MARKERS: [u32; 2] = [DataDescriptor::SIGNATURE, 0x30304b50];
+
+/// The most a data descriptor takes, with its signature and 64-bit sizes,
+/// plus the signature of the header that follows it.
+const DESCRIPTOR_LOOKAHEAD: usize = 4 + 4 + 16 + 4;
+
+/// Reads the entries of an archive in the order they were written, from
+/// input that can't be seeked, like stdin, a socket, or an HTTP body.
+///
+/// The central directory, which comes last, is never consulted, so an entry
+/// is described by its local file header alone. That leaves out what only
+/// the directory records, like the entry's comment and permissions. Reading
+/// stops at the central directory.
+///
+/// When an entry is written with a data descriptor, its size isn't known
+/// until its data has been read. Its data is taken to end at the first data
+/// descriptor that records the number of bytes read so far and is followed
+/// by another header, so that compressed data doesn't have to be
+/// decompressed to find where it ends.
+///
+/// # Example
+///
+/// ```rust
+/// use rawzip::{ZipArchiveWriter, ZipStreamReader, ZipVerification};
+/// use std::io::{Read, Write};
+///
+/// // A non-seekable writer, so the entry is written with a data descriptor
+/// let mut output = Vec::new();
+/// let mut archive = ZipArchiveWriter::new(&mut output);
+/// let (mut entry, config) = archive.new_file("file.txt").start()?;
+/// let mut writer = config.wrap(&mut entry);
+/// writer.write_all(b"Hello, world!")?;
+/// let (_, descriptor) = writer.finish()?;
+/// entry.finish(descriptor)?;
+/// archive.finish()?;
+///
+/// let mut reader = ZipStreamReader::new(output.as_slice());
+/// while let Some(mut entry) = reader.next_entry()? {
+///     assert_eq!(entry.file_path().as_ref(), b"file.txt");
+///     assert!(entry.has_data_descriptor());
+///
+///     let mut contents = Vec::new();
+///     entry.read_to_end(&mut contents)?;
+///     assert_eq!(contents, b"Hello, world!");
+///
+///     entry.claim_verifier()?.valid(ZipVerification {
+///         crc: rawzip::crc32(&contents),
+///         uncompressed_size: contents.len() as u64,
+///     })?;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ZipStreamReader<R> {
+    input: StreamInput<R>,
+    /// The file name and extra fields of the current entry.
+    header: Vec<u8>,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: Read> ZipStreamReader<R> {
+    /// Creates a reader of the archive that `reader` yields, which is read
+    /// in chunks of [`RECOMMENDED_BUFFER_SIZE`].
+    pub fn new(reader: R) -> Self {
+        ZipStreamReader {
+            input: StreamInput {
+                reader,
+                buffer: vec![0u8; RECOMMENDED_BUFFER_SIZE],
+                start: 0,
+                end: 0,
+                eof: false,
+                data: None,
+            },
+            header: Vec::new(),
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Returns the next entry, or `None` once the central directory is
+    /// reached.
+    ///
+    /// Whatever is left of the previous entry's data is skipped. Running out
+    /// of input before the central directory is an error, as the archive
+    /// would have been cut short.
+    pub fn next_entry(&mut self) -> Result<Option<ZipStreamEntry<'_, R>>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        self.input.finish_entry()?;
+        self.input.fill(ZipLocalFileHeaderFixed::SIZE)?;
+        if !self.started {
+            self.started = true;
+            if self.input.available().len() >= 4
+                && SPLIT_ARCHIVE_MARKERS.contains(&le_u32(self.input.available()))
+            {
+                self.input.consume(4);
+                self.input.fill(ZipLocalFileHeaderFixed::SIZE)?;
+            }
+        }
+
+        let available = self.input.available();
+        if available.len() < 4 {
+            return Err(Error::from(ErrorKind::Eof));
+        }
+
+        let signature = le_u32(available);
+        if matches!(
+            signature,
+            CENTRAL_HEADER_SIGNATURE
+                | END_OF_CENTRAL_DIR_SIGNATURE
+                | END_OF_CENTRAL_DIR_SIGNATURE64
+                | ARCHIVE_EXTRA_DATA_SIGNATURE
+                | DIGITAL_SIGNATURE_SIGNATURE
+        ) {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        let fixed = ZipLocalFileHeaderFixed::parse(available)?;
+        self.input.consume(ZipLocalFileHeaderFixed::SIZE);
+        self.header.clear();
+        self.header.resize(fixed.variable_length(), 0);
+        self.input.read_exact(&mut self.header)?;
+
+        let (file_name, extra_field) = self.header.split_at(fixed.file_name_len as usize);
+        let mut compressed_size = u64::from(fixed.compressed_size);
+        let mut uncompressed_size = u64::from(fixed.uncompressed_size);
+        let mut zip64 = false;
+        for (field_id, mut field) in ExtraFields::new(extra_field) {
+            if field_id != ExtraFieldId::ZIP64 {
+                continue;
+            }
+
+            zip64 = true;
+            if fixed.uncompressed_size == u32::MAX {
+                if let Some(size) = field.get(..8).map(le_u64) {
+                    uncompressed_size = size;
+                    field = &field[8..];
+                }
+            }
+            if fixed.compressed_size == u32::MAX {
+                if let Some(size) = field.get(..8).map(le_u64) {
+                    compressed_size = size;
+                }
+            }
+            break;
+        }
+
+        let descriptor = fixed.flags & 0x08 != 0;
+        self.input.data = Some(EntryData {
+            // The sizes are usually left as zero when there's a data
+            // descriptor, in which case the descriptor is searched for
+            remaining: (!descriptor || compressed_size != 0).then_some(compressed_size),
+            read: 0,
+            descriptor,
+            verification: (!descriptor).then_some(ZipVerification {
+                crc: fixed.crc32,
+                uncompressed_size,
+            }),
+        });
+
+        Ok(Some(ZipStreamEntry {
+            file_path: ZipFilePath::from_bytes(file_name),
+            extra_fields: extra_field,
+            flags: fixed.flags,
+            compression_method: fixed.compression_method.as_method(),
+            last_mod_time: fixed.last_mod_time,
+            last_mod_date: fixed.last_mod_date,
+            crc32: fixed.crc32,
+            compressed_size,
+            uncompressed_size,
+            zip64,
+            input: &mut self.input,
+        }))
+    }
+
+    /// Consumes the reader, returning the underlying reader.
+    ///
+    /// Input that has been read ahead of the current position is lost.
+    pub fn into_inner(self) -> R {
+        self.input.reader
+    }
+}
+
+/// An entry yielded by [`ZipStreamReader::next_entry`], described by its
+/// local file header.
+///
+/// The entry reads its compressed data, which is to be decompressed
+/// according to its [compression method](ZipStreamEntry::compression_method).
+#[derive(Debug)]
+pub struct ZipStreamEntry<'a, R> {
+    file_path: ZipFilePath<RawPath<'a>>,
+    extra_fields: &'a [u8],
+    flags: u16,
+    compression_method: CompressionMethod,
+    last_mod_time: u16,
+    last_mod_date: u16,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    zip64: bool,
+    input: &'a mut StreamInput<R>,
+}
+
+impl<'a, R: Read> ZipStreamEntry<'a, R> {
+    /// Returns the file path in its raw form.
+    ///
+    /// See [`ZipFileHeaderRecord::file_path`](crate::ZipFileHeaderRecord::file_path)
+    /// for the care to take with it.
+    #[inline]
+    pub fn file_path(&self) -> ZipFilePath<RawPath<'a>> {
+        self.file_path
+    }
+
+    /// Returns an iterator over the extra fields of the local file header.
+    #[inline]
+    pub fn extra_fields(&self) -> ExtraFields<'a> {
+        ExtraFields::new(self.extra_fields)
+    }
+
+    /// Describes if the file is a directory.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.file_path.is_dir()
+    }
+
+    /// Returns true if the entry's CRC32 and sizes are recorded in a data
+    /// descriptor that follows its data, rather than in its header.
+    #[inline]
+    pub fn has_data_descriptor(&self) -> bool {
+        self.flags & 0x08 != 0
+    }
+
+    /// Returns true if the entry's local file header carries a ZIP64 extra
+    /// field.
+    #[inline]
+    pub fn is_zip64(&self) -> bool {
+        self.zip64
+    }
+
+    /// The compression method used to compress the data
+    #[inline]
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    /// Returns the last modification date and time.
+    ///
+    /// This method parses the extra field data to locate more accurate timestamps.
+    #[inline]
+    pub fn last_modified(&self) -> ZipDateTimeKind {
+        extract_best_timestamp(self.extra_fields(), self.last_mod_time, self.last_mod_date)
+    }
+
+    /// Returns the MS-DOS date and time stored in the header, without
+    /// consulting the extra fields.
+    #[inline]
+    pub fn dos_last_modified(&self) -> DosDateTime {
+        DosDateTime::new(self.last_mod_time, self.last_mod_date)
+    }
+
+    /// The CRC32 recorded in the header, which is usually zero when the
+    /// entry has a data descriptor.
+    #[inline]
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// The purported number of bytes of the compressed data, which is
+    /// usually zero when the entry has a data descriptor.
+    ///
+    /// **WARNING**: this number has not yet been validated, so don't trust it
+    /// to make allocation decisions.
+    #[inline]
+    pub fn compressed_size_hint(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// The purported number of bytes of the uncompressed data, which is
+    /// usually zero when the entry has a data descriptor.
+    ///
+    /// **WARNING**: this number has not yet been validated, so don't trust it
+    /// to make allocation decisions.
+    #[inline]
+    pub fn uncompressed_size_hint(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Returns the expected CRC32 and uncompressed size of the entry, to
+    /// verify its decompressed data against.
+    ///
+    /// Whatever is left of the entry's data is skipped, and its data
+    /// descriptor is read if it has one.
+    pub fn claim_verifier(self) -> Result<ZipVerification, Error> {
+        self.input.finish_entry()
+    }
+}
+
+impl<R: Read> Read for ZipStreamEntry<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read_data(buf)
+    }
+}
+
+/// The progress through an entry's data.
+#[derive(Debug)]
+struct EntryData {
+    /// The number of bytes of data left, or `None` when the data ends at
+    /// the data descriptor.
+    remaining: Option<u64>,
+    read: u64,
+    descriptor: bool,
+    /// Set once the data and data descriptor have been read.
+    verification: Option<ZipVerification>,
+}
+
+/// The input along with the bytes that have been read ahead.
+#[derive(Debug)]
+struct StreamInput<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+    eof: bool,
+    data: Option<EntryData>,
+}
+
+impl<R: Read> StreamInput<R> {
+    fn available(&self) -> &[u8] {
+        &self.buffer[self.start..self.end]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+    }
+
+    /// Reads until at least `len` bytes are available or the input ends.
+    fn fill(&mut self, len: usize) -> Result<(), Error> {
+        if self.end - self.start >= len || self.eof {
+            return Ok(());
+        }
+
+        self.buffer.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        while self.end < len {
+            let read = match self.reader.read(&mut self.buffer[self.end..]) {
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::from(e)),
+            };
+            if read == 0 {
+                self.eof = true;
+                break;
+            }
+            self.end += read;
+        }
+
+        Ok(())
+    }
+
+    /// Reads exactly enough to fill `buf`, which may be larger than the
+    /// read-ahead buffer.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let buffered = buf.len().min(self.end - self.start);
+        buf[..buffered].copy_from_slice(&self.buffer[self.start..self.start + buffered]);
+        self.consume(buffered);
+        self.reader.read_exact(&mut buf[buffered..]).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                Error::from(ErrorKind::Eof)
+            } else {
+                Error::from(e)
+            }
+        })
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(data) = self.data.as_ref() else {
+            return Ok(0);
+        };
+        if data.remaining == Some(0) {
+            if data.verification.is_none() {
+                self.read_descriptor_after_data()?;
+            }
+            return Ok(0);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let Some(remaining) = data.remaining else {
+            let read = self.read_until_descriptor(buf)?;
+            self.data.as_mut().expect("entry data").read += read as u64;
+            return Ok(read);
+        };
+
+        let read = self.read_sized(buf, remaining)?;
+        let data = self.data.as_mut().expect("entry data");
+        data.read += read as u64;
+        data.remaining = Some(remaining - read as u64);
+        if remaining == read as u64 {
+            self.read_descriptor_after_data()?;
+        }
+
+        Ok(read)
+    }
+
+    fn read_sized(&mut self, buf: &mut [u8], remaining: u64) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if self.start == self.end {
+            if self.eof {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            // Read large amounts directly, bypassing the buffer
+            if len >= self.buffer.len() {
+                let read = self.reader.read(&mut buf[..len])?;
+                if read == 0 {
+                    self.eof = true;
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                return Ok(read);
+            }
+
+            self.fill(1).map_err(to_io)?;
+            if self.start == self.end {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+
+        let len = len.min(self.end - self.start);
+        buf[..len].copy_from_slice(&self.buffer[self.start..self.start + len]);
+        self.consume(len);
+        Ok(len)
+    }
+
+    fn read_until_descriptor(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.data.as_ref().expect("entry data").read;
+        loop {
+            self.fill(DESCRIPTOR_LOOKAHEAD).map_err(to_io)?;
+            let available = self.available();
+
+            // The positions where there's enough input to rule out a
+            // descriptor starting
+            let decidable = if self.eof {
+                available.len()
+            } else {
+                available.len() + 1 - DESCRIPTOR_LOOKAHEAD
+            };
+
+            let mut len = 0;
+            while len < decidable && len < buf.len() {
+                let position = read + len as u64;
+                if let Some(descriptor) = find_descriptor(&available[len..], position, self.eof) {
+                    if len == 0 {
+                        self.consume(descriptor.len);
+                        self.finish_descriptor(descriptor);
+                        return Ok(0);
+                    }
+                    break;
+                }
+                len += 1;
+            }
+
+            if len == 0 && self.eof {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            if len > 0 {
+                buf[..len].copy_from_slice(&available[..len]);
+                self.consume(len);
+                return Ok(len);
+            }
+
+            // Wait for more input to make room to look ahead
+            let start = self.start;
+            self.buffer.copy_within(start..self.end, 0);
+            self.end -= start;
+            self.start = 0;
+            let read = self.reader.read(&mut self.buffer[self.end..])?;
+            if read == 0 {
+                self.eof = true;
+            }
+            self.end += read;
+        }
+    }
+
+    /// Reads the data descriptor, if any, of an entry whose size is known.
+    fn read_descriptor_after_data(&mut self) -> io::Result<()> {
+        let data = self.data.as_mut().expect("entry data");
+        if !data.descriptor {
+            return Ok(());
+        }
+
+        let read = data.read;
+        self.fill(DESCRIPTOR_LOOKAHEAD).map_err(to_io)?;
+        let descriptor = find_descriptor(self.available(), read, self.eof).ok_or_else(|| {
+            let actual = self.available().get(..4).map_or(0, le_u32);
+            to_io(Error::from(ErrorKind::InvalidSignature {
+                expected: DataDescriptor::SIGNATURE,
+                actual,
+            }))
+        })?;
+        self.consume(descriptor.len);
+        self.finish_descriptor(descriptor);
+        Ok(())
+    }
+
+    fn finish_descriptor(&mut self, descriptor: StreamDescriptor) {
+        let data = self.data.as_mut().expect("entry data");
+        data.remaining = Some(0);
+        data.verification = Some(ZipVerification {
+            crc: descriptor.crc,
+            uncompressed_size: descriptor.uncompressed_size,
+        });
+    }
+
+    /// Skips the rest of the current entry, returning what its data is to
+    /// be verified against.
+    fn finish_entry(&mut self) -> Result<ZipVerification, Error> {
+        let mut sink = [0u8; 1 << 12];
+        loop {
+            let Some(data) = self.data.as_ref() else {
+                return Ok(ZipVerification {
+                    crc: 0,
+                    uncompressed_size: 0,
+                });
+            };
+
+            if let (Some(0), Some(verification)) = (data.remaining, data.verification) {
+                self.data = None;
+                return Ok(verification);
+            }
+
+            match self.read_data(&mut sink) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Err(Error::from(ErrorKind::Eof))
+                }
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+    }
+}
+
+/// A data descriptor found in the input.
+#[derive(Debug, Clone, Copy)]
+struct StreamDescriptor {
+    crc: u32,
+    uncompressed_size: u64,
+    /// The number of bytes it takes.
+    len: usize,
+}
+
+/// Returns the data descriptor at the start of `data` if it records
+/// `compressed_size` and is followed by another header or the end of the
+/// input.
+///
+/// The descriptor's signature is optional, and its sizes are either 4 or 8
+/// bytes (spec 4.3.9), so each layout is tried.
+fn find_descriptor(data: &[u8], compressed_size: u64, eof: bool) -> Option<StreamDescriptor> {
+    let signed = data.len() >= 4 && le_u32(data) == DataDescriptor::SIGNATURE;
+    for (signature, wide) in [(true, false), (true, true), (false, false), (false, true)] {
+        if signature && !signed {
+            continue;
+        }
+
+        let fields = &data[if signature { 4 } else { 0 }..];
+        let size_len = if wide { 8 } else { 4 };
+        let len = (data.len() - fields.len()) + 4 + size_len * 2;
+        if data.len() < len {
+            continue;
+        }
+
+        let (recorded, uncompressed_size) = if wide {
+            (le_u64(&fields[4..12]), le_u64(&fields[12..20]))
+        } else {
+            (
+                u64::from(le_u32(&fields[4..8])),
+                u64::from(le_u32(&fields[8..12])),
+            )
+        };
+        if recorded != compressed_size {
+            continue;
+        }
+
+        let followed = match data.get(len..len + 4) {
+            Some(next) => matches!(
+                le_u32(next),
+                ZipLocalFileHeaderFixed::SIGNATURE
+                    | CENTRAL_HEADER_SIGNATURE
+                    | END_OF_CENTRAL_DIR_SIGNATURE
+                    | END_OF_CENTRAL_DIR_SIGNATURE64
+                    | ARCHIVE_EXTRA_DATA_SIGNATURE
+                    | DIGITAL_SIGNATURE_SIGNATURE
+            ),
+            None => eof && data.len() == len,
+        };
+        if followed {
+            return Some(StreamDescriptor {
+                crc: le_u32(fields),
+                uncompressed_size,
+                len,
+            });
+        }
+    }
+
+    None
+}
+
+fn to_io(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -89,9 +89,12 @@
 //! ```
 
 use crate::{
-    extra_fields::{ExtraFieldId, ExtraFields},
-    utils::{le_u16, le_u32, le_u64},
+    errors::ErrorKind,
+    extra_fields::{ExtraFieldId, ExtraFields, NtfsTimestamps, PkwareUnix},
+    utils::le_u32,
+    Error,
 };
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Represents the time zone of a timestamp.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -418,9 +421,23 @@ impl ZipDateTime<Utc> {
         }
     }
 
+    /// Creates a ZipDateTime from a Unix timestamp and the nanoseconds past
+    /// it, or `None` outside of the years 1 through 65535.
+    fn from_unix_nanos(seconds: i64, nanosecond: u32) -> Option<UtcDateTime> {
+        let min = UtcDateTime::from_components(1, 1, 1, 0, 0, 0, 0)?.to_unix();
+        let max = UtcDateTime::from_components(u16::MAX, 12, 31, 23, 59, 59, 0)?.to_unix();
+        if !(min..=max).contains(&seconds) || nanosecond > 999_999_999 {
+            return None;
+        }
+
+        let mut datetime = UtcDateTime::from_unix(seconds);
+        datetime.nanosecond = nanosecond;
+        Some(datetime)
+    }
+
     /// Creates a ZipDateTime from an NTFS timestamp (100ns ticks since 1601)
     pub(crate) fn from_ntfs(ticks: u64) -> UtcDateTime {
-        let unix_seconds = (ticks / 10_000_000).saturating_sub(NTFS_EPOCH_OFFSET) as i64;
+        let unix_seconds = (ticks / 10_000_000) as i64 - NTFS_EPOCH_OFFSET as i64;
         let (year, month, day, hour, minute, second) = unix_timestamp_to_components(unix_seconds);
         let nanosecond = ((ticks % 10_000_000) * 100) as u32;
         ZipDateTime {
@@ -435,6 +452,19 @@ impl ZipDateTime<Utc> {
         }
     }
 
+    /// Converts to an NTFS timestamp (100ns ticks since 1601).
+    ///
+    /// Dates before 1601 saturate to zero.
+    pub(crate) fn to_ntfs(self) -> u64 {
+        let seconds = self.to_unix().saturating_add(NTFS_EPOCH_OFFSET as i64);
+        let Ok(seconds) = u64::try_from(seconds) else {
+            return 0;
+        };
+        seconds
+            .saturating_mul(10_000_000)
+            .saturating_add(u64::from(self.nanosecond / 100))
+    }
+
     /// Convert to Unix timestamp (seconds since epoch).
     ///
     /// Returns the number of seconds since the Unix epoch (1970-01-01 00:00:00 UTC).
@@ -450,6 +480,178 @@ impl ZipDateTime<Utc> {
     }
 }
 
+/// Converts a `SystemTime`, keeping its nanoseconds.
+///
+/// Times before the Unix epoch are supported. Fails with
+/// [`ErrorKind::InvalidInput`] for times outside of the years 1 through
+/// 65535.
+///
+/// # Example
+///
+/// ```
+/// # use rawzip::time::UtcDateTime;
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH - Duration::from_millis(1500);
+/// let datetime = UtcDateTime::try_from(time)?;
+/// assert_eq!(datetime.to_string(), "1969-12-31T23:59:58.500000000Z");
+/// assert_eq!(SystemTime::try_from(datetime)?, time);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+impl TryFrom<SystemTime> for UtcDateTime {
+    type Error = Error;
+
+    fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+        let (seconds, nanosecond) = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (
+                i64::try_from(duration.as_secs()).ok(),
+                duration.subsec_nanos(),
+            ),
+            Err(err) => {
+                let duration = err.duration();
+                let seconds = i64::try_from(duration.as_secs()).ok().map(|x| -x);
+                match duration.subsec_nanos() {
+                    0 => (seconds, 0),
+                    nanos => (
+                        seconds.and_then(|x| x.checked_sub(1)),
+                        1_000_000_000 - nanos,
+                    ),
+                }
+            }
+        };
+
+        seconds
+            .and_then(|seconds| UtcDateTime::from_unix_nanos(seconds, nanosecond))
+            .ok_or_else(out_of_range)
+    }
+}
+
+/// Converts to a `SystemTime`.
+///
+/// Fails with [`ErrorKind::InvalidInput`] when the platform's `SystemTime`
+/// can't represent the time.
+impl TryFrom<UtcDateTime> for SystemTime {
+    type Error = Error;
+
+    fn try_from(datetime: UtcDateTime) -> Result<Self, Self::Error> {
+        let seconds = datetime.to_unix();
+        let nanos = Duration::from_nanos(u64::from(datetime.nanosecond()));
+        let time = if seconds >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(seconds.unsigned_abs()))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+        };
+
+        time.and_then(|time| time.checked_add(nanos))
+            .ok_or_else(out_of_range)
+    }
+}
+
+/// Converts to a chrono `DateTime<Utc>`, which covers every year that a
+/// `UtcDateTime` can hold.
+#[cfg(feature = "chrono")]
+impl From<UtcDateTime> for chrono::DateTime<chrono::Utc> {
+    fn from(datetime: UtcDateTime) -> Self {
+        chrono::NaiveDate::from_ymd_opt(
+            i32::from(datetime.year),
+            u32::from(datetime.month),
+            u32::from(datetime.day),
+        )
+        .and_then(|date| {
+            date.and_hms_nano_opt(
+                u32::from(datetime.hour),
+                u32::from(datetime.minute),
+                u32::from(datetime.second),
+                datetime.nanosecond,
+            )
+        })
+        .expect("components are valid")
+        .and_utc()
+    }
+}
+
+/// Converts a chrono `DateTime<Utc>`, keeping its nanoseconds.
+///
+/// A leap second is folded into the second before it. Fails with
+/// [`ErrorKind::InvalidInput`] for times outside of the years 1 through
+/// 65535.
+///
+/// # Example
+///
+/// ```
+/// # use rawzip::time::UtcDateTime;
+/// let time = chrono::DateTime::from_timestamp(1_700_000_000, 5).unwrap();
+/// let datetime = UtcDateTime::try_from(time)?;
+/// assert_eq!(datetime.to_string(), "2023-11-14T22:13:20.000000005Z");
+/// assert_eq!(chrono::DateTime::from(datetime), time);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::DateTime<chrono::Utc>> for UtcDateTime {
+    type Error = Error;
+
+    fn try_from(time: chrono::DateTime<chrono::Utc>) -> Result<Self, Self::Error> {
+        let nanosecond = time.timestamp_subsec_nanos().min(999_999_999);
+        UtcDateTime::from_unix_nanos(time.timestamp(), nanosecond).ok_or_else(out_of_range)
+    }
+}
+
+/// Converts to a time `OffsetDateTime` in UTC.
+///
+/// Fails with [`ErrorKind::InvalidInput`] for years past 9999, unless the
+/// time crate's `large-dates` feature is enabled.
+#[cfg(feature = "time")]
+impl TryFrom<UtcDateTime> for ::time::OffsetDateTime {
+    type Error = Error;
+
+    fn try_from(datetime: UtcDateTime) -> Result<Self, Self::Error> {
+        let month = ::time::Month::try_from(datetime.month).map_err(|_| out_of_range())?;
+        ::time::Date::from_calendar_date(i32::from(datetime.year), month, datetime.day)
+            .and_then(|date| {
+                date.with_hms_nano(
+                    datetime.hour,
+                    datetime.minute,
+                    datetime.second,
+                    datetime.nanosecond,
+                )
+            })
+            .map(|datetime| datetime.assume_utc())
+            .map_err(|_| out_of_range())
+    }
+}
+
+/// Converts a time `OffsetDateTime` to UTC, keeping its nanoseconds.
+///
+/// Fails with [`ErrorKind::InvalidInput`] for times outside of the years 1
+/// through 65535.
+///
+/// # Example
+///
+/// ```
+/// # use rawzip::time::UtcDateTime;
+/// let time = time::OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_000_000_005)?;
+/// let datetime = UtcDateTime::try_from(time)?;
+/// assert_eq!(datetime.to_string(), "2023-11-14T22:13:20.000000005Z");
+/// assert_eq!(time::OffsetDateTime::try_from(datetime)?, time);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "time")]
+impl TryFrom<::time::OffsetDateTime> for UtcDateTime {
+    type Error = Error;
+
+    fn try_from(time: ::time::OffsetDateTime) -> Result<Self, Self::Error> {
+        UtcDateTime::from_unix_nanos(time.unix_timestamp(), time.nanosecond())
+            .ok_or_else(out_of_range)
+    }
+}
+
+/// The error for times that can't be converted.
+fn out_of_range() -> Error {
+    Error::from(ErrorKind::InvalidInput {
+        msg: "time is out of range".to_string(),
+    })
+}
+
 impl ZipDateTime<Local> {
     /// Creates a ZipDateTime from a DosDateTime
     pub(crate) fn from_dos(dos: DosDateTime) -> LocalDateTime {
@@ -526,6 +728,18 @@ impl DosDateTime {
         raw_second.min(58)
     }
 
+    /// Returns the packed time word, as stored in the headers.
+    #[must_use]
+    pub const fn packed_time(&self) -> u16 {
+        self.time
+    }
+
+    /// Returns the packed date word, as stored in the headers.
+    #[must_use]
+    pub const fn packed_date(&self) -> u16 {
+        self.date
+    }
+
     /// Returns the packed time and date components as (time, date).
     #[must_use]
     pub(crate) const fn into_parts(self) -> (u16, u16) {
@@ -581,6 +795,11 @@ pub(crate) fn extract_best_timestamp(
                     last_timestamp = Some(ZipDateTimeKind::Utc(timestamp));
                 }
             }
+            ExtraFieldId::UNIX => {
+                if let Some(field) = PkwareUnix::parse(field_data) {
+                    last_timestamp = Some(ZipDateTimeKind::Utc(field.modified()));
+                }
+            }
             _ => {}
         }
     }
@@ -595,31 +814,7 @@ pub(crate) fn extract_best_timestamp(
 
 /// Parses NTFS timestamp extra field (0x000a)
 fn parse_ntfs_timestamp(data: &[u8]) -> Option<UtcDateTime> {
-    if data.len() < 32 {
-        return None;
-    }
-
-    // NTFS extra field format:
-    // 4 bytes: reserved (usually 0)
-    // 2 bytes: attribute tag (0x0001 for timestamps)
-    // 2 bytes: attribute size (24 bytes for 3 timestamps)
-    // 8 bytes: modification time
-    // 8 bytes: access time
-    // 8 bytes: creation time
-
-    let tag = le_u16(&data[4..6]);
-    if tag != 0x0001 {
-        return None;
-    }
-
-    let size = le_u16(&data[6..8]) as usize;
-    if size < 24 || data.len() < 8 + size {
-        return None;
-    }
-
-    // Extract modification time (first 8 bytes of timestamp data)
-    let mtime_ticks = le_u64(&data[8..16]);
-    Some(UtcDateTime::from_ntfs(mtime_ticks))
+    NtfsTimestamps::parse(data).map(|times| times.modified())
 }
 
 /// Parses Extended Timestamp extra field (0x5455)
@@ -659,14 +854,10 @@ fn parse_unix_timestamp(data: &[u8]) -> Option<UtcDateTime> {
 fn unix_timestamp_to_components(timestamp: i64) -> (u16, u8, u8, u8, u8, u8) {
     const SECONDS_PER_DAY: i64 = 86400;
 
-    // Break timestamp into days and seconds within day
-    let total_days = timestamp / SECONDS_PER_DAY;
-    let mut seconds_in_day = timestamp % SECONDS_PER_DAY;
-
-    // Handle negative remainder for negative timestamps
-    if seconds_in_day < 0 {
-        seconds_in_day += SECONDS_PER_DAY;
-    }
+    // Break timestamp into days and seconds within day, rounding the days
+    // down for negative timestamps
+    let total_days = timestamp.div_euclid(SECONDS_PER_DAY);
+    let seconds_in_day = timestamp.rem_euclid(SECONDS_PER_DAY);
 
     // Convert seconds within day to H:M:S
     let hour = (seconds_in_day / 3600) as u8;
@@ -1015,6 +1206,68 @@ mod tests {
         assert_eq!(datetime.to_unix(), negative_timestamp);
     }
 
+    #[test]
+    fn test_system_time_conversion() {
+        let time = UNIX_EPOCH - Duration::from_secs(1);
+        let datetime = UtcDateTime::try_from(time).unwrap();
+        assert_eq!(datetime, utc_from_components(1969, 12, 31, 23, 59, 59, 0));
+        assert_eq!(SystemTime::try_from(datetime).unwrap(), time);
+
+        let time = UNIX_EPOCH - Duration::new(86_400 * 365, 250_000_000);
+        let datetime = UtcDateTime::try_from(time).unwrap();
+        assert_eq!(
+            datetime,
+            utc_from_components(1968, 12, 31, 23, 59, 59, 750_000_000)
+        );
+        assert_eq!(SystemTime::try_from(datetime).unwrap(), time);
+
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let datetime = UtcDateTime::try_from(time).unwrap();
+        assert_eq!(
+            datetime,
+            utc_from_components(2023, 11, 14, 22, 13, 20, 123_456_789)
+        );
+        assert_eq!(SystemTime::try_from(datetime).unwrap(), time);
+
+        let min = utc_from_components(1, 1, 1, 0, 0, 0, 0);
+        if let Ok(time) = SystemTime::try_from(min) {
+            assert_eq!(UtcDateTime::try_from(time).unwrap(), min);
+            assert!(UtcDateTime::try_from(time - Duration::from_nanos(1)).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_chrono_conversion() {
+        let datetime = utc_from_components(1969, 12, 31, 23, 59, 58, 500_000_000);
+        let time = chrono::DateTime::<chrono::Utc>::from(datetime);
+        assert_eq!(time.timestamp_millis(), -1500);
+        assert_eq!(UtcDateTime::try_from(time).unwrap(), datetime);
+
+        let max = utc_from_components(u16::MAX, 12, 31, 23, 59, 59, 999_999_999);
+        let time = chrono::DateTime::<chrono::Utc>::from(max);
+        assert_eq!(UtcDateTime::try_from(time).unwrap(), max);
+        let time = time + chrono::Duration::nanoseconds(1);
+        assert!(UtcDateTime::try_from(time).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn test_time_conversion() {
+        let datetime = utc_from_components(1969, 12, 31, 23, 59, 58, 500_000_000);
+        let time = ::time::OffsetDateTime::try_from(datetime).unwrap();
+        assert_eq!(time.unix_timestamp_nanos(), -1_500_000_000);
+        assert_eq!(UtcDateTime::try_from(time).unwrap(), datetime);
+
+        let offset = ::time::UtcOffset::from_hms(2, 0, 0).unwrap();
+        let time = time.to_offset(offset);
+        assert_eq!(time.hour(), 1);
+        assert_eq!(UtcDateTime::try_from(time).unwrap(), datetime);
+
+        let max = utc_from_components(u16::MAX, 12, 31, 23, 59, 59, 0);
+        assert!(::time::OffsetDateTime::try_from(max).is_err());
+    }
+
     #[test]
     fn test_days_from_civil() {
         // Test Unix epoch
@@ -1179,15 +1432,14 @@ mod property_tests {
 
         // Convert NTFS ticks to Unix timestamp for jiff
         // NTFS ticks are 100-nanosecond intervals since 1601-01-01
-        let unix_seconds = (ntfs_ticks / 10_000_000).saturating_sub(NTFS_EPOCH_OFFSET);
+        let unix_seconds = (ntfs_ticks / 10_000_000) as i64 - NTFS_EPOCH_OFFSET as i64;
         let nanoseconds = ((ntfs_ticks % 10_000_000) * 100) as u32;
 
-        if unix_seconds > u32::MAX as u64 {
+        if unix_seconds > u32::MAX as i64 {
             return;
         }
 
-        let Ok(jiff_timestamp) = jiff::Timestamp::new(unix_seconds as i64, nanoseconds as i32)
-        else {
+        let Ok(jiff_timestamp) = jiff::Timestamp::new(unix_seconds, nanoseconds as i32) else {
             return;
         };
 
@@ -1203,6 +1455,15 @@ mod property_tests {
         assert_eq!(zip_datetime.nanosecond(), nanoseconds, "nanosecond");
     }
 
+    /// Property test: NTFS timestamps after the Unix epoch round trip
+    #[quickcheck]
+    fn prop_ntfs_timestamp_roundtrip(ntfs_ticks: u64) {
+        let ntfs_ticks = ntfs_ticks % (u64::from(u32::MAX) * 10_000_000);
+        let ntfs_ticks = ntfs_ticks + NTFS_EPOCH_OFFSET * 10_000_000;
+        let zip_datetime = UtcDateTime::from_ntfs(ntfs_ticks);
+        assert_eq!(zip_datetime.to_ntfs(), ntfs_ticks);
+    }
+
     /// Property test: DOS timestamp conversion should always produce valid jiff datetimes
     #[quickcheck]
     fn prop_dos_timestamp_always_valid(dos_time: u16, dos_date: u16) {
@@ -0,0 +1,78 @@
+use crate::{
+    CompressionMethod, Error, ReaderAt, ZipArchive, ZipArchiveWriter, ZipFileHeaderRecord,
+    RECOMMENDED_BUFFER_SIZE,
+};
+use std::io::Write;
+
+/// Rewrites the entries of `source` to `dest`, recompressing each with the
+/// compression method chosen by `method_map`.
+///
+/// Entries are streamed one at a time: each is decompressed with the
+/// built-in codecs, verified against its CRC32, and compressed again, so
+/// memory use doesn't grow with the size of the entries. Names, timestamps,
+/// permissions, comments, and extra fields are carried over, as with
+/// [`ZipArchiveWriter::copy_entry`].
+///
+/// Entries whose method doesn't change are copied without recompressing
+/// them, as are directories and encrypted entries. Transcoding an entry from
+/// or to a method without a built-in codec returns an error. Zstd entries
+/// are decompressed with the source's
+/// [dictionary](ZipArchive::zstd_dictionary), if it has one.
+///
+/// The archive comment isn't copied, and `dest` isn't finished, so that
+/// more entries can be added.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::io::{Cursor, Write};
+/// use rawzip::CompressionMethod;
+///
+/// # let mut source = Vec::new();
+/// # let mut archive = rawzip::ZipArchiveWriter::new(&mut source);
+/// # let mut writer = archive.new_file("file.txt").start_deflate(6)?;
+/// # writer.write_all(b"Hello, world!")?;
+/// # writer.finish()?;
+/// # archive.finish()?;
+/// let source = rawzip::ZipArchive::from_slice(&source)?.into_zip_archive();
+/// let mut output = Vec::new();
+/// let mut dest = rawzip::ZipArchiveWriter::new(&mut output);
+/// rawzip::transcode(&source, &mut dest, |record| match record.compression_method() {
+///     CompressionMethod::Deflate => CompressionMethod::Store,
+///     method => method,
+/// })?;
+/// dest.finish()?;
+///
+/// let archive = rawzip::ZipArchive::from_slice(&output)?;
+/// let entry = archive.entries().next_entry()?.unwrap();
+/// assert_eq!(entry.compression_method(), CompressionMethod::Store);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn transcode<R, W, F>(
+    source: &ZipArchive<R>,
+    dest: &mut ZipArchiveWriter<W>,
+    mut method_map: F,
+) -> Result<(), Error>
+where
+    R: ReaderAt,
+    W: Write,
+    F: FnMut(&ZipFileHeaderRecord<'_>) -> CompressionMethod,
+{
+    let mut buffer = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+    #[cfg(feature = "zstd")]
+    let dictionary = source.zstd_dictionary(&mut buffer)?;
+    let mut entries = source.entries(&mut buffer);
+    while let Some(record) = entries.next_entry()? {
+        let entry = source.get_entry(record.wayfinder())?;
+        let method = method_map(&record);
+        let builder = dest.copy_entry_with(&record).compression_method(method);
+        #[cfg(feature = "zstd")]
+        let builder = match dictionary.as_deref() {
+            Some(dictionary) => builder.zstd_dictionary(dictionary),
+            None => builder,
+        };
+        builder.copy(entry.reader())?;
+    }
+
+    Ok(())
+}
@@ -1,62 +1,602 @@
+use crate::SplitZipWriter;
 use crate::{
+    compat::{CompatIssue, CompatPolicy, CompatTarget},
     crc,
-    errors::ErrorKind,
-    extra_fields::{ExtraFieldId, ExtraFieldsContainer},
-    mode::CREATOR_UNIX,
-    path::{NormalizedPath, ZipFilePath},
+    errors::{ErrorKind, Zip64Limit},
+    extra_fields::{ExtraFieldId, ExtraFields, ExtraFieldsContainer, NtfsTimestamps},
+    mode::{unix_mode_to_msdos_attributes, DosAttributes, HostSystem, CREATOR_UNIX},
+    path::ZipFilePath,
     time::{DosDateTime, UtcDateTime},
-    CompressionMethod, DataDescriptor, Error, Header, ZipFileHeaderFixed, ZipLocalFileHeaderFixed,
-    CENTRAL_HEADER_SIGNATURE, END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE, END_OF_CENTRAL_DIR_SIGNATURE64,
-    END_OF_CENTRAL_DIR_SIGNAUTRE_BYTES,
+    utils::{le_u16, le_u64},
+    CompressionMethod, CompressionMethodId, DataDescriptor, Error, FileReader, Header, RangeReader,
+    ZipFileHeaderFixed, ZipFileHeaderRecord, ZipLocalFileHeaderFixed, ZipLocator,
+    ARCHIVE_EXTRA_DATA_SIGNATURE, CENTRAL_HEADER_SIGNATURE, DIGITAL_SIGNATURE_SIGNATURE,
+    END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE, END_OF_CENTRAL_DIR_SIGNATURE64,
+    END_OF_CENTRAL_DIR_SIGNAUTRE_BYTES, RECOMMENDED_BUFFER_SIZE,
 };
-use std::io::{self, Write};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "_codec")]
+use crate::codec::{self, Decoder, Encoder};
+#[cfg(any(feature = "aes", feature = "zipcrypto"))]
+use crate::encryption::{Encryption, Encryptor};
+#[cfg(feature = "_codec")]
+use crate::parallel::CompressorPool;
+#[cfg(feature = "deflate")]
+use crate::parallel::Job;
+#[cfg(feature = "_codec")]
+use std::collections::VecDeque;
+
+mod copy_builder;
+mod dir_builder;
+mod file_builder;
+mod setters;
+mod symlink_builder;
+
+pub use copy_builder::ZipCopyBuilder;
+pub use dir_builder::ZipDirBuilder;
+pub use file_builder::ZipFileBuilder;
+pub use symlink_builder::ZipSymlinkBuilder;
 
 // ZIP64 constants
 const ZIP64_VERSION_NEEDED: u16 = 45; // 4.5
+const INFO_ZIP_VERSION_MADE_BY: u16 = 30; // 3.0, as written by zip 3.0
 const ZIP64_EOCD_SIZE: usize = 56;
+const ZIP64_EOCD_LOCATOR_SIZE: usize = 20;
+const EOCD_SIZE: usize = 22;
 
 // General purpose bit flags
+const FLAG_ENCRYPTED: u16 = 0x01; // bit 0: entry is encrypted
+const FLAG_LZMA_END_MARKER: u16 = 0x02; // bit 1: LZMA stream has an end marker
+const FLAG_COMPRESSION_OPTIONS: u16 = 0x06; // bits 1-2: method specific options
 const FLAG_DATA_DESCRIPTOR: u16 = 0x08; // bit 3: data descriptor present
 const FLAG_UTF8_ENCODING: u16 = 0x800; // bit 11: UTF-8 encoding flag (EFS)
 
 // ZIP64 thresholds - when to switch to ZIP64 format
-const ZIP64_THRESHOLD_FILE_SIZE: u64 = u32::MAX as u64;
-const ZIP64_THRESHOLD_OFFSET: u64 = u32::MAX as u64;
+const ZIP64_THRESHOLD: u64 = u32::MAX as u64; // sizes and offsets
 const ZIP64_THRESHOLD_ENTRIES: usize = u16::MAX as usize;
+const ZIP64_THRESHOLD_DISKS: u32 = u16::MAX as u32;
 
 #[derive(Debug)]
 struct CountWriter<W> {
     writer: W,
     count: u64,
+    splitter: Option<Splitter<W>>,
+    /// Bytes counted but not yet written to `writer`, up to `buffer_size`.
+    buffer: Vec<u8>,
+    buffer_size: usize,
+}
+
+/// Seeks the output of a writer that was built with
+/// [`ZipArchiveWriterBuilder::build_seekable`].
+///
+/// A function pointer is stored rather than requiring `W: Seek` so that the
+/// rest of the writer's API is oblivious to whether the output is seekable.
+struct Seeker<W>(fn(&mut W, SeekFrom) -> io::Result<u64>);
+
+impl<W> Clone for Seeker<W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<W> Copy for Seeker<W> {}
+
+impl<W> std::fmt::Debug for Seeker<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Seeker")
+    }
+}
+
+fn seek_writer<W: Seek>(writer: &mut W, pos: SeekFrom) -> io::Result<u64> {
+    writer.seek(pos)
+}
+
+fn truncate_file(file: &mut File, len: u64) -> io::Result<()> {
+    file.set_len(len)
+}
+
+/// Splits the output of a writer that was built with
+/// [`ZipArchiveWriterBuilder::build_split`] into volumes.
+struct Splitter<W> {
+    position: fn(&W) -> (u32, u64),
+    reserve: fn(&mut W, u64) -> io::Result<()>,
+}
+
+impl<W> Clone for Splitter<W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<W> Copy for Splitter<W> {}
+
+impl<W> std::fmt::Debug for Splitter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Splitter")
+    }
 }
 
 impl<W> CountWriter<W> {
     fn new(writer: W, count: u64) -> Self {
-        CountWriter { writer, count }
+        CountWriter {
+            writer,
+            count,
+            splitter: None,
+            buffer: Vec::new(),
+            buffer_size: 0,
+        }
     }
 
     fn count(&self) -> u64 {
         self.count
     }
+
+    /// Returns the volume number and the offset within it of the next byte
+    /// to be written.
+    fn position(&self) -> (u32, u64) {
+        match self.splitter {
+            Some(splitter) => (splitter.position)(&self.writer),
+            None => (0, self.count),
+        }
+    }
+
+    /// Keeps the next `len` bytes within a single volume of a split archive,
+    /// as headers must not span volumes.
+    fn reserve(&mut self, len: u64) -> io::Result<()> {
+        match self.splitter {
+            Some(splitter) => (splitter.reserve)(&mut self.writer, len),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes out the buffered bytes, keeping those that couldn't be written
+    /// should the writer fail.
+    fn drain(&mut self) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buffer.len() {
+                break Ok(());
+            }
+            match self.writer.write(&self.buffer[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buffer.drain(..written);
+        result
+    }
+
+    /// Returns the underlying writer once the buffered bytes are written
+    /// out, for writes that bypass the count or seek.
+    fn inner_mut(&mut self) -> io::Result<&mut W>
+    where
+        W: Write,
+    {
+        self.drain()?;
+        Ok(&mut self.writer)
+    }
 }
 
 impl<W: Write> Write for CountWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let bytes_written = self.writer.write(buf)?;
+        let bytes_written = if self.buffer_size == 0 {
+            self.writer.write(buf)?
+        } else {
+            if self.buffer.len() + buf.len() > self.buffer_size {
+                self.drain()?;
+            }
+            if buf.len() >= self.buffer_size {
+                self.writer.write(buf)?
+            } else {
+                self.buffer.extend_from_slice(buf);
+                buf.len()
+            }
+        };
         self.count += bytes_written as u64;
         Ok(bytes_written)
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
         self.writer.flush()
     }
 }
 
+/// Catches archives that are dropped without being finished, which leaves
+/// them without a central directory.
+///
+/// It is armed whenever an entry is completed and disarmed while the next
+/// one is being written, so that an archive abandoned because writing an
+/// entry failed isn't mistaken for a forgotten `finish`. Only debug builds
+/// assert on it, so that the bug surfaces in tests rather than in the
+/// extractors of whoever receives the archive.
+#[derive(Debug, Default)]
+struct FinishGuard {
+    armed: bool,
+}
+
+impl Drop for FinishGuard {
+    fn drop(&mut self) {
+        if self.armed && !std::thread::panicking() {
+            debug_assert!(
+                false,
+                "ZipArchiveWriter dropped without calling finish, leaving the archive without a central directory; call into_inner to abandon it deliberately"
+            );
+        }
+    }
+}
+
+/// The callback given to [`ZipArchiveWriter::on_progress`] and the running
+/// totals that it is handed.
+struct Progress {
+    callback: Box<dyn FnMut(&ZipProgress) + Send>,
+    /// Uncompressed bytes taken in for the current entry, which is shared
+    /// with its [`ZipDataWriter`] as the data writer is not tied to the
+    /// archive.
+    consumed: Arc<AtomicU64>,
+    entries: u64,
+}
+
+impl std::fmt::Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("consumed", &self.consumed)
+            .field("entries", &self.entries)
+            .finish_non_exhaustive()
+    }
+}
+
+type HeaderCallback = dyn FnMut(&mut ZipHeaderFields<'_, '_>) -> Result<(), Error> + Send;
+
+/// The callback given to [`ZipArchiveWriter::on_header`].
+struct HeaderHook(Box<HeaderCallback>);
+
+impl std::fmt::Debug for HeaderHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HeaderHook(..)")
+    }
+}
+
+type SignerCallback = dyn FnMut(&[u8]) -> Result<Vec<u8>, Error> + Send;
+
+/// The callback given to [`ZipArchiveWriter::sign_directory`].
+struct DirectorySigner(Box<SignerCallback>);
+
+impl std::fmt::Debug for DirectorySigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DirectorySigner(..)")
+    }
+}
+
+/// A snapshot of the progress of an archive being written, passed to the
+/// callback given to [`ZipArchiveWriter::on_progress`].
+#[derive(Debug, Clone, Copy)]
+pub struct ZipProgress {
+    entries_completed: u64,
+    entry_consumed: u64,
+    entry_produced: u64,
+    entry_completed: bool,
+    stream_offset: u64,
+}
+
+impl ZipProgress {
+    /// Returns the number of entries completed since the callback was set.
+    pub fn entries_completed(&self) -> u64 {
+        self.entries_completed
+    }
+
+    /// Returns the number of uncompressed bytes of the current entry that
+    /// have been taken in so far.
+    ///
+    /// Only data written through a [`ZipDataWriter`] or the writers of the
+    /// built-in codecs is seen before the entry is completed. Otherwise, this
+    /// is zero until the entry is completed, when it is the entry's
+    /// uncompressed size.
+    pub fn entry_consumed(&self) -> u64 {
+        self.entry_consumed
+    }
+
+    /// Returns the number of compressed bytes of the current entry that have
+    /// been written to the archive so far.
+    pub fn entry_produced(&self) -> u64 {
+        self.entry_produced
+    }
+
+    /// Returns true if this update marks the completion of the current entry.
+    pub fn entry_completed(&self) -> bool {
+        self.entry_completed
+    }
+
+    /// Returns the offset in the output stream. See
+    /// [`ZipArchiveWriter::stream_offset`].
+    pub fn stream_offset(&self) -> u64 {
+        self.stream_offset
+    }
+}
+
+/// The metadata of an entry that has already been written, returned by
+/// [`ZipArchiveWriter::edit`].
+///
+/// Changes are made to the entry's central directory record.
+#[derive(Debug)]
+pub struct ZipEntryEdit<'a> {
+    header: &'a mut FileHeader,
+}
+
+impl ZipEntryEdit<'_> {
+    /// Sets the modification time.
+    ///
+    /// The MS-DOS time is clamped to the range that it can represent, and
+    /// the time is also written to the extended timestamp, NTFS, and Unix
+    /// extra fields that the entry already has. No fields are added.
+    pub fn set_last_modified(&mut self, time: UtcDateTime) {
+        (self.header.last_mod_time, self.header.last_mod_date) =
+            DosDateTime::from(&time).into_parts();
+        self.header
+            .extra_fields
+            .for_each_field_mut(|id, data| set_extra_field_mtime(id, data, &time));
+        self.header.edited_last_modified = Some(time);
+    }
+
+    /// Sets the Unix permissions and marks the entry as made on Unix, so that
+    /// extractors apply them. See [`ZipFileBuilder::unix_permissions`].
+    ///
+    /// The MS-DOS attributes in the low bits of the external attributes are
+    /// kept.
+    pub fn set_unix_permissions(&mut self, permissions: u32) {
+        self.header.external_attrs = (permissions << 16) | (self.header.external_attrs & 0xFFFF);
+        self.header.version_made_by = (CREATOR_UNIX << 8) | (self.header.version_made_by & 0xFF);
+    }
+
+    /// Sets the comment, which is written as given.
+    ///
+    /// Returns an error if the comment is longer than 65,535 bytes.
+    pub fn set_comment(&mut self, comment: &str) -> Result<(), Error> {
+        if comment.len() > u16::MAX as usize {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "file comment too long".to_string(),
+            }));
+        }
+
+        self.header.comment = comment.as_bytes().to_vec();
+        Ok(())
+    }
+}
+
+/// Overwrites the modification time held by an extra field, for the fields
+/// that hold one.
+fn set_extra_field_mtime(id: ExtraFieldId, data: &mut [u8], time: &UtcDateTime) {
+    let seconds = (time.to_unix().max(0) as u32).to_le_bytes();
+    match id {
+        ExtraFieldId::EXTENDED_TIMESTAMP if data.len() >= 5 && data[0] & 0x01 != 0 => {
+            data[1..5].copy_from_slice(&seconds);
+        }
+        ExtraFieldId::INFO_ZIP_UNIX_ORIGINAL | ExtraFieldId::UNIX if data.len() >= 8 => {
+            data[4..8].copy_from_slice(&seconds);
+        }
+        ExtraFieldId::NTFS if data.len() >= 4 => {
+            // Attributes of a 2 byte tag and size each follow 4 reserved bytes
+            let mut pos = 4;
+            while pos + 4 <= data.len() {
+                let tag = le_u16(&data[pos..pos + 2]);
+                let size = usize::from(le_u16(&data[pos + 2..pos + 4]));
+                if tag == 0x0001 && size >= 24 && pos + 12 <= data.len() {
+                    data[pos + 4..pos + 12].copy_from_slice(&time.to_ntfs().to_le_bytes());
+                    return;
+                }
+                pos += 4 + size;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The header fields of an entry that is about to be written, passed to the
+/// callback given to [`ZipArchiveWriter::on_header`].
+///
+/// The sizes, CRC32, and compression method are determined by the data and
+/// can't be changed. Neither should the flags that describe how the data is
+/// stored, like the data descriptor and encryption flags.
+#[derive(Debug)]
+pub struct ZipHeaderFields<'a, 'name> {
+    name: &'a mut Cow<'name, [u8]>,
+    header: &'a mut FileHeader,
+}
+
+impl ZipHeaderFields<'_, '_> {
+    /// Returns the name of the entry, as it will be written.
+    pub fn name(&self) -> &[u8] {
+        self.name
+    }
+
+    /// Replaces the name of the entry, which is written verbatim.
+    ///
+    /// The name is validated again when
+    /// [`validate_names`](ZipArchiveWriterBuilder::validate_names) is
+    /// enabled. Renaming a directory entry should keep the trailing slash.
+    pub fn set_name(&mut self, name: impl Into<Vec<u8>>) {
+        *self.name = Cow::Owned(name.into());
+    }
+
+    /// Returns the general purpose bit flags.
+    pub fn flags(&self) -> u16 {
+        self.header.flags
+    }
+
+    /// Sets the general purpose bit flags.
+    pub fn set_flags(&mut self, flags: u16) {
+        self.header.flags = flags;
+    }
+
+    /// Returns the MS-DOS modification time.
+    pub fn dos_last_modified(&self) -> DosDateTime {
+        DosDateTime::new(self.header.last_mod_time, self.header.last_mod_date)
+    }
+
+    /// Sets the MS-DOS modification time, clamped to the range that it can
+    /// represent.
+    ///
+    /// Times stored in extra fields are left as is. Remove them with
+    /// [`retain_extra_fields`](ZipHeaderFields::retain_extra_fields).
+    pub fn set_dos_last_modified(&mut self, time: UtcDateTime) {
+        (self.header.last_mod_time, self.header.last_mod_date) =
+            DosDateTime::from(&time).into_parts();
+    }
+
+    /// Returns the extra fields of both the local and central headers.
+    pub fn extra_fields(&self) -> ExtraFields<'_> {
+        self.header.extra_fields.iter()
+    }
+
+    /// Adds an extra field to the given headers.
+    ///
+    /// The ZIP64 field is rejected as the writer generates it when needed.
+    pub fn add_extra_field(
+        &mut self,
+        id: ExtraFieldId,
+        data: &[u8],
+        location: Header,
+    ) -> Result<(), Error> {
+        if id == ExtraFieldId::ZIP64 {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "the ZIP64 extra field is managed by the writer".to_string(),
+            }));
+        }
+
+        self.header.extra_fields.add_field(id, data, location)
+    }
+
+    /// Keeps only the extra fields for which the predicate returns true.
+    pub fn retain_extra_fields(&mut self, keep: impl FnMut(ExtraFieldId, &[u8]) -> bool) {
+        self.header.extra_fields.retain_fields(keep);
+    }
+
+    /// Returns the "version made by" field, whose high byte is the host
+    /// system.
+    pub fn version_made_by(&self) -> u16 {
+        self.header.version_made_by
+    }
+
+    /// Sets the "version made by" field.
+    pub fn set_version_made_by(&mut self, version: u16) {
+        self.header.version_made_by = version;
+    }
+
+    /// Returns the internal file attributes.
+    pub fn internal_attributes(&self) -> u16 {
+        self.header.internal_attrs
+    }
+
+    /// Sets the internal file attributes.
+    pub fn set_internal_attributes(&mut self, attributes: u16) {
+        self.header.internal_attrs = attributes;
+    }
+
+    /// Returns the external file attributes, whose meaning depends on the
+    /// host system.
+    pub fn external_attributes(&self) -> u32 {
+        self.header.external_attrs
+    }
+
+    /// Sets the external file attributes.
+    pub fn set_external_attributes(&mut self, attributes: u32) {
+        self.header.external_attrs = attributes;
+    }
+
+    /// Returns the comment stored in the central directory record.
+    pub fn comment(&self) -> &[u8] {
+        &self.header.comment
+    }
+
+    /// Sets the comment stored in the central directory record.
+    ///
+    /// Writing the entry fails if the comment is longer than 65,535 bytes.
+    pub fn set_comment(&mut self, comment: &[u8]) {
+        self.header.comment = comment.to_vec();
+    }
+}
+
 /// Builds a `ZipArchiveWriter`.
 #[derive(Debug, Default)]
 pub struct ZipArchiveWriterBuilder {
     count: u64,
     capacity: usize,
+    buffer_size: usize,
+    prefix: Vec<u8>,
+    adjust_prefix_offsets: bool,
+    options: ZipArchiveOptions,
+}
+
+/// Archive wide settings that affect how entries are written.
+#[derive(Debug, Clone)]
+struct ZipArchiveOptions {
+    unicode_path_field: bool,
+    unicode_comment_field: bool,
+    validate_names: bool,
+    path_encoding: PathEncoding,
+    host_system: Option<HostSystem>,
+    fixed_timestamp: Option<UtcDateTime>,
+    precise_times: bool,
+    dos_timestamps: DosTimestampPolicy,
+    jar_marker: bool,
+    info_zip: bool,
+    zip64: bool,
+    force_zip64: bool,
+    zip64_threshold: u64,
+    zip64_entry_threshold: usize,
+    version_needed: Option<u16>,
+    data_descriptor_width: DataDescriptorWidth,
+    sort_central_directory: bool,
+    create_parent_directories: bool,
+    duplicate_names: DuplicateNamePolicy,
+    compat: Option<(CompatTarget, CompatPolicy)>,
+    eocd_disks: Option<(u32, u32)>,
+    flush_policy: FlushPolicy,
+    #[cfg(feature = "_codec")]
+    compression_threads: usize,
+}
+
+impl Default for ZipArchiveOptions {
+    fn default() -> Self {
+        ZipArchiveOptions {
+            unicode_path_field: false,
+            unicode_comment_field: false,
+            validate_names: false,
+            path_encoding: PathEncoding::default(),
+            host_system: None,
+            fixed_timestamp: None,
+            precise_times: false,
+            dos_timestamps: DosTimestampPolicy::Clamp,
+            jar_marker: false,
+            info_zip: false,
+            zip64: true,
+            force_zip64: false,
+            zip64_threshold: ZIP64_THRESHOLD,
+            zip64_entry_threshold: ZIP64_THRESHOLD_ENTRIES,
+            version_needed: None,
+            data_descriptor_width: DataDescriptorWidth::default(),
+            sort_central_directory: false,
+            create_parent_directories: false,
+            duplicate_names: DuplicateNamePolicy::Allow,
+            compat: None,
+            eocd_disks: None,
+            flush_policy: FlushPolicy::default(),
+            #[cfg(feature = "_codec")]
+            compression_threads: 0,
+        }
+    }
 }
 
 impl ZipArchiveWriterBuilder {
@@ -71,6 +611,40 @@ impl ZipArchiveWriterBuilder {
         self
     }
 
+    /// Sets the size of the buffer that small writes, like headers and data
+    /// descriptors, are gathered in before they are passed to the underlying
+    /// writer. Writes as large as the buffer bypass it.
+    ///
+    /// The default of 0 disables the buffer, which suits writers that buffer
+    /// on their own, like a [`BufWriter`](std::io::BufWriter), as each header
+    /// is already passed on in a single write. The buffer is written out
+    /// whenever the archive is flushed, so a
+    /// [`FlushPolicy`] covers it too. See
+    /// [`ZipArchiveWriter::buffered_len`] for how much it holds.
+    ///
+    /// Split and async archives, which keep track of what they have written
+    /// themselves, aren't buffered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::ZipArchiveWriter;
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_write_buffer_size(8 * 1024)
+    ///     .build(Vec::new());
+    /// archive.new_dir("dir/").create()?;
+    /// assert!(archive.get_ref().is_empty());
+    /// assert_eq!(archive.buffered_len() as u64, archive.stream_offset());
+    ///
+    /// let output = archive.finish()?;
+    /// assert!(rawzip::ZipArchive::from_slice(&output).is_ok());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_write_buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
     /// Sets the starting offset for writing. Useful when there is prelude data
     /// prior to the zip archive.
     ///
@@ -110,1315 +684,8364 @@ impl ZipArchiveWriterBuilder {
         self
     }
 
-    /// Builds a `ZipArchiveWriter` that writes to `writer`.
-    pub fn build<W>(&self, writer: W) -> ZipArchiveWriter<W> {
-        ZipArchiveWriter {
-            writer: CountWriter::new(writer, self.count),
-            files: Vec::with_capacity(self.capacity),
-            file_names: Vec::new(),
-        }
-    }
-}
-
-/// Create a new Zip archive.
-///
-/// Basic usage:
-/// ```rust
-/// use std::io::Write;
-///
-/// let mut output = std::io::Cursor::new(Vec::new());
-/// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
-/// let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
-/// let mut writer = config.wrap(&mut entry);
-/// writer.write_all(b"Hello, world!").unwrap();
-/// let (_, output) = writer.finish().unwrap();
-/// entry.finish(output).unwrap();
-/// archive.finish().unwrap();
-/// ```
-///
-/// Use the builder for customization:
-/// ```rust
-/// use std::io::Write;
-///
-/// let mut output = std::io::Cursor::new(Vec::<u8>::new());
-/// let mut _archive = rawzip::ZipArchiveWriter::builder()
-///     .with_capacity(1000)  // Optimize for 1000 anticipated files
-///     .build(&mut output);
-/// // ... add files as usual
-/// ```
-#[derive(Debug)]
-pub struct ZipArchiveWriter<W> {
-    files: Vec<FileHeader>,
-    file_names: Vec<u8>,
-    writer: CountWriter<W>,
-}
-
-impl ZipArchiveWriter<()> {
-    /// Creates a `ZipArchiveWriterBuilder` for configuring the writer.
-    pub fn builder() -> ZipArchiveWriterBuilder {
-        ZipArchiveWriterBuilder::new()
-    }
-}
-
-impl<W> ZipArchiveWriter<W> {
-    /// Creates a new `ZipArchiveWriter` that writes to `writer`.
-    pub fn new(writer: W) -> Self {
-        ZipArchiveWriterBuilder::new().build(writer)
-    }
-
-    /// Returns the current offset in the output stream.
+    /// Writes `prefix` to the output before the first local file header.
     ///
-    /// Analagous to [`std::io::Cursor::position`].
+    /// This produces self-extracting archives, where the prefix is an
+    /// executable stub or an installer script, and the result is both
+    /// runnable and a valid zip file.
     ///
-    /// This can be used to determine various offsets during ZIP archive
-    /// creation:
+    /// When `adjust_offsets` is true, the offsets in the central directory
+    /// are relative to the start of the output, including the prefix. This is
+    /// what most readers expect and is equivalent to
+    /// [`with_offset`](ZipArchiveWriterBuilder::with_offset) for prelude data
+    /// that the writer writes itself. When false, the offsets are relative to
+    /// the first local file header, as if the prefix was concatenated onto
+    /// an existing archive. Readers that rely on the offsets being unadjusted
+    /// are rare, and ZIP64 archives require adjusted offsets.
     ///
-    /// - Local header offset
-    /// - Start of compressed data offset
-    /// - End of compressed data offset
-    /// - End of data descriptor offset / next file's local header offset
+    /// The prefix is written along with the first entry, or when the archive
+    /// is finished.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use std::io::Write;
-    ///
-    /// let mut output = std::io::Cursor::new(Vec::new());
-    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let stub = b"#!/bin/sh\nexec unzip -o \"$0\"\n";
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::builder()
+    ///     .with_prefix(stub, true)
+    ///     .build(&mut output);
+    /// archive.new_dir("bin/").create()?;
+    /// archive.finish()?;
     ///
-    /// // 1. Get local header offset
-    /// let local_header_offset = archive.stream_offset();
-    /// let mut file = archive.new_file("test.txt").create().unwrap();
+    /// assert!(output.starts_with(stub));
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.local_header_offset(), stub.len() as u64);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_prefix(mut self, prefix: &[u8], adjust_offsets: bool) -> Self {
+        self.prefix = prefix.to_vec();
+        self.adjust_prefix_offsets = adjust_offsets;
+        self
+    }
+
+    /// Writes non-ASCII names in the Info-ZIP Unicode path extra field.
     ///
-    /// // 2. Get start of data offset
-    /// let data_start_offset = file.stream_offset();
+    /// By default, a name that isn't plain ASCII is written as UTF-8 with the
+    /// language encoding flag set. Some legacy extractors ignore that flag,
+    /// but honor the Unicode path (`0x7075`) extra field. When enabled, such
+    /// names are written as a lossy CP437 transliteration in the header, with
+    /// the UTF-8 name placed in the extra field.
     ///
-    /// // Write some data
-    /// let mut writer = rawzip::ZipDataWriter::new(&mut file);
-    /// writer.write_all(b"Hello World").unwrap();
-    /// let (_, desc) = writer.finish().unwrap();
+    /// # Example
     ///
-    /// // 3. Get end of compressed data offset
-    /// let end_data_offset = file.stream_offset();
+    /// ```rust
+    /// # use rawzip::{extra_fields::ExtraFieldId, ZipArchive, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_unicode_path_field(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("café/").create()?;
+    /// archive.finish()?;
     ///
-    /// let compressed_bytes = file.finish(desc).unwrap();
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.file_path().as_ref(), b"caf\x82/");
+    /// let (_, field) = entry
+    ///     .extra_fields()
+    ///     .find(|(id, _)| *id == ExtraFieldId::INFO_ZIP_UNICODE_PATH)
+    ///     .unwrap();
+    /// assert_eq!(&field[5..], "café/".as_bytes());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_unicode_path_field(mut self, enabled: bool) -> Self {
+        self.options.unicode_path_field = enabled;
+        self
+    }
+
+    /// Writes non-ASCII entry comments to the Info-ZIP Unicode comment extra
+    /// field as well.
     ///
-    /// // 4. Get end of data descriptor offset (next file's local header offset)
-    /// let end_descriptor_offset = archive.stream_offset();
+    /// Comments are written as UTF-8, but the language encoding flag that
+    /// marks them as such is only set for entries whose name isn't ASCII,
+    /// and some extractors ignore it for comments regardless. When enabled,
+    /// an entry with a non-ASCII comment also carries the Unicode comment
+    /// (`0x6375`) extra field in the central directory, which holds the
+    /// CRC32 of the comment bytes and the UTF-8 comment. The comment bytes
+    /// themselves are left as is.
     ///
-    /// archive.finish().unwrap();
+    /// # Example
     ///
-    /// assert_eq!(local_header_offset, 0);
-    /// assert!(data_start_offset > local_header_offset);
-    /// assert_eq!(end_data_offset, data_start_offset + b"Hello World".len() as u64);
-    /// assert_eq!(end_descriptor_offset, end_data_offset + 16); // 16 bytes for data descriptor
-    /// assert_eq!(compressed_bytes, end_data_offset - data_start_offset);
+    /// ```rust
+    /// # use rawzip::{extra_fields::ExtraFieldId, ZipArchive, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_unicode_comment_field(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("dir/").comment("résumé").create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// let (_, field) = entry
+    ///     .extra_fields()
+    ///     .find(|(id, _)| *id == ExtraFieldId::INFO_ZIP_UNICODE_COMMENT)
+    ///     .unwrap();
+    /// assert_eq!(field[1..5], rawzip::crc32("résumé".as_bytes()).to_le_bytes());
+    /// assert_eq!(&field[5..], "résumé".as_bytes());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn stream_offset(&self) -> u64 {
-        self.writer.count()
+    pub fn with_unicode_comment_field(mut self, enabled: bool) -> Self {
+        self.options.unicode_comment_field = enabled;
+        self
     }
-}
-
-/// Options for CRC32 calculation in ZIP files.
-#[derive(Debug, Clone, Copy, Default)]
-pub enum Crc32Option {
-    /// Calculate CRC32 automatically from the data.
-    #[default]
-    Calculate,
-    /// Use a custom CRC32 value and skip calculation.
-    Custom(u32),
-    /// Skip CRC32 calculation entirely (sets CRC32 to 0).
-    Skip,
-}
 
-impl Crc32Option {
-    /// Returns the initial CRC32 value for this option.
-    #[inline]
-    pub fn initial_value(&self) -> u32 {
-        match self {
-            Crc32Option::Calculate => 0,
-            Crc32Option::Custom(value) => *value,
-            Crc32Option::Skip => 0,
-        }
+    /// Rejects entry names that extractors would flag as path traversal
+    /// (zip slip) attempts, rather than quietly normalizing them.
+    ///
+    /// Names are normalized by default: backslashes become forward slashes,
+    /// and leading slashes, `..` components, and drive letters are dropped.
+    /// So a name like `../etc/passwd` is written as `etc/passwd`. When
+    /// validation is enabled, names that are absolute, contain `..`
+    /// components, NUL bytes, or drive letter prefixes (like `C:`) are
+    /// instead rejected with [`ErrorKind::InvalidPath`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{ErrorKind, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .validate_names(true)
+    ///     .build(&mut output);
+    /// let err = archive.new_dir("../outside/").create().unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::InvalidPath { .. }));
+    /// archive.new_dir("inside/").create()?;
+    /// archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn validate_names(mut self, enabled: bool) -> Self {
+        self.options.validate_names = enabled;
+        self
     }
-}
-
-/// A builder for creating a new file entry in a ZIP archive.
-#[derive(Debug)]
-pub struct ZipFileBuilder<'archive, 'name, W> {
-    archive: &'archive mut ZipArchiveWriter<W>,
-    name: &'name str,
-    compression_method: CompressionMethod,
-    modification_time: Option<UtcDateTime>,
-    unix_permissions: Option<u32>,
-    extra_fields: ExtraFieldsContainer,
-    crc32_option: Crc32Option,
-}
 
-impl<'archive, W> ZipFileBuilder<'archive, '_, W>
-where
-    W: Write,
-{
-    /// Sets the compression method for the file entry.
-    #[must_use]
-    #[inline]
-    pub fn compression_method(mut self, compression_method: CompressionMethod) -> Self {
-        self.compression_method = compression_method;
+    /// Sets how [`ZipArchiveWriter::new_file_path`] and
+    /// [`ZipArchiveWriter::new_dir_path`] treat paths that aren't valid
+    /// UTF-8. By default, they are rejected.
+    pub fn with_path_encoding(mut self, encoding: PathEncoding) -> Self {
+        self.options.path_encoding = encoding;
         self
     }
 
-    /// Sets the modification time for the file entry.
+    /// Sets the host system that is recorded for every entry.
     ///
-    /// Only accepts UTC timestamps to ensure Extended Timestamp fields are written correctly.
-    #[must_use]
-    #[inline]
-    pub fn last_modified(mut self, modification_time: UtcDateTime) -> Self {
-        self.modification_time = Some(modification_time);
+    /// The host system is the upper byte of the "version made by" field and
+    /// determines how readers interpret the external file attributes. By
+    /// default, entries with [Unix permissions](ZipFileBuilder::unix_permissions)
+    /// are marked as Unix and all others as MS-DOS. An entry may override the
+    /// archive's host system with [`ZipFileBuilder::host_system`].
+    pub fn with_host_system(mut self, host_system: HostSystem) -> Self {
+        self.options.host_system = Some(host_system);
         self
     }
 
-    /// Sets the Unix permissions for the file entry.
+    /// Produces reproducible archives by stamping every entry with the same
+    /// modification time.
     ///
-    /// Accepts either:
-    /// - Basic permission bits (e.g., 0o644 for rw-r--r--, 0o755 for rwxr-xr-x)
-    /// - Full Unix mode including file type (e.g., 0o100644 for regular file, 0o040755 for directory)
-    /// - Special permission bits are preserved (SUID: 0o4000, SGID: 0o2000, sticky: 0o1000)
+    /// The writer never consults the clock, the environment, or the file
+    /// system, so the bytes written are determined solely by the entries
+    /// (their names, data, and options) in the order they are added. What
+    /// typically differs between builds are the timestamps, so in this mode:
     ///
-    /// When set, the archive will be created with Unix-compatible "version made by" field
-    /// to ensure proper interpretation of the permissions by zip readers.
-    #[must_use]
-    #[inline]
-    pub fn unix_permissions(mut self, permissions: u32) -> Self {
-        self.unix_permissions = Some(permissions);
-        self
-    }
-
-    /// Adds an extra field to this file entry.
+    /// - The modification time of each entry is `timestamp`, such as the one
+    ///   given by `SOURCE_DATE_EPOCH`.
+    /// - Access and creation times are omitted.
+    /// - NTFS timestamps, if requested, are all `timestamp`.
+    ///
+    /// Entries copied with [`ZipArchiveWriter::copy_entry`] keep their
+    /// metadata. It's up to the caller to add entries in a stable order, for
+    /// instance by sorting a directory listing, as entries are streamed out
+    /// and can't be reordered.
     ///
-    /// Extra fields contain additional metadata about files in ZIP archives,
-    /// such as timestamps, alignment information, and platform-specific data.
+    /// # Example
     ///
-    /// No deduplication is performed - duplicate field IDs will result in
-    /// multiple entries
+    /// ```rust
+    /// # use rawzip::{time::UtcDateTime, ZipArchiveWriter};
+    /// let build = |mtime: UtcDateTime| {
+    ///     let mut output = Vec::new();
+    ///     let mut archive = ZipArchiveWriter::builder()
+    ///         .deterministic(UtcDateTime::from_unix(1_700_000_000))
+    ///         .build(&mut output);
+    ///     archive.new_dir("dir/").last_modified(mtime).create()?;
+    ///     archive.finish()?;
+    ///     Ok::<_, rawzip::Error>(output)
+    /// };
     ///
-    /// Will return an error if the total size exceeds 65,535 bytes for the
-    /// specified headers.
+    /// let first = build(UtcDateTime::from_unix(1))?;
+    /// let second = build(UtcDateTime::from_unix(2))?;
+    /// assert_eq!(first, second);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn deterministic(mut self, timestamp: UtcDateTime) -> Self {
+        self.options.fixed_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Lays out entries the way Info-ZIP's `zip(1)` does on Unix, so that
+    /// the output can be compared byte for byte with archives it produces.
     ///
-    /// Rawzip will automatically add extra fields:
+    /// In this profile, each new entry:
     ///
-    /// - `EXTENDED_TIMESTAMP` when `last_modified()` is set
-    /// - `ZIP64` when 32-bit thresholds are met
+    /// - Is made by Unix, version 3.0, and needs version 1.0 when stored.
+    /// - Has an extended timestamp (`UT`) field in both headers, with the
+    ///   access time in the local header defaulting to the modification
+    ///   time.
+    /// - Has an Info-ZIP new Unix (`ux`) field with the
+    ///   [owner](ZipFileBuilder::unix_owner), `0:0` when unset, in the local
+    ///   header and an empty one in the central directory.
+    /// - Defaults to `0o100644` for files and `0o040755` for directories when
+    ///   no [Unix permissions](ZipFileBuilder::unix_permissions) are given,
+    ///   and carries the MS-DOS directory and read-only attributes that the
+    ///   mode implies.
+    /// - Marks the deflate level in general purpose bits 1 and 2: maximum
+    ///   for levels 8 and 9, fast for levels 1 and 2.
     ///
-    /// # Examples
+    /// Options set on an entry take precedence. Entries copied from another
+    /// archive keep their metadata.
     ///
-    /// Create files with different extra field headers and verify the
-    /// behavior. Only the central directory is checked. To check the local
-    /// extra fields, see
-    /// [`ZipEntry::local_header`](crate::ZipEntry::local_header)
+    /// # Example
     ///
     /// ```rust
-    /// # use std::io::{Cursor, Write};
-    /// # use rawzip::{ZipArchive, ZipArchiveWriter, ZipDataWriter, extra_fields::ExtraFieldId, Header};
-    /// let mut output = Cursor::new(Vec::new());
-    /// let mut archive = ZipArchiveWriter::new(&mut output);
+    /// # use rawzip::{extra_fields::ExtraFieldId, time::UtcDateTime, ZipArchive, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder().info_zip().build(&mut output);
+    /// archive
+    ///     .new_dir("dir/")
+    ///     .last_modified(UtcDateTime::from_unix(1_700_000_000))
+    ///     .create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.mode().value(), 0o040755);
+    /// let ids: Vec<_> = entry.extra_fields().map(|(id, _)| id).collect();
+    /// assert_eq!(
+    ///     ids,
+    ///     [ExtraFieldId::EXTENDED_TIMESTAMP, ExtraFieldId::INFO_ZIP_UNIX_UID_GID]
+    /// );
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn info_zip(mut self) -> Self {
+        self.options.info_zip = true;
+        self
+    }
+
+    /// Writes the NTFS extra field for entries whose modification time has a
+    /// fractional second, so that it survives the trip through the archive.
     ///
-    /// let my_custom_field = ExtraFieldId::new(0x6666);
+    /// The MS-DOS time in the headers has a 2 second resolution and the
+    /// extended timestamp field a 1 second resolution, while the NTFS field
+    /// keeps times down to 100ns. Readers, including this crate's, prefer the
+    /// NTFS field when present. Entries with whole second times, and those
+    /// given [`ZipFileBuilder::ntfs_times`], are left as is.
     ///
-    /// // File with extra fields only in the local file header
-    /// let mut local_file = archive.new_file("video.mp4")
-    ///     .extra_field(my_custom_field, b"field1", Header::LOCAL)?
-    ///     .create()?;
-    /// let mut writer = ZipDataWriter::new(&mut local_file);
-    /// writer.write_all(b"video data")?;
-    /// let (_, desc) = writer.finish()?;
-    /// local_file.finish(desc)?;
-    ///
-    /// // File with extra fields only in the central directory
-    /// let mut central_file = archive.new_file("document.pdf")
-    ///     .extra_field(my_custom_field, b"field2", Header::CENTRAL)?
-    ///     .create()?;
-    /// let mut writer = ZipDataWriter::new(&mut central_file);
-    /// writer.write_all(b"PDF content")?;
-    /// let (_, desc) = writer.finish()?;
-    /// central_file.finish(desc)?;
-    ///
-    /// // File with extra fields in both headers for maximum compatibility
-    /// assert_eq!(Header::default(), Header::LOCAL | Header::CENTRAL);
-    /// let mut both_file = archive.new_file("important.dat")
-    ///     .extra_field(my_custom_field, b"field3", Header::default())?
-    ///     .create()?;
-    /// let mut writer = ZipDataWriter::new(&mut both_file);
-    /// writer.write_all(b"important data")?;
-    /// let (_, desc) = writer.finish()?;
-    /// both_file.finish(desc)?;
+    /// # Example
     ///
+    /// ```rust
+    /// # use rawzip::{time::{UtcDateTime, ZipDateTimeKind}, ZipArchive, ZipArchiveWriter};
+    /// let mtime = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 123_456_700).unwrap();
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .preserve_subsecond_times(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("dir/").last_modified(mtime).create()?;
     /// archive.finish()?;
     ///
-    /// // Verify the behavior when reading back the central directory
-    /// let zip_data = output.into_inner();
-    /// let archive = ZipArchive::from_slice(&zip_data)?;
-    ///
-    /// for entry_result in archive.entries() {
-    ///     let entry = entry_result?;
-    ///     
-    ///     // Find our custom field in the central directory
-    ///     let custom_field_data = entry.extra_fields()
-    ///         .find(|(id, _)| *id == my_custom_field)
-    ///         .map(|(_, data)| data);
-    ///     
-    ///     match entry.file_path().as_ref() {
-    ///         b"video.mp4" => {
-    ///             // local only field should not be in central directory
-    ///             assert_eq!(custom_field_data, None);
-    ///         }
-    ///         b"document.pdf" => {
-    ///             // central only field should be in central directory
-    ///             assert_eq!(custom_field_data, Some(b"field2".as_slice()));
-    ///         }
-    ///         b"important.dat" => {
-    ///             // both location field should be in central directory
-    ///             assert_eq!(custom_field_data, Some(b"field3".as_slice()));
-    ///         }
-    ///         _ => {}
-    ///     }
-    /// }
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(mtime));
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn extra_field(
-        mut self,
-        id: ExtraFieldId,
-        data: &[u8],
-        location: Header,
-    ) -> Result<Self, Error> {
-        self.extra_fields.add_field(id, data, location)?;
-        Ok(self)
+    pub fn preserve_subsecond_times(mut self, enabled: bool) -> Self {
+        self.options.precise_times = enabled;
+        self
     }
 
-    /// Sets the CRC32 calculation option for the file entry.
+    /// Sets what happens to modification times that the MS-DOS date and time
+    /// in the headers can't represent, which are those before 1980 or after
+    /// 2107. They are clamped by default.
     ///
-    /// By default, CRC32 is calculated automatically from the data. Use this
-    /// method to:
+    /// The extended timestamp field is written regardless, so readers that
+    /// understand it see the actual time.
     ///
-    /// - Skip CRC32 calculation entirely (for performance or when verification
-    ///   isn't desired)
-    /// - Provide a pre-calculated CRC32 value
-    #[must_use]
-    #[inline]
-    pub fn crc32(mut self, crc32_option: Crc32Option) -> Self {
-        self.crc32_option = crc32_option;
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{time::UtcDateTime, DosTimestampPolicy, ErrorKind, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .dos_timestamp_policy(DosTimestampPolicy::Error)
+    ///     .build(&mut output);
+    /// let err = archive
+    ///     .new_dir("dir/")
+    ///     .last_modified(UtcDateTime::from_unix(0))
+    ///     .create()
+    ///     .unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+    /// archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn dos_timestamp_policy(mut self, policy: DosTimestampPolicy) -> Self {
+        self.options.dos_timestamps = policy;
         self
     }
 
-    /// Creates the file entry and returns a writer for the file's content.
-    #[deprecated(
-        since = "0.4.0",
-        note = "Use `start()` method instead as it allows for more flexibility (ie: CRC configuration)"
-    )]
-    pub fn create(self) -> Result<ZipEntryWriter<'archive, W>, Error> {
-        let (entry_writer, _) = self.start()?;
-        Ok(entry_writer)
+    /// Marks the archive as a JAR file, like the JDK's `jar` tool does, by
+    /// giving the first entry an empty Java extra field (`0xCAFE`).
+    ///
+    /// The field is placed ahead of the entry's other extra fields in both
+    /// headers, which is where tools that sniff for JAR files look for it.
+    /// The first entry is conventionally the `META-INF/` directory.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{extra_fields::ExtraFieldId, ZipArchive, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .jar_marker(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("META-INF/").create()?;
+    /// archive.finish()?;
+    ///
+    /// assert_eq!(&output[30..43], b"META-INF/\xfe\xca\x00\x00");
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// let (id, data) = entry.extra_fields().next().unwrap();
+    /// assert_eq!((id, data), (ExtraFieldId::JAVA_JAR, &[][..]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn jar_marker(mut self, enabled: bool) -> Self {
+        self.options.jar_marker = enabled;
+        self
     }
 
-    /// Mark the start of file data
+    /// Writes every entry and the end of central directory with ZIP64
+    /// records, regardless of their size.
     ///
-    /// Returns a tuple:
+    /// By default, ZIP64 structures are only written once a size, offset, or
+    /// entry count overflows. When forced:
     ///
-    /// - `entry` handles the ZIP format and writes compressed data to the archive
-    /// - `config` constructs data writers that handle uncompressed data and CRC32 calculation
+    /// - Each local file header carries a ZIP64 extra field with both sizes,
+    ///   so data descriptors have 64-bit sizes.
+    /// - Each central directory header carries a ZIP64 extra field with the
+    ///   sizes and local header offset.
+    /// - The ZIP64 end of central directory record and locator are written.
     ///
-    /// # Examples
+    /// This is useful when streaming entries of unknown size, as the 64-bit
+    /// fields are in place up front, and for testing readers.
     ///
-    /// For stored (uncompressed) files:
-    /// ```
-    /// # use std::io::Write;
-    /// # let mut output = std::io::Cursor::new(Vec::new());
-    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
-    /// let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
-    /// let mut writer = config.wrap(&mut entry);
-    /// writer.write_all(b"Hello").unwrap();
-    /// let (_, output) = writer.finish().unwrap();
-    /// entry.finish(output).unwrap();
-    /// # archive.finish().unwrap();
-    /// ```
+    /// # Example
     ///
-    /// For deflate compression:
-    /// ```
-    /// # use std::io::Write;
-    /// # let mut output = std::io::Cursor::new(Vec::new());
-    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
-    /// let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
-    /// let encoder = flate2::write::DeflateEncoder::new(&mut entry, flate2::Compression::default());
-    /// let mut writer = config.wrap(encoder);
-    /// writer.write_all(b"Hello").unwrap();
-    /// let (encoder, output) = writer.finish().unwrap();
-    /// encoder.finish().unwrap();
-    /// entry.finish(output).unwrap();
-    /// # archive.finish().unwrap();
+    /// ```rust
+    /// # use rawzip::{extra_fields::ExtraFieldId, ZipArchive, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .force_zip64(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("dir/").create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert!(entry.extra_fields().any(|(id, _)| id == ExtraFieldId::ZIP64));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn start(self) -> Result<(ZipEntryWriter<'archive, W>, ZipDataWriterConfig), Error> {
-        let crc32_option = self.crc32_option;
-        let options = ZipEntryOptions {
-            compression_method: self.compression_method,
-            modification_time: self.modification_time,
-            unix_permissions: self.unix_permissions,
-            extra_fields: self.extra_fields,
-        };
-        let entry_writer = self.archive.new_file_with_options(self.name, options)?;
-
-        let data_writer_config = ZipDataWriterConfig { crc32_option };
-
-        Ok((entry_writer, data_writer_config))
-    }
-}
-
-/// A builder for creating a new directory entry in a ZIP archive.
-#[derive(Debug)]
-pub struct ZipDirBuilder<'a, W> {
-    archive: &'a mut ZipArchiveWriter<W>,
-    name: &'a str,
-    modification_time: Option<UtcDateTime>,
-    unix_permissions: Option<u32>,
-    extra_fields: ExtraFieldsContainer,
-}
-
-impl<W> ZipDirBuilder<'_, W>
-where
-    W: Write,
-{
-    /// Sets the modification time for the directory entry.
     ///
-    /// See [`ZipFileBuilder::last_modified`] for details.
-    #[must_use]
-    #[inline]
-    pub fn last_modified(mut self, modification_time: UtcDateTime) -> Self {
-        self.modification_time = Some(modification_time);
+    /// Forcing ZIP64 re-enables it if it was [disabled](Self::with_zip64).
+    pub fn force_zip64(mut self, enabled: bool) -> Self {
+        self.options.force_zip64 = enabled;
+        self.options.zip64 |= enabled;
         self
     }
 
-    /// Sets the Unix permissions for the directory entry.
+    /// Sets whether ZIP64 may be used when a limit of the original format is
+    /// hit, which it is by default.
     ///
-    /// See [`ZipFileBuilder::unix_permissions`] for details.
-    #[must_use]
-    #[inline]
-    pub fn unix_permissions(mut self, permissions: u32) -> Self {
-        self.unix_permissions = Some(permissions);
+    /// Some readers don't understand ZIP64. With it disabled, the writer
+    /// fails with [`ErrorKind::Zip64Required`] as soon as an entry's size or
+    /// an offset into the archive reaches the [threshold](Self::with_zip64_threshold),
+    /// or the entry count reaches the [entry threshold](Self::with_zip64_entry_threshold),
+    /// rather than write an archive that needs it. Writes to an entry that
+    /// would cross the threshold are cut short, and fail once nothing more
+    /// fits, with the error wrapped in an [`io::Error`].
+    ///
+    /// Disabling ZIP64 undoes [`force_zip64`](Self::force_zip64).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{ErrorKind, Zip64Limit, ZipArchiveWriter};
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_zip64(false)
+    ///     .with_zip64_entry_threshold(2)
+    ///     .build(Vec::new());
+    /// archive.new_dir("a/").create()?;
+    /// let err = archive.new_dir("b/").create().unwrap_err();
+    /// assert!(matches!(
+    ///     err.kind(),
+    ///     ErrorKind::Zip64Required { limit: Zip64Limit::EntryCount }
+    /// ));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_zip64(mut self, enabled: bool) -> Self {
+        self.options.zip64 = enabled;
+        self.options.force_zip64 &= enabled;
         self
     }
 
-    /// Adds an extra field to this directory entry.
+    /// Sets the size at which sizes and offsets are written to ZIP64 extra
+    /// fields.
     ///
-    /// See [`ZipFileBuilder::extra_field`] for details and examples.
-    /// The same behavior notes apply: append-only, no deduplication, and automatic fields.
-    pub fn extra_field(
-        mut self,
-        id: ExtraFieldId,
-        data: &[u8],
-        location: Header,
-    ) -> Result<Self, Error> {
-        self.extra_fields.add_field(id, data, location)?;
-        Ok(self)
-    }
-
-    /// Creates the directory entry.
-    pub fn create(self) -> Result<(), Error> {
-        let options = ZipEntryOptions {
-            compression_method: CompressionMethod::Store, // Directories always use Store
-            modification_time: self.modification_time,
-            unix_permissions: self.unix_permissions,
-            extra_fields: self.extra_fields,
-        };
-        self.archive.new_dir_with_options(self.name, options)
+    /// An entry whose compressed size, uncompressed size, or local header
+    /// offset is at least `threshold` is recorded in a ZIP64 extra field, and
+    /// the ZIP64 end of central directory is written when the central
+    /// directory's offset or size is at least `threshold`. The default, and
+    /// the maximum, is `0xFFFFFFFF`, the largest value that is reserved to
+    /// mean that the actual value is in the ZIP64 extra field.
+    ///
+    /// Lowering the threshold exercises the ZIP64 code paths of readers
+    /// without multi-gigabyte archives.
+    ///
+    /// The sizes of a streamed entry are unknown when its local file header
+    /// is written, so an entry that ends up above the threshold relies on
+    /// its data descriptor, which has 64-bit sizes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # use rawzip::{extra_fields::ExtraFieldId, ZipArchive, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_zip64_threshold(100)
+    ///     .build(&mut output);
+    /// let (mut entry, config) = archive.new_file("large.bin").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(&[0u8; 200])?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.uncompressed_size_hint(), 200);
+    /// assert!(entry.extra_fields().any(|(id, _)| id == ExtraFieldId::ZIP64));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_zip64_threshold(mut self, threshold: u64) -> Self {
+        self.options.zip64_threshold = threshold.min(ZIP64_THRESHOLD);
+        self
     }
-}
-
-impl<W> ZipArchiveWriter<W>
-where
-    W: Write,
-{
-    /// Writes a local file header with filtered extra fields.
-    fn write_local_header(
-        &mut self,
-        file_path: &ZipFilePath<NormalizedPath>,
-        flags: u16,
-        compression_method: CompressionMethod,
-        options: &mut ZipEntryOptions,
-    ) -> Result<(), Error> {
-        // Get DOS timestamp from options or use 0 as default
-        let (dos_time, dos_date) = options
-            .modification_time
-            .as_ref()
-            .map(|dt| DosDateTime::from(dt).into_parts())
-            .unwrap_or((0, 0));
-
-        if let Some(datetime) = options.modification_time.as_ref() {
-            let unix_time = datetime.to_unix().max(0) as u32;
-            let mut data = [0u8; 5];
-            data[0] = 1; // Flags: modification time present
-            data[1..].copy_from_slice(&unix_time.to_le_bytes());
-            options.extra_fields.add_field(
-                ExtraFieldId::EXTENDED_TIMESTAMP,
-                &data,
-                Header::CENTRAL,
-            )?;
-        }
-
-        let header = ZipLocalFileHeaderFixed {
-            signature: ZipLocalFileHeaderFixed::SIGNATURE,
-            version_needed: 20,
-            flags,
-            compression_method: compression_method.as_id(),
-            last_mod_time: dos_time,
-            last_mod_date: dos_date,
-            crc32: 0, // must be zero if data descriptor is used (4.4.4)
-            compressed_size: 0,
-            uncompressed_size: 0,
-            file_name_len: file_path.len() as u16,
-            extra_field_len: options.extra_fields.local_size,
-        };
 
-        header.write(&mut self.writer)?;
-        self.writer.write_all(file_path.as_ref().as_bytes())?;
-        options
-            .extra_fields
-            .write_extra_fields(&mut self.writer, Header::LOCAL)?;
-        Ok(())
+    /// Sets the number of entries at which the ZIP64 end of central directory
+    /// is written.
+    ///
+    /// The default, and the maximum, is `0xFFFF`, the largest value that is
+    /// reserved to mean that the actual count is in the ZIP64 end of central
+    /// directory.
+    pub fn with_zip64_entry_threshold(mut self, threshold: usize) -> Self {
+        self.options.zip64_entry_threshold = threshold.min(ZIP64_THRESHOLD_ENTRIES);
+        self
     }
 
-    /// Creates a builder for adding a new directory to the archive.
+    /// Writes `version` as the "version needed to extract" of every entry
+    /// added to the archive, including copied entries, instead of deriving
+    /// it from the features that each entry uses.
     ///
-    /// The name of the directory must end with a `/`.
+    /// The version is encoded as the major version times ten plus the minor
+    /// version, so `20` stands for 2.0 (4.4.3). Some consumers refuse
+    /// entries that claim to need a newer version than they support, even
+    /// though the features used are compatible. An entry may override the
+    /// archive's version with [`ZipFileBuilder::version_needed`].
+    ///
+    /// The version is written as is, so it is up to the caller to ensure
+    /// that the consumer can extract the entries.
     ///
     /// # Example
     ///
     /// ```rust
-    /// # use std::io::Cursor;
-    /// # let mut output = Cursor::new(Vec::new());
-    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
-    /// archive.new_dir("my-dir/")
-    ///     .unix_permissions(0o755)
-    ///     .create()?;
+    /// # let mut output = Vec::new();
+    /// // ZIP64 entries would otherwise need version 4.5
+    /// let mut archive = rawzip::ZipArchiveWriter::builder()
+    ///     .force_zip64(true)
+    ///     .with_version_needed(20)
+    ///     .build(&mut output);
+    /// archive.new_dir("dir/").create()?;
+    /// archive.finish()?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    #[must_use]
-    pub fn new_dir<'a>(&'a mut self, name: &'a str) -> ZipDirBuilder<'a, W> {
-        ZipDirBuilder {
-            archive: self,
-            name,
-            modification_time: None,
-            unix_permissions: None,
-            extra_fields: ExtraFieldsContainer::new(),
-        }
+    pub fn with_version_needed(mut self, version: u16) -> Self {
+        self.options.version_needed = Some(version);
+        self
     }
 
-    /// Adds a new directory to the archive with options (internal method).
+    /// Sets the width of the sizes in data descriptors.
     ///
-    /// The name of the directory must end with a `/`.
-    fn new_dir_with_options(
-        &mut self,
-        name: &str,
-        mut options: ZipEntryOptions,
-    ) -> Result<(), Error> {
-        let file_path = ZipFilePath::from_str(name);
-        if !file_path.is_dir() {
-            return Err(Error::from(ErrorKind::InvalidInput {
-                msg: "not a directory".to_string(),
-            }));
-        }
-
-        if file_path.len() > u16::MAX as usize {
-            return Err(Error::from(ErrorKind::InvalidInput {
-                msg: "directory name too long".to_string(),
-            }));
-        }
+    /// Data descriptors follow entries whose sizes weren't known when the
+    /// local file header was written. By default, their sizes are 64-bit
+    /// only when the entry requires ZIP64. Some readers only understand one
+    /// of the variants, so the width can be fixed instead. See
+    /// [`DataDescriptorWidth`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # use rawzip::{DataDescriptorWidth, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_data_descriptor_width(DataDescriptorWidth::Bits64)
+    ///     .build(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// let data_end = entry.stream_offset();
+    /// entry.finish(descriptor)?;
+    /// assert_eq!(archive.stream_offset(), data_end + 24);
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_data_descriptor_width(mut self, width: DataDescriptorWidth) -> Self {
+        self.options.data_descriptor_width = width;
+        self
+    }
 
-        let local_header_offset = self.writer.count();
-        let mut flags = 0u16;
-        if file_path.needs_utf8_encoding() {
-            flags |= FLAG_UTF8_ENCODING;
-        } else {
-            flags &= !FLAG_UTF8_ENCODING;
-        }
+    /// Writes the central directory records sorted by name rather than in
+    /// the order the entries were added.
+    ///
+    /// The entries' data is still laid out in the order it was written.
+    /// Names are compared byte by byte, and entries with the same name keep
+    /// their relative order. Sorting makes the central directory
+    /// deterministic when entries are added concurrently, and lets readers
+    /// binary search it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::builder()
+    ///     .sort_central_directory(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("b/").create()?;
+    /// archive.new_dir("a/").create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let mut entries = archive.entries();
+    /// let first = entries.next_entry()?.unwrap();
+    /// assert_eq!(first.file_path().as_ref(), b"a/");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn sort_central_directory(mut self, enabled: bool) -> Self {
+        self.options.sort_central_directory = enabled;
+        self
+    }
 
-        // Store the name bytes in the central buffer
-        let name_bytes = file_path.as_ref().as_bytes();
-        let name_len = name_bytes.len() as u16;
-        self.file_names.extend_from_slice(name_bytes);
+    /// Writes a directory entry for each parent directory of an entry that
+    /// hasn't been written yet, so that adding `a/b/c.txt` adds `a/` and
+    /// `a/b/` before it.
+    ///
+    /// Directories that are already in the archive, whether added
+    /// explicitly or created for an earlier entry, aren't written again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::builder()
+    ///     .create_parent_directories(true)
+    ///     .build(&mut output);
+    /// archive.new_dir("a/").create()?;
+    /// archive.new_dir("a/b/c/").create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// assert_eq!(archive.entries_hint(), 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn create_parent_directories(mut self, enabled: bool) -> Self {
+        self.options.create_parent_directories = enabled;
+        self
+    }
 
-        self.write_local_header(&file_path, flags, CompressionMethod::Store, &mut options)?;
+    /// Sets what happens when an entry is added under a name that was
+    /// already written. Duplicates are allowed by default.
+    ///
+    /// Names are compared byte for byte after they're normalized and
+    /// encoded, and the entries of an [appended](ZipArchiveWriter::append)
+    /// archive are taken into account.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{DuplicateNamePolicy, ErrorKind, ZipArchiveWriter};
+    /// let mut output = Vec::new();
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .duplicate_names(DuplicateNamePolicy::Error)
+    ///     .build(&mut output);
+    /// archive.new_dir("dir/").create()?;
+    /// let err = archive.new_dir("dir/").create().unwrap_err();
+    /// assert!(matches!(err.kind(), ErrorKind::DuplicateName { .. }));
+    /// archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn duplicate_names(mut self, policy: DuplicateNamePolicy) -> Self {
+        self.options.duplicate_names = policy;
+        self
+    }
 
-        let file_header = FileHeader {
-            name_len,
-            compression_method: CompressionMethod::Store,
-            local_header_offset,
-            compressed_size: 0,
-            uncompressed_size: 0,
-            crc: 0,
-            flags,
-            modification_time: options.modification_time,
-            unix_permissions: options.unix_permissions,
-            extra_fields: options.extra_fields,
-        };
-        self.files.push(file_header);
+    /// Checks the archive against what `target` can read, such as the
+    /// compression methods it supports and whether it understands ZIP64.
+    ///
+    /// Entries are checked as they're added, including copied entries, and
+    /// the archive as a whole when it's finished. Depending on the
+    /// `policy`, an issue either fails with [`ErrorKind::Incompatible`] or
+    /// is returned by [`ZipArchiveSummary::compat_issues`]. Data descriptors
+    /// are always written with their signature, which some readers rely on.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{CompatIssue, CompatPolicy, CompatTarget, CompressionMethod, ZipArchiveWriter};
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_compat_target(CompatTarget::WindowsExplorer, CompatPolicy::Warn)
+    ///     .build(Vec::new());
+    /// let (mut entry, config) = archive
+    ///     .new_file("data.zst")
+    ///     .compression_method(CompressionMethod::Zstd)
+    ///     .start()?;
+    /// # let (_, descriptor) = config.wrap(&mut entry).finish()?;
+    /// # entry.finish(descriptor)?;
+    /// let (_, summary) = archive.finish_with_summary()?;
+    /// assert!(matches!(
+    ///     summary.compat_issues(),
+    ///     [CompatIssue::CompressionMethod { .. }]
+    /// ));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_compat_target(mut self, target: CompatTarget, policy: CompatPolicy) -> Self {
+        self.options.compat = Some((target, policy));
+        self
+    }
 
-        Ok(())
+    /// Sets the disk numbers recorded in the end of central directory: the
+    /// number of the disk it's on, and the number of the disk where the
+    /// central directory starts. They're otherwise those of the volumes
+    /// written to, which are 0 unless the archive is
+    /// [split](crate::SplitZipWriter).
+    ///
+    /// The ZIP64 end of central directory and its locator, when written,
+    /// carry the same numbers. Numbers of 65,535 or more are only recorded
+    /// there, so they make the archive ZIP64. Set the entries' numbers with
+    /// [`ZipFileBuilder::disk_number_start`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::ZipArchiveWriter;
+    /// let mut output = Vec::new();
+    /// let archive = ZipArchiveWriter::builder()
+    ///     .with_eocd_disk_numbers(3, 2)
+    ///     .build(&mut output);
+    /// archive.finish()?;
+    ///
+    /// assert_eq!(output[4..8], [3, 0, 2, 0]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_eocd_disk_numbers(mut self, disk: u32, directory_disk: u32) -> Self {
+        self.options.eocd_disks = Some((disk, directory_disk));
+        self
     }
 
-    /// Creates a builder for adding a new file to the archive.
+    /// Sets when the underlying writer is flushed, which by default is only
+    /// once the archive is finished.
+    ///
+    /// Flushing sooner lets a reader on the other end of a buffered writer,
+    /// such as a socket behind a [`BufWriter`](std::io::BufWriter), see each
+    /// entry as soon as it's written.
     ///
     /// # Example
     ///
     /// ```rust
-    /// # use std::io::{Cursor, Write};
-    /// # let mut output = Cursor::new(Vec::new());
-    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
-    /// let (mut entry, config) = archive.new_file("my-file")
-    ///     .compression_method(rawzip::CompressionMethod::Deflate)
-    ///     .unix_permissions(0o644)
-    ///     .start()?;
+    /// # use std::io::{BufWriter, Write};
+    /// # use rawzip::{FlushPolicy, ZipArchiveWriter};
+    /// let mut archive = ZipArchiveWriter::builder()
+    ///     .with_flush_policy(FlushPolicy::Entry)
+    ///     .build(BufWriter::new(Vec::new()));
+    /// let (mut entry, config) = archive.new_file("a.txt").start()?;
     /// let mut writer = config.wrap(&mut entry);
-    /// writer.write_all(b"Hello, world!")?;
-    /// let (_, output) = writer.finish()?;
-    /// entry.finish(output)?;
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    ///
+    /// // The entry has reached the inner writer
+    /// assert!(archive.get_ref().buffer().is_empty());
+    /// # archive.finish()?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    #[must_use]
-    pub fn new_file<'name>(&mut self, name: &'name str) -> ZipFileBuilder<'_, 'name, W> {
-        ZipFileBuilder {
-            archive: self,
-            name,
-            compression_method: CompressionMethod::Store,
-            modification_time: None,
-            unix_permissions: None,
-            extra_fields: ExtraFieldsContainer::new(),
-            crc32_option: Crc32Option::default(),
-        }
+    pub fn with_flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.options.flush_policy = policy;
+        self
     }
 
-    /// Adds a new file to the archive with options (internal method).
-    fn new_file_with_options(
-        &mut self,
-        name: &str,
-        mut options: ZipEntryOptions,
-    ) -> Result<ZipEntryWriter<'_, W>, Error> {
-        let file_path = ZipFilePath::from_str(name.trim_end_matches('/'));
+    /// Sets the number of threads that compress entries queued with
+    /// [`ZipFileBuilder::queue_compressed`] or started with
+    /// `ZipFileBuilder::start_deflate_parallel`.
+    ///
+    /// Defaults to the available parallelism. The threads are spawned when
+    /// the first entry is queued.
+    #[cfg(feature = "_codec")]
+    pub fn with_compression_threads(mut self, threads: usize) -> Self {
+        self.options.compression_threads = threads;
+        self
+    }
 
-        if file_path.len() > u16::MAX as usize {
-            return Err(Error::from(ErrorKind::InvalidInput {
-                msg: "file name too long".to_string(),
-            }));
+    /// Builds a `ZipArchiveWriter` that writes to `writer`.
+    pub fn build<W>(&self, writer: W) -> ZipArchiveWriter<W> {
+        let mut count = self.count;
+        if self.adjust_prefix_offsets {
+            count += self.prefix.len() as u64;
         }
 
-        let local_header_offset = self.writer.count();
-        let mut flags = FLAG_DATA_DESCRIPTOR;
-        if file_path.needs_utf8_encoding() {
-            flags |= FLAG_UTF8_ENCODING;
-        } else {
-            flags &= !FLAG_UTF8_ENCODING;
+        let mut writer = CountWriter::new(writer, count);
+        writer.buffer_size = self.buffer_size;
+        ZipArchiveWriter {
+            writer,
+            prefix: self.prefix.clone(),
+            files: Vec::with_capacity(self.capacity),
+            file_names: Vec::new(),
+            comment: Vec::new(),
+            extra_data: Vec::new(),
+            options: self.options.clone(),
+            seeker: None,
+            truncate: None,
+            discarded_end: 0,
+            open_entry: false,
+            guard: FinishGuard::default(),
+            names: None,
+            scratch: Vec::new(),
+            #[cfg(feature = "_codec")]
+            queue: None,
+            #[cfg(feature = "zstd")]
+            zstd_dictionary: None,
+            progress: None,
+            header_hook: None,
+            signer: None,
+            compat_issues: Vec::new(),
         }
+    }
 
-        // Store the name bytes in the central buffer
-        let name_bytes = file_path.as_ref().as_bytes();
-        let name_len = name_bytes.len() as u16;
-        self.file_names.extend_from_slice(name_bytes);
+    /// Builds a `ZipArchiveWriter` that seeks back to fill in the local file
+    /// header of each entry once its data is written.
+    ///
+    /// Entries written by a streaming writer are normally followed by a data
+    /// descriptor that holds the CRC32 and sizes, as they aren't known when
+    /// the local file header is written. Some strict readers reject data
+    /// descriptors, so with a seekable output, the local file header is
+    /// patched instead and no data descriptors are written.
+    ///
+    /// The local file header can't grow, so an entry whose sizes turn out to
+    /// require ZIP64 is still given a data descriptor.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::{Cursor, Write};
+    /// let mut output = Cursor::new(Vec::new());
+    /// let mut archive = rawzip::ZipArchiveWriter::builder().build_seekable(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let output = output.into_inner();
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert!(!entry.has_data_descriptor());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn build_seekable<W: Write + Seek>(&self, writer: W) -> ZipArchiveWriter<W> {
+        let mut archive = self.build(writer);
+        archive.seeker = Some(Seeker(seek_writer::<W>));
+        archive
+    }
 
-        self.write_local_header(&file_path, flags, options.compression_method, &mut options)?;
+    /// Builds a `ZipArchiveWriter` that writes a split archive across the
+    /// volumes of a [`SplitZipWriter`].
+    ///
+    /// Local header offsets and the central directory offset are relative to
+    /// the volume they are written to, and the central directory and end of
+    /// central directory record the volume numbers, so that the result can be
+    /// read by tools that support split archives.
+    pub fn build_split(&self, writer: SplitZipWriter) -> ZipArchiveWriter<SplitZipWriter> {
+        let written = writer.written();
+        let mut archive = self.build(writer);
+        archive.writer.count += written;
+        archive.writer.buffer_size = 0;
+        archive.writer.splitter = Some(Splitter {
+            position: SplitZipWriter::position,
+            reserve: SplitZipWriter::reserve,
+        });
+        archive
+    }
 
-        Ok(ZipEntryWriter {
-            inner: self,
-            compressed_bytes: 0,
-            name_len,
-            local_header_offset,
-            compression_method: options.compression_method,
-            flags,
-            modification_time: options.modification_time,
-            unix_permissions: options.unix_permissions,
-            extra_fields: options.extra_fields,
-        })
+    /// Opens an existing archive so that new entries can be appended to it
+    /// with the options of the builder.
+    ///
+    /// See [`ZipArchiveWriter::append`] for details. The
+    /// [prefix](ZipArchiveWriterBuilder::with_prefix) and
+    /// [offset](ZipArchiveWriterBuilder::with_offset) don't apply, as the
+    /// archive is already in place.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let path = std::env::temp_dir().join("rawzip-builder-append-doc.zip");
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(std::fs::File::create(&path)?);
+    /// # archive.new_dir("b/").create()?;
+    /// # archive.finish()?;
+    /// let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+    /// let mut archive = rawzip::ZipArchiveWriter::builder()
+    ///     .sort_central_directory(true)
+    ///     .append(file)?;
+    /// archive.new_dir("a/").create()?;
+    /// archive.finish()?;
+    ///
+    /// let data = std::fs::read(&path)?;
+    /// let archive = rawzip::ZipArchive::from_slice(&data)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.file_path().as_ref(), b"a/");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn append(&self, mut file: File) -> Result<ZipArchiveWriter<File>, Error> {
+        let end = file.seek(SeekFrom::End(0))?;
+        self.append_until(file, end)
     }
 
-    /// Finishes writing the archive and returns the underlying writer.
+    /// Reopens a partially written archive from a
+    /// [checkpoint](ZipArchiveWriter::checkpoint) with the options of the
+    /// builder.
     ///
-    /// This writes the central directory and the end of central directory
-    /// record. ZIP64 format is used automatically when thresholds are exceeded.
-    pub fn finish(mut self) -> Result<W, Error>
-    where
-        W: Write,
-    {
-        let central_directory_offset = self.writer.count();
-        let total_entries = self.files.len();
+    /// See [`ZipArchiveWriter::resume`] for details.
+    pub fn resume(
+        &self,
+        mut file: File,
+        checkpoint: &ZipCheckpoint,
+    ) -> Result<ZipArchiveWriter<File>, Error> {
+        file.seek(SeekFrom::Start(checkpoint.offset))?;
+        file.write_all(&checkpoint.directory)?;
+        let end = checkpoint.offset + checkpoint.directory.len() as u64;
+        self.append_until(file, end)
+    }
 
-        // Determine if we need ZIP64 format
-        let needs_zip64 = total_entries >= ZIP64_THRESHOLD_ENTRIES
-            || central_directory_offset >= ZIP64_THRESHOLD_OFFSET
-            || self.files.iter().any(|f| f.needs_zip64());
+    /// Opens the archive that ends at `end` for appending, leaving what
+    /// follows it to be truncated once the archive is finished.
+    fn append_until(&self, file: File, end: u64) -> Result<ZipArchiveWriter<File>, Error> {
+        let mut buffer = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+        let archive = ZipLocator::new()
+            .locate_in_reader(FileReader::from(file), &mut buffer, end)
+            .map_err(|(_, e)| e)?;
+
+        let mut files = Vec::new();
+        let mut file_names = Vec::new();
+        let mut entries = archive.entries(&mut buffer);
+        while let Some(record) = entries.next_entry()? {
+            files.push(FileHeader::from_record(&record, Header::CENTRAL)?);
+            file_names.extend_from_slice(record.file_path().as_bytes());
+        }
 
-        let mut name_offset = 0;
+        let mut comment = Vec::new();
+        archive.comment().read_to_end(&mut comment)?;
+
+        // New entries go where the archive extra data record was, which is
+        // written again ahead of the central directory
+        let mut extra_data = Vec::new();
+        let offset = match archive.archive_extra_data_offset(&mut buffer)? {
+            Some(offset) => {
+                let end = archive.directory_offset();
+                RangeReader::new(archive.get_ref(), offset + 8..end)
+                    .read_to_end(&mut extra_data)?;
+                offset
+            }
+            None => archive.directory_offset(),
+        };
+        let mut file = archive.into_inner().into_inner();
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut archive = self.build(file);
+        archive.files = files;
+        archive.file_names = file_names;
+        archive.comment = comment;
+        archive.extra_data = extra_data;
+        archive.prefix.clear();
+        archive.writer.count = offset;
+        archive.truncate = Some(truncate_file);
+        archive.guard.armed = true;
+        Ok(archive)
+    }
+}
 
-        // Write central directory entries
-        for file in &self.files {
-            // Version made by and version needed to extract
-            let version_needed = if file.needs_zip64() {
-                ZIP64_VERSION_NEEDED
-            } else {
-                20
-            };
+/// Create a new Zip archive.
+///
+/// Basic usage:
+/// ```rust
+/// use std::io::Write;
+///
+/// let mut output = std::io::Cursor::new(Vec::new());
+/// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+/// let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+/// let mut writer = config.wrap(&mut entry);
+/// writer.write_all(b"Hello, world!").unwrap();
+/// let (_, output) = writer.finish().unwrap();
+/// entry.finish(output).unwrap();
+/// archive.finish().unwrap();
+/// ```
+///
+/// Use the builder for customization:
+/// ```rust
+/// use std::io::Write;
+///
+/// let mut output = std::io::Cursor::new(Vec::<u8>::new());
+/// let mut _archive = rawzip::ZipArchiveWriter::builder()
+///     .with_capacity(1000)  // Optimize for 1000 anticipated files
+///     .build(&mut output);
+/// // ... add files as usual
+/// ```
+///
+/// The central directory is only written by
+/// [`finish`](ZipArchiveWriter::finish), so in debug builds, dropping an
+/// archive that has entries without finishing it panics. Use
+/// [`into_inner`](ZipArchiveWriter::into_inner) to abandon an archive
+/// deliberately.
+#[derive(Debug)]
+pub struct ZipArchiveWriter<W> {
+    files: Vec<FileHeader>,
+    file_names: Vec<u8>,
+    comment: Vec<u8>,
+    /// The data of the archive extra data record, which is left out when
+    /// empty.
+    extra_data: Vec<u8>,
+    prefix: Vec<u8>,
+    options: ZipArchiveOptions,
+    seeker: Option<Seeker<W>>,
+    /// Cuts the output off at the end of the archive once it's finished, for
+    /// files that held more than is written, like an archive appended to.
+    truncate: Option<fn(&mut W, u64) -> io::Result<()>>,
+    /// The end of the data of aborted entries that were rewound over, which
+    /// the central directory must not be written before.
+    discarded_end: u64,
+    /// Set while a [`ZipEntryWriter`] is out, so that an entry that is
+    /// dropped without being finished is caught.
+    open_entry: bool,
+    /// Catches archives that are dropped without being finished.
+    guard: FinishGuard,
+    /// The names of the entries, which are collected on first use when
+    /// duplicate names are checked or parent directories are created.
+    names: Option<HashSet<Vec<u8>>>,
+    /// Reused to assemble headers, so that writing entries doesn't allocate
+    /// once it has grown to fit them.
+    scratch: Vec<u8>,
+    /// Entries being compressed in the background.
+    #[cfg(feature = "_codec")]
+    queue: Option<EntryQueue>,
+    /// The dictionary that zstd entries are compressed against.
+    #[cfg(feature = "zstd")]
+    zstd_dictionary: Option<Arc<[u8]>>,
+    progress: Option<Progress>,
+    header_hook: Option<HeaderHook>,
+    signer: Option<DirectorySigner>,
+    /// The issues found by the compatibility check, when they're only
+    /// reported.
+    compat_issues: Vec<CompatIssue>,
+    writer: CountWriter<W>,
+}
+
+impl ZipArchiveWriter<()> {
+    /// Creates a `ZipArchiveWriterBuilder` for configuring the writer.
+    pub fn builder() -> ZipArchiveWriterBuilder {
+        ZipArchiveWriterBuilder::new()
+    }
+}
+
+impl<W> ZipArchiveWriter<W> {
+    /// Creates a new `ZipArchiveWriter` that writes to `writer`.
+    pub fn new(writer: W) -> Self {
+        ZipArchiveWriterBuilder::new().build(writer)
+    }
+
+    /// Returns the current offset in the output stream.
+    ///
+    /// Analagous to [`std::io::Cursor::position`].
+    ///
+    /// This can be used to determine various offsets during ZIP archive
+    /// creation:
+    ///
+    /// - Local header offset
+    /// - Start of compressed data offset
+    /// - End of compressed data offset
+    /// - End of data descriptor offset / next file's local header offset
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    ///
+    /// let mut output = std::io::Cursor::new(Vec::new());
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    ///
+    /// // 1. Get local header offset
+    /// let local_header_offset = archive.stream_offset();
+    /// let mut file = archive.new_file("test.txt").create().unwrap();
+    ///
+    /// // 2. Get start of data offset
+    /// let data_start_offset = file.stream_offset();
+    ///
+    /// // Write some data
+    /// let mut writer = rawzip::ZipDataWriter::new(&mut file);
+    /// writer.write_all(b"Hello World").unwrap();
+    /// let (_, desc) = writer.finish().unwrap();
+    ///
+    /// // 3. Get end of compressed data offset
+    /// let end_data_offset = file.stream_offset();
+    ///
+    /// let compressed_bytes = file.finish(desc).unwrap();
+    ///
+    /// // 4. Get end of data descriptor offset (next file's local header offset)
+    /// let end_descriptor_offset = archive.stream_offset();
+    ///
+    /// archive.finish().unwrap();
+    ///
+    /// assert_eq!(local_header_offset, 0);
+    /// assert!(data_start_offset > local_header_offset);
+    /// assert_eq!(end_data_offset, data_start_offset + b"Hello World".len() as u64);
+    /// assert_eq!(end_descriptor_offset, end_data_offset + 16); // 16 bytes for data descriptor
+    /// assert_eq!(compressed_bytes, end_data_offset - data_start_offset);
+    /// ```
+    pub fn stream_offset(&self) -> u64 {
+        self.writer.count()
+    }
+
+    /// Returns a reference to the underlying writer.
+    ///
+    /// What is [buffered](ZipArchiveWriterBuilder::with_write_buffer_size)
+    /// has yet to reach it.
+    pub fn get_ref(&self) -> &W {
+        &self.writer.writer
+    }
+
+    /// Returns the number of bytes held in the
+    /// [write buffer](ZipArchiveWriterBuilder::with_write_buffer_size), which
+    /// are counted by [`stream_offset`](ZipArchiveWriter::stream_offset) but
+    /// haven't been passed to the underlying writer.
+    pub fn buffered_len(&self) -> usize {
+        self.writer.buffer.len()
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Not public, as writing to it directly would throw off the offsets.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.writer.writer
+    }
+
+    /// Turns off the write buffer, for writers that are flushed through
+    /// [`get_mut`](ZipArchiveWriter::get_mut).
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn without_write_buffer(mut self) -> Self {
+        self.writer.buffer_size = 0;
+        self
+    }
+
+    /// Returns the underlying writer without finishing the archive.
+    ///
+    /// The central directory is not written, so what has been written is
+    /// not a valid archive. This is for abandoning an archive, such as when
+    /// a transfer is aborted, while keeping the writer. To complete the
+    /// archive and recover the writer, use
+    /// [`finish`](ZipArchiveWriter::finish).
+    ///
+    /// Bytes held in the
+    /// [write buffer](ZipArchiveWriterBuilder::with_write_buffer_size) are
+    /// discarded. Call [`write_buffered`](ZipArchiveWriter::write_buffered)
+    /// first to keep them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.new_dir("dir/").create()?;
+    /// let output = archive.into_inner();
+    /// assert!(rawzip::ZipArchive::from_slice(output).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn into_inner(mut self) -> W {
+        self.guard.armed = false;
+        self.writer.writer
+    }
+
+    /// Writes out the bytes held in the
+    /// [write buffer](ZipArchiveWriterBuilder::with_write_buffer_size)
+    /// without flushing the underlying writer.
+    pub fn write_buffered(&mut self) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        self.writer.drain()?;
+        Ok(())
+    }
+
+    /// Sets the archive comment that is written at the end of the archive.
+    ///
+    /// Returns an error if the comment is longer than 65,535 bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.set_comment(b"built by ci #42")?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// assert_eq!(archive.comment().as_bytes(), b"built by ci #42");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_comment(&mut self, comment: &[u8]) -> Result<(), Error> {
+        if comment.len() > u16::MAX as usize {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "archive comment too long".to_string(),
+            }));
+        }
+
+        self.comment.clear();
+        self.comment.extend_from_slice(comment);
+        Ok(())
+    }
+
+    /// Sets the data of the archive extra data record (4.3.11), which is
+    /// written between the last entry and the central directory.
+    ///
+    /// The record is meant for data about the archive as a whole, like a
+    /// detached signature manifest. It's written when the data isn't empty.
+    /// Read it back with [`ZipArchive::archive_extra_data`](crate::ZipArchive::archive_extra_data).
+    ///
+    /// Returns an error if the data is 4 GiB or longer.
+    pub fn set_archive_extra_data(&mut self, data: &[u8]) -> Result<(), Error> {
+        if u32::try_from(data.len()).is_err() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "archive extra data too long".to_string(),
+            }));
+        }
+
+        self.extra_data.clear();
+        self.extra_data.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Writes a zstd dictionary to the archive, which zstd entries written
+    /// afterwards with the built-in codec are compressed against.
+    ///
+    /// The dictionary is stored as an entry named [`ZSTD_DICTIONARY_NAME`](crate::ZSTD_DICTIONARY_NAME),
+    /// where readers find it with [`ZipArchive::zstd_dictionary`](crate::ZipArchive::zstd_dictionary). Entries
+    /// that are too small for zstd to pick up on the repetition between them
+    /// shrink considerably when compressed against a dictionary trained on
+    /// samples of them with [`train_zstd_dictionary`](crate::train_zstd_dictionary).
+    ///
+    /// This is a rawzip convention rather than part of the ZIP format: the
+    /// entries are marked as plain zstd, so other readers, like 7-Zip and
+    /// libarchive, fail to decompress them without the dictionary. Only
+    /// write a dictionary for archives that are read back by code that
+    /// passes [`ZipArchive::zstd_dictionary`](crate::ZipArchive::zstd_dictionary) to its decoder, as
+    /// [`transcode`](crate::transcode) and
+    /// [`ZipCopyBuilder::zstd_dictionary`] do.
+    ///
+    /// Returns an error if a dictionary has already been written.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{CompressionMethod, ZipArchive};
+    /// # use std::io::{Read, Write};
+    /// let samples = (0..200)
+    ///     .map(|i| format!(r#"{{"id": {i}, "kind": "sample", "tags": ["a", "b"]}}"#))
+    ///     .collect::<Vec<_>>();
+    /// let dictionary = rawzip::train_zstd_dictionary(&samples, 4096)?;
+    ///
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.set_zstd_dictionary(&dictionary)?;
+    /// let mut writer = archive.new_file("1.json").start_zstd(3)?;
+    /// writer.write_all(samples[1].as_bytes())?;
+    /// writer.finish()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let dictionary = archive.zstd_dictionary()?.unwrap();
+    /// let entry = archive.entries().nth(1).unwrap()?;
+    /// assert_eq!(entry.compression_method(), CompressionMethod::Zstd);
+    /// let entry = archive.get_entry(entry.wayfinder())?;
+    /// let decoder = zstd::Decoder::with_dictionary(entry.data(), dictionary)?;
+    /// let mut data = String::new();
+    /// entry.verifying_reader(decoder).read_to_string(&mut data)?;
+    /// assert_eq!(data, samples[1]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn set_zstd_dictionary(&mut self, dictionary: &[u8]) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        if self.zstd_dictionary.is_some() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "zstd dictionary already written".to_string(),
+            }));
+        }
+
+        let (mut entry, config) = self
+            .new_file(crate::ZSTD_DICTIONARY_NAME)
+            .compression_method(CompressionMethod::Store)
+            .start()?;
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(dictionary)?;
+        let (_, output) = writer.finish()?;
+        entry.finish(output)?;
+        self.zstd_dictionary = Some(Arc::from(dictionary));
+        Ok(())
+    }
+
+    /// Returns the dictionary that zstd entries are compressed against, if
+    /// one has been written.
+    #[cfg(feature = "_codec")]
+    fn dictionary(&self) -> Option<Arc<[u8]>> {
+        #[cfg(feature = "zstd")]
+        return self.zstd_dictionary.clone();
+        #[cfg(not(feature = "zstd"))]
+        None
+    }
+
+    /// Removes all entries with the given name from the central directory.
+    ///
+    /// The removed entries' data is left in place, so the archive is not any
+    /// smaller, but the entries will no longer be listed once the archive is
+    /// finished. To reclaim the space of archives opened in
+    /// [append](ZipArchiveWriter::append) mode, see
+    /// [`compact`](ZipArchiveWriter::compact).
+    ///
+    /// Names are compared against the raw bytes stored in the archive.
+    /// Replacing an entry is accomplished by removing it and adding a new
+    /// entry with the same name.
+    ///
+    /// Returns true if an entry was removed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.new_dir("old/").create()?;
+    /// archive.new_dir("new/").create()?;
+    /// assert!(archive.remove("old/"));
+    /// assert!(!archive.remove("missing/"));
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// assert_eq!(archive.entries_hint(), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn remove(&mut self, name: impl AsRef<[u8]>) -> bool {
+        let name = name.as_ref();
+        let mut name_offset = 0;
+        let mut removed = false;
+        for file in &mut self.files {
+            let new_name_offset = name_offset + file.name_len as usize;
+            if !file.removed && &self.file_names[name_offset..new_name_offset] == name {
+                file.removed = true;
+                removed = true;
+            }
+            name_offset = new_name_offset;
+        }
+        if let Some(names) = self.names.as_mut() {
+            names.remove(name);
+        }
+        removed
+    }
+
+    /// Returns an editor for the timestamp, permissions, and comment of the
+    /// entry named `name`, or `None` if there isn't one.
+    ///
+    /// Names are compared against the raw bytes stored in the archive. When
+    /// several entries share the name, the last one is edited, as that is
+    /// the one most extractors end up with.
+    ///
+    /// The changes are written with the central directory, leaving the
+    /// entry's data alone, so an archive opened with
+    /// [`append`](ZipArchiveWriter::append) is edited without rewriting its
+    /// payloads. The local file header keeps the old timestamp unless
+    /// [`update_local_headers`](ZipArchiveWriter::update_local_headers) is
+    /// called.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{time::{UtcDateTime, ZipDateTimeKind}, ZipArchive, ZipArchiveWriter};
+    /// # let path = std::env::temp_dir().join("rawzip-edit-doc.zip");
+    /// # let mut archive = ZipArchiveWriter::new(std::fs::File::create(&path)?);
+    /// # archive.new_dir("dir/").last_modified(UtcDateTime::from_unix(1_600_000_000)).create()?;
+    /// # archive.finish()?;
+    /// let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+    /// let mut archive = ZipArchiveWriter::append(file)?;
+    /// let mtime = UtcDateTime::from_unix(1_700_000_000);
+    /// let mut entry = archive.edit("dir/").unwrap();
+    /// entry.set_last_modified(mtime);
+    /// entry.set_comment("fixed")?;
+    /// archive.update_local_headers()?;
+    /// archive.finish()?;
+    ///
+    /// let data = std::fs::read(&path)?;
+    /// let archive = ZipArchive::from_slice(&data)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(mtime));
+    /// assert_eq!(entry.comment().as_bytes(), b"fixed");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn edit(&mut self, name: impl AsRef<[u8]>) -> Option<ZipEntryEdit<'_>> {
+        let name = name.as_ref();
+        let mut name_offset = 0;
+        let mut found = None;
+        for (i, file) in self.files.iter().enumerate() {
+            let new_name_offset = name_offset + file.name_len as usize;
+            if !file.removed && &self.file_names[name_offset..new_name_offset] == name {
+                found = Some(i);
+            }
+            name_offset = new_name_offset;
+        }
+
+        let header = &mut self.files[found?];
+        Some(ZipEntryEdit { header })
+    }
+
+    /// Returns the names of the entries that haven't been removed.
+    pub(crate) fn entry_names(&self) -> impl Iterator<Item = &[u8]> {
+        let mut name_offset = 0;
+        self.files.iter().filter_map(move |file| {
+            let start = name_offset;
+            name_offset += file.name_len as usize;
+            (!file.removed).then(|| &self.file_names[start..name_offset])
+        })
+    }
+
+    /// Sets a callback that is updated as entries are written, so that
+    /// frontends can render progress without wrapping every reader and
+    /// writer themselves.
+    ///
+    /// The callback is invoked whenever compressed data is written to the
+    /// archive and when an entry is completed. Compressors buffer their
+    /// output, so updates arrive in bursts rather than on every write of
+    /// uncompressed data.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let completed = Arc::new(AtomicU64::new(0));
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let counter = Arc::clone(&completed);
+    /// archive.on_progress(move |progress| {
+    ///     counter.store(progress.entries_completed(), Ordering::Relaxed);
+    /// });
+    ///
+    /// let (mut entry, config) = archive.new_file("file.txt").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.new_dir("dir/").create()?;
+    /// archive.finish()?;
+    ///
+    /// assert_eq!(completed.load(Ordering::Relaxed), 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn on_progress(&mut self, callback: impl FnMut(&ZipProgress) + Send + 'static) {
+        self.progress = Some(Progress {
+            callback: Box::new(callback),
+            consumed: Arc::new(AtomicU64::new(0)),
+            entries: 0,
+        });
+    }
+
+    /// Sets a callback that is handed the header fields of every entry right
+    /// before they're written, whether the entry was added with a builder or
+    /// copied from another archive.
+    ///
+    /// This is a single place to enforce policies on the archive, like
+    /// normalizing timestamps or permissions. An error returned by the
+    /// callback is returned by whichever call was writing the entry, and the
+    /// entry is not written.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::{extra_fields::ExtraFieldId, time::UtcDateTime, ZipArchive};
+    /// let epoch = UtcDateTime::from_components(1980, 1, 1, 0, 0, 0, 0).unwrap();
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.on_header(move |header| {
+    ///     header.set_dos_last_modified(epoch);
+    ///     header.retain_extra_fields(|id, _| id != ExtraFieldId::EXTENDED_TIMESTAMP);
+    ///     Ok(())
+    /// });
+    ///
+    /// let now = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 0).unwrap();
+    /// archive.new_dir("dir/").last_modified(now).create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.dos_last_modified().year(), 1980);
+    /// assert_eq!(entry.extra_fields().count(), 0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn on_header(
+        &mut self,
+        callback: impl FnMut(&mut ZipHeaderFields<'_, '_>) -> Result<(), Error> + Send + 'static,
+    ) {
+        self.header_hook = Some(HeaderHook(Box::new(callback)));
+    }
+
+    /// Sets a callback that signs the central directory when the archive is
+    /// finished.
+    ///
+    /// The callback is given the serialized central directory headers and
+    /// returns the signature, which is written in the digital signature
+    /// record (4.3.13) that follows them. The format of the signature is up
+    /// to the caller. A signature longer than 65,535 bytes is an error.
+    ///
+    /// The directory of a [checkpoint](ZipArchiveWriter::checkpoint) isn't
+    /// signed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rawzip::ZipArchive;
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.sign_directory(|directory| Ok(rawzip::crc32(directory).to_le_bytes().to_vec()));
+    /// archive.new_dir("dir/").create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = ZipArchive::from_slice(&output)?;
+    /// assert_eq!(archive.entries().count(), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn sign_directory(
+        &mut self,
+        signer: impl FnMut(&[u8]) -> Result<Vec<u8>, Error> + Send + 'static,
+    ) {
+        self.signer = Some(DirectorySigner(Box::new(signer)));
+    }
+
+    /// Invokes the header callback, if there is one, and checks the name and
+    /// comment that it may have changed.
+    fn apply_header_hook(
+        &mut self,
+        name: &mut Cow<'_, [u8]>,
+        header: &mut FileHeader,
+    ) -> Result<(), Error> {
+        let Some(hook) = self.header_hook.as_mut() else {
+            return Ok(());
+        };
+
+        (hook.0)(&mut ZipHeaderFields {
+            name: &mut *name,
+            header: &mut *header,
+        })?;
+
+        if let Cow::Owned(name) = name {
+            if self.options.validate_names {
+                validate_name(name)?;
+            }
+            header.name_len = u16::try_from(name.len()).map_err(|_| {
+                Error::from(ErrorKind::InvalidInput {
+                    msg: "file name too long".to_string(),
+                })
+            })?;
+        }
+
+        if header.comment.len() > u16::MAX as usize {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "file comment too long".to_string(),
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Invokes the progress callback, if there is one, for the current
+    /// entry. A completed entry reports its final uncompressed size.
+    fn report_progress(&mut self, produced: u64, completed: Option<u64>) {
+        let stream_offset = self.writer.count();
+        let Some(progress) = self.progress.as_mut() else {
+            return;
+        };
+
+        let consumed = match completed {
+            Some(uncompressed_size) => {
+                progress.entries += 1;
+                uncompressed_size
+            }
+            None => progress.consumed.load(Ordering::Relaxed),
+        };
+
+        (progress.callback)(&ZipProgress {
+            entries_completed: progress.entries,
+            entry_consumed: consumed,
+            entry_produced: produced,
+            entry_completed: completed.is_some(),
+            stream_offset,
+        });
+    }
+}
+
+impl<W: Read + Write + Seek> ZipArchiveWriter<W> {
+    /// Writes the timestamps changed with [`edit`](ZipArchiveWriter::edit)
+    /// to the local file headers of the edited entries.
+    ///
+    /// The MS-DOS time and the times kept in the extended timestamp, NTFS,
+    /// and Unix extra fields are overwritten in place, as the header can't
+    /// change size without moving the data that follows it. A local header
+    /// without one of these fields doesn't gain it.
+    pub fn update_local_headers(&mut self) -> Result<(), Error> {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        let end = self.writer.count();
+        let file = self.writer.inner_mut()?;
+        let mut position = end;
+        let mut variable = Vec::new();
+        for header in &mut self.files {
+            let Some(time) = header.edited_last_modified.take() else {
+                continue;
+            };
+
+            let offset = header.local_header_offset;
+            file.seek(SeekFrom::Current(offset as i64 - position as i64))?;
+            let mut fixed = [0u8; ZipLocalFileHeaderFixed::SIZE];
+            file.read_exact(&mut fixed)?;
+            let mut local = ZipLocalFileHeaderFixed::parse(&fixed)?;
+            local.last_mod_time = header.last_mod_time;
+            local.last_mod_date = header.last_mod_date;
+
+            let name_len = usize::from(local.file_name_len);
+            variable.resize(name_len + usize::from(local.extra_field_len), 0);
+            file.read_exact(&mut variable)?;
+            crate::extra_fields::for_each_field_mut(&mut variable[name_len..], |id, data| {
+                set_extra_field_mtime(id, data, &time)
+            });
+
+            let len = (ZipLocalFileHeaderFixed::SIZE + variable.len()) as i64;
+            file.seek(SeekFrom::Current(-len))?;
+            local.write(&mut *file)?;
+            file.write_all(&variable)?;
+            position = offset + len as u64;
+        }
+
+        file.seek(SeekFrom::Current(end as i64 - position as i64))?;
+        Ok(())
+    }
+}
+
+impl ZipArchiveWriter<File> {
+    /// Opens an existing archive so that new entries can be appended to it.
+    ///
+    /// The central directory and comment of the archive are read into memory.
+    /// New entries are written in place of the central directory, and the
+    /// combined central directory is written on
+    /// [`finish`](ZipArchiveWriter::finish), which then truncates the file.
+    ///
+    /// The archive is left as is until the first entry is written, after
+    /// which it is unreadable until `finish` succeeds.
+    ///
+    /// Use [`ZipArchiveWriterBuilder::append`] to write the new entries with
+    /// other options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let path = std::env::temp_dir().join("rawzip-append-doc.zip");
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(std::fs::File::create(&path)?);
+    /// # archive.new_dir("existing/").create()?;
+    /// # archive.finish()?;
+    /// let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+    /// let mut archive = rawzip::ZipArchiveWriter::append(file)?;
+    /// let (mut entry, config) = archive.new_file("existing/new.txt").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, output) = writer.finish()?;
+    /// entry.finish(output)?;
+    /// archive.finish()?;
+    ///
+    /// let data = std::fs::read(&path)?;
+    /// let archive = rawzip::ZipArchive::from_slice(&data)?;
+    /// assert_eq!(archive.entries_hint(), 2);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn append(file: File) -> Result<Self, Error> {
+        ZipArchiveWriter::builder().append(file)
+    }
+
+    /// Reopens a partially written archive from a
+    /// [checkpoint](ZipArchiveWriter::checkpoint), so that a job that was
+    /// interrupted can pick up where it left off.
+    ///
+    /// The checkpointed central directory is written back in place, over
+    /// whatever was written after the checkpoint, which leaves a valid
+    /// archive that is then opened like [`append`](ZipArchiveWriter::append).
+    /// The leftovers beyond it are truncated away on
+    /// [`finish`](ZipArchiveWriter::finish). The offsets in the checkpoint
+    /// must be relative to the start of the file.
+    ///
+    /// Use [`ZipArchiveWriterBuilder::resume`] to write the new entries with
+    /// other options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let path = std::env::temp_dir().join("rawzip-resume-doc.zip");
+    /// let mut archive = rawzip::ZipArchiveWriter::new(std::fs::File::create(&path)?);
+    /// archive.new_dir("done/").create()?;
+    /// let checkpoint = archive.checkpoint()?.to_bytes();
+    /// archive.new_dir("lost/").create()?;
+    /// // The job crashes before the archive is finished
+    /// drop(archive.into_inner());
+    ///
+    /// let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+    /// let checkpoint = rawzip::ZipCheckpoint::from_bytes(&checkpoint)?;
+    /// let mut archive = rawzip::ZipArchiveWriter::resume(file, &checkpoint)?;
+    /// archive.new_dir("resumed/").create()?;
+    /// archive.finish()?;
+    ///
+    /// let data = std::fs::read(&path)?;
+    /// let archive = rawzip::ZipArchive::from_slice(&data)?;
+    /// assert_eq!(archive.entries_hint(), 2);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn resume(file: File, checkpoint: &ZipCheckpoint) -> Result<Self, Error> {
+        ZipArchiveWriter::builder().resume(file, checkpoint)
+    }
+
+    /// Reclaims the space occupied by [removed](ZipArchiveWriter::remove)
+    /// entries.
+    ///
+    /// The data of the remaining entries is shifted down to fill the gaps left
+    /// behind and the file is truncated once the archive is
+    /// [finished](ZipArchiveWriter::finish). An entry is assumed to occupy the
+    /// bytes up to the next entry's local header, so data that sits between
+    /// entries is kept with the entry that precedes it. Any data prior to the
+    /// first entry is left untouched.
+    pub fn compact(&mut self) -> Result<(), Error> {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        let end = self.writer.count();
+        let mut order = (0..self.files.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| self.files[i].local_header_offset);
+
+        let file = self.writer.inner_mut()?;
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut write_pos = order
+            .first()
+            .map(|&i| self.files[i].local_header_offset)
+            .unwrap_or(end);
+
+        for (pos, &i) in order.iter().enumerate() {
+            let start = self.files[i].local_header_offset;
+            let stop = order
+                .get(pos + 1)
+                .map(|&j| self.files[j].local_header_offset)
+                .unwrap_or(end);
+            if self.files[i].removed {
+                continue;
+            }
+
+            if start != write_pos {
+                let mut copied = 0;
+                while copied < stop - start {
+                    let len = buffer.len().min((stop - start - copied) as usize);
+                    file.seek(SeekFrom::Start(start + copied))?;
+                    file.read_exact(&mut buffer[..len])?;
+                    file.seek(SeekFrom::Start(write_pos + copied))?;
+                    file.write_all(&buffer[..len])?;
+                    copied += len as u64;
+                }
+            }
+
+            self.files[i].local_header_offset = write_pos;
+            write_pos += stop - start;
+        }
+
+        // Drop the removed entries for good
+        let mut file_names = Vec::with_capacity(self.file_names.len());
+        let mut name_offset = 0;
+        for header in &self.files {
+            let new_name_offset = name_offset + header.name_len as usize;
+            if !header.removed {
+                file_names.extend_from_slice(&self.file_names[name_offset..new_name_offset]);
+            }
+            name_offset = new_name_offset;
+        }
+        self.files.retain(|x| !x.removed);
+        self.file_names = file_names;
+
+        file.seek(SeekFrom::Start(write_pos))?;
+        self.writer.count = write_pos;
+        self.truncate = Some(truncate_file);
+        Ok(())
+    }
+
+    /// Replaces the entry named `name` with the one that `write` adds to the
+    /// archive it's handed, leaving the other entries where they are.
+    ///
+    /// The replacement is written to a buffer first, through an archive with
+    /// the same options, zstd dictionary,
+    /// and [header hook](ZipArchiveWriter::on_header), and must be a single
+    /// entry with the same name. It's only given the
+    /// [JAR marker](ZipArchiveWriterBuilder::jar_marker) when it replaces the
+    /// first entry, and its parent directories aren't
+    /// [created](ZipArchiveWriterBuilder::create_parent_directories) again. It
+    /// then takes the place of the old entry when it fits in the bytes up to
+    /// the next entry, or when the old entry is the last one, in which case
+    /// the file is truncated after it. Otherwise, the old entry is
+    /// [removed](ZipArchiveWriter::remove) and the replacement is written
+    /// after the last entry, with [`compact`](ZipArchiveWriter::compact)
+    /// left to reclaim the space. Either way, the data of the other entries
+    /// isn't touched.
+    ///
+    /// When several entries share the name, the last one is replaced.
+    /// Padding for [alignment](ZipFileBuilder::alignment) is worked out for
+    /// the old entry's position, so an aligned entry that doesn't fit is
+    /// written unaligned.
+    ///
+    /// Returns true if the replacement was written in place.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let path = std::env::temp_dir().join("rawzip-replace-doc.zip");
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(std::fs::File::create(&path)?);
+    /// # archive.add_bytes("config.toml", b"debug = true\nlevel = 3\n")?;
+    /// # archive.add_bytes("payload.bin", &[0u8; 1024])?;
+    /// # archive.finish()?;
+    /// let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+    /// let mut archive = rawzip::ZipArchiveWriter::append(file)?;
+    /// let in_place = archive.replace("config.toml", |archive| {
+    ///     archive.add_bytes("config.toml", b"debug = false\n")?;
+    ///     Ok(())
+    /// })?;
+    /// assert!(in_place);
+    /// archive.finish()?;
+    ///
+    /// let data = std::fs::read(&path)?;
+    /// let archive = rawzip::ZipArchive::from_slice(&data)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// let entry = archive.get_entry(entry.wayfinder())?;
+    /// assert_eq!(entry.data(), b"debug = false\n");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn replace<F>(&mut self, name: impl AsRef<[u8]>, write: F) -> Result<bool, Error>
+    where
+        F: FnOnce(&mut ZipArchiveWriter<Vec<u8>>) -> Result<(), Error>,
+    {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        let name = name.as_ref();
+        let mut name_offset = 0;
+        let mut found = None;
+        for (i, file) in self.files.iter().enumerate() {
+            let new_name_offset = name_offset + file.name_len as usize;
+            if !file.removed && &self.file_names[name_offset..new_name_offset] == name {
+                found = Some(i);
+            }
+            name_offset = new_name_offset;
+        }
+        let index = found.ok_or_else(|| {
+            Error::from(ErrorKind::InvalidInput {
+                msg: "no entry to replace".to_string(),
+            })
+        })?;
+
+        let end = self.writer.count();
+        let start = self.files[index].local_header_offset;
+        let slot_end = self
+            .files
+            .iter()
+            .map(|file| file.local_header_offset)
+            .filter(|&offset| offset > start)
+            .min()
+            .unwrap_or(end);
+
+        // The replacement takes the place of an existing entry, so only the
+        // first entry is marked as a JAR, and its parent directories are
+        // already in the archive
+        let mut options = self.options.clone();
+        options.jar_marker &= index == 0;
+        options.create_parent_directories = false;
+        let mut replacement = ZipArchiveWriterBuilder {
+            count: start,
+            options,
+            ..ZipArchiveWriterBuilder::default()
+        }
+        .build(Vec::new());
+        #[cfg(feature = "zstd")]
+        {
+            replacement.zstd_dictionary = self.zstd_dictionary.clone();
+        }
+        replacement.header_hook = self.header_hook.take();
+        let written = write(&mut replacement);
+        self.header_hook = replacement.header_hook.take();
+        written?;
+        replacement.check_open_entry()?;
+        replacement.write_queued(0)?;
+        replacement.guard.armed = false;
+        if replacement.files.len() != 1 || replacement.file_names != name {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "replacement must be a single entry of the same name".to_string(),
+            }));
+        }
+        let mut header = replacement.files.remove(0);
+        let bytes = std::mem::take(&mut replacement.writer.writer);
+        self.compat_issues.append(&mut replacement.compat_issues);
+
+        let len = bytes.len() as u64;
+        if slot_end == end || start + len <= slot_end {
+            let file = self.writer.inner_mut()?;
+            file.seek(SeekFrom::Start(start))?;
+            file.write_all(&bytes)?;
+            if slot_end == end {
+                self.writer.count = start + len;
+                self.truncate = Some(truncate_file);
+            } else {
+                file.seek(SeekFrom::Start(end))?;
+            }
+            self.files[index] = header;
+            return Ok(true);
+        }
+
+        header.local_header_offset = end;
+        self.writer.write_all(&bytes)?;
+        self.files[index].removed = true;
+        self.files.push(header);
+        self.file_names.extend_from_slice(name);
+        Ok(false)
+    }
+}
+
+/// How paths that aren't valid UTF-8 are turned into entry names by
+/// [`ZipArchiveWriter::new_file_path`] and
+/// [`ZipArchiveWriter::new_dir_path`].
+///
+/// Paths that are valid UTF-8 are always normalized like the names given to
+/// [`ZipArchiveWriter::new_file`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathEncoding {
+    /// Return an [`ErrorKind::InvalidPath`] error.
+    #[default]
+    Strict,
+    /// Replace invalid sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    Lossy,
+    /// On Unix, write the path's bytes verbatim, as
+    /// [`ZipArchiveWriter::new_file_bytes`] does. Elsewhere, paths aren't
+    /// made of bytes, so this is the same as `Strict`.
+    Raw,
+}
+
+/// What [`ZipArchiveWriter`] does when an entry is added under a name that
+/// was already written.
+///
+/// Set with [`ZipArchiveWriterBuilder::duplicate_names`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateNamePolicy {
+    /// Write every entry, leaving it to readers to pick one of them.
+    #[default]
+    Allow,
+    /// Fail with [`ErrorKind::DuplicateName`] before anything is written for
+    /// the later entry.
+    Error,
+    /// Replace the entry that was written first with the later one once the
+    /// later one is finished.
+    ///
+    /// The earlier entry is [removed](ZipArchiveWriter::remove) from the
+    /// central directory, but its data can't be taken back from a streaming
+    /// output and is left in place as dead space.
+    Overwrite,
+}
+
+/// What [`ZipArchiveWriter`] does with modification times that the MS-DOS
+/// date and time can't represent, which are those before 1980 or after 2107.
+///
+/// Set with [`ZipArchiveWriterBuilder::dos_timestamp_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DosTimestampPolicy {
+    /// Clamp the year into the MS-DOS range, keeping the rest of the date
+    /// and time.
+    #[default]
+    Clamp,
+    /// Fail with [`ErrorKind::InvalidInput`] before anything is written for
+    /// the entry.
+    Error,
+    /// Zero the MS-DOS date and time, leaving the time to the extended
+    /// timestamp field.
+    ///
+    /// The extended timestamp only holds times from 1970 to 2106, so the
+    /// NTFS field is added for times outside of that range.
+    ExtendedOnly,
+}
+
+/// When the underlying writer is flushed, set with
+/// [`ZipArchiveWriterBuilder::with_flush_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Only when the archive is finished. Flushing an entry, as
+    /// [`ZipDataWriter::finish`] does, doesn't reach the archive's writer.
+    #[default]
+    Finish,
+    /// After each entry is written in full, as well as when the archive is
+    /// finished.
+    Entry,
+    /// After each entry's local file header, so that the receiving end can
+    /// start on an entry before its data arrives, as well as after each
+    /// entry and when the archive is finished.
+    Header,
+}
+
+/// The width of the sizes written to data descriptors.
+///
+/// A data descriptor holds an entry's CRC32 followed by its compressed and
+/// uncompressed sizes, which are either 4 or 8 bytes each (4.3.9).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataDescriptorWidth {
+    /// Use 8 byte sizes when the local file header carries a ZIP64 extra
+    /// field, such as when ZIP64 is [forced](ZipArchiveWriterBuilder::force_zip64),
+    /// or when the sizes don't fit in 4 bytes, and 4 byte sizes otherwise. A
+    /// lowered [threshold](ZipArchiveWriterBuilder::with_zip64_threshold)
+    /// doesn't widen the descriptor of an entry that isn't otherwise ZIP64.
+    #[default]
+    Auto,
+    /// Always use 8 byte sizes.
+    Bits64,
+    /// Always use 4 byte sizes. Finishing an entry whose sizes don't fit in
+    /// 4 bytes is an error.
+    Bits32,
+}
+
+/// Options for CRC32 calculation in ZIP files.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Crc32Option {
+    /// Calculate CRC32 automatically from the data.
+    #[default]
+    Calculate,
+    /// Use a custom CRC32 value and skip calculation.
+    Custom(u32),
+    /// Skip CRC32 calculation entirely (sets CRC32 to 0).
+    Skip,
+}
+
+impl Crc32Option {
+    /// Returns the initial CRC32 value for this option.
+    #[inline]
+    pub fn initial_value(&self) -> u32 {
+        match self {
+            Crc32Option::Calculate => 0,
+            Crc32Option::Custom(value) => *value,
+            Crc32Option::Skip => 0,
+        }
+    }
+}
+
+/// The name of an entry as given by the caller.
+#[derive(Debug, Clone)]
+enum EntryName<'a> {
+    /// A name that is normalized and encoded as needed.
+    Str(Cow<'a, str>),
+
+    /// A name that is written to the headers verbatim.
+    Raw(Cow<'a, [u8]>),
+}
+
+impl EntryName<'_> {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            EntryName::Str(name) => name.as_bytes(),
+            EntryName::Raw(name) => name,
+        }
+    }
+
+    /// Returns the name without trailing slashes, so that a file isn't
+    /// mistaken for a directory.
+    fn trim_dir(&self) -> EntryName<'_> {
+        match self {
+            EntryName::Str(name) => EntryName::Str(Cow::Borrowed(name.trim_end_matches('/'))),
+            EntryName::Raw(name) => {
+                let len = name.iter().rposition(|&c| c != b'/').map_or(0, |i| i + 1);
+                EntryName::Raw(Cow::Borrowed(&name[..len]))
+            }
+        }
+    }
+
+    #[cfg(feature = "_codec")]
+    fn into_owned(self) -> EntryName<'static> {
+        match self {
+            EntryName::Str(name) => EntryName::Str(Cow::Owned(name.into_owned())),
+            EntryName::Raw(name) => EntryName::Raw(Cow::Owned(name.into_owned())),
+        }
+    }
+}
+
+impl<W> ZipArchiveWriter<W>
+where
+    W: Write,
+{
+    /// Writes the entries queued with [`ZipFileBuilder::queue_compressed`]
+    /// that have finished compressing, waiting on the oldest ones while more
+    /// than `max_pending` remain.
+    #[cfg(feature = "_codec")]
+    fn write_queued(&mut self, max_pending: u64) -> Result<(), Error> {
+        // Taken so that adding the entries doesn't recurse into here
+        let Some(mut queue) = self.queue.take() else {
+            return Ok(());
+        };
+
+        let result = loop {
+            let block = queue.pool.pending() > max_pending;
+            let Some(compressed) = queue.pool.next(block) else {
+                break Ok(());
+            };
+
+            let Some((name, mut options)) = queue.entries.pop_front() else {
+                break Ok(());
+            };
+
+            let written = compressed.and_then(|compressed| {
+                options.compression_method = compressed.method;
+                let output = DataDescriptorOutput {
+                    crc: compressed.crc,
+                    compressed_size: 0,
+                    uncompressed_size: compressed.uncompressed_size,
+                };
+
+                let mut entry = self.new_file_with_options(&name, options)?;
+                entry.write_all(&compressed.payload)?;
+                entry.finish(output)
+            });
+
+            if let Err(e) = written {
+                break Err(e);
+            }
+        };
+
+        self.queue = Some(queue);
+        result
+    }
+
+    #[cfg(not(feature = "_codec"))]
+    fn write_queued(&mut self, _max_pending: u64) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Returns an error if the last [`ZipEntryWriter`] was dropped without
+    /// being finished or aborted, or failed to finish, as its local file
+    /// header and data have already been written and the archive is unusable.
+    fn check_open_entry(&self) -> Result<(), Error> {
+        if self.open_entry {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "previous entry was left unfinished".to_string(),
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Reorders the entries by name for the central directory. The sort is
+    /// stable, so entries with the same name keep their order.
+    fn sort_entries(&mut self) {
+        let mut name_offset = 0;
+        let mut entries = std::mem::take(&mut self.files)
+            .into_iter()
+            .map(|file| {
+                let start = name_offset;
+                name_offset += file.name_len as usize;
+                (start..name_offset, file)
+            })
+            .collect::<Vec<_>>();
+        entries
+            .sort_by(|(a, _), (b, _)| self.file_names[a.clone()].cmp(&self.file_names[b.clone()]));
+
+        let mut file_names = Vec::with_capacity(self.file_names.len());
+        for (name, file) in entries {
+            file_names.extend_from_slice(&self.file_names[name]);
+            self.files.push(file);
+        }
+        self.file_names = file_names;
+    }
+
+    /// Adds a completed entry to the central directory.
+    fn push_entry(&mut self, header: FileHeader) -> Result<(), Error> {
+        if !header.removed {
+            self.report_progress(header.compressed_size, Some(header.uncompressed_size));
+        }
+
+        let overwrite = self.options.duplicate_names == DuplicateNamePolicy::Overwrite;
+        if !header.removed && (overwrite || self.names.is_some()) {
+            let name = self.file_names[self.file_names.len() - header.name_len as usize..].to_vec();
+            // The entry isn't in `files` yet, so only the earlier ones are
+            // removed
+            if overwrite && self.name_set().contains(&name) {
+                self.remove(&name);
+            }
+            self.name_set().insert(name);
+        }
+
+        self.files.push(header);
+        self.guard.armed = true;
+        if self.options.flush_policy != FlushPolicy::Finish {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the prefix given to [`ZipArchiveWriterBuilder::with_prefix`],
+    /// if it hasn't been written yet.
+    ///
+    /// Whether the prefix counts towards the stream offset was settled when
+    /// the writer was built, so it bypasses the count.
+    fn write_prefix(&mut self) -> Result<(), Error> {
+        if !self.prefix.is_empty() {
+            self.writer.inner_mut()?.write_all(&self.prefix)?;
+            self.prefix = Vec::new();
+        }
+
+        Ok(())
+    }
+
+    /// Writes a local file header with filtered extra fields.
+    ///
+    /// The CRC and sizes are only written when the entry does not use a data
+    /// descriptor, as they must be zero otherwise (4.4.4).
+    fn write_local_header(&mut self, file_name: &[u8], file: &FileHeader) -> Result<(), Error> {
+        // Assembled first, so that the header is written at once
+        let buffer = &mut self.scratch;
+        buffer.clear();
+        let header = local_header_fixed(file_name.len() as u16, file);
+        header.write(&mut *buffer)?;
+        buffer.extend_from_slice(file_name);
+        file.extra_fields
+            .write_extra_fields(&mut *buffer, Header::LOCAL)?;
+        self.writer.write_all(&self.scratch)?;
+        if self.options.flush_policy == FlushPolicy::Header {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the fixed portion of an entry's local file header now that
+    /// its CRC32 and sizes are known.
+    ///
+    /// When the sizes require ZIP64, which the local header has no room for,
+    /// the entry is switched over to a data descriptor instead. A ZIP64 extra
+    /// field reserved by [`force_zip64`](ZipArchiveWriterBuilder::force_zip64)
+    /// is filled in alongside the fixed portion.
+    fn patch_local_header(&mut self, seeker: Seeker<W>, file: &mut FileHeader) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        if file.force_zip64 {
+            file.finalize_local_extra_fields()?;
+        } else if file.needs_local_zip64() {
+            file.flags |= FLAG_DATA_DESCRIPTOR;
+            file.version_needed = file.version_needed.max(ZIP64_VERSION_NEEDED);
+            file.write_data_descriptor(&mut self.writer, self.options.data_descriptor_width)?;
+        }
+
+        let name_len = file.name_len;
+        let header = local_header_fixed(name_len, file);
+        let distance = (self.writer.count() - file.local_header_offset) as i64;
+        let mut written = ZipLocalFileHeaderFixed::SIZE as i64;
+        (seeker.0)(self.writer.inner_mut()?, SeekFrom::Current(-distance))?;
+        header.write(self.writer.inner_mut()?)?;
+        if file.force_zip64 {
+            (seeker.0)(
+                self.writer.inner_mut()?,
+                SeekFrom::Current(i64::from(name_len)),
+            )?;
+            file.extra_fields
+                .write_extra_fields(self.writer.inner_mut()?, Header::LOCAL)?;
+            written += i64::from(name_len) + i64::from(file.extra_fields.local_size);
+        }
+        (seeker.0)(
+            self.writer.inner_mut()?,
+            SeekFrom::Current(distance - written),
+        )?;
+        Ok(())
+    }
+
+    /// Creates a builder for adding a new directory to the archive.
+    ///
+    /// The name of the directory must end with a `/` or `\`. It is
+    /// normalized the same way as the names given to
+    /// [`new_file`](ZipArchiveWriter::new_file).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Cursor;
+    /// # let mut output = Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.new_dir("my-dir/")
+    ///     .unix_permissions(0o755)
+    ///     .create()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn new_dir<'a>(&'a mut self, name: &'a str) -> ZipDirBuilder<'a, W> {
+        ZipDirBuilder {
+            archive: self,
+            name: EntryName::Str(Cow::Borrowed(name)),
+            // Directories always use Store
+            options: ZipEntryOptions::new(CompressionMethod::Store),
+        }
+    }
+
+    /// Writes the `mimetype` entry that EPUB and OpenDocument files begin
+    /// with.
+    ///
+    /// Both formats require the first entry of the archive to be an
+    /// uncompressed file named `mimetype` without extra fields, so that the
+    /// media type can be sniffed at a fixed offset. The entry is written with
+    /// its CRC32 and size in the local file header and has no data
+    /// descriptor. Returns an error if any entry has already been added.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.write_mimetype("application/epub+zip")?;
+    /// archive.new_dir("META-INF/").create()?;
+    /// archive.finish()?;
+    ///
+    /// assert_eq!(&output[30..58], b"mimetypeapplication/epub+zip");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_mimetype(&mut self, mimetype: &str) -> Result<(), Error> {
+        if !self.files.is_empty() || !self.file_names.is_empty() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "the mimetype must be the first entry".to_string(),
+            }));
+        }
+
+        self.new_stored_file_with_options(
+            "mimetype",
+            mimetype.as_bytes(),
+            ZipEntryOptions::new(CompressionMethod::Store),
+        )
+    }
+
+    /// Adds a symbolic link that points to `target`.
+    ///
+    /// The link is written with stored compression and a Unix mode of
+    /// `0o120777`, unless other permissions are given.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.new_symlink("latest", "releases/v1.2.0").create()?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert!(entry.mode().is_symlink());
+    /// let data = archive.get_entry(entry.wayfinder())?;
+    /// assert_eq!(data.data(), b"releases/v1.2.0");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn new_symlink<'a>(
+        &'a mut self,
+        name: &'a str,
+        target: &'a str,
+    ) -> ZipSymlinkBuilder<'a, W> {
+        ZipSymlinkBuilder {
+            archive: self,
+            name,
+            target,
+            options: ZipEntryOptions::new(CompressionMethod::Store),
+        }
+    }
+
+    /// Adds a new directory to the archive with options (internal method).
+    ///
+    /// The name of the directory must end with a `/`.
+    fn new_dir_with_options(
+        &mut self,
+        name: EntryName<'_>,
+        mut options: ZipEntryOptions,
+    ) -> Result<(), Error> {
+        // Validated up front, as normalization may strip the trailing slash
+        if self.options.validate_names {
+            validate_name(name.as_bytes())?;
+        }
+
+        let is_dir = match &name {
+            EntryName::Str(name) => ZipFilePath::from_str(name).is_dir(),
+            EntryName::Raw(name) => name.ends_with(b"/"),
+        };
+        if !is_dir {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "not a directory".to_string(),
+            }));
+        }
+
+        if let Some(attributes) = options.dos_attributes.as_mut() {
+            *attributes |= DosAttributes::DIRECTORY;
+        }
+
+        let header = self.begin_entry(name, 0, options, |_| Ok(()))?;
+        self.push_entry(header)?;
+        Ok(())
+    }
+
+    /// Validates and encodes the name of a new entry, then writes its local
+    /// file header.
+    ///
+    /// The `prepare` callback may adjust the header prior to it being
+    /// written. Nothing is recorded for the entry when an error occurs prior
+    /// to the local header being written.
+    fn begin_entry(
+        &mut self,
+        name: EntryName<'_>,
+        flags: u16,
+        mut options: ZipEntryOptions,
+        prepare: impl FnOnce(&mut FileHeader) -> Result<(), Error>,
+    ) -> Result<FileHeader, Error> {
+        self.guard.armed = false;
+        self.check_open_entry()?;
+        let queued = self.write_queued(0);
+        self.guard.armed = false;
+        queued?;
+        self.write_prefix()?;
+        if self.options.validate_names {
+            validate_name(name.as_bytes())?;
+        }
+        let parents = self.missing_parent_dirs(&name)?;
+
+        let file_path;
+        let (name_bytes, name_flags) = match &name {
+            EntryName::Str(name) => {
+                file_path = ZipFilePath::from_str(name);
+                self.encode_name(file_path.as_ref(), &mut options)?
+            }
+            EntryName::Raw(name) => {
+                let flags = match options.utf8_flag {
+                    Some(true) => FLAG_UTF8_ENCODING,
+                    _ => 0,
+                };
+                (Cow::Borrowed(name.as_ref()), flags)
+            }
+        };
+
+        let mut name_bytes = name_bytes;
+        if name_bytes.len() > u16::MAX as usize {
+            let msg = if name_bytes.ends_with(b"/") {
+                "directory name too long"
+            } else {
+                "file name too long"
+            };
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: msg.to_string(),
+            }));
+        }
+
+        options.host_system = options.host_system.or(self.options.host_system);
+        options.version_needed = options.version_needed.or(self.options.version_needed);
+        if let Some(timestamp) = self.options.fixed_timestamp {
+            options.modification_time = Some(timestamp);
+            options.access_time = None;
+            options.creation_time = None;
+            if let Some(ntfs_times) = options.ntfs_times.as_mut() {
+                *ntfs_times = NtfsTimes::default();
+            }
+        }
+        if self.options.info_zip {
+            options.info_zip = true;
+            options.host_system.get_or_insert(HostSystem::UNIX);
+            if let Some(modified) = options.modification_time {
+                options.access_time.get_or_insert(modified);
+            }
+            options.unix_owner.get_or_insert((0, 0));
+            let is_dir = name_bytes.ends_with(b"/");
+            let default_mode = if is_dir { 0o040755 } else { 0o100644 };
+            let mode = *options.unix_permissions.get_or_insert(default_mode);
+            options
+                .dos_attributes
+                .get_or_insert_with(|| unix_mode_to_msdos_attributes(mode, is_dir));
+        }
+        if self.options.unicode_comment_field && !options.comment.is_ascii() {
+            let mut data = Vec::with_capacity(5 + options.comment.len());
+            data.push(1); // Version
+            data.extend_from_slice(&crc::crc32(&options.comment).to_le_bytes());
+            data.extend_from_slice(&options.comment);
+            options.extra_fields.add_field(
+                ExtraFieldId::INFO_ZIP_UNICODE_COMMENT,
+                &data,
+                Header::CENTRAL,
+            )?;
+        }
+        if self.options.precise_times
+            && options.ntfs_times.is_none()
+            && options
+                .modification_time
+                .is_some_and(|time| time.nanosecond() != 0)
+        {
+            options.ntfs_times = Some(NtfsTimes::default());
+        }
+        let dos_out_of_range = options
+            .modification_time
+            .is_some_and(|time| !(1980..=2107).contains(&time.year()));
+        if dos_out_of_range && self.options.dos_timestamps == DosTimestampPolicy::Error {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "modification time is outside of the MS-DOS range".to_string(),
+            }));
+        }
+
+        // The extended timestamp holds unsigned 32-bit seconds, so the times
+        // it can't hold are left to the NTFS field, which readers consult
+        // after it
+        let extended_out_of_range = options
+            .modification_time
+            .is_some_and(|time| u32::try_from(time.to_unix()).is_err());
+        if dos_out_of_range
+            && extended_out_of_range
+            && self.options.dos_timestamps == DosTimestampPolicy::ExtendedOnly
+            && options.ntfs_times.is_none()
+        {
+            options.ntfs_times = Some(NtfsTimes::default());
+        }
+
+        // The marker goes to the first parent directory when there's one
+        if self.options.jar_marker
+            && self.files.is_empty()
+            && self.file_names.is_empty()
+            && parents.is_empty()
+        {
+            options
+                .extra_fields
+                .prepend_field(ExtraFieldId::JAVA_JAR, &[], Header::default())?;
+        }
+
+        let alignment = u64::from(options.alignment);
+        let disk_number = options.disk_number;
+        let (mask, overrides) = options.flags_override;
+        let flags = flags | name_flags | method_flags(options.compression_method);
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        let flags = match options.encryption {
+            Some(_) => flags | FLAG_ENCRYPTED,
+            None => flags,
+        };
+        let flags = (flags & !mask) | overrides;
+        let mut header = FileHeader::new(name_bytes.len() as u16, flags, options)?;
+        if dos_out_of_range && self.options.dos_timestamps == DosTimestampPolicy::ExtendedOnly {
+            header.last_mod_time = 0;
+            header.last_mod_date = 0;
+        }
+        self.apply_header_hook(&mut name_bytes, &mut header)?;
+        let name_bytes = name_bytes.as_ref();
+        self.check_duplicate_name(name_bytes)?;
+        for parent in parents {
+            let options = ZipEntryOptions::new(CompressionMethod::Store);
+            self.new_dir_with_options(parent, options)?;
+        }
+        header.zip64_threshold = self.options.zip64_threshold;
+        if self.options.force_zip64 {
+            header.force_zip64 = true;
+            header.finalize_local_extra_fields()?;
+        }
+
+        // Leave room for the ZIP64 and alignment fields that may be added
+        let header_len = header.local_header_len() + 20 + alignment + 6;
+        self.writer.reserve(header_len)?;
+        (header.disk_number, header.local_header_offset) = self.writer.position();
+        if let Some(disk) = disk_number {
+            header.disk_number = disk;
+        }
+        prepare(&mut header)?;
+        self.check_zip64_entry(&header)?;
+        self.check_compat(name_bytes, &header)?;
+
+        // Store the name bytes in the central buffer
+        self.file_names.extend_from_slice(name_bytes);
+        self.write_local_header(name_bytes, &header)?;
+        Ok(header)
+    }
+
+    /// Fails with [`ErrorKind::Zip64Required`] if ZIP64 is disabled and the
+    /// entry about to be written would need it.
+    fn check_zip64_entry(&self, header: &FileHeader) -> Result<(), Error> {
+        if self.options.zip64 {
+            return Ok(());
+        }
+
+        // Removed entries are only counted when the limit is close
+        let threshold = self.options.zip64_entry_threshold;
+        let full = self.files.len() + 1 >= threshold
+            && self.files.iter().filter(|f| !f.removed).count() + 1 >= threshold;
+        let limit = if full {
+            Zip64Limit::EntryCount
+        } else if header.is_zip64_value(header.local_header_offset) || header.disk_needs_zip64() {
+            Zip64Limit::ArchiveSize
+        } else if header.sizes_need_zip64() {
+            Zip64Limit::EntrySize
+        } else {
+            return Ok(());
+        };
+        Err(Error::from(ErrorKind::Zip64Required { limit }))
+    }
+
+    /// Checks the entry about to be written against the compatibility
+    /// target, failing or recording the issues depending on the policy.
+    fn check_compat(&mut self, name: &[u8], header: &FileHeader) -> Result<(), Error> {
+        let Some((target, policy)) = self.options.compat else {
+            return Ok(());
+        };
+
+        let mut issues = target.entry_issues(
+            name,
+            header.compression_method.as_method(),
+            header.flags,
+            header.extra_fields.iter().map(|(id, _)| id),
+        );
+        match policy {
+            CompatPolicy::Error if !issues.is_empty() => {
+                Err(Error::from(ErrorKind::Incompatible {
+                    target,
+                    issue: issues.swap_remove(0),
+                }))
+            }
+            _ => {
+                self.compat_issues.append(&mut issues);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the names of the entries that haven't been removed, collecting
+    /// them on first use.
+    fn name_set(&mut self) -> &mut HashSet<Vec<u8>> {
+        let names = match self.names.take() {
+            Some(names) => names,
+            None => self.entry_names().map(<[u8]>::to_vec).collect(),
+        };
+        self.names.insert(names)
+    }
+
+    /// Fails with [`ErrorKind::DuplicateName`] if the name was already
+    /// written and duplicates are rejected.
+    fn check_duplicate_name(&mut self, name: &[u8]) -> Result<(), Error> {
+        if self.options.duplicate_names == DuplicateNamePolicy::Error
+            && self.name_set().contains(name)
+        {
+            return Err(Error::from(ErrorKind::DuplicateName {
+                name: String::from_utf8_lossy(name).into_owned(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Returns the parents of `name` that aren't in the archive yet, from
+    /// the top down, when enabled with
+    /// [`ZipArchiveWriterBuilder::create_parent_directories`].
+    ///
+    /// They're only written once the entry is known not to be a duplicate,
+    /// each one finding its own parents already written.
+    fn missing_parent_dirs(
+        &mut self,
+        name: &EntryName<'_>,
+    ) -> Result<Vec<EntryName<'static>>, Error> {
+        if !self.options.create_parent_directories {
+            return Ok(Vec::new());
+        }
+
+        let parents = match name {
+            EntryName::Str(name) => {
+                let path = ZipFilePath::from_str(name);
+                let path: &str = path.as_ref();
+                path.match_indices('/')
+                    .filter(|(i, _)| i + 1 < path.len())
+                    .map(|(i, _)| EntryName::Str(Cow::Owned(path[..=i].to_string())))
+                    .collect::<Vec<_>>()
+            }
+            EntryName::Raw(name) => name
+                .iter()
+                .enumerate()
+                .filter(|&(i, &c)| c == b'/' && i + 1 < name.len())
+                .map(|(i, _)| EntryName::Raw(Cow::Owned(name[..=i].to_vec())))
+                .collect(),
+        };
+
+        let mut missing = Vec::new();
+        for parent in parents {
+            let exists = match &parent {
+                EntryName::Str(parent) => {
+                    let mut options = ZipEntryOptions::new(CompressionMethod::Store);
+                    let (key, _) = self.encode_name(parent, &mut options)?;
+                    self.name_set().contains(key.as_ref())
+                }
+                EntryName::Raw(parent) => self.name_set().contains(parent.as_ref()),
+            };
+            if !exists {
+                missing.push(parent);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Creates a builder for adding a new file to the archive.
+    ///
+    /// The name is normalized before it is written, so that names taken from
+    /// Windows paths don't show up as a single mangled file name: backslashes
+    /// are converted to forward slashes and leading `./` and `/` are
+    /// stripped, along with `..` components and drive letters. See
+    /// [`ZipFilePath`] for the full set of rules, and
+    /// [`validate_names`](ZipArchiveWriterBuilder::validate_names) for
+    /// rejecting the latter instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::{Cursor, Write};
+    /// # let mut output = Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("my-file")
+    ///     .compression_method(rawzip::CompressionMethod::Deflate)
+    ///     .unix_permissions(0o644)
+    ///     .start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello, world!")?;
+    /// let (_, output) = writer.finish()?;
+    /// entry.finish(output)?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn new_file<'name>(&mut self, name: &'name str) -> ZipFileBuilder<'_, 'name, W> {
+        ZipFileBuilder {
+            archive: self,
+            name: EntryName::Str(Cow::Borrowed(name)),
+            options: ZipEntryOptions::new(CompressionMethod::Store),
+            compression_level: None,
+            crc32_option: Crc32Option::default(),
+            store_if_larger: false,
+            best_method: None,
+        }
+    }
+
+    /// Adds a file entry with the contents of a reader, compressed with the
+    /// built-in codec for the given method.
+    ///
+    /// Returns the number of compressed bytes written. This is shorthand for
+    /// [`ZipFileBuilder::copy_from`], which should be used when the entry
+    /// needs any other options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// # #[cfg(feature = "deflate")]
+    /// # let method = rawzip::CompressionMethod::Deflate;
+    /// # #[cfg(all(not(feature = "deflate"), feature = "zstd"))]
+    /// # let method = rawzip::CompressionMethod::Zstd;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd")), feature = "bzip2"))]
+    /// # let method = rawzip::CompressionMethod::Bzip2;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd", feature = "bzip2")), feature = "lzma"))]
+    /// # let method = rawzip::CompressionMethod::Lzma;
+    /// # #[cfg(not(any(feature = "deflate", feature = "zstd", feature = "bzip2", feature = "lzma")))]
+    /// # let method = rawzip::CompressionMethod::Xz;
+    /// let data: &[u8] = b"Hello, world!";
+    /// archive.add_file("file.txt", data, method)?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "_codec")]
+    pub fn add_file(
+        &mut self,
+        name: &str,
+        reader: impl Read,
+        method: CompressionMethod,
+    ) -> Result<u64, Error> {
+        self.new_file(name)
+            .compression_method(method)
+            .copy_from(reader)
+    }
+
+    /// Adds a file entry with the given contents in a single call.
+    ///
+    /// Meant for small payloads like manifests. The data is always stored,
+    /// regardless of which compression features are enabled, so the archive
+    /// bytes don't change with the crate's feature set. Use
+    /// [`new_file`](Self::new_file) with an explicit
+    /// [`compression_method`](ZipFileBuilder::compression_method) to compress.
+    /// Returns the number of compressed bytes written.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// archive.add_bytes("manifest.json", br#"{"version":1}"#)?;
+    /// archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<u64, Error> {
+        let len = data.len() as u64;
+        let mut writer = self
+            .new_file(name)
+            .predeclare(crc::crc32(data), len, len)
+            .start_stored()?;
+        writer.write_all(data)?;
+        writer.finish()
+    }
+
+    /// Creates a builder for adding a new file whose name is written to the
+    /// headers verbatim.
+    ///
+    /// This is for names that aren't UTF-8, such as legacy CP437 or
+    /// Shift-JIS names copied from other archives. The name is not
+    /// normalized, though it is still subject to
+    /// [`validate_names`](ZipArchiveWriterBuilder::validate_names). The
+    /// UTF-8 flag is not set unless requested with
+    /// [`utf8_flag`](ZipFileBuilder::utf8_flag).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// // "ファイル.txt" in Shift-JIS
+    /// let name = b"\x83t\x83@\x83C\x83\x8b.txt";
+    /// let (mut entry, config) = archive.new_file_bytes(name).start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.file_path().as_ref(), name);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn new_file_bytes<'name>(&mut self, name: &'name [u8]) -> ZipFileBuilder<'_, 'name, W> {
+        ZipFileBuilder {
+            archive: self,
+            name: EntryName::Raw(Cow::Borrowed(name)),
+            options: ZipEntryOptions::new(CompressionMethod::Store),
+            compression_level: None,
+            crc32_option: Crc32Option::default(),
+            store_if_larger: false,
+            best_method: None,
+        }
+    }
+
+    /// Creates a builder for adding a new file named after a file system
+    /// path, such as one relative to a directory being archived.
+    ///
+    /// Paths that aren't valid UTF-8 are handled according to the
+    /// [`PathEncoding`] set with
+    /// [`with_path_encoding`](ZipArchiveWriterBuilder::with_path_encoding),
+    /// which by default returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # use std::path::Path;
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let path = Path::new("docs").join("readme.txt");
+    /// let (mut entry, config) = archive.new_file_path(&path)?.start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.file_path().as_ref(), b"docs/readme.txt");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn new_file_path<'name>(
+        &mut self,
+        path: &'name Path,
+    ) -> Result<ZipFileBuilder<'_, 'name, W>, Error> {
+        let name = self.path_name(path)?;
+        Ok(ZipFileBuilder {
+            archive: self,
+            name,
+            options: ZipEntryOptions::new(CompressionMethod::Store),
+            compression_level: None,
+            crc32_option: Crc32Option::default(),
+            store_if_larger: false,
+            best_method: None,
+        })
+    }
+
+    /// Creates a builder for adding a new directory named after a file
+    /// system path. The trailing `/` is added if missing.
+    ///
+    /// Paths are converted the same way as by
+    /// [`new_file_path`](ZipArchiveWriter::new_file_path).
+    pub fn new_dir_path<'a>(&'a mut self, path: &'a Path) -> Result<ZipDirBuilder<'a, W>, Error> {
+        let name = match self.path_name(path)? {
+            EntryName::Str(name) if name.ends_with(['/', '\\']) => EntryName::Str(name),
+            EntryName::Str(name) => EntryName::Str(Cow::Owned(format!("{name}/"))),
+            EntryName::Raw(name) if name.ends_with(b"/") => EntryName::Raw(name),
+            EntryName::Raw(name) => {
+                let mut name = name.into_owned();
+                name.push(b'/');
+                EntryName::Raw(Cow::Owned(name))
+            }
+        };
+
+        Ok(ZipDirBuilder {
+            archive: self,
+            name,
+            options: ZipEntryOptions::new(CompressionMethod::Store),
+        })
+    }
+
+    /// Converts a file system path to an entry name according to the
+    /// configured [`PathEncoding`].
+    fn path_name<'p>(&self, path: &'p Path) -> Result<EntryName<'p>, Error> {
+        let path = path.as_os_str();
+        if let Some(name) = path.to_str() {
+            return Ok(EntryName::Str(Cow::Borrowed(name)));
+        }
+
+        match self.options.path_encoding {
+            PathEncoding::Lossy => return Ok(EntryName::Str(path.to_string_lossy())),
+            #[cfg(unix)]
+            PathEncoding::Raw => {
+                use std::os::unix::ffi::OsStrExt;
+                return Ok(EntryName::Raw(Cow::Borrowed(path.as_bytes())));
+            }
+            _ => {}
+        }
+
+        Err(Error::from(ErrorKind::InvalidPath {
+            path: path.to_string_lossy().into_owned(),
+            msg: "is not valid UTF-8".to_string(),
+        }))
+    }
+
+    /// Returns the name as written to the headers and the flags it requires.
+    ///
+    /// Names that are not plain ASCII are flagged as UTF-8, unless the
+    /// Unicode path extra field was requested, in which case the field is
+    /// added to the options and a CP437 rendition of the name is returned.
+    /// A flag requested with [`ZipFileBuilder::utf8_flag`] takes precedence.
+    fn encode_name<'n>(
+        &self,
+        name: &'n str,
+        options: &mut ZipEntryOptions,
+    ) -> Result<(Cow<'n, [u8]>, u16), Error> {
+        if options.utf8_flag == Some(true) {
+            return Ok((Cow::Borrowed(name.as_bytes()), FLAG_UTF8_ENCODING));
+        }
+
+        if !ZipFilePath::from_str(name).needs_utf8_encoding() {
+            return Ok((Cow::Borrowed(name.as_bytes()), 0));
+        }
+
+        if !self.options.unicode_path_field {
+            let flags = match options.utf8_flag {
+                Some(false) => 0,
+                _ => FLAG_UTF8_ENCODING,
+            };
+            return Ok((Cow::Borrowed(name.as_bytes()), flags));
+        }
+
+        let header_name = cp437_lossy(name);
+        let mut data = Vec::with_capacity(5 + name.len());
+        data.push(1); // Version
+        data.extend_from_slice(&crc::crc32(&header_name).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        options.extra_fields.add_field(
+            ExtraFieldId::INFO_ZIP_UNICODE_PATH,
+            &data,
+            Header::default(),
+        )?;
+        Ok((Cow::Owned(header_name), 0))
+    }
+
+    /// Adds a new file to the archive with options (internal method).
+    fn new_file_with_options(
+        &mut self,
+        name: &EntryName<'_>,
+        options: ZipEntryOptions,
+    ) -> Result<ZipEntryWriter<'_, W>, Error> {
+        // A data descriptor is unnecessary when the values are known up
+        // front. With a seekable output, the local header is patched instead.
+        let declared = options.declared;
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        let encryption = options.encryption.clone();
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        if encryption.is_some() && declared.is_some() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "encrypted entries can't have predeclared sizes".to_string(),
+            }));
+        }
+
+        let flags = if declared.is_some() || self.seeker.is_some() {
+            0
+        } else {
+            FLAG_DATA_DESCRIPTOR
+        };
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        let flags = match &encryption {
+            Some(encryption) if encryption.is_zipcrypto() => FLAG_DATA_DESCRIPTOR,
+            _ => flags,
+        };
+
+        let alignment = options.alignment;
+        let header = self.begin_entry(name.trim_dir(), flags, options, |header| {
+            if let Some(declared) = declared {
+                header.crc = declared.crc32;
+                header.compressed_size = declared.compressed_size;
+                header.uncompressed_size = declared.uncompressed_size;
+                header.finalize_local_extra_fields()?;
+            }
+            header.align_local_data(alignment)
+        })?;
+
+        if let Some(progress) = &self.progress {
+            progress.consumed.store(0, Ordering::Relaxed);
+        }
+
+        self.open_entry = true;
+        let entry = ZipEntryWriter {
+            data_offset: self.writer.count(),
+            inner: self,
+            compressed_bytes: 0,
+            header,
+            declared: declared.is_some(),
+            #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+            encryptor: None,
+            transform: None,
+        };
+
+        // The encryption header precedes the encrypted data. ZipCrypto's is
+        // checked against the last modified time, as there's a data
+        // descriptor.
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        if let Some(encryption) = encryption {
+            let check = (entry.header.last_mod_time >> 8) as u8;
+            let (encryptor, header) = Encryptor::new(&encryption, check)?;
+            let mut entry = entry;
+            entry.write_all(&header)?;
+            entry.encryptor = Some(encryptor);
+            return Ok(entry);
+        }
+
+        Ok(entry)
+    }
+
+    /// Adds a stored file whose entire contents are known up front.
+    ///
+    /// As the CRC32 and size are known, they are written to the local file
+    /// header and there is no data descriptor.
+    fn new_stored_file_with_options(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        options: ZipEntryOptions,
+    ) -> Result<(), Error> {
+        let alignment = options.alignment;
+        let name = EntryName::Str(Cow::Borrowed(name.trim_end_matches('/')));
+        let header = self.begin_entry(name, 0, options, |header| {
+            header.crc = crc::crc32(data);
+            header.compressed_size = data.len() as u64;
+            header.uncompressed_size = data.len() as u64;
+            header.finalize_local_extra_fields()?;
+            header.align_local_data(alignment)
+        })?;
+
+        self.writer.write_all(data)?;
+        self.push_entry(header)?;
+        Ok(())
+    }
+
+    /// Copies an entry from another archive without recompressing it.
+    ///
+    /// The `record` supplies the metadata (name, timestamps, attributes,
+    /// CRC32, sizes, and extra fields) and `compressed` supplies the entry's
+    /// compressed data, such as from [`ZipEntry::reader`](crate::ZipEntry::reader)
+    /// or [`ZipSliceEntry::data`](crate::ZipSliceEntry::data). Exactly
+    /// `record.compressed_size_hint()` bytes are copied.
+    ///
+    /// As the CRC32 and sizes are already known, they are written to the local
+    /// file header and a data descriptor is omitted, unless the entry is
+    /// encrypted. Since only the central directory extra fields are available
+    /// to the record, they are written to both headers. The ZIP64 extra field
+    /// is regenerated as needed for the new archive.
+    ///
+    /// Returns the number of compressed bytes copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::{Cursor, Write};
+    /// # let mut source = Vec::new();
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut source);
+    /// # for name in ["keep.txt", "drop.txt"] {
+    /// #     let (mut entry, config) = archive.new_file(name).start()?;
+    /// #     let mut writer = config.wrap(&mut entry);
+    /// #     writer.write_all(b"contents")?;
+    /// #     let (_, output) = writer.finish()?;
+    /// #     entry.finish(output)?;
+    /// # }
+    /// # archive.finish()?;
+    /// let source = rawzip::ZipArchive::from_slice(&source)?;
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// for record in source.entries() {
+    ///     let record = record?;
+    ///     if record.file_path().as_ref() == b"drop.txt" {
+    ///         continue;
+    ///     }
+    ///
+    ///     let entry = source.get_entry(record.wayfinder())?;
+    ///     archive.copy_entry(&record, entry.data())?;
+    /// }
+    /// archive.finish()?;
+    ///
+    /// let copied = rawzip::ZipArchive::from_slice(&output)?;
+    /// assert_eq!(copied.entries_hint(), 1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn copy_entry<R>(
+        &mut self,
+        record: &ZipFileHeaderRecord<'_>,
+        compressed: R,
+    ) -> Result<u64, Error>
+    where
+        R: io::Read,
+    {
+        self.copy_entry_with(record).copy(compressed)
+    }
+
+    /// Creates a builder for copying an entry from another archive like
+    /// [`copy_entry`](ZipArchiveWriter::copy_entry), but under a different
+    /// name or with its extra fields rewritten.
+    ///
+    /// The entry's data is still copied without recompressing it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let mut source = Vec::new();
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut source);
+    /// # let (mut entry, config) = archive.new_file("lib/file.txt").start()?;
+    /// # let mut writer = config.wrap(&mut entry);
+    /// # writer.write_all(b"contents")?;
+    /// # let (_, output) = writer.finish()?;
+    /// # entry.finish(output)?;
+    /// # archive.finish()?;
+    /// let source = rawzip::ZipArchive::from_slice(&source)?;
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// for record in source.entries() {
+    ///     let record = record?;
+    ///     let name = format!("vendor/{}", record.file_path().try_normalize()?.as_ref());
+    ///     let entry = source.get_entry(record.wayfinder())?;
+    ///     archive
+    ///         .copy_entry_with(&record)
+    ///         .name(&name)
+    ///         .map_extra_fields(|id, data| {
+    ///             // Drop the extended timestamps
+    ///             (id != rawzip::extra_fields::ExtraFieldId::EXTENDED_TIMESTAMP)
+    ///                 .then(|| data.to_vec())
+    ///         })
+    ///         .copy(entry.data())?;
+    /// }
+    /// archive.finish()?;
+    ///
+    /// let copied = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = copied.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.file_path().as_ref(), b"vendor/lib/file.txt");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn copy_entry_with<'a>(
+        &'a mut self,
+        record: &'a ZipFileHeaderRecord<'a>,
+    ) -> ZipCopyBuilder<'a, W> {
+        ZipCopyBuilder {
+            archive: self,
+            record,
+            name: None,
+            map_extra_fields: None,
+            #[cfg(feature = "_codec")]
+            compression_method: None,
+            #[cfg(feature = "zstd")]
+            zstd_dictionary: None,
+        }
+    }
+
+    /// Writes an entry that is copied from another archive, along with its
+    /// compressed data.
+    fn copy_data<R>(
+        &mut self,
+        name: &[u8],
+        mut header: FileHeader,
+        compressed: R,
+    ) -> Result<u64, Error>
+    where
+        R: io::Read,
+    {
+        // Encrypted entries may rely on the data descriptor bit to determine
+        // how the password is verified, so leave those as is.
+        if header.flags & FLAG_ENCRYPTED == 0 {
+            header.flags &= !FLAG_DATA_DESCRIPTOR;
+        }
+
+        self.write_copied_header(name, &mut header)?;
+        let copied = io::copy(
+            &mut compressed.take(header.compressed_size),
+            &mut self.writer,
+        )?;
+        if copied != header.compressed_size {
+            return Err(Error::from(ErrorKind::InvalidSize {
+                expected: header.compressed_size,
+                actual: copied,
+            }));
+        }
+
+        if header.has_data_descriptor() {
+            self.writer.reserve(24)?;
+            header.write_data_descriptor(&mut self.writer, self.options.data_descriptor_width)?;
+        }
+
+        self.push_entry(header)?;
+        Ok(copied)
+    }
+
+    /// Returns to an entry that was [reserved](ZipFileBuilder::reserve) to
+    /// write its data.
+    ///
+    /// Like [`ZipFileBuilder::start`], this returns the entry's writer and a
+    /// config for data writers. Writing more than the reserved size fails.
+    /// Once the entry is finished, its local header and central directory
+    /// record are patched and the archive carries on from where it left off.
+    ///
+    /// Reservations must be filled before the archive is
+    /// [compacted](ZipArchiveWriter::compact). A reservation that doesn't
+    /// belong to an unfilled entry of this archive returns an
+    /// [`ErrorKind::InvalidInput`] error.
+    pub fn fill(
+        &mut self,
+        reservation: ZipReservation,
+    ) -> Result<(ZipReservedEntryWriter<'_, W>, ZipDataWriterConfig), Error> {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        let seeker = self.seeker.ok_or_else(|| {
+            Error::from(ErrorKind::InvalidInput {
+                msg: "reserving an entry requires a seekable output".to_string(),
+            })
+        })?;
+
+        let end = self.writer.count();
+        let reserved = self.files.get(reservation.index).is_some_and(|header| {
+            !header.removed
+                && header.compressed_size == 0
+                && header.local_header_offset + header.local_header_len() == reservation.data_offset
+                && reservation.data_offset + reservation.max_size <= end
+        });
+        if !reserved {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "reservation doesn't belong to an unfilled entry of the archive".to_string(),
+            }));
+        }
+        self.guard.armed = false;
+
+        (seeker.0)(
+            self.writer.inner_mut()?,
+            SeekFrom::Current(-((end - reservation.data_offset) as i64)),
+        )?;
+        self.open_entry = true;
+
+        let config = ZipDataWriterConfig {
+            crc32_option: reservation.crc32_option,
+            compression_level: reservation.compression_level,
+            consumed: None,
+        };
+        let writer = ZipReservedEntryWriter {
+            archive: self,
+            reservation,
+            seeker,
+            end,
+            written: 0,
+        };
+        Ok((writer, config))
+    }
+
+    /// Writes an entry that is copied from another archive, recompressing
+    /// its compressed data with a different compression method.
+    ///
+    /// The CRC32 and sizes are recomputed as they would be for a new entry,
+    /// and the entry is abandoned if the data doesn't match the CRC32 and
+    /// size of the source.
+    #[cfg(feature = "_codec")]
+    fn recompress_data<R>(
+        &mut self,
+        name: &[u8],
+        mut header: FileHeader,
+        compressed: R,
+        method: CompressionMethod,
+        dictionary: Option<&[u8]>,
+    ) -> Result<u64, Error>
+    where
+        R: io::Read,
+    {
+        let (expected_crc, expected_size) = (header.crc, header.uncompressed_size);
+        let source = compressed.take(header.compressed_size);
+        let source_method = header.compression_method.as_method();
+        let mut decoder = Decoder::new(source, source_method, dictionary)?;
+
+        header.compression_method = method.as_id();
+        header.version_needed = version_needed(method);
+        header.crc = 0;
+        header.compressed_size = 0;
+        header.uncompressed_size = 0;
+
+        // The deflate options don't carry over to other methods
+        header.flags &= !(FLAG_DATA_DESCRIPTOR | FLAG_COMPRESSION_OPTIONS);
+        header.flags |= method_flags(method);
+        if self.seeker.is_none() {
+            header.flags |= FLAG_DATA_DESCRIPTOR;
+        }
+
+        self.write_copied_header(name, &mut header)?;
+        self.open_entry = true;
+        let entry = ZipEntryWriter {
+            data_offset: self.writer.count(),
+            inner: self,
+            compressed_bytes: 0,
+            header,
+            declared: false,
+            #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+            encryptor: None,
+            transform: None,
+        };
+
+        let encoder = Encoder::new(entry, method, None)?;
+        let mut writer = ZipDataWriter::with_crc32(encoder, Crc32Option::Calculate);
+        io::copy(&mut decoder, &mut writer)?;
+        let (encoder, output) = writer.finish()?;
+        let entry = encoder.finish()?;
+        if output.crc != expected_crc {
+            entry.abort()?;
+            return Err(Error::from(ErrorKind::InvalidChecksum {
+                expected: expected_crc,
+                actual: output.crc,
+            }));
+        }
+
+        if output.uncompressed_size != expected_size {
+            entry.abort()?;
+            return Err(Error::from(ErrorKind::InvalidSize {
+                expected: expected_size,
+                actual: output.uncompressed_size,
+            }));
+        }
+
+        entry.finish(output)
+    }
+
+    /// Starts copying an entry from another archive, by creating its header
+    /// from the record.
+    fn begin_copy(&mut self, record: &ZipFileHeaderRecord<'_>) -> Result<FileHeader, Error>
+    where
+        W: Write,
+    {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        self.guard.armed = false;
+        self.write_prefix()?;
+        let mut header = FileHeader::from_record(record, Header::default())?;
+        header.version_needed_override = self.options.version_needed;
+        header.force_zip64 = self.options.force_zip64;
+        header.zip64_threshold = self.options.zip64_threshold;
+        Ok(header)
+    }
+
+    /// Writes the local header of an entry that is copied from another
+    /// archive.
+    fn write_copied_header(&mut self, name: &[u8], header: &mut FileHeader) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut name = Cow::Borrowed(name);
+        self.apply_header_hook(&mut name, header)?;
+        let name = name.as_ref();
+        self.check_duplicate_name(name)?;
+        header.finalize_local_extra_fields()?;
+        self.writer.reserve(header.local_header_len())?;
+        (header.disk_number, header.local_header_offset) = self.writer.position();
+        self.check_zip64_entry(header)?;
+        self.check_compat(name, header)?;
+
+        self.file_names.extend_from_slice(name);
+        self.write_local_header(name, header)?;
+        Ok(())
+    }
+
+    /// Captures what's needed to resume writing the archive after the
+    /// entries written so far, should the job be interrupted.
+    ///
+    /// The checkpoint holds the central directory that would be written if
+    /// the archive was finished now, along with where it would go. Queued
+    /// entries are written first and the output is flushed, but it's up to
+    /// the caller to make sure the data has reached the disk, for instance
+    /// with [`File::sync_data`], before persisting the checkpoint. Pass it to
+    /// [`ZipArchiveWriter::resume`] to carry on.
+    ///
+    /// Split archives can't be checkpointed.
+    pub fn checkpoint(&mut self) -> Result<ZipCheckpoint, Error>
+    where
+        W: Write,
+    {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        self.write_prefix()?;
+        if self.writer.splitter.is_some() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "split archives can't be checkpointed".to_string(),
+            }));
+        }
+
+        if self.options.sort_central_directory {
+            self.sort_entries();
+        }
+
+        let offset = self.writer.count();
+        let mut files = self.files.clone();
+        let mut directory = CountWriter::new(Vec::new(), offset);
+        write_directory(
+            &mut directory,
+            &mut files,
+            &self.file_names,
+            &self.comment,
+            &self.extra_data,
+            None,
+            &self.options,
+        )?;
+        self.writer.flush()?;
+        Ok(ZipCheckpoint {
+            offset,
+            directory: directory.writer,
+        })
+    }
+
+    /// Finishes writing the archive and returns the underlying writer.
+    ///
+    /// This writes the central directory and the end of central directory
+    /// record. ZIP64 format is used automatically when thresholds are exceeded.
+    pub fn finish(self) -> Result<W, Error>
+    where
+        W: Write,
+    {
+        let (writer, _) = self.finish_with_summary()?;
+        Ok(writer)
+    }
+
+    /// Finishes writing the archive like [`finish`](ZipArchiveWriter::finish)
+    /// and additionally returns a summary of what was written.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello, world!")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// let (_, summary) = archive.finish_with_summary()?;
+    ///
+    /// assert_eq!(summary.entries(), 1);
+    /// assert_eq!(summary.uncompressed_size(), 13);
+    /// assert_eq!(summary.compressed_size(), 13);
+    /// assert!(!summary.is_zip64());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn finish_with_summary(mut self) -> Result<(W, ZipArchiveSummary), Error>
+    where
+        W: Write,
+    {
+        self.check_open_entry()?;
+        self.write_queued(0)?;
+        self.guard.armed = false;
+        self.write_prefix()?;
+
+        // Overwrite whatever remains of aborted entries when the output can't
+        // be truncated, as stray data must not trail the archive
+        if self.truncate.is_none() {
+            let padding = self.discarded_end.saturating_sub(self.writer.count());
+            io::copy(&mut io::repeat(0).take(padding), &mut self.writer)?;
+        }
+
+        if self.options.sort_central_directory {
+            self.sort_entries();
+        }
+
+        let mut summary = write_directory(
+            &mut self.writer,
+            &mut self.files,
+            &self.file_names,
+            &self.comment,
+            &self.extra_data,
+            self.signer.as_mut(),
+            &self.options,
+        )?;
+        self.writer.flush()?;
+        if let Some(truncate) = self.truncate {
+            let end = self.writer.count();
+            truncate(&mut self.writer.writer, end)?;
+        }
+
+        summary.compat_issues = std::mem::take(&mut self.compat_issues);
+        if let Some((target, _)) = self.options.compat {
+            if summary.zip64 && !target.supports_zip64() {
+                summary.compat_issues.push(CompatIssue::Zip64);
+            }
+        }
+        Ok((self.writer.writer, summary))
+    }
+}
+
+/// A writer for a file in a ZIP archive.
+///
+/// This writer is created by `ZipArchiveWriter::new_file`.
+/// Data written to this writer is compressed and written to the underlying archive.
+///
+/// After writing all data, call `finish` to complete the entry, or `abort` to
+/// abandon it. If the writer is dropped without either, the archive refuses
+/// to add further entries or be finished, as the partial entry has already
+/// been written.
+#[derive(Debug)]
+pub struct ZipEntryWriter<'a, W> {
+    inner: &'a mut ZipArchiveWriter<W>,
+    compressed_bytes: u64,
+    header: FileHeader,
+    declared: bool,
+    /// The stream offset where the entry's data begins.
+    data_offset: u64,
+    #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+    encryptor: Option<Encryptor>,
+    transform: Option<Transform>,
+}
+
+/// A transform applied to an entry's data after it's compressed, such as an
+/// encryption scheme that isn't built in.
+///
+/// Set with [`ZipEntryWriter::set_transform`]. The entry's compressed size
+/// covers the transformed data along with the header and trailer.
+pub trait DataTransform {
+    /// Returns the bytes that precede the transformed data, like a salt.
+    fn header(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Transforms the next chunk of data in place.
+    fn transform(&mut self, data: &mut [u8]);
+
+    /// Returns the bytes that follow the transformed data, like an
+    /// authentication code.
+    fn finish(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+/// The transform given to [`ZipEntryWriter::set_transform`].
+struct Transform(Box<dyn DataTransform + Send>);
+
+impl std::fmt::Debug for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Transform(..)")
+    }
+}
+
+/// Configuration for creating data writers that handle uncompressed data and CRC32 calculation.
+#[derive(Debug)]
+pub struct ZipDataWriterConfig {
+    crc32_option: Crc32Option,
+    compression_level: Option<i32>,
+    consumed: Option<Arc<AtomicU64>>,
+}
+
+impl ZipDataWriterConfig {
+    /// Returns the compression level requested with
+    /// [`ZipFileBuilder::compression_level`], if any.
+    ///
+    /// Custom codecs should consult this when constructing their encoder.
+    #[inline]
+    pub fn compression_level(&self) -> Option<i32> {
+        self.compression_level
+    }
+
+    /// Wraps an encoder with a data writer configured with this builder's options.
+    pub fn wrap<E>(self, encoder: E) -> ZipDataWriter<E> {
+        let mut writer = ZipDataWriter::with_crc32(encoder, self.crc32_option);
+        writer.consumed = self.consumed;
+        writer
+    }
+}
+
+impl<'a, W> ZipEntryWriter<'a, W> {
+    /// Returns the total number of bytes successfully written (bytes out).
+    pub fn compressed_bytes(&self) -> u64 {
+        self.compressed_bytes
+    }
+
+    /// Returns the current offset in the output stream.
+    ///
+    /// See [`ZipArchiveWriter::stream_offset`] for more information.
+    pub fn stream_offset(&self) -> u64 {
+        self.inner.stream_offset()
+    }
+
+    /// Returns a mutable reference to the archive's underlying writer.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn output_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    /// Transforms the data written from here on before it reaches the
+    /// archive, like the built-in encryption does.
+    ///
+    /// This sits between the encoder and the archive, so the compressed data
+    /// is transformed. The transform's header is written right away. Flags
+    /// and extra fields that the transform calls for are set on the
+    /// [builder](ZipFileBuilder::general_purpose_flags), as is
+    /// [skipping the CRC32](ZipFileBuilder::crc32) when the transform
+    /// authenticates the data itself.
+    ///
+    /// Returns an error once data has been written, or for entries that use
+    /// the built-in encryption.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// struct Xor(u8);
+    ///
+    /// impl rawzip::DataTransform for Xor {
+    ///     fn transform(&mut self, data: &mut [u8]) {
+    ///         data.iter_mut().for_each(|x| *x ^= self.0);
+    ///     }
+    /// }
+    ///
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start()?;
+    /// entry.set_transform(Xor(0x5a))?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// let data = archive.get_entry(entry.wayfinder())?.data();
+    /// assert_eq!(data, b"Hello".map(|x| x ^ 0x5a));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_transform(
+        &mut self,
+        transform: impl DataTransform + Send + 'static,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        if self.encryptor.is_some() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "encrypted entries can't be transformed".to_string(),
+            }));
+        }
+
+        if self.compressed_bytes != 0 || self.transform.is_some() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "the transform must be set before any data is written".to_string(),
+            }));
+        }
+
+        let mut transform = Transform(Box::new(transform));
+        let header = transform.0.header();
+        self.write_all(&header)?;
+        self.transform = Some(transform);
+        Ok(())
+    }
+
+    /// Shortens `buf` to what fits below the ZIP64 threshold when ZIP64 is
+    /// disabled, failing once nothing does.
+    fn limit_to_zip32<'b>(&self, buf: &'b [u8]) -> io::Result<&'b [u8]> {
+        let options = &self.inner.options;
+        if options.zip64 || buf.is_empty() {
+            return Ok(buf);
+        }
+
+        let max = options.zip64_threshold.saturating_sub(1);
+        let entry_room = max.saturating_sub(self.compressed_bytes);
+        let archive_room = max.saturating_sub(self.inner.writer.position().1);
+        let room = entry_room.min(archive_room);
+        if room == 0 {
+            let limit = if entry_room == 0 {
+                Zip64Limit::EntrySize
+            } else {
+                Zip64Limit::ArchiveSize
+            };
+            let err = Error::from(ErrorKind::Zip64Required { limit });
+            return Err(io::Error::new(io::ErrorKind::Other, err));
+        }
+
+        let len = usize::try_from(room).map_or(buf.len(), |room| buf.len().min(room));
+        Ok(&buf[..len])
+    }
+
+    /// Writes a chunk of encrypted or transformed data in full.
+    fn write_chunk(&mut self, chunk: &[u8]) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        self.inner.writer.write_all(chunk)?;
+        self.compressed_bytes += chunk.len() as u64;
+        self.inner.report_progress(self.compressed_bytes, None);
+        Ok(chunk.len())
+    }
+
+    /// Finishes writing the file entry.
+    ///
+    /// This writes the data descriptor if necessary and adds the file entry to the central directory.
+    ///
+    /// If the CRC32 and sizes were [predeclared](ZipFileBuilder::predeclare),
+    /// an error is returned when they don't match the data that was written.
+    /// The same goes for an entry whose sizes call for ZIP64 when it is
+    /// [disabled](ZipArchiveWriterBuilder::with_zip64). As the entry's header
+    /// and data have already been written by then, the archive rejects any
+    /// further entries and can't be finished.
+    pub fn finish(self, output: DataDescriptorOutput) -> Result<u64, Error>
+    where
+        W: Write,
+    {
+        let summary = self.finish_with_summary(output)?;
+        Ok(summary.compressed_size)
+    }
+
+    /// Like [`finish`](ZipEntryWriter::finish), but returns where the entry
+    /// was written, such as for building an index of the archive that serves
+    /// entries with HTTP range requests.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// let summary = entry.finish_with_summary(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// assert_eq!(summary.local_header_offset(), 0);
+    /// let data_range = summary.data_range();
+    /// assert_eq!(&output[data_range.start as usize..data_range.end as usize], b"Hello");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn finish_with_summary(
+        self,
+        mut output: DataDescriptorOutput,
+    ) -> Result<ZipEntrySummary, Error>
+    where
+        W: Write,
+    {
+        let local_header_offset = self.data_offset - self.header.local_header_len();
+
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        let trailer_len = match self.encryptor {
+            Some(encryptor) => {
+                if !encryptor.records_crc() {
+                    output.crc = 0;
+                }
+
+                let trailer = encryptor.finish();
+                self.inner.writer.write_all(&trailer)?;
+                trailer.len() as u64
+            }
+            None => 0,
+        };
+        #[cfg(not(any(feature = "aes", feature = "zipcrypto")))]
+        let trailer_len = 0;
+
+        let trailer_len = match self.transform {
+            Some(mut transform) => {
+                let trailer = transform.0.finish();
+                self.inner.writer.write_all(&trailer)?;
+                trailer_len + trailer.len() as u64
+            }
+            None => trailer_len,
+        };
+
+        output.compressed_size = self.compressed_bytes + trailer_len;
+        if self.declared {
+            let header = &self.header;
+            if output.compressed_size != header.compressed_size {
+                return Err(Error::from(ErrorKind::InvalidSize {
+                    expected: header.compressed_size,
+                    actual: output.compressed_size,
+                }));
+            }
+
+            if output.uncompressed_size != header.uncompressed_size {
+                return Err(Error::from(ErrorKind::InvalidSize {
+                    expected: header.uncompressed_size,
+                    actual: output.uncompressed_size,
+                }));
+            }
+
+            if output.crc != header.crc {
+                return Err(Error::from(ErrorKind::InvalidChecksum {
+                    expected: header.crc,
+                    actual: output.crc,
+                }));
+            }
+
+            // Only when the flag was overridden
+            if header.has_data_descriptor() {
+                self.inner.writer.reserve(24)?;
+                header.write_data_descriptor(
+                    &mut self.inner.writer,
+                    self.inner.options.data_descriptor_width,
+                )?;
+            }
+
+            let summary = ZipEntrySummary::new(local_header_offset, self.data_offset, header);
+            self.inner.open_entry = false;
+            self.inner.push_entry(self.header)?;
+            return Ok(summary);
+        }
+
+        let mut file_header = self.header;
+        file_header.compressed_size = output.compressed_size;
+        file_header.uncompressed_size = output.uncompressed_size;
+        file_header.crc = output.crc;
+        if !self.inner.options.zip64 && file_header.sizes_need_zip64() {
+            return Err(Error::from(ErrorKind::Zip64Required {
+                limit: Zip64Limit::EntrySize,
+            }));
+        }
+
+        if file_header.has_data_descriptor() {
+            self.inner.writer.reserve(24)?;
+            file_header.write_data_descriptor(
+                &mut self.inner.writer,
+                self.inner.options.data_descriptor_width,
+            )?;
+        } else if let Some(seeker) = self.inner.seeker {
+            self.inner.patch_local_header(seeker, &mut file_header)?;
+        }
+
+        let summary = ZipEntrySummary::new(local_header_offset, self.data_offset, &file_header);
+        self.inner.open_entry = false;
+        self.inner.push_entry(file_header)?;
+        Ok(summary)
+    }
+
+    /// Abandons the entry, such as when reading its source fails partway
+    /// through, so that the archive can still be finished.
+    ///
+    /// The entry is left out of the central directory. On a
+    /// [seekable](ZipArchiveWriterBuilder::build_seekable) output, the
+    /// archive rewinds to where the entry began, so that the next entry
+    /// overwrites it. Otherwise, the partial data can't be taken back, so a
+    /// data descriptor is written with the number of bytes that made it out
+    /// and the data is left in place as dead space. Archives opened in
+    /// [append](ZipArchiveWriter::append) mode can reclaim it with
+    /// [`compact`](ZipArchiveWriter::compact).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::{Cursor, Write};
+    /// let mut output = Cursor::new(Vec::new());
+    /// let mut archive = rawzip::ZipArchiveWriter::builder().build_seekable(&mut output);
+    /// let (mut entry, _config) = archive.new_file("partial.txt").start()?;
+    /// entry.write_all(b"Hel")?;
+    /// entry.abort()?;
+    /// archive.finish()?;
+    ///
+    /// let output = output.into_inner();
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// assert_eq!(archive.entries_hint(), 0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn abort(self) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut file_header = self.header;
+        let archive = self.inner;
+        archive.open_entry = false;
+
+        if let Some(seeker) = archive.seeker {
+            let start = file_header.local_header_offset;
+            let end = archive.writer.count();
+            (seeker.0)(
+                archive.writer.inner_mut()?,
+                SeekFrom::Current(-((end - start) as i64)),
+            )?;
+            archive.writer.count = start;
+            archive.discarded_end = archive.discarded_end.max(end);
+            let names_len = archive.file_names.len() - file_header.name_len as usize;
+            archive.file_names.truncate(names_len);
+            archive.guard.armed = true;
+            return Ok(());
+        }
+
+        if file_header.has_data_descriptor() {
+            file_header.compressed_size = self.compressed_bytes;
+            file_header.uncompressed_size = 0;
+            file_header.crc = 0;
+            archive.writer.reserve(24)?;
+            file_header.write_data_descriptor(
+                &mut archive.writer,
+                archive.options.data_descriptor_width,
+            )?;
+        } else if self.compressed_bytes < file_header.compressed_size {
+            // Fill out the predeclared size so that the local headers can
+            // still be walked
+            let padding = file_header.compressed_size - self.compressed_bytes;
+            io::copy(&mut io::repeat(0).take(padding), &mut archive.writer)?;
+        }
+
+        file_header.removed = true;
+        archive.push_entry(file_header)?;
+        Ok(())
+    }
+}
+
+impl<W> Write for ZipEntryWriter<'_, W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = self.limit_to_zip32(buf)?;
+
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        if let Some(encryptor) = self.encryptor.as_mut() {
+            // The data is encrypted a chunk at a time, which is written in
+            // full, as the keystream can't be rewound for a partial write
+            let mut chunk = [0u8; 4096];
+            let len = buf.len().min(chunk.len());
+            let chunk = &mut chunk[..len];
+            chunk.copy_from_slice(&buf[..len]);
+            encryptor.encrypt(chunk);
+            return self.write_chunk(chunk);
+        }
+
+        if let Some(transform) = self.transform.as_mut() {
+            // Likewise, a transform may not be able to take back a chunk
+            let mut chunk = [0u8; 4096];
+            let len = buf.len().min(chunk.len());
+            let chunk = &mut chunk[..len];
+            chunk.copy_from_slice(&buf[..len]);
+            transform.0.transform(chunk);
+            return self.write_chunk(chunk);
+        }
+
+        let bytes_written = self.inner.writer.write(buf)?;
+        self.compressed_bytes += bytes_written as u64;
+        self.inner.report_progress(self.compressed_bytes, None);
+        Ok(bytes_written)
+    }
+
+    /// Flushes the archive's writer, unless the archive is only flushed once
+    /// it's [finished](FlushPolicy::Finish).
+    fn flush(&mut self) -> io::Result<()> {
+        if self.inner.options.flush_policy == FlushPolicy::Finish {
+            return Ok(());
+        }
+
+        self.inner.writer.flush()
+    }
+}
+
+/// A writer for an entry's already compressed data.
+///
+/// Created by [`ZipFileBuilder::start_raw`]. Bytes are written to the archive
+/// verbatim.
+#[derive(Debug)]
+pub struct ZipRawEntryWriter<'archive, W> {
+    entry: ZipEntryWriter<'archive, W>,
+    crc32: u32,
+    uncompressed_size: u64,
+}
+
+impl<W: Write> ZipRawEntryWriter<'_, W> {
+    /// Finishes the file entry with the CRC32 and uncompressed size declared
+    /// in [`ZipFileBuilder::start_raw`].
+    ///
+    /// Returns the number of compressed bytes written.
+    pub fn finish(self) -> Result<u64, Error> {
+        let summary = self.finish_with_summary()?;
+        Ok(summary.compressed_size)
+    }
+
+    /// Like [`finish`](ZipRawEntryWriter::finish), but returns where the
+    /// entry was written. See [`ZipEntryWriter::finish_with_summary`].
+    pub fn finish_with_summary(self) -> Result<ZipEntrySummary, Error> {
+        let output = DataDescriptorOutput {
+            crc: self.crc32,
+            compressed_size: 0,
+            uncompressed_size: self.uncompressed_size,
+        };
+        self.entry.finish_with_summary(output)
+    }
+
+    /// Abandons the entry. See [`ZipEntryWriter::abort`].
+    pub fn abort(self) -> Result<(), Error> {
+        self.entry.abort()
+    }
+}
+
+impl<W: Write> Write for ZipRawEntryWriter<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.entry.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.entry.flush()
+    }
+}
+
+/// A writer for an entry that is stored without compression.
+///
+/// Created by [`ZipFileBuilder::start_stored`]. Data written here is
+/// checksummed and counted before it reaches the archive. Call `finish` once
+/// all data has been written.
+#[derive(Debug)]
+pub struct ZipStoredWriter<'archive, W> {
+    inner: ZipDataWriter<ZipEntryWriter<'archive, W>>,
+}
+
+impl<W: Write> ZipStoredWriter<'_, W> {
+    /// Finishes the file entry.
+    ///
+    /// Returns the number of bytes written, like [`ZipEntryWriter::finish`].
+    pub fn finish(self) -> Result<u64, Error> {
+        let summary = self.finish_with_summary()?;
+        Ok(summary.compressed_size)
+    }
+
+    /// Like [`finish`](ZipStoredWriter::finish), but returns where the entry
+    /// was written. See [`ZipEntryWriter::finish_with_summary`].
+    pub fn finish_with_summary(self) -> Result<ZipEntrySummary, Error> {
+        let (entry, output) = self.inner.finish()?;
+        entry.finish_with_summary(output)
+    }
+
+    /// Abandons the entry. See [`ZipEntryWriter::abort`].
+    pub fn abort(self) -> Result<(), Error> {
+        let (entry, _) = self.inner.into_parts();
+        entry.abort()
+    }
+}
+
+impl<W: Write> Write for ZipStoredWriter<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Room for an entry's data, returned by [`ZipFileBuilder::reserve`] and
+/// redeemed with [`ZipArchiveWriter::fill`].
+#[derive(Debug)]
+#[must_use = "the entry is left empty unless the reservation is filled"]
+pub struct ZipReservation {
+    index: usize,
+    data_offset: u64,
+    max_size: u64,
+    crc32_option: Crc32Option,
+    compression_level: Option<i32>,
+}
+
+impl ZipReservation {
+    /// Returns the stream offset where the entry's data begins.
+    pub fn data_offset(&self) -> u64 {
+        self.data_offset
+    }
+
+    /// Returns the most compressed bytes the entry can hold.
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+}
+
+/// A writer for the data of a reserved entry.
+///
+/// Created by [`ZipArchiveWriter::fill`]. Call `finish` once all data has
+/// been written to patch the entry's headers, or `abort` to leave the entry
+/// empty. Either returns the archive to where it left off.
+#[derive(Debug)]
+pub struct ZipReservedEntryWriter<'archive, W> {
+    archive: &'archive mut ZipArchiveWriter<W>,
+    reservation: ZipReservation,
+    seeker: Seeker<W>,
+    /// The stream offset to return to.
+    end: u64,
+    written: u64,
+}
+
+impl<W: Write> ZipReservedEntryWriter<'_, W> {
+    /// Returns the number of compressed bytes written.
+    pub fn compressed_bytes(&self) -> u64 {
+        self.written
+    }
+
+    /// Finishes writing the entry and returns the number of compressed
+    /// bytes written.
+    ///
+    /// An error is returned if the sizes call for ZIP64, but the reservation
+    /// was too small to make room for it in the local header.
+    pub fn finish(self, output: DataDescriptorOutput) -> Result<u64, Error> {
+        let archive = self.archive;
+        archive.open_entry = false;
+        let mut position = self.reservation.data_offset + self.written;
+        let header = &mut archive.files[self.reservation.index];
+        let fits = header.force_zip64
+            || !header.is_zip64_value(self.written)
+                && !header.is_zip64_value(output.uncompressed_size);
+
+        if fits {
+            header.compressed_size = self.written;
+            header.uncompressed_size = output.uncompressed_size;
+            header.crc = output.crc;
+            header.finalize_local_extra_fields()?;
+
+            let offset = header.local_header_offset;
+            (self.seeker.0)(
+                archive.writer.inner_mut()?,
+                SeekFrom::Current(-((position - offset) as i64)),
+            )?;
+            local_header_fixed(header.name_len, header).write(archive.writer.inner_mut()?)?;
+            position = offset + ZipLocalFileHeaderFixed::SIZE as u64;
+            if header.force_zip64 {
+                (self.seeker.0)(
+                    archive.writer.inner_mut()?,
+                    SeekFrom::Current(i64::from(header.name_len)),
+                )?;
+                header
+                    .extra_fields
+                    .write_extra_fields(archive.writer.inner_mut()?, Header::LOCAL)?;
+                position += u64::from(header.name_len) + u64::from(header.extra_fields.local_size);
+            }
+        }
+
+        (self.seeker.0)(
+            archive.writer.inner_mut()?,
+            SeekFrom::Current((self.end - position) as i64),
+        )?;
+        archive.guard.armed = true;
+        if !fits {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "reserved entry has no room for ZIP64 sizes".to_string(),
+            }));
+        }
+
+        Ok(self.written)
+    }
+
+    /// Leaves the entry empty and returns the archive to where it left off.
+    pub fn abort(self) -> Result<(), Error> {
+        let position = self.reservation.data_offset + self.written;
+        let archive = self.archive;
+        archive.open_entry = false;
+        (self.seeker.0)(
+            archive.writer.inner_mut()?,
+            SeekFrom::Current((self.end - position) as i64),
+        )?;
+        archive.guard.armed = true;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ZipReservedEntryWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let remaining = self.reservation.max_size - self.written;
+        if remaining == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "data exceeds the reserved size",
+            ));
+        }
+
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let bytes_written = self.archive.writer.inner_mut()?.write(&buf[..len])?;
+        self.written += bytes_written as u64;
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.archive.writer.flush()
+    }
+}
+
+/// A writer for a file entry that is compressed with a built-in codec.
+///
+/// Created by methods like [`ZipFileBuilder::start_compressed`]. Uncompressed
+/// data written here is compressed, checksummed, and counted before it reaches
+/// the archive. Call `finish` once all data has been written.
+#[cfg(feature = "_codec")]
+#[derive(Debug)]
+pub struct ZipFileWriter<'archive, W: Write> {
+    inner: ZipFileWriterInner<'archive, W>,
+}
+
+#[cfg(feature = "_codec")]
+#[derive(Debug)]
+enum ZipFileWriterInner<'archive, W: Write> {
+    Streaming(ZipDataWriter<Encoder<ZipEntryWriter<'archive, W>>>),
+    Buffered(BufferedFileWriter<'archive, W>),
+    Trial(Box<TrialFileWriter<'archive, W>>),
+    #[cfg(feature = "deflate")]
+    Parallel(ParallelDeflateWriter<'archive, W>),
+}
+
+/// Entries queued with [`ZipFileBuilder::queue_compressed`], in the order
+/// they are written to the archive, and the pool compressing them.
+#[cfg(feature = "_codec")]
+#[derive(Debug)]
+struct EntryQueue {
+    pool: CompressorPool,
+    entries: VecDeque<(EntryName<'static>, ZipEntryOptions)>,
+}
+
+/// The size of the blocks that are deflated independently by
+/// [`ZipFileBuilder::start_deflate_parallel`].
+#[cfg(feature = "deflate")]
+const PARALLEL_DEFLATE_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Deflates an entry's data in blocks on worker threads and stitches the
+/// blocks back together in order.
+#[cfg(feature = "deflate")]
+#[derive(Debug)]
+struct ParallelDeflateWriter<'archive, W> {
+    entry: ZipEntryWriter<'archive, W>,
+    pool: CompressorPool,
+    level: u32,
+    crc32_option: Crc32Option,
+    block: Vec<u8>,
+    crc: u32,
+    uncompressed_size: u64,
+    consumed: Option<Arc<AtomicU64>>,
+}
+
+#[cfg(feature = "deflate")]
+impl<W: Write> ParallelDeflateWriter<'_, W> {
+    fn submit_block(&mut self, last: bool) -> Result<(), Error> {
+        let capacity = if last { 0 } else { PARALLEL_DEFLATE_BLOCK_SIZE };
+        let data = std::mem::replace(&mut self.block, Vec::with_capacity(capacity));
+        self.pool.submit(Job::DeflateBlock {
+            data,
+            level: self.level,
+            last,
+        })?;
+
+        let max_pending = if last { 0 } else { self.pool.capacity() };
+        self.write_blocks(max_pending)
+    }
+
+    /// Writes the blocks that have been compressed, waiting on the oldest
+    /// ones while more than `max_pending` remain.
+    fn write_blocks(&mut self, max_pending: u64) -> Result<(), Error> {
+        while let Some(block) = self.pool.next(self.pool.pending() > max_pending) {
+            let block = block?;
+            self.entry.write_all(&block.payload)?;
+            self.crc = crc::crc32_combine(self.crc, block.crc, block.uncompressed_size);
+            self.uncompressed_size += block.uncompressed_size;
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(PARALLEL_DEFLATE_BLOCK_SIZE - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+        if let Some(consumed) = &self.consumed {
+            consumed.fetch_add(len as u64, Ordering::Relaxed);
+        }
+
+        if self.block.len() == PARALLEL_DEFLATE_BLOCK_SIZE {
+            self.submit_block(false)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        }
+
+        Ok(len)
+    }
+
+    fn finish(mut self) -> Result<ZipEntrySummary, Error> {
+        self.submit_block(true)?;
+        let crc = match self.crc32_option {
+            Crc32Option::Calculate => self.crc,
+            Crc32Option::Custom(crc) => crc,
+            Crc32Option::Skip => 0,
+        };
+
+        let output = DataDescriptorOutput {
+            crc,
+            compressed_size: 0,
+            uncompressed_size: self.uncompressed_size,
+        };
+        self.entry.finish_with_summary(output)
+    }
+}
+
+/// Holds an entry's uncompressed data until it is known whether compression
+/// pays off.
+#[cfg(feature = "_codec")]
+#[derive(Debug)]
+struct BufferedFileWriter<'archive, W> {
+    archive: &'archive mut ZipArchiveWriter<W>,
+    name: EntryName<'static>,
+    options: ZipEntryOptions,
+    level: Option<i32>,
+    data: ZipDataWriter<Vec<u8>>,
+}
+
+#[cfg(feature = "_codec")]
+impl<'archive, W: Write> BufferedFileWriter<'archive, W> {
+    fn finish(mut self) -> Result<ZipEntrySummary, Error> {
+        let (data, output) = self.data.finish()?;
+        let (payload, method) = codec::compress(
+            data,
+            self.options.compression_method,
+            self.level,
+            true,
+            self.archive.dictionary().as_deref(),
+        )?;
+        self.options.compression_method = method;
+
+        let mut entry = self
+            .archive
+            .new_file_with_options(&self.name, self.options)?;
+        entry.write_all(&payload)?;
+        entry.finish_with_summary(output)
+    }
+
+    /// Like [`finish`](BufferedFileWriter::finish), but the entry is written
+    /// with whichever compression method makes it the smallest.
+    fn finish_smallest(mut self) -> Result<ZipEntrySummary, Error> {
+        let (data, output) = self.data.finish()?;
+        let (payload, method) = codec::compress_smallest(
+            data,
+            self.options.compression_method,
+            self.level,
+            self.archive.dictionary().as_deref(),
+        )?;
+        self.options.compression_method = method;
+
+        let mut entry = self
+            .archive
+            .new_file_with_options(&self.name, self.options)?;
+        entry.write_all(&payload)?;
+        entry.finish_with_summary(output)
+    }
+
+    /// Starts the entry with the configured compression method and passes it
+    /// the data buffered so far.
+    fn spill(self) -> Result<ZipDataWriter<Encoder<ZipEntryWriter<'archive, W>>>, Error> {
+        let crc32_option = self.data.crc32_option;
+        let (data, _) = self.data.into_parts();
+        let method = self.options.compression_method;
+        let dictionary = self.archive.dictionary();
+        let entry = self
+            .archive
+            .new_file_with_options(&self.name, self.options)?;
+
+        let consumed = entry
+            .inner
+            .progress
+            .as_ref()
+            .map(|progress| Arc::clone(&progress.consumed));
+        let config = ZipDataWriterConfig {
+            crc32_option,
+            compression_level: self.level,
+            consumed,
+        };
+
+        let encoder = Encoder::with_dictionary(entry, method, self.level, dictionary.as_deref())?;
+        let mut writer = config.wrap(encoder);
+        writer.write_all(&data)?;
+        Ok(writer)
+    }
+}
+
+/// An entry started with [`ZipFileBuilder::best_method`].
+///
+/// The data is buffered until the entry is finished, so that each candidate
+/// compression method can be tried, unless it outgrows the maximum size, at
+/// which point the entry is streamed with the configured method.
+#[cfg(feature = "_codec")]
+#[derive(Debug)]
+struct TrialFileWriter<'archive, W: Write> {
+    buffered: Option<BufferedFileWriter<'archive, W>>,
+    streaming: Option<ZipDataWriter<Encoder<ZipEntryWriter<'archive, W>>>>,
+    max_size: usize,
+}
+
+#[cfg(feature = "_codec")]
+impl<W: Write> TrialFileWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(buffered) = self.buffered.as_mut() {
+            if buffered.data.inner.len() + buf.len() <= self.max_size {
+                return buffered.data.write(buf);
+            }
+
+            let buffered = self.buffered.take().expect("entry is buffered");
+            let writer = buffered
+                .spill()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.streaming = Some(writer);
+        }
+
+        match self.streaming.as_mut() {
+            Some(writer) => writer.write(buf),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "entry failed to start",
+            )),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.streaming.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<ZipEntrySummary, Error> {
+        if let Some(buffered) = self.buffered {
+            return buffered.finish_smallest();
+        }
+
+        match self.streaming {
+            Some(writer) => {
+                let (encoder, output) = writer.finish()?;
+                let entry = encoder.finish()?;
+                entry.finish_with_summary(output)
+            }
+            None => Err(Error::from(ErrorKind::InvalidInput {
+                msg: "entry failed to start".to_string(),
+            })),
+        }
+    }
+
+    fn abort(self) -> Result<(), Error> {
+        match self.streaming {
+            Some(writer) => {
+                let (encoder, _) = writer.finish()?;
+                encoder.finish()?.abort()
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "_codec")]
+impl<W: Write> ZipFileWriter<'_, W> {
+    /// Finishes the compressed stream and the file entry.
+    ///
+    /// Returns the number of compressed bytes written, like
+    /// [`ZipEntryWriter::finish`].
+    pub fn finish(self) -> Result<u64, Error> {
+        let summary = self.finish_with_summary()?;
+        Ok(summary.compressed_size)
+    }
+
+    /// Like [`finish`](ZipFileWriter::finish), but returns where the entry
+    /// was written. See [`ZipEntryWriter::finish_with_summary`].
+    pub fn finish_with_summary(self) -> Result<ZipEntrySummary, Error> {
+        match self.inner {
+            ZipFileWriterInner::Streaming(writer) => {
+                let (encoder, output) = writer.finish()?;
+                let entry = encoder.finish()?;
+                entry.finish_with_summary(output)
+            }
+            ZipFileWriterInner::Buffered(writer) => writer.finish(),
+            ZipFileWriterInner::Trial(writer) => writer.finish(),
+            #[cfg(feature = "deflate")]
+            ZipFileWriterInner::Parallel(writer) => writer.finish(),
+        }
+    }
+
+    /// Abandons the entry. See [`ZipEntryWriter::abort`].
+    ///
+    /// The compressed stream is ended before the entry is abandoned, so any
+    /// bytes it flushes are discarded along with the rest. An entry whose
+    /// compression is decided once all its data is buffered hasn't been
+    /// written yet, so it is simply dropped.
+    pub fn abort(self) -> Result<(), Error> {
+        match self.inner {
+            ZipFileWriterInner::Streaming(writer) => {
+                let (encoder, _) = writer.finish()?;
+                encoder.finish()?.abort()
+            }
+            ZipFileWriterInner::Buffered(_) => Ok(()),
+            ZipFileWriterInner::Trial(writer) => writer.abort(),
+            #[cfg(feature = "deflate")]
+            ZipFileWriterInner::Parallel(writer) => writer.entry.abort(),
+        }
+    }
+}
+
+#[cfg(feature = "_codec")]
+impl<W: Write> Write for ZipFileWriter<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            ZipFileWriterInner::Streaming(writer) => writer.write(buf),
+            ZipFileWriterInner::Buffered(writer) => writer.data.write(buf),
+            ZipFileWriterInner::Trial(writer) => writer.write(buf),
+            #[cfg(feature = "deflate")]
+            ZipFileWriterInner::Parallel(writer) => writer.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            ZipFileWriterInner::Streaming(writer) => writer.flush(),
+            ZipFileWriterInner::Buffered(writer) => writer.data.flush(),
+            ZipFileWriterInner::Trial(writer) => writer.flush(),
+            #[cfg(feature = "deflate")]
+            ZipFileWriterInner::Parallel(writer) => writer.entry.flush(),
+        }
+    }
+}
+
+/// A writer for the uncompressed data of a Zip file entry.
+///
+/// This writer will keep track of the data necessary to write the data
+/// descriptor (ie: number of bytes written and the CRC32 checksum).
+///
+/// Once all the data has been written, invoke the `finish` method to receive the
+/// `DataDescriptorOutput` necessary to finalize the entry.
+#[derive(Debug)]
+pub struct ZipDataWriter<W> {
+    inner: W,
+    uncompressed_bytes: u64,
+    crc: u32,
+    crc32_option: Crc32Option,
+    consumed: Option<Arc<AtomicU64>>,
+}
+
+impl<W> ZipDataWriter<W> {
+    /// Creates a new `ZipDataWriter` that writes to an underlying writer.
+    #[deprecated(
+        since = "0.4.0",
+        note = "Use the tuple-based API: `ZipFileBuilder::start()` returns `(writer, builder)` which can propagate the CRC32 option"
+    )]
+    pub fn new(inner: W) -> Self {
+        Self::with_crc32_option(inner, Crc32Option::default())
+    }
+
+    /// Creates a new `ZipDataWriter` with the specified CRC32 option.
+    ///
+    /// This is an internal method. Use the tuple-based API via
+    /// `ZipFileBuilder::start()` instead.
+    pub(crate) fn with_crc32(inner: W, crc32_option: Crc32Option) -> Self {
+        Self::with_crc32_option(inner, crc32_option)
+    }
+
+    /// Creates a new `ZipDataWriter` with a specific CRC32 calculation option.
+    fn with_crc32_option(inner: W, crc32_option: Crc32Option) -> Self {
+        let crc = crc32_option.initial_value();
+        ZipDataWriter {
+            inner,
+            uncompressed_bytes: 0,
+            crc,
+            crc32_option,
+            consumed: None,
+        }
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consumes self and returns the inner writer and the data descriptor to be
+    /// passed to a `ZipEntryWriter`.
+    ///
+    /// The writer is returned to facilitate situations where the underlying
+    /// compressor needs to be notified that no more data will be written so it
+    /// can write any sort of necessary epilogue (think zstd).
+    ///
+    /// The `DataDescriptorOutput` contains the CRC32 checksum and uncompressed size,
+    /// which is needed by `ZipEntryWriter::finish`.
+    pub fn finish(mut self) -> Result<(W, DataDescriptorOutput), Error>
+    where
+        W: Write,
+    {
+        self.flush()?;
+        Ok(self.into_parts())
+    }
+
+    /// Like [`finish`](ZipDataWriter::finish), but without flushing, so that
+    /// it is available to writers that aren't [`std::io::Write`], such as
+    /// async writers. Flushing or shutting down the writer is then up to the
+    /// caller.
+    pub fn into_parts(self) -> (W, DataDescriptorOutput) {
+        let output = DataDescriptorOutput {
+            crc: self.crc,
+            compressed_size: 0,
+            uncompressed_size: self.uncompressed_bytes,
+        };
+
+        (self.inner, output)
+    }
+
+    /// Writes `buf` to the underlying writer with `write` and accounts for
+    /// the bytes that `written` reports were taken.
+    ///
+    /// The bytes count towards the entry's progress before they are written,
+    /// so that progress reported as they reach the archive includes them.
+    pub(crate) fn write_with<T>(
+        &mut self,
+        buf: &[u8],
+        write: impl FnOnce(&mut W, &[u8]) -> T,
+        written: impl FnOnce(&T) -> usize,
+    ) -> T {
+        if let Some(consumed) = &self.consumed {
+            consumed.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        }
+
+        let result = write(&mut self.inner, buf);
+        let len = written(&result);
+        if let Some(consumed) = &self.consumed {
+            consumed.fetch_sub((buf.len() - len) as u64, Ordering::Relaxed);
+        }
+
+        self.uncompressed_bytes += len as u64;
+
+        // Only calculate CRC32 if the option is Calculate
+        if matches!(self.crc32_option, Crc32Option::Calculate) {
+            self.crc = crc::crc32_chunk(&buf[..len], self.crc);
+        }
+
+        result
+    }
+}
+
+impl<W> Write for ZipDataWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_with(buf, W::write, |result| *result.as_ref().unwrap_or(&0))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// An output that discards what is written to it and counts the bytes, for
+/// computing the size of an archive before writing it.
+///
+/// An archive written to a planner is laid out exactly as it would be on a
+/// streaming output with the same options, while entries that are
+/// [planned](ZipFileBuilder::plan) are accounted for by their size alone.
+/// This gives the final size of an archive of stored entries up front, such
+/// as for pre-allocating storage or sending a `Content-Length` header. When
+/// the compressed sizes aren't known yet, the header overhead is the
+/// difference between the planned size and the
+/// [compressed size](ZipArchiveSummary::compressed_size) of the entries.
+///
+/// The layout follows a writer built with
+/// [`build`](ZipArchiveWriterBuilder::build), where entries whose sizes
+/// aren't predeclared are followed by a data descriptor. Archives written to
+/// a [seekable](ZipArchiveWriterBuilder::build_seekable) or
+/// [split](ZipArchiveWriterBuilder::build_split) output are laid out
+/// differently.
+///
+/// # Example
+///
+/// ```rust
+/// let builder = rawzip::ZipArchiveWriter::builder().with_unicode_path_field(true);
+/// let mut planner = builder.build(rawzip::SizePlanner::new());
+/// planner.new_dir("photos/").create()?;
+/// planner.new_file("photos/beach.jpg").plan(1_500_000, 1_500_000)?;
+/// planner.new_file("photos/forest.jpg").plan(2_250_000, 2_250_000)?;
+/// let (planner, summary) = planner.finish_with_summary()?;
+///
+/// let overhead = planner.size() - summary.compressed_size();
+/// assert_eq!(planner.size(), 3_750_000 + overhead);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct SizePlanner {
+    size: u64,
+    /// The bytes written, by their offset, when they are kept for a
+    /// [`ZipPlanLayout`](crate::ZipPlanLayout).
+    recorded: Option<Vec<(u64, Vec<u8>)>>,
+}
+
+impl SizePlanner {
+    /// Creates a planner with nothing written.
+    pub fn new() -> Self {
+        SizePlanner::default()
+    }
+
+    /// Creates a planner that keeps the bytes written, which are everything
+    /// but the data of planned entries.
+    pub(crate) fn recording() -> Self {
+        SizePlanner {
+            size: 0,
+            recorded: Some(Vec::new()),
+        }
+    }
+
+    /// Returns the runs of bytes written, along with their offsets.
+    pub(crate) fn into_recorded(self) -> Vec<(u64, Vec<u8>)> {
+        self.recorded.unwrap_or_default()
+    }
+
+    /// Returns the number of bytes written, including the data of planned
+    /// entries.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Write for SizePlanner {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(recorded) = self.recorded.as_mut() {
+            match recorded.last_mut() {
+                Some((offset, bytes)) if *offset + bytes.len() as u64 == self.size => {
+                    bytes.extend_from_slice(buf);
+                }
+                _ => recorded.push((self.size, buf.to_vec())),
+            }
+        }
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Where an entry was written, returned by
+/// [`ZipEntryWriter::finish_with_summary`].
+///
+/// Offsets are positions in the output stream, as reported by
+/// [`ZipArchiveWriter::stream_offset`], so they include any
+/// [offset](ZipArchiveWriterBuilder::with_offset) the writer started at. For
+/// split archives, they span all volumes.
+#[derive(Debug, Clone)]
+pub struct ZipEntrySummary {
+    local_header_offset: u64,
+    data_offset: u64,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    crc: u32,
+}
+
+impl ZipEntrySummary {
+    fn new(local_header_offset: u64, data_offset: u64, header: &FileHeader) -> Self {
+        ZipEntrySummary {
+            local_header_offset,
+            data_offset,
+            compressed_size: header.compressed_size,
+            uncompressed_size: header.uncompressed_size,
+            crc: header.crc,
+        }
+    }
+
+    /// Returns the offset of the entry's local file header.
+    pub fn local_header_offset(&self) -> u64 {
+        self.local_header_offset
+    }
+
+    /// Returns the range of the entry's compressed data, which follows the
+    /// local file header.
+    pub fn data_range(&self) -> std::ops::Range<u64> {
+        self.data_offset..self.data_offset + self.compressed_size
+    }
+
+    /// Returns the size of the entry's compressed data.
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Returns the size of the entry's uncompressed data.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Returns the CRC32 of the entry's uncompressed data.
+    pub fn crc32(&self) -> u32 {
+        self.crc
+    }
+}
+
+/// The state of a partially written archive, returned by
+/// [`ZipArchiveWriter::checkpoint`] and resumed with
+/// [`ZipArchiveWriter::resume`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipCheckpoint {
+    offset: u64,
+    directory: Vec<u8>,
+}
+
+impl ZipCheckpoint {
+    /// Returns the stream offset where the entries written so far end.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Serializes the checkpoint, so that it can be persisted.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.directory.len());
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(&self.directory);
+        bytes
+    }
+
+    /// Deserializes a checkpoint created with
+    /// [`to_bytes`](ZipCheckpoint::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let valid = bytes.len() >= 8 + EOCD_SIZE
+            && bytes[8..]
+                .windows(4)
+                .any(|x| x == END_OF_CENTRAL_DIR_SIGNAUTRE_BYTES);
+        if !valid {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "invalid checkpoint".to_string(),
+            }));
+        }
+
+        Ok(ZipCheckpoint {
+            offset: le_u64(&bytes[..8]),
+            directory: bytes[8..].to_vec(),
+        })
+    }
+}
+
+/// A summary of a finished archive, returned by
+/// [`ZipArchiveWriter::finish_with_summary`].
+#[derive(Debug, Clone)]
+pub struct ZipArchiveSummary {
+    entries: u64,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    central_directory_offset: u64,
+    central_directory_size: u64,
+    zip64: bool,
+    compat_issues: Vec<CompatIssue>,
+}
+
+impl ZipArchiveSummary {
+    /// Returns the number of entries in the central directory.
+    pub fn entries(&self) -> u64 {
+        self.entries
+    }
+
+    /// Returns the total compressed size of the entries' data.
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Returns the total uncompressed size of the entries' data.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Returns the offset of the central directory.
+    ///
+    /// For split archives, the offset is relative to the start of the volume
+    /// that the central directory begins on.
+    pub fn central_directory_offset(&self) -> u64 {
+        self.central_directory_offset
+    }
+
+    /// Returns the size of the central directory.
+    pub fn central_directory_size(&self) -> u64 {
+        self.central_directory_size
+    }
+
+    /// Returns true if the ZIP64 end of central directory was written.
+    pub fn is_zip64(&self) -> bool {
+        self.zip64
+    }
+
+    /// Returns the issues found by the
+    /// [compatibility check](ZipArchiveWriterBuilder::with_compat_target),
+    /// in the order they were found.
+    pub fn compat_issues(&self) -> &[CompatIssue] {
+        &self.compat_issues
+    }
+}
+
+/// Contains information written in the data descriptor after the file data.
+#[derive(Debug, Clone)]
+pub struct DataDescriptorOutput {
+    crc: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+}
+
+impl DataDescriptorOutput {
+    /// Returns the CRC32 checksum of the uncompressed data.
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Returns the uncompressed size of the data.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FileHeader {
+    name_len: u16,
+    compression_method: CompressionMethodId,
+    disk_number: u32,
+    local_header_offset: u64,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    crc: u32,
+    flags: u16,
+    last_mod_time: u16,
+    last_mod_date: u16,
+    version_needed: u16,
+    version_made_by: u16,
+    internal_attrs: u16,
+    external_attrs: u32,
+    extra_fields: ExtraFieldsContainer,
+    comment: Vec<u8>,
+    removed: bool,
+    force_zip64: bool,
+    zip64_threshold: u64,
+    /// Written in place of the derived version needed to extract.
+    version_needed_override: Option<u16>,
+    /// The modification time set with [`ZipEntryEdit::set_last_modified`],
+    /// until it's written to the local header.
+    edited_last_modified: Option<UtcDateTime>,
+}
+
+impl FileHeader {
+    /// Creates a header from the central directory record of another archive.
+    ///
+    /// Extra fields are written to the given headers, except for the ZIP64
+    /// field, which is regenerated as needed.
+    fn from_record(record: &ZipFileHeaderRecord<'_>, location: Header) -> Result<Self, Error> {
+        let mut extra_fields = ExtraFieldsContainer::new();
+        for (id, data) in record.extra_fields() {
+            if id != ExtraFieldId::ZIP64 {
+                extra_fields.add_field(id, data, location)?;
+            }
+        }
+
+        Ok(FileHeader {
+            name_len: record.file_path().len() as u16,
+            compression_method: record.compression_method,
+            disk_number: 0,
+            local_header_offset: record.local_header_offset(),
+            compressed_size: record.compressed_size,
+            uncompressed_size: record.uncompressed_size,
+            crc: record.crc32,
+            flags: record.flags,
+            last_mod_time: record.last_mod_time,
+            last_mod_date: record.last_mod_date,
+            version_needed: record.version_needed,
+            version_made_by: record.version_made_by,
+            internal_attrs: record.internal_file_attrs,
+            external_attrs: record.external_file_attrs,
+            extra_fields,
+            comment: record.comment().as_bytes().to_vec(),
+            removed: false,
+            edited_last_modified: None,
+            force_zip64: false,
+            zip64_threshold: ZIP64_THRESHOLD,
+            version_needed_override: None,
+        })
+    }
+
+    /// Creates the header for an entry whose position, CRC32, and sizes are
+    /// to be determined.
+    fn new(name_len: u16, flags: u16, options: ZipEntryOptions) -> Result<Self, Error> {
+        // Get DOS timestamp from options or use 0 as default
+        let (last_mod_time, last_mod_date) = options
+            .modification_time
+            .as_ref()
+            .map(|dt| DosDateTime::from(dt).into_parts())
+            .unwrap_or((0, 0));
+
+        if options.comment.len() > u16::MAX as usize {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "file comment too long".to_string(),
+            }));
+        }
+
+        let mut extra_fields = options.extra_fields;
+        add_extended_timestamp(
+            &mut extra_fields,
+            options.modification_time.as_ref(),
+            options.access_time.as_ref(),
+            options.creation_time.as_ref(),
+        )?;
+
+        if let Some(ntfs) = options.ntfs_times {
+            let modified = ntfs.modified.or(options.modification_time);
+            if let Some(modified) = modified {
+                let accessed = ntfs.accessed.or(options.access_time).unwrap_or(modified);
+                let created = ntfs.created.or(options.creation_time).unwrap_or(modified);
+                add_ntfs_timestamp(&mut extra_fields, &modified, &accessed, &created)?;
+            }
+        }
+
+        if let Some((uid, gid)) = options.unix_owner {
+            let mut data = [0u8; 11];
+            data[0] = 1; // Version
+            data[1] = 4; // UID size
+            data[2..6].copy_from_slice(&uid.to_le_bytes());
+            data[6] = 4; // GID size
+            data[7..11].copy_from_slice(&gid.to_le_bytes());
+            if options.info_zip {
+                // Info-ZIP leaves the IDs out of the central directory
+                extra_fields.add_field(
+                    ExtraFieldId::INFO_ZIP_UNIX_UID_GID,
+                    &data,
+                    Header::LOCAL,
+                )?;
+                extra_fields.add_field(
+                    ExtraFieldId::INFO_ZIP_UNIX_UID_GID,
+                    &[],
+                    Header::CENTRAL,
+                )?;
+            } else {
+                extra_fields.add_field(
+                    ExtraFieldId::INFO_ZIP_UNIX_UID_GID,
+                    &data,
+                    Header::default(),
+                )?;
+            }
+        }
+
+        // Set version_made_by to indicate Unix when Unix permissions are
+        // present, unless a host system was given
+        let version_made_by = match options.host_system {
+            Some(host_system) => u16::from(host_system.as_u8()) << 8,
+            None => options
+                .unix_permissions
+                .map(|_| CREATOR_UNIX << 8)
+                .unwrap_or(0),
+        };
+        let version_made_by = if options.info_zip {
+            version_made_by | INFO_ZIP_VERSION_MADE_BY
+        } else {
+            version_made_by
+        };
+
+        let unix_attrs = options.unix_permissions.map(|x| x << 16).unwrap_or(0);
+        let dos_attrs = options.dos_attributes.map_or(0, |x| u32::from(x.as_u8()));
+        let external_attrs = options.external_attrs.unwrap_or(unix_attrs | dos_attrs);
+
+        // Encrypted entries are marked with the AES method, while the method
+        // that the data is compressed with moves to the AES extra field
+        #[cfg(feature = "aes")]
+        let aes_field = options
+            .encryption
+            .as_ref()
+            .and_then(|x| crate::encryption::aes_extra_field(x, options.compression_method));
+        #[cfg(feature = "aes")]
+        let compression_method = match aes_field {
+            Some(data) => {
+                extra_fields.add_field(ExtraFieldId::WINZIP_AES, &data, Header::default())?;
+                CompressionMethod::Aes
+            }
+            None => options.compression_method,
+        };
+        #[cfg(not(feature = "aes"))]
+        let compression_method = options.compression_method;
+
+        Ok(FileHeader {
+            name_len,
+            compression_method: compression_method.as_id(),
+            disk_number: 0,
+            local_header_offset: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            crc: 0,
+            flags,
+            last_mod_time,
+            last_mod_date,
+            version_needed: match compression_method {
+                CompressionMethod::Store if options.info_zip => 10,
+                _ => version_needed(compression_method),
+            },
+            version_made_by,
+            internal_attrs: options.internal_attrs,
+            external_attrs,
+            extra_fields,
+            comment: options.comment,
+            removed: false,
+            edited_last_modified: None,
+            force_zip64: false,
+            zip64_threshold: ZIP64_THRESHOLD,
+            version_needed_override: options.version_needed,
+        })
+    }
+
+    /// Returns the length of the local file header, including the name and
+    /// extra fields.
+    fn local_header_len(&self) -> u64 {
+        ZipLocalFileHeaderFixed::SIZE as u64
+            + u64::from(self.name_len)
+            + u64::from(self.extra_fields.local_size)
+    }
+
+    #[inline]
+    fn has_data_descriptor(&self) -> bool {
+        self.flags & FLAG_DATA_DESCRIPTOR != 0
+    }
+
+    /// Returns true if the value belongs in a ZIP64 extra field.
+    #[inline]
+    fn is_zip64_value(&self, value: u64) -> bool {
+        self.force_zip64 || value >= self.zip64_threshold
+    }
+
+    fn sizes_need_zip64(&self) -> bool {
+        self.is_zip64_value(self.compressed_size) || self.is_zip64_value(self.uncompressed_size)
+    }
+
+    /// Returns true if the disk number start is in the ZIP64 extra field.
+    #[inline]
+    fn disk_needs_zip64(&self) -> bool {
+        self.disk_number >= ZIP64_THRESHOLD_DISKS
+    }
+
+    fn needs_zip64(&self) -> bool {
+        self.sizes_need_zip64()
+            || self.is_zip64_value(self.local_header_offset)
+            || self.disk_needs_zip64()
+    }
+
+    /// Returns true if the local file header carries a ZIP64 extra field,
+    /// either because it's forced or the sizes written there overflow.
+    fn needs_local_zip64(&self) -> bool {
+        self.force_zip64 || !self.has_data_descriptor() && self.sizes_need_zip64()
+    }
+
+    /// Returns the value for a 32-bit header field, which is `0xFFFFFFFF`
+    /// when the actual value is in the ZIP64 extra field.
+    fn zip64_clamp(&self, value: u64) -> u32 {
+        if self.is_zip64_value(value) {
+            u32::MAX
+        } else {
+            value as u32
+        }
+    }
+
+    /// Adds the ZIP64 extra field to the local file header when the sizes
+    /// written there overflow. The local field must contain both sizes
+    /// (4.5.3). If the field was already added, its sizes are updated.
+    fn finalize_local_extra_fields(&mut self) -> Result<(), Error> {
+        if self.needs_local_zip64() {
+            let mut sink = [0u8; 16];
+            sink[..8].copy_from_slice(&self.uncompressed_size.to_le_bytes());
+            sink[8..].copy_from_slice(&self.compressed_size.to_le_bytes());
+            if !self
+                .extra_fields
+                .replace_field(ExtraFieldId::ZIP64, &sink, Header::LOCAL)
+            {
+                self.extra_fields
+                    .add_field(ExtraFieldId::ZIP64, &sink, Header::LOCAL)?;
+            }
+            self.version_needed = self.version_needed.max(ZIP64_VERSION_NEEDED);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the data descriptor for the entry's CRC32 and sizes.
+    ///
+    /// When the width is automatic, sizes are 64-bit when either overflows or
+    /// the local file header carries a ZIP64 extra field (4.3.9.2).
+    fn write_data_descriptor<W>(
+        &self,
+        writer: &mut W,
+        width: DataDescriptorWidth,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let zip64 = match width {
+            // Only sizes that overflow are widened for streamed entries, as
+            // a lowered threshold doesn't put a ZIP64 field in their local
+            // header (4.3.9.2)
+            DataDescriptorWidth::Auto => {
+                self.needs_local_zip64()
+                    || self.compressed_size >= ZIP64_THRESHOLD
+                    || self.uncompressed_size >= ZIP64_THRESHOLD
+            }
+            DataDescriptorWidth::Bits64 => true,
+            DataDescriptorWidth::Bits32 => {
+                if self.compressed_size > u64::from(u32::MAX)
+                    || self.uncompressed_size > u64::from(u32::MAX)
+                {
+                    return Err(Error::from(ErrorKind::InvalidInput {
+                        msg: "entry too large for a 32-bit data descriptor".to_string(),
+                    }));
+                }
+
+                false
+            }
+        };
+
+        write_data_descriptor(
+            writer,
+            self.crc,
+            self.compressed_size,
+            self.uncompressed_size,
+            zip64,
+        )
+    }
+
+    /// Pads the local extra fields so that the entry data starts at a
+    /// multiple of `alignment` bytes from the start of the output.
+    ///
+    /// The padding is an Android alignment extra field (`0xd935`), which is
+    /// what zipalign and apksigner write.
+    fn align_local_data(&mut self, alignment: u16) -> Result<(), Error> {
+        if alignment <= 1 {
+            return Ok(());
+        }
+
+        // The alignment field has a 4 byte header and records the alignment
+        // in its first 2 bytes
+        let data_start = self.local_header_offset + self.local_header_len() + 6;
+        let alignment_u64 = u64::from(alignment);
+        let padding = ((alignment_u64 - data_start % alignment_u64) % alignment_u64) as usize;
+
+        let mut data = vec![0u8; 2 + padding];
+        data[..2].copy_from_slice(&alignment.to_le_bytes());
+        self.extra_fields
+            .add_field(ExtraFieldId::ANDROID_ZIP_ALIGNMENT, &data, Header::LOCAL)
+    }
+
+    fn finalize_extra_fields(&mut self) -> Result<(), Error> {
+        if self.needs_zip64() {
+            let mut sink = [0u8; 28];
+            let mut pos = 0;
+            if self.is_zip64_value(self.uncompressed_size) {
+                sink[pos..pos + 8].copy_from_slice(&self.uncompressed_size.to_le_bytes());
+                pos += 8;
+            }
+            if self.is_zip64_value(self.compressed_size) {
+                sink[pos..pos + 8].copy_from_slice(&self.compressed_size.to_le_bytes());
+                pos += 8;
+            }
+            if self.is_zip64_value(self.local_header_offset) {
+                sink[pos..pos + 8].copy_from_slice(&self.local_header_offset.to_le_bytes());
+                pos += 8;
+            }
+            if self.disk_needs_zip64() {
+                sink[pos..pos + 4].copy_from_slice(&self.disk_number.to_le_bytes());
+                pos += 4;
+            }
+            self.extra_fields
+                .add_field(ExtraFieldId::ZIP64, &sink[..pos], Header::CENTRAL)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a data descriptor, using 64-bit sizes when `zip64` is set.
+fn write_data_descriptor<W>(
+    writer: &mut W,
+    crc: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    zip64: bool,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let mut buffer = [0u8; 24];
+    buffer[0..4].copy_from_slice(&DataDescriptor::SIGNATURE.to_le_bytes());
+    buffer[4..8].copy_from_slice(&crc.to_le_bytes());
+
+    let out_data = if zip64 {
+        // Use 64-bit sizes for ZIP64
+        buffer[8..16].copy_from_slice(&compressed_size.to_le_bytes());
+        buffer[16..24].copy_from_slice(&uncompressed_size.to_le_bytes());
+        &buffer[..]
+    } else {
+        // Use 32-bit sizes for standard ZIP
+        buffer[8..12].copy_from_slice(&(compressed_size as u32).to_le_bytes());
+        buffer[12..16].copy_from_slice(&(uncompressed_size as u32).to_le_bytes());
+        &buffer[..16]
+    };
+
+    writer.write_all(out_data)?;
+    Ok(())
+}
+
+/// Writes the central directory records of the entries that haven't been
+/// removed, followed by the end of central directory records.
+fn write_directory<W: Write>(
+    writer: &mut CountWriter<W>,
+    files: &mut [FileHeader],
+    file_names: &[u8],
+    comment: &[u8],
+    extra_data: &[u8],
+    mut signer: Option<&mut DirectorySigner>,
+    options: &ZipArchiveOptions,
+) -> Result<ZipArchiveSummary, Error> {
+    // The archive extra data record precedes the central directory (4.3.6)
+    if !extra_data.is_empty() {
+        let mut record = Vec::with_capacity(8 + extra_data.len());
+        record.extend_from_slice(&ARCHIVE_EXTRA_DATA_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&(extra_data.len() as u32).to_le_bytes());
+        record.extend_from_slice(extra_data);
+        writer.reserve(record.len() as u64)?;
+        writer.write_all(&record)?;
+    }
+
+    let central_directory_start = writer.count();
+    let total_entries = files.iter().filter(|f| !f.removed).count();
+
+    // The volume and offset within it where the central directory starts
+    // and the number of records on the last volume it spans
+    let mut directory_position = None;
+    let mut last_disk = (0, 0);
+
+    let force_zip64 = options.force_zip64;
+    let threshold = options.zip64_threshold;
+    let entries_overflow = force_zip64 || total_entries >= options.zip64_entry_threshold;
+    let disks_overflow = options.eocd_disks.is_some_and(|(disk, directory_disk)| {
+        disk >= ZIP64_THRESHOLD_DISKS || directory_disk >= ZIP64_THRESHOLD_DISKS
+    });
+    if !options.zip64 {
+        let limit = if entries_overflow {
+            Some(Zip64Limit::EntryCount)
+        } else if central_directory_start >= threshold
+            || disks_overflow
+            || files.iter().any(|f| !f.removed && f.disk_needs_zip64())
+        {
+            Some(Zip64Limit::ArchiveSize)
+        } else if files.iter().any(|f| !f.removed && f.needs_zip64()) {
+            Some(Zip64Limit::EntrySize)
+        } else {
+            None
+        };
+        if let Some(limit) = limit {
+            return Err(Error::from(ErrorKind::Zip64Required { limit }));
+        }
+    }
+
+    let mut name_offset = 0;
+    let mut record = Vec::new();
+
+    // The headers are kept for the signer
+    let mut directory = Vec::new();
+
+    // Write central directory entries
+    for file in files.iter_mut() {
+        let new_name_offset = name_offset + file.name_len as usize;
+        let file_name = &file_names[name_offset..new_name_offset];
+        name_offset = new_name_offset;
+        if file.removed {
+            continue;
+        }
+
+        file.finalize_extra_fields()?;
+        let version_needed = match file.version_needed_override {
+            Some(version_needed) => version_needed,
+            None if file.needs_zip64() => file.version_needed.max(ZIP64_VERSION_NEEDED),
+            None => file.version_needed,
+        };
+
+        // The lower byte of version made by is the spec version, which is
+        // at least the version needed to extract.
+        let version_made_by =
+            (file.version_made_by & 0xff00) | (file.version_made_by & 0xff).max(version_needed);
+
+        let header = ZipFileHeaderFixed {
+            signature: CENTRAL_HEADER_SIGNATURE,
+            version_made_by,
+            version_needed,
+            flags: file.flags,
+            compression_method: file.compression_method,
+            last_mod_time: file.last_mod_time,
+            last_mod_date: file.last_mod_date,
+            crc32: file.crc,
+            compressed_size: file.zip64_clamp(file.compressed_size),
+            uncompressed_size: file.zip64_clamp(file.uncompressed_size),
+            file_name_len: file.name_len,
+            extra_field_len: file.extra_fields.central_size,
+            file_comment_len: file.comment.len() as u16,
+            disk_number_start: file.disk_number.min(ZIP64_THRESHOLD_DISKS) as u16,
+            internal_file_attrs: file.internal_attrs,
+            external_file_attrs: file.external_attrs,
+            local_header_offset: file.zip64_clamp(file.local_header_offset),
+        };
+
+        let record_len = ZipFileHeaderFixed::SIZE as u64
+            + u64::from(file.name_len)
+            + u64::from(file.extra_fields.central_size)
+            + file.comment.len() as u64;
+        writer.reserve(record_len)?;
+        let (disk, offset) = writer.position();
+        directory_position.get_or_insert((disk, offset));
+        last_disk = match last_disk {
+            (last, records) if last == disk => (disk, records + 1),
+            _ => (disk, 1),
+        };
+
+        // Each record is assembled first, so that it's written at once
+        record.clear();
+        header.write(&mut record)?;
+
+        // File name
+        record.extend_from_slice(file_name);
+
+        // Extra fields
+        file.extra_fields
+            .write_extra_fields(&mut record, Header::CENTRAL)?;
+
+        // File comment
+        record.extend_from_slice(&file.comment);
+        writer.write_all(&record)?;
+        if signer.is_some() {
+            directory.extend_from_slice(&record);
+        }
+    }
+
+    // The digital signature record closes the central directory (4.3.13)
+    if let Some(signer) = signer.as_mut() {
+        let signature = (signer.0)(&directory)?;
+        let signature_len = u16::try_from(signature.len()).map_err(|_| {
+            Error::from(ErrorKind::InvalidInput {
+                msg: "directory signature too long".to_string(),
+            })
+        })?;
+
+        record.clear();
+        record.extend_from_slice(&DIGITAL_SIGNATURE_SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&signature_len.to_le_bytes());
+        record.extend_from_slice(&signature);
+        writer.reserve(record.len() as u64)?;
+        directory_position.get_or_insert(writer.position());
+        writer.write_all(&record)?;
+    }
+
+    let central_directory_end = writer.count();
+    let central_directory_size = central_directory_end - central_directory_start;
+    let (directory_disk, central_directory_offset) =
+        directory_position.unwrap_or_else(|| writer.position());
+
+    // Readers find the ZIP64 end of central directory through the entry
+    // count or central directory offset, so the offset is marked when
+    // either the offset or size overflows.
+    let directory_overflow =
+        force_zip64 || central_directory_offset >= threshold || central_directory_size >= threshold;
+    if directory_overflow && !options.zip64 {
+        return Err(Error::from(ErrorKind::Zip64Required {
+            limit: Zip64Limit::ArchiveSize,
+        }));
+    }
+
+    // Determine if we need ZIP64 format
+    let needs_zip64 = entries_overflow
+        || directory_overflow
+        || disks_overflow
+        || files.iter().any(|f| !f.removed && f.needs_zip64());
+    if let Some((target, CompatPolicy::Error)) = options.compat {
+        if needs_zip64 && !target.supports_zip64() {
+            return Err(Error::from(ErrorKind::Incompatible {
+                target,
+                issue: CompatIssue::Zip64,
+            }));
+        }
+    }
+
+    // The end of central directory records are written to the last volume
+    let zip64_len = if needs_zip64 {
+        ZIP64_EOCD_SIZE + ZIP64_EOCD_LOCATOR_SIZE
+    } else {
+        0
+    };
+    let eocd_len = zip64_len + EOCD_SIZE + comment.len();
+    writer.reserve(eocd_len as u64)?;
+    let (eocd_disk, zip64_eocd_offset) = writer.position();
+    let mut trailer = Vec::with_capacity(eocd_len);
+    let disk_entries = match last_disk {
+        (disk, records) if disk == eocd_disk => records,
+        _ => 0,
+    };
+    let (eocd_disk, directory_disk) = options.eocd_disks.unwrap_or((eocd_disk, directory_disk));
+
+    // Write ZIP64 structures if needed
+    if needs_zip64 {
+        // Write ZIP64 End of Central Directory Record
+        write_zip64_eocd(
+            &mut trailer,
+            (eocd_disk, directory_disk),
+            disk_entries,
+            total_entries as u64,
+            central_directory_size,
+            central_directory_offset,
+        )?;
+
+        // Write ZIP64 End of Central Directory Locator
+        write_zip64_eocd_locator(&mut trailer, eocd_disk, zip64_eocd_offset)?;
+    }
+
+    // Write regular End of Central Directory Record
+    trailer.write_all(&END_OF_CENTRAL_DIR_SIGNAUTRE_BYTES)?;
+
+    // Disk numbers - use 0xFFFF if ZIP64
+    let eocd_disk = eocd_disk.min(ZIP64_THRESHOLD_DISKS) as u16;
+    let directory_disk = directory_disk.min(ZIP64_THRESHOLD_DISKS) as u16;
+    trailer.write_all(&eocd_disk.to_le_bytes())?;
+    trailer.write_all(&directory_disk.to_le_bytes())?;
+
+    // Number of entries - use 0xFFFF if ZIP64
+    let (disk_entries, entries_count) = if entries_overflow {
+        (u16::MAX, u16::MAX)
+    } else {
+        (disk_entries as u16, total_entries as u16)
+    };
+    trailer.write_all(&disk_entries.to_le_bytes())?;
+    trailer.write_all(&entries_count.to_le_bytes())?;
+
+    // Central directory size - use 0xFFFFFFFF if ZIP64
+    let cd_size = if directory_overflow {
+        u32::MAX
+    } else {
+        central_directory_size as u32
+    };
+    trailer.write_all(&cd_size.to_le_bytes())?;
+
+    // Central directory offset - use 0xFFFFFFFF if ZIP64
+    let cd_offset = if directory_overflow {
+        u32::MAX
+    } else {
+        central_directory_offset as u32
+    };
+    trailer.write_all(&cd_offset.to_le_bytes())?;
+
+    // Comment length and comment
+    trailer.write_all(&(comment.len() as u16).to_le_bytes())?;
+    trailer.write_all(comment)?;
+    writer.write_all(&trailer)?;
+
+    let files = files.iter().filter(|f| !f.removed);
+    let summary = ZipArchiveSummary {
+        entries: total_entries as u64,
+        compressed_size: files.clone().map(|f| f.compressed_size).sum(),
+        uncompressed_size: files.map(|f| f.uncompressed_size).sum(),
+        central_directory_offset,
+        central_directory_size,
+        zip64: needs_zip64,
+        compat_issues: Vec::new(),
+    };
+    Ok(summary)
+}
+
+/// Writes the ZIP64 End of Central Directory Record
+/// Returns the version needed to extract entries compressed with the method
+/// (4.4.3.2).
+fn version_needed(method: CompressionMethod) -> u16 {
+    match method {
+        CompressionMethod::Bzip2 => 46,
+        CompressionMethod::Aes => 51,
+        CompressionMethod::Lzma | CompressionMethod::Xz => 63,
+        _ => 20,
+    }
+}
+
+/// Returns the general purpose flags that the compression method implies.
+///
+/// LZMA entries are marked as ending with an end marker (5.8.9), as the
+/// built-in codec writes one.
+fn method_flags(method: CompressionMethod) -> u16 {
+    match method {
+        CompressionMethod::Lzma => FLAG_LZMA_END_MARKER,
+        _ => 0,
+    }
+}
+
+/// Builds the fixed portion of an entry's local file header.
+fn local_header_fixed(file_name_len: u16, file: &FileHeader) -> ZipLocalFileHeaderFixed {
+    let (crc32, compressed_size, uncompressed_size) = if file.has_data_descriptor() {
+        if file.needs_local_zip64() {
+            (0, u32::MAX, u32::MAX)
+        } else {
+            (0, 0, 0)
+        }
+    } else if file.needs_local_zip64() {
+        (file.crc, u32::MAX, u32::MAX)
+    } else {
+        (
+            file.crc,
+            file.compressed_size as u32,
+            file.uncompressed_size as u32,
+        )
+    };
+
+    ZipLocalFileHeaderFixed {
+        signature: ZipLocalFileHeaderFixed::SIGNATURE,
+        version_needed: file.version_needed_override.unwrap_or(file.version_needed),
+        flags: file.flags,
+        compression_method: file.compression_method,
+        last_mod_time: file.last_mod_time,
+        last_mod_date: file.last_mod_date,
+        crc32,
+        compressed_size,
+        uncompressed_size,
+        file_name_len,
+        extra_field_len: file.extra_fields.local_size,
+    }
+}
+
+/// Adds the extended timestamp (0x5455) extra field for the given times.
+///
+/// The local header carries each time that is present, while the central
+/// directory only carries the modification time (section 4.5.1 of the
+/// Info-ZIP extra field notes). When only a modification time is set, the
+/// field is written to the central directory alone, as readers only consult
+/// the richer local field for access and creation times.
+fn add_extended_timestamp(
+    extra_fields: &mut ExtraFieldsContainer,
+    modification_time: Option<&UtcDateTime>,
+    access_time: Option<&UtcDateTime>,
+    creation_time: Option<&UtcDateTime>,
+) -> Result<(), Error> {
+    let times = [modification_time, access_time, creation_time];
+    let mut flags = 0u8;
+    let mut local = [0u8; 13];
+    let mut local_len = 1;
+    for (bit, time) in times.iter().enumerate() {
+        if let Some(time) = time {
+            flags |= 1 << bit;
+            let unix_time = time.to_unix().max(0) as u32;
+            local[local_len..local_len + 4].copy_from_slice(&unix_time.to_le_bytes());
+            local_len += 4;
+        }
+    }
+
+    if flags == 0 {
+        return Ok(());
+    }
+
+    local[0] = flags;
+
+    // The central field is a prefix of the local field: the flags and
+    // modification time
+    let central_len = if modification_time.is_some() { 5 } else { 1 };
+    if flags == 1 {
+        return extra_fields.add_field(
+            ExtraFieldId::EXTENDED_TIMESTAMP,
+            &local[..central_len],
+            Header::CENTRAL,
+        );
+    }
+
+    extra_fields.add_field(
+        ExtraFieldId::EXTENDED_TIMESTAMP,
+        &local[..local_len],
+        Header::LOCAL,
+    )?;
+    extra_fields.add_field(
+        ExtraFieldId::EXTENDED_TIMESTAMP,
+        &local[..central_len],
+        Header::CENTRAL,
+    )
+}
+
+/// Adds the NTFS (0x000a) extra field with a single timestamp attribute.
+fn add_ntfs_timestamp(
+    extra_fields: &mut ExtraFieldsContainer,
+    modified: &UtcDateTime,
+    accessed: &UtcDateTime,
+    created: &UtcDateTime,
+) -> Result<(), Error> {
+    let data = NtfsTimestamps::new(*modified, *accessed, *created).to_bytes();
+    extra_fields.add_field(ExtraFieldId::NTFS, &data, Header::default())
+}
+
+/// The characters of code page 437 from 0x80 onwards.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+    ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩\
+    ≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/// Encodes a name as CP437, the default zip encoding. Characters without a
+/// CP437 equivalent are replaced with an underscore.
+fn cp437_lossy(name: &str) -> Vec<u8> {
+    name.chars()
+        .map(|ch| {
+            if ch.is_ascii() {
+                return ch as u8;
+            }
+
+            CP437_HIGH
+                .chars()
+                .position(|x| x == ch)
+                .map_or(b'_', |x| 0x80 + x as u8)
+        })
+        .collect()
+}
+
+/// Rejects names that would be normalized into a different path for being
+/// absolute, traversing upwards, or naming a drive (4.4.17.1), as well as
+/// names with NUL bytes, which truncate the name in C based extractors.
+fn validate_name(name: &[u8]) -> Result<(), Error> {
+    let is_separator = |c: &u8| matches!(c, b'/' | b'\\');
+    let msg = if name.contains(&0) {
+        "contains a NUL byte"
+    } else if name.first().is_some_and(is_separator) {
+        "is an absolute path"
+    } else if name
+        .split(is_separator)
+        .any(|component| matches!(component, [letter, b':', ..] if letter.is_ascii_alphabetic()))
+    {
+        "contains a drive or device letter"
+    } else if name.split(is_separator).any(|component| component == b"..") {
+        "contains a parent directory component"
+    } else {
+        return Ok(());
+    };
+
+    Err(Error::from(ErrorKind::InvalidPath {
+        path: String::from_utf8_lossy(name).into_owned(),
+        msg: msg.to_string(),
+    }))
+}
+
+fn write_zip64_eocd<W>(
+    writer: &mut W,
+    (disk, directory_disk): (u32, u32),
+    disk_entries: u64,
+    total_entries: u64,
+    central_directory_size: u64,
+    central_directory_offset: u64,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    // ZIP64 End of Central Directory Record signature
+    writer.write_all(&END_OF_CENTRAL_DIR_SIGNATURE64.to_le_bytes())?;
+
+    // Size of ZIP64 end of central directory record (excluding signature and this field)
+    let record_size = (ZIP64_EOCD_SIZE - 12) as u64;
+    writer.write_all(&record_size.to_le_bytes())?;
+
+    // Version made by
+    writer.write_all(&ZIP64_VERSION_NEEDED.to_le_bytes())?;
+
+    // Version needed to extract
+    writer.write_all(&ZIP64_VERSION_NEEDED.to_le_bytes())?;
+
+    // Number of this disk
+    writer.write_all(&disk.to_le_bytes())?;
+
+    // Number of the disk with the start of the central directory
+    writer.write_all(&directory_disk.to_le_bytes())?;
+
+    // Total number of entries in the central directory on this disk
+    writer.write_all(&disk_entries.to_le_bytes())?;
+
+    // Total number of entries in the central directory
+    writer.write_all(&total_entries.to_le_bytes())?;
+
+    // Size of the central directory
+    writer.write_all(&central_directory_size.to_le_bytes())?;
+
+    // Offset of start of central directory with respect to the starting disk number
+    writer.write_all(&central_directory_offset.to_le_bytes())?;
+
+    Ok(())
+}
+
+/// Writes the ZIP64 End of Central Directory Locator
+fn write_zip64_eocd_locator<W>(
+    writer: &mut W,
+    disk: u32,
+    zip64_eocd_offset: u64,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    // ZIP64 End of Central Directory Locator signature
+    writer.write_all(&END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE.to_le_bytes())?;
+
+    // Number of the disk with the start of the ZIP64 end of central directory
+    writer.write_all(&disk.to_le_bytes())?;
+
+    // Relative offset of the ZIP64 end of central directory record
+    writer.write_all(&zip64_eocd_offset.to_le_bytes())?;
+
+    // Total number of disks
+    writer.write_all(&(disk + 1).to_le_bytes())?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct ZipEntryOptions {
+    compression_method: CompressionMethod,
+    modification_time: Option<UtcDateTime>,
+    unix_permissions: Option<u32>,
+    access_time: Option<UtcDateTime>,
+    creation_time: Option<UtcDateTime>,
+    ntfs_times: Option<NtfsTimes>,
+    unix_owner: Option<(u32, u32)>,
+    host_system: Option<HostSystem>,
+    internal_attrs: u16,
+    dos_attributes: Option<DosAttributes>,
+    external_attrs: Option<u32>,
+    alignment: u16,
+    declared: Option<DeclaredSizes>,
+    extra_fields: ExtraFieldsContainer,
+    comment: Vec<u8>,
+    utf8_flag: Option<bool>,
+    /// The mask and values of general purpose flags set by the caller.
+    flags_override: (u16, u16),
+    version_needed: Option<u16>,
+    /// Set by the archive's Info-ZIP profile.
+    info_zip: bool,
+    /// Recorded in place of the volume that the entry is written to.
+    disk_number: Option<u32>,
+    #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+    encryption: Option<Encryption>,
+}
+
+/// The CRC32 and sizes of an entry that are known before its data is written.
+#[derive(Debug, Clone, Copy)]
+struct DeclaredSizes {
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+}
+
+/// The times written to the NTFS extra field.
+///
+/// Absent times are taken from the entry's extended timestamp options.
+#[derive(Debug, Clone, Default)]
+struct NtfsTimes {
+    modified: Option<UtcDateTime>,
+    accessed: Option<UtcDateTime>,
+    created: Option<UtcDateTime>,
+}
+
+impl ZipEntryOptions {
+    fn new(compression_method: CompressionMethod) -> Self {
+        ZipEntryOptions {
+            compression_method,
+            modification_time: None,
+            unix_permissions: None,
+            access_time: None,
+            creation_time: None,
+            ntfs_times: None,
+            unix_owner: None,
+            host_system: None,
+            internal_attrs: 0,
+            dos_attributes: None,
+            external_attrs: None,
+            alignment: 0,
+            declared: None,
+            extra_fields: ExtraFieldsContainer::new(),
+            comment: Vec::new(),
+            utf8_flag: None,
+            flags_override: (0, 0),
+            version_needed: None,
+            info_zip: false,
+            disk_number: None,
+            #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+            encryption: None,
+        }
+    }
+
+    fn apply_metadata(&mut self, metadata: &std::fs::Metadata) {
+        if let Some(modified) = metadata
+            .modified()
+            .ok()
+            .and_then(|x| UtcDateTime::try_from(x).ok())
+        {
+            self.modification_time = Some(modified);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            self.unix_permissions = Some(metadata.permissions().mode());
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::MetadataExt;
+            let known = DosAttributes::READ_ONLY
+                | DosAttributes::HIDDEN
+                | DosAttributes::SYSTEM
+                | DosAttributes::DIRECTORY
+                | DosAttributes::ARCHIVE;
+            let attributes = metadata.file_attributes() & u32::from(known.as_u8());
+            self.dos_attributes = Some(DosAttributes::new(attributes as u8));
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            let mut attributes = DosAttributes::default();
+            if metadata.permissions().readonly() {
+                attributes |= DosAttributes::READ_ONLY;
+            }
+            if metadata.is_dir() {
+                attributes |= DosAttributes::DIRECTORY;
+            }
+            self.dos_attributes = Some(attributes);
+        }
+    }
+
+    /// Adds a user supplied extra field.
+    ///
+    /// The ZIP64 field is rejected as the writer generates it when needed.
+    fn add_extra_field(
+        &mut self,
+        id: ExtraFieldId,
+        data: &[u8],
+        location: Header,
+    ) -> Result<(), Error> {
+        if id == ExtraFieldId::ZIP64 {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "the ZIP64 extra field is managed by the writer".to_string(),
+            }));
+        }
+
+        self.extra_fields.add_field(id, data, location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time::ZipDateTimeKind, ZipArchive};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_name_lifetime_independence() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+
+        // Test file builder with temporary name
+        {
+            let (mut entry, config) = {
+                let temp_name = format!("temp-{}.txt", 42);
+                archive.new_file(&temp_name).start().unwrap()
+            };
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"test").unwrap();
+            let (_, desc) = writer.finish().unwrap();
+            entry.finish(desc).unwrap();
+        }
+
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn test_builder_with_offset_and_capacity() {
+        let mut output = Cursor::new(Vec::new());
+
+        output.write_all(b"PREFIX DATA").unwrap();
+        let offset = output.position();
+
+        let mut archive = ZipArchiveWriterBuilder::new()
+            .with_capacity(5)
+            .with_offset(offset)
+            .build(&mut output);
+
+        let (mut entry, config) = archive.new_file("test.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello World").unwrap();
+        let (_, desc) = writer.finish().unwrap();
+        entry.finish(desc).unwrap();
+
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn test_stream_offset_methods() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+
+        // Test case 1: Get local header offset
+        let local_header_offset = archive.stream_offset();
+        let (mut file, config) = archive.new_file("test.txt").start().unwrap();
+
+        // Test case 2: Get start of data offset
+        let data_start_offset = file.stream_offset();
+
+        // Write some data
+        let mut writer = config.wrap(&mut file);
+        writer.write_all(b"Hello World").unwrap();
+        let (_, desc) = writer.finish().unwrap();
+
+        // Test case 3: Get end of compressed data offset
+        let end_data_offset = file.stream_offset();
+
+        let compressed_bytes = file.finish(desc).unwrap();
+
+        // Test case 4: Get end of data descriptor offset (next file's local header offset)
+        let end_descriptor_offset = archive.stream_offset();
+
+        archive.finish().unwrap();
+
+        // Verify the offsets make sense
+        assert_eq!(local_header_offset, 0);
+        assert!(data_start_offset > local_header_offset);
+        assert_eq!(
+            end_data_offset,
+            data_start_offset + b"Hello World".len() as u64
+        );
+        assert_eq!(end_descriptor_offset, end_data_offset + 16); // 16 bytes for data descriptor
+        assert_eq!(compressed_bytes, end_data_offset - data_start_offset);
+    }
+
+    #[test]
+    fn test_crc32_options() {
+        use std::io::Write;
+
+        let data = b"Hello, world!";
+        let correct_crc = crate::crc32(data);
+        let incorrect_crc = 0x12345678u32;
+
+        // Test with default CRC calculation
+        {
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = ZipArchiveWriter::new(&mut output);
+            let (mut entry, config) = archive.new_file("normal.txt").start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(data).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+        }
+
+        // Test with correct custom CRC - should succeed
+        {
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = ZipArchiveWriter::new(&mut output);
+            let (mut entry, config) = archive
+                .new_file("correct.txt")
+                .crc32(Crc32Option::Custom(correct_crc))
+                .start()
+                .unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(data).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+
+            // Verify the archive can be read
+            let output = output.into_inner();
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            let mut entries = archive.entries();
+            let entry = entries.next_entry().unwrap().unwrap();
+            let wayfinder = entry.wayfinder();
+            let entry = archive.get_entry(wayfinder).unwrap();
+            let mut verifier = entry.verifying_reader(entry.data());
+            let mut actual = Vec::new();
+            std::io::copy(&mut verifier, &mut actual).unwrap();
+            assert_eq!(&actual, data);
+        }
+
+        // Test with incorrect custom CRC - verification should fail
+        {
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = ZipArchiveWriter::new(&mut output);
+            let (mut entry, config) = archive
+                .new_file("incorrect.txt")
+                .crc32(Crc32Option::Custom(incorrect_crc))
+                .start()
+                .unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(data).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+
+            // Verify the archive fails verification
+            let output = output.into_inner();
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            let mut entries = archive.entries();
+            let entry = entries.next_entry().unwrap().unwrap();
+            let wayfinder = entry.wayfinder();
+            let entry = archive.get_entry(wayfinder).unwrap();
+            let mut verifier = entry.verifying_reader(entry.data());
+            let mut actual = Vec::new();
+            let result = std::io::copy(&mut verifier, &mut actual);
+
+            // Verification should fail with InvalidChecksum error
+            assert!(result.is_err());
+            let err = result.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            let source = err.into_inner().unwrap();
+            let zip_error = source.downcast::<crate::Error>().unwrap();
+            match zip_error.kind() {
+                ErrorKind::InvalidChecksum { expected, actual } => {
+                    assert_eq!(*expected, incorrect_crc);
+                    assert_eq!(*actual, correct_crc);
+                }
+                _ => panic!("Expected InvalidChecksum error, got {:?}", zip_error.kind()),
+            }
+        }
+
+        // Test with skipped CRC - should have CRC of 0, and should validate fine
+        {
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = ZipArchiveWriter::new(&mut output);
+            let (mut entry, config) = archive
+                .new_file("skipped.txt")
+                .crc32(Crc32Option::Skip)
+                .start()
+                .unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(data).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+
+            // Verify the archive can be read
+            let output = output.into_inner();
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            let mut entries = archive.entries();
+            let entry = entries.next_entry().unwrap().unwrap();
+            let wayfinder = entry.wayfinder();
+            let entry = archive.get_entry(wayfinder).unwrap();
+            let mut verifier = entry.verifying_reader(entry.data());
+            let mut actual = Vec::new();
+            std::io::copy(&mut verifier, &mut actual).unwrap();
+            assert_eq!(&actual, data);
+        }
+    }
+
+    #[test]
+    fn test_tuple_api() {
+        use std::io::Write;
+
+        let data = b"Hello, world!";
+        let custom_crc = 0x12345678u32;
+
+        // Test the new tuple-based API with custom CRC
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("test.txt")
+            .crc32(Crc32Option::Custom(custom_crc))
+            .start()
+            .unwrap();
+
+        // Using the new unified API - the CRC option is automatically configured
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(data).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+
+        // Verify the CRC was correctly applied
+        assert_eq!(descriptor.crc, custom_crc);
+
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn test_compression_level_config() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+
+        let (entry, config) = archive.new_file("default.txt").start().unwrap();
+        assert_eq!(config.compression_level(), None);
+        let (_, descriptor) = config.wrap(std::io::sink()).finish().unwrap();
+        entry.finish(descriptor).unwrap();
+
+        let (entry, config) = archive
+            .new_file("fast.txt")
+            .compression_level(-5)
+            .start()
+            .unwrap();
+        assert_eq!(config.compression_level(), Some(-5));
+        let (_, descriptor) = config.wrap(std::io::sink()).finish().unwrap();
+        entry.finish(descriptor).unwrap();
+
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn test_start_raw_precompressed() {
+        use std::io::Read;
+
+        let data = b"Hello, precompressed world! ".repeat(20);
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let mut writer = archive
+            .new_file("blob.txt")
+            .compression_method(CompressionMethod::Deflate)
+            .start_raw(crate::crc32(&data), data.len() as u64)
+            .unwrap();
+        writer.write_all(&compressed).unwrap();
+        assert_eq!(writer.finish().unwrap(), compressed.len() as u64);
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        assert_eq!(entry.crc32(), crate::crc32(&data));
+        assert_eq!(entry.uncompressed_size_hint(), data.len() as u64);
+        assert_eq!(entry.compressed_size_hint(), compressed.len() as u64);
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let decoder = flate2::read::DeflateDecoder::new(entry.data());
+        let mut actual = Vec::new();
+        entry
+            .verifying_reader(decoder)
+            .read_to_end(&mut actual)
+            .unwrap();
+        assert_eq!(actual, data);
+    }
+
+    #[test]
+    fn test_start_stored() {
+        let data = b"Hello, stored world!";
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::builder().build_seekable(&mut output);
+        let mut writer = archive.new_file("aborted.txt").start_stored().unwrap();
+        writer.write_all(b"partial").unwrap();
+        writer.abort().unwrap();
+
+        let mut writer = archive
+            .new_file("file.txt")
+            .compression_method(CompressionMethod::Deflate)
+            .start_stored()
+            .unwrap();
+        writer.write_all(data).unwrap();
+        assert_eq!(writer.finish().unwrap(), data.len() as u64);
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.file_path().as_ref(), b"file.txt");
+        assert_eq!(entry.compression_method(), CompressionMethod::Store);
+        assert_eq!(entry.crc32(), crate::crc32(data));
+        assert_eq!(entry.uncompressed_size_hint(), data.len() as u64);
+        assert!(!entry.has_data_descriptor());
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        assert_eq!(entry.data(), data);
+        assert!(entries.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_archive_comment() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        assert!(archive.set_comment(&vec![b'a'; 65536]).is_err());
+        archive.set_comment(&vec![b'a'; 65535]).unwrap();
+        archive.set_comment(b"final comment").unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        assert_eq!(archive.comment().as_bytes(), b"final comment");
+    }
+
+    #[test]
+    fn test_raw_extra_field_id() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        assert!(archive
+            .new_file("zip64.txt")
+            .extra_field(ExtraFieldId::ZIP64, &[0u8; 8], Header::default())
+            .is_err());
+
+        let (mut entry, config) = archive
+            .new_file("vendor.txt")
+            .extra_field(0xABCD, b"vendor", Header::default())
+            .unwrap()
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"data").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        let entry = entries.next_entry().unwrap().unwrap();
+        let fields = entry.extra_fields().collect::<Vec<_>>();
+        assert_eq!(fields, vec![(ExtraFieldId::new(0xABCD), &b"vendor"[..])]);
+
+        // Written to both the local and central headers
+        let needle = b"\xCD\xAB\x06\x00vendor";
+        let occurrences = output.windows(needle.len()).filter(|x| x == needle).count();
+        assert_eq!(occurrences, 2);
+    }
+
+    #[test]
+    fn test_unix_owner() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        archive
+            .new_dir("home/")
+            .unix_permissions(0o755)
+            .unix_owner(1000, 100)
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        let expected = [1, 4, 0xe8, 0x03, 0, 0, 4, 100, 0, 0, 0];
+        let central = entry
+            .extra_fields()
+            .find(|(id, _)| *id == ExtraFieldId::INFO_ZIP_UNIX_UID_GID)
+            .map(|(_, data)| data);
+        assert_eq!(central, Some(&expected[..]));
+
+        let local_entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let local = local_entry
+            .extra_fields()
+            .find(|(id, _)| *id == ExtraFieldId::INFO_ZIP_UNIX_UID_GID)
+            .map(|(_, data)| data);
+        assert_eq!(local, Some(&expected[..]));
+    }
+
+    #[test]
+    fn test_cp437_lossy() {
+        assert_eq!(CP437_HIGH.chars().count(), 128);
+        assert_eq!(cp437_lossy("plain/name.txt"), b"plain/name.txt");
+        assert_eq!(cp437_lossy("Ça ÿ\u{a0}"), b"\x80a \x98\xff");
+        assert_eq!(cp437_lossy("日本/€"), b"__/_");
+    }
+
+    #[test]
+    fn test_unicode_path_field() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::builder()
+            .with_unicode_path_field(true)
+            .build(&mut output);
+        let (mut entry, config) = archive.new_file("dir/日本.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"data").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.new_dir("ascii/").create().unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.file_path().as_ref(), b"dir/__.txt");
+        assert_eq!(entry.flags & FLAG_UTF8_ENCODING, 0);
+        let mut expected = vec![1];
+        expected.extend_from_slice(&crc::crc32(b"dir/__.txt").to_le_bytes());
+        expected.extend_from_slice("dir/日本.txt".as_bytes());
+        let field = entry
+            .extra_fields()
+            .find(|(id, _)| *id == ExtraFieldId::INFO_ZIP_UNICODE_PATH)
+            .map(|(_, data)| data);
+        assert_eq!(field, Some(expected.as_slice()));
+
+        let local = archive.get_entry(entry.wayfinder()).unwrap();
+        assert!(local
+            .extra_fields()
+            .any(|(id, _)| id == ExtraFieldId::INFO_ZIP_UNICODE_PATH));
+
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.file_path().as_ref(), b"ascii/");
+        assert_eq!(entry.extra_fields().count(), 0);
+    }
+
+    #[test]
+    fn test_raw_names_and_utf8_flag() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let cases: [(&[u8], Option<bool>); 4] = [
+            (b"\x83t\x83@\x83C\x83\x8b.txt", None),
+            (b"raw/\xff/", Some(true)),
+            ("日本.txt".as_bytes(), None),
+            ("日本.txt".as_bytes(), Some(false)),
+        ];
+        for (i, (name, flag)) in cases.into_iter().enumerate() {
+            let mut builder = if i < 2 {
+                archive.new_file_bytes(name)
+            } else {
+                archive.new_file(std::str::from_utf8(name).unwrap())
+            };
+            if let Some(flag) = flag {
+                builder = builder.utf8_flag(flag);
+            }
+
+            let (mut entry, config) = builder.start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"data").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+        }
+        archive.finish().unwrap();
+
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entries = archive
+            .entries()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let utf8 = entry.flags & FLAG_UTF8_ENCODING != 0;
+                (entry.file_path().as_ref().to_vec(), utf8)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (b"\x83t\x83@\x83C\x83\x8b.txt".to_vec(), false),
+                (b"raw/\xff".to_vec(), true),
+                ("日本.txt".as_bytes().to_vec(), true),
+                ("日本.txt".as_bytes().to_vec(), false),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_path_encoding() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"dir/\xff.txt"));
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        let err = archive.new_file_path(path).map(|_| ()).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidPath { .. }));
+
+        let mut output = Vec::new();
+        for encoding in [PathEncoding::Lossy, PathEncoding::Raw] {
+            let mut archive = ZipArchiveWriter::builder()
+                .with_path_encoding(encoding)
+                .build(&mut output);
+            archive
+                .new_dir_path(Path::new(OsStr::from_bytes(b"\xfe")))
+                .unwrap()
+                .create()
+                .unwrap();
+            let (mut entry, config) = archive.new_file_path(path).unwrap().start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"data").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            let names = archive
+                .entries()
+                .map(|entry| entry.unwrap().file_path().as_ref().to_vec())
+                .collect::<Vec<_>>();
+            let expected = match encoding {
+                PathEncoding::Lossy => vec![
+                    "\u{fffd}/".as_bytes().to_vec(),
+                    "dir/\u{fffd}.txt".as_bytes().to_vec(),
+                ],
+                _ => vec![b"\xfe/".to_vec(), b"dir/\xff.txt".to_vec()],
+            };
+            assert_eq!(names, expected);
+            output.clear();
+        }
+    }
+
+    #[test]
+    fn test_general_purpose_flags() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::builder().build_seekable(&mut output);
+        let cases = [
+            ("option.txt", None, 0x0006, 0x0006),
+            ("utf8.txt", None, 0x0800, 0x0800),
+            ("seekable.txt", None, 0x0008, 0x0008),
+            ("declared.txt", Some(crc::crc32(b"data")), 0x0008, 0x0008),
+            ("cleared.txt", None, 0x0800, 0x0000),
+        ];
+        for (name, crc, mask, flags) in cases {
+            let mut builder = archive
+                .new_file(name)
+                .utf8_flag(true)
+                .general_purpose_flags(mask, flags);
+            if let Some(crc) = crc {
+                builder = builder.predeclare(crc, 4, 4);
+            }
+
+            let (mut entry, config) = builder.start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"data").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+        }
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        for ((name, _, mask, flags), record) in cases.into_iter().zip(archive.entries()) {
+            let record = record.unwrap();
+            assert_eq!(record.file_path().as_ref(), name.as_bytes());
+            let entry = archive.get_entry(record.wayfinder()).unwrap();
+            let mut contents = Vec::new();
+            entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents)
+                .unwrap();
+            assert_eq!(contents, b"data");
+            assert_eq!(record.flags & mask, flags);
+        }
+    }
+
+    #[test]
+    fn test_header_hook() {
+        let mut source = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut source);
+        archive
+            .new_dir("copied/")
+            .unix_permissions(0o040777)
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+        let source = ZipArchive::from_slice(&source).unwrap();
+        let copied = source.entries().next_entry().unwrap().unwrap();
+
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        archive.on_header(|header| {
+            if header.name().starts_with(b"secret") {
+                return Err(Error::from(ErrorKind::InvalidInput {
+                    msg: "secrets aren't allowed".to_string(),
+                }));
+            }
+
+            let mut name = b"pkg/".to_vec();
+            name.extend_from_slice(header.name());
+            header.set_name(name);
+            let mode = header.external_attributes() >> 16;
+            header.set_external_attributes(((mode & !0o777) | 0o755) << 16);
+            header.set_comment(b"checked");
+            Ok(())
+        });
+
+        let time = UtcDateTime::from_components(2024, 1, 1, 0, 0, 0, 0).unwrap();
+        archive
+            .new_dir("dir/")
+            .unix_permissions(0o040700)
+            .last_modified(time)
+            .create()
+            .unwrap();
+        assert!(archive.new_dir("secret/").create().is_err());
+        let data = source.get_entry(copied.wayfinder()).unwrap().data();
+        archive.copy_entry(&copied, data).unwrap();
+        archive.finish().unwrap();
+
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entries = archive
+            .entries()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let local = archive.get_entry(entry.wayfinder()).unwrap();
+                assert_eq!(local.file_path().as_ref(), entry.file_path().as_ref());
+                assert_eq!(entry.comment().as_bytes(), b"checked");
+                (entry.file_path().as_ref().to_vec(), entry.mode().value())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (b"pkg/dir/".to_vec(), 0o040755),
+                (b"pkg/copied/".to_vec(), 0o040755)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sign_directory() {
+        use std::sync::Mutex;
+
+        for signature_len in [4, 100] {
+            let signed = Arc::new(Mutex::new(Vec::new()));
+            let mut output = Vec::new();
+            let mut archive = ZipArchiveWriter::new(&mut output);
+            let directory = signed.clone();
+            archive.sign_directory(move |data| {
+                directory.lock().unwrap().extend_from_slice(data);
+                Ok(vec![0xaa; signature_len])
+            });
+            archive.new_dir("a/").create().unwrap();
+            archive.new_dir("b/").create().unwrap();
+            archive.finish().unwrap();
+
+            // The record follows the headers and is part of the directory
+            let eocd = &output[output.len() - 22..];
+            let size = u32::from_le_bytes(eocd[12..16].try_into().unwrap()) as usize;
+            let offset = u32::from_le_bytes(eocd[16..20].try_into().unwrap()) as usize;
+            let directory = &output[offset..offset + size];
+            let (headers, record) = directory.split_at(size - 6 - signature_len);
+            assert_eq!(headers, &signed.lock().unwrap()[..]);
+            assert_eq!(record[..4], DIGITAL_SIGNATURE_SIGNATURE.to_le_bytes());
+            assert_eq!(record[4..6], (signature_len as u16).to_le_bytes());
+            assert!(record[6..].iter().all(|&b| b == 0xaa));
+
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            assert_eq!(archive.entries().count(), 2);
+            let mut buffer = vec![0u8; crate::RECOMMENDED_BUFFER_SIZE];
+            let archive =
+                ZipArchive::from_seekable(std::io::Cursor::new(&output), &mut buffer).unwrap();
+            let mut entries = archive.entries(&mut buffer);
+            let mut count = 0;
+            while entries.next_entry().unwrap().is_some() {
+                count += 1;
+            }
+            assert_eq!(count, 2);
+        }
+
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        archive.sign_directory(|_| Ok(vec![0; 1 << 16]));
+        assert!(archive.finish().is_err());
+    }
+
+    #[test]
+    fn test_zip64_disabled() {
+        fn limit(err: &Error) -> Option<Zip64Limit> {
+            match err.kind() {
+                ErrorKind::Zip64Required { limit } => Some(*limit),
+                ErrorKind::IO(err) => err
+                    .get_ref()
+                    .and_then(|err| err.downcast_ref::<Error>())
+                    .and_then(limit),
+                _ => None,
+            }
+        }
+
+        let builder = || {
+            ZipArchiveWriter::builder()
+                .force_zip64(true)
+                .with_zip64(false)
+                .with_zip64_threshold(100)
+        };
+
+        // Writes are cut short below the threshold, past the 35 byte header
+        let mut archive = builder().build(Vec::new());
+        let (mut entry, _config) = archive.new_file("a.bin").start().unwrap();
+        assert_eq!(entry.write(&[0u8; 200]).unwrap(), 64);
+        let err = Error::from(entry.write(&[0u8; 1]).unwrap_err());
+        assert_eq!(limit(&err), Some(Zip64Limit::ArchiveSize));
+
+        // Predeclared sizes are checked up front
+        let mut archive = builder().build(Vec::new());
+        let err = archive
+            .new_file("a.bin")
+            .predeclare(0, 100, 100)
+            .start()
+            .unwrap_err();
+        assert_eq!(limit(&err), Some(Zip64Limit::EntrySize));
+
+        // An entry that would start past the threshold, with 32 byte headers
+        let mut archive = builder().build(Vec::new());
+        for name in ["a/", "b/", "c/", "d/"] {
+            archive.new_dir(name).create().unwrap();
+        }
+        let err = archive.new_dir("e/").create().unwrap_err();
+        assert_eq!(limit(&err), Some(Zip64Limit::ArchiveSize));
+
+        // An entry that only overflows once compressed data is accounted
+        // for has its header and data out, so the archive can't continue
+        let mut archive = builder().build(Vec::new());
+        let (mut entry, _config) = archive.new_file("a.bin").start().unwrap();
+        entry.write_all(b"compressed").unwrap();
+        let output = DataDescriptorOutput {
+            crc: 0,
+            compressed_size: 10,
+            uncompressed_size: 200,
+        };
+        let err = entry.finish(output).unwrap_err();
+        assert_eq!(limit(&err), Some(Zip64Limit::EntrySize));
+        assert!(archive.new_dir("b/").create().is_err());
+        assert!(archive.finish().is_err());
+
+        // The central directory is no exception, as the data descriptor
+        // takes the entry past the threshold
+        let mut archive = builder().build(Vec::new());
+        let (mut entry, config) = archive.new_file("a.bin").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(&[0u8; 50]).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        let err = archive.finish().unwrap_err();
+        assert_eq!(limit(&err), Some(Zip64Limit::ArchiveSize));
+
+        // Forcing ZIP64 re-enables it
+        let mut archive = builder().force_zip64(true).build(Vec::new());
+        let (mut entry, _config) = archive.new_file("a.bin").start().unwrap();
+        entry.write_all(&[0u8; 200]).unwrap();
+    }
+
+    #[test]
+    fn test_zip64_disk_numbers() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .with_eocd_disk_numbers(0x10000, 0xFFFF)
+            .build(&mut output);
+        for (name, disk) in [("a/", 0xFFFE), ("b/", 0xFFFF), ("c/", 0x12345)] {
+            archive
+                .new_dir(name)
+                .disk_number_start(disk)
+                .create()
+                .unwrap();
+        }
+        archive.finish().unwrap();
+
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        for disk in [0xFFFE, 0xFFFF, 0x12345] {
+            let entry = entries.next_entry().unwrap().unwrap();
+            assert_eq!(entry.disk_number_start(), disk);
+        }
+
+        // The end of central directory defers to the ZIP64 record
+        let eocd = &output[output.len() - EOCD_SIZE..];
+        assert_eq!(eocd[4..8], [0xFF; 4]);
+        let zip64_eocd =
+            &output[output.len() - EOCD_SIZE - ZIP64_EOCD_LOCATOR_SIZE - ZIP64_EOCD_SIZE..];
+        assert_eq!(zip64_eocd[16..20], 0x10000u32.to_le_bytes());
+        assert_eq!(zip64_eocd[20..24], 0xFFFFu32.to_le_bytes());
+
+        // Without ZIP64, they're rejected rather than truncated
+        let mut archive = ZipArchiveWriter::builder()
+            .with_zip64(false)
+            .build(Vec::new());
+        let err = archive
+            .new_dir("a/")
+            .disk_number_start(0x10000)
+            .create()
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::Zip64Required {
+                limit: Zip64Limit::ArchiveSize
+            }
+        ));
+        let archive = ZipArchiveWriter::builder()
+            .with_zip64(false)
+            .with_eocd_disk_numbers(0, 0xFFFF)
+            .build(Vec::new());
+        let err = archive.finish().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::Zip64Required {
+                limit: Zip64Limit::ArchiveSize
+            }
+        ));
+    }
+
+    #[test]
+    fn test_compat_target() {
+        // Java can't find the end of a stored entry with a data descriptor
+        let mut archive = ZipArchiveWriter::builder()
+            .with_compat_target(CompatTarget::Java8, CompatPolicy::Error)
+            .build(Vec::new());
+        let err = archive.new_file("a.txt").start().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::Incompatible {
+                target: CompatTarget::Java8,
+                issue: CompatIssue::StoredDataDescriptor { .. },
+            }
+        ));
+        archive
+            .new_file("a.txt")
+            .predeclare(0, 0, 0)
+            .start()
+            .unwrap();
+
+        // Explorer misreads unflagged names and archives that need ZIP64
+        let mut archive = ZipArchiveWriter::builder()
+            .force_zip64(true)
+            .with_compat_target(CompatTarget::WindowsExplorer, CompatPolicy::Warn)
+            .build(Vec::new());
+        archive.new_dir("café/").create().unwrap();
+        let (mut entry, config) = archive.new_file("naïve").utf8_flag(false).start().unwrap();
+        let (_, descriptor) = config.wrap(&mut entry).finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        let (_, summary) = archive.finish_with_summary().unwrap();
+        assert_eq!(
+            summary.compat_issues(),
+            [
+                CompatIssue::NameEncoding {
+                    name: "naïve".to_string()
+                },
+                CompatIssue::Zip64
+            ]
+        );
+
+        let mut archive = ZipArchiveWriter::builder()
+            .force_zip64(true)
+            .with_compat_target(CompatTarget::WindowsExplorer, CompatPolicy::Error)
+            .build(Vec::new());
+        archive.new_dir("dir/").create().unwrap();
+        let err = archive.finish().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::Incompatible {
+                issue: CompatIssue::Zip64,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_data_transform() {
+        struct Checksum(u8);
+
+        impl DataTransform for Checksum {
+            fn header(&mut self) -> Vec<u8> {
+                b"HDR".to_vec()
+            }
+
+            fn transform(&mut self, data: &mut [u8]) {
+                for byte in data.iter_mut() {
+                    self.0 = self.0.wrapping_add(*byte);
+                    *byte = byte.rotate_left(1);
+                }
+            }
+
+            fn finish(&mut self) -> Vec<u8> {
+                vec![self.0]
+            }
+        }
+
+        let data = b"Hello, world!";
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::builder().build_seekable(&mut output);
+        let (mut entry, config) = archive
+            .new_file("file.txt")
+            .crc32(Crc32Option::Skip)
+            .start()
+            .unwrap();
+        entry.set_transform(Checksum(0)).unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(data).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        assert!(entry.set_transform(Checksum(0)).is_err());
+        assert_eq!(entry.finish(descriptor).unwrap(), 3 + 13 + 1);
+
+        let (mut entry, _) = archive.new_file("late.txt").start().unwrap();
+        entry.write_all(b"data").unwrap();
+        assert!(entry.set_transform(Checksum(0)).is_err());
+        entry.abort().unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        assert_eq!(entry.compressed_size_hint(), 17);
+        assert_eq!(entry.uncompressed_size_hint(), 13);
+        assert_eq!(entry.crc32(), 0);
+        let local = archive.get_entry(entry.wayfinder()).unwrap();
+        let mut expected = b"HDR".to_vec();
+        expected.extend(data.iter().map(|x| x.rotate_left(1)));
+        expected.push(data.iter().fold(0u8, |acc, x| acc.wrapping_add(*x)));
+        assert_eq!(local.data(), expected.as_slice());
+    }
+
+    #[test]
+    fn test_version_needed_override() {
+        let mut source = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut source);
+        archive.new_dir("copied/").create().unwrap();
+        archive.finish().unwrap();
+        let source = ZipArchive::from_slice(&source).unwrap();
+        let copied = source.entries().next_entry().unwrap().unwrap();
+
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .force_zip64(true)
+            .with_version_needed(20)
+            .build(&mut output);
+        archive.new_dir("archive/").create().unwrap();
+        let (mut entry, config) = archive
+            .new_file("entry.txt")
+            .version_needed(10)
+            .start()
+            .unwrap();
+        let (_, descriptor) = config.wrap(&mut entry).finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        let data = source.get_entry(copied.wayfinder()).unwrap().data();
+        archive.copy_entry(&copied, data).unwrap();
+        archive.finish().unwrap();
+
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let versions = archive
+            .entries()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let offset = entry.local_header_offset() as usize;
+                let local_header = ZipLocalFileHeaderFixed::parse(&output[offset..]).unwrap();
+                assert_eq!(local_header.version_needed, entry.version_needed);
+                entry.version_needed
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec![20, 10, 20]);
+    }
+
+    #[test]
+    fn test_host_system() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::builder()
+            .with_host_system(HostSystem::NTFS)
+            .build(&mut output);
+        archive.new_dir("archive/").create().unwrap();
+        archive
+            .new_dir("unix/")
+            .unix_permissions(0o755)
+            .host_system(HostSystem::UNIX)
+            .create()
+            .unwrap();
+        archive
+            .new_dir("other/")
+            .host_system(HostSystem::new(18))
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let hosts = archive
+            .entries()
+            .map(|x| x.unwrap().version_made_by >> 8)
+            .collect::<Vec<_>>();
+        assert_eq!(hosts, vec![11, 3, 18]);
+
+        // Without an archive wide host system, permissions imply Unix
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        archive.new_dir("dos/").create().unwrap();
+        archive
+            .new_dir("unix/")
+            .unix_permissions(0o755)
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let hosts = archive
+            .entries()
+            .map(|x| x.unwrap().version_made_by >> 8)
+            .collect::<Vec<_>>();
+        assert_eq!(hosts, vec![0, 3]);
+    }
+
+    #[test]
+    fn test_info_zip() {
+        let mtime = UtcDateTime::from_unix(1_700_000_000);
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .info_zip()
+            .build_seekable(Cursor::new(&mut output));
+        archive
+            .new_dir("dir/")
+            .last_modified(mtime)
+            .create()
+            .unwrap();
+        let (mut entry, config) = archive
+            .new_file("dir/file.txt")
+            .last_modified(mtime)
+            .unix_permissions(0o100444)
+            .unix_owner(1000, 100)
+            .compression_method(CompressionMethod::Deflate)
+            .compression_level(9)
+            .start()
+            .unwrap();
+        let (_, descriptor) = config.wrap(&mut entry).finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
+
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entries = archive
+            .entries()
+            .map(|entry| entry.unwrap())
+            .collect::<Vec<_>>();
+        let (dir, file) = (&entries[0], &entries[1]);
+        assert_eq!(dir.version_made_by, 0x031e);
+        assert_eq!(dir.version_needed, 10);
+        assert_eq!(dir.external_file_attrs, 0o040755 << 16 | 0x10);
+        assert_eq!(dir.flags, 0);
+        assert_eq!(file.version_needed, 20);
+        assert_eq!(file.external_file_attrs, 0o100444 << 16 | 0x01);
+        assert_eq!(file.flags, 0x02);
+
+        // The local header has both times and the owner, while the central
+        // directory has the modification time and an empty owner field
+        let unix_time = (mtime.to_unix() as u32).to_le_bytes();
+        let central = file.extra_fields().collect::<Vec<_>>();
+        let mut timestamp = vec![3];
+        timestamp.extend_from_slice(&unix_time);
+        assert_eq!(
+            central,
+            vec![
+                (ExtraFieldId::EXTENDED_TIMESTAMP, &timestamp[..]),
+                (ExtraFieldId::INFO_ZIP_UNIX_UID_GID, &[][..]),
+            ]
+        );
+
+        let offset = file.local_header_offset() as usize;
+        let local_header = ZipLocalFileHeaderFixed::parse(&output[offset..]).unwrap();
+        let start = offset + ZipLocalFileHeaderFixed::SIZE + local_header.file_name_len as usize;
+        let local = &output[start..start + local_header.extra_field_len as usize];
+        let mut expected = vec![0x55, 0x54, 9, 0, 3];
+        expected.extend_from_slice(&unix_time);
+        expected.extend_from_slice(&unix_time);
+        expected.extend_from_slice(&[0x75, 0x78, 11, 0, 1, 4, 0xe8, 3, 0, 0, 4, 100, 0, 0, 0]);
+        assert_eq!(local, &expected[..]);
+    }
+
+    #[test]
+    fn test_internal_attributes() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("text.txt")
+            .internal_attributes(1)
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"text\n").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.new_dir("dir/").create().unwrap();
+        archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let attrs = archive
+            .entries()
+            .map(|x| x.unwrap().internal_file_attrs)
+            .collect::<Vec<_>>();
+        assert_eq!(attrs, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_dos_attributes() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("readonly.txt")
+            .dos_attributes(DosAttributes::READ_ONLY | DosAttributes::ARCHIVE)
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"data").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive
+            .new_dir("hidden/")
+            .dos_attributes(DosAttributes::HIDDEN)
+            .create()
+            .unwrap();
+        archive
+            .new_dir("both/")
+            .unix_permissions(0o040755)
+            .dos_attributes(DosAttributes::default())
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
 
-            // Set version_made_by to indicate Unix when Unix permissions are present
-            let version_made_by_hi = file.unix_permissions.map(|_| CREATOR_UNIX).unwrap_or(0);
-            let version_made_by = (version_made_by_hi << 8) | version_needed;
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entries = archive
+            .entries()
+            .map(|x| x.unwrap())
+            .map(|x| (x.external_file_attrs, x.mode().value()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (0x21, 0o100444),
+                (0x12, 0o040777),
+                ((0o040755 << 16) | 0x10, 0o040755),
+            ]
+        );
+    }
 
-            let (dos_time, dos_date) = file
-                .modification_time
-                .as_ref()
-                .map(|dt| DosDateTime::from(dt).into_parts())
-                .unwrap_or((0, 0));
-
-            let header = ZipFileHeaderFixed {
-                signature: CENTRAL_HEADER_SIGNATURE,
-                version_made_by,
-                version_needed,
-                flags: file.flags,
-                compression_method: file.compression_method.as_id(),
-                last_mod_time: dos_time,
-                last_mod_date: dos_date,
-                crc32: file.crc,
-                compressed_size: file.compressed_size.min(ZIP64_THRESHOLD_FILE_SIZE) as u32,
-                uncompressed_size: file.uncompressed_size.min(ZIP64_THRESHOLD_FILE_SIZE) as u32,
-                file_name_len: file.name_len,
-                extra_field_len: file.extra_fields.central_size,
-                file_comment_len: 0,
-                disk_number_start: 0,
-                internal_file_attrs: 0,
-                external_file_attrs: file.unix_permissions.map(|x| x << 16).unwrap_or(0),
-                local_header_offset: file.local_header_offset.min(ZIP64_THRESHOLD_OFFSET) as u32,
-            };
+    #[test]
+    fn test_external_attributes_override() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        archive
+            .new_dir("vms/")
+            .unix_permissions(0o755)
+            .dos_attributes(DosAttributes::HIDDEN)
+            .external_attributes(0xdead_beef)
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
 
-            header.write(&mut self.writer)?;
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        assert_eq!(entry.external_file_attrs, 0xdead_beef);
+        assert_eq!(entry.version_made_by >> 8, CREATOR_UNIX);
+    }
 
-            // File name
-            let new_name_offset = name_offset + file.name_len as usize;
-            self.writer
-                .write_all(&self.file_names[name_offset..new_name_offset])?;
-            name_offset = new_name_offset;
+    #[test]
+    fn test_alignment() {
+        let prefix = b"odd sized prefix";
+        let mut output = Cursor::new(Vec::new());
+        output.write_all(prefix).unwrap();
+        let mut archive = ZipArchiveWriter::builder()
+            .with_offset(prefix.len() as u64)
+            .build(&mut output);
 
-            // Extra fields
-            file.extra_fields
-                .write_extra_fields(&mut self.writer, Header::CENTRAL)?;
+        let alignments = [4, 4096, 4, 0, 3, 1];
+        for (i, alignment) in alignments.iter().enumerate() {
+            let name = format!("{}{}", "n".repeat(i), i);
+            let (mut entry, config) = archive
+                .new_file(&name)
+                .alignment(*alignment)
+                .extra_field(0x6666, &vec![0u8; i], Header::LOCAL)
+                .unwrap()
+                .start()
+                .unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(name.as_bytes()).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
         }
+        archive.finish().unwrap();
 
-        let central_directory_end = self.writer.count();
-        let central_directory_size = central_directory_end - central_directory_offset;
-
-        // Write ZIP64 structures if needed
-        if needs_zip64 {
-            let zip64_eocd_offset = self.writer.count();
-
-            // Write ZIP64 End of Central Directory Record
-            write_zip64_eocd(
-                &mut self.writer,
-                total_entries as u64,
-                central_directory_size,
-                central_directory_offset,
-            )?;
-
-            // Write ZIP64 End of Central Directory Locator
-            write_zip64_eocd_locator(&mut self.writer, zip64_eocd_offset)?;
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        for alignment in alignments {
+            let entry = entries.next_entry().unwrap().unwrap();
+            let local = archive.get_entry(entry.wayfinder()).unwrap();
+            let (start, _) = local.compressed_data_range();
+            assert_eq!(start % u64::from(alignment.max(1)), 0);
+            assert_eq!(local.data(), entry.file_path().as_ref());
+
+            let field = local
+                .extra_fields()
+                .find(|(id, _)| *id == ExtraFieldId::ANDROID_ZIP_ALIGNMENT);
+            if alignment > 1 {
+                let (_, data) = field.unwrap();
+                assert_eq!(&data[..2], &alignment.to_le_bytes());
+                assert!(data.len() < 2 + alignment as usize);
+            } else {
+                assert!(field.is_none());
+            }
         }
+    }
 
-        // Write regular End of Central Directory Record
-        self.writer.write_all(&END_OF_CENTRAL_DIR_SIGNAUTRE_BYTES)?;
+    #[test]
+    fn test_add_bytes() {
+        let text = b"add me ".repeat(100);
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        assert_eq!(archive.add_bytes("tiny.txt", b"hi").unwrap(), 2);
+        let compressed = archive.add_bytes("text.txt", &text).unwrap();
+        archive.finish().unwrap();
 
-        // Disk numbers
-        self.writer.write_all(&[0u8; 4])?;
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.compression_method(), CompressionMethod::Store);
+        let local = archive.get_entry(entry.wayfinder()).unwrap();
+        assert_eq!(local.data(), b"hi");
+        std::io::copy(
+            &mut local.verifying_reader(local.data()),
+            &mut std::io::sink(),
+        )
+        .unwrap();
+
+        // Compressible data is stored too, whatever the enabled features
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.compressed_size_hint(), compressed);
+        assert_eq!(entry.compression_method(), CompressionMethod::Store);
+        assert_eq!(compressed, text.len() as u64);
+    }
 
-        // Number of entries - use 0xFFFF if ZIP64
-        let entries_count = total_entries.min(ZIP64_THRESHOLD_ENTRIES) as u16;
-        self.writer.write_all(&entries_count.to_le_bytes())?;
-        self.writer.write_all(&entries_count.to_le_bytes())?;
+    #[test]
+    fn test_write_mimetype() {
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        archive
+            .write_mimetype("application/vnd.oasis.opendocument.text")
+            .unwrap();
+        assert!(archive.write_mimetype("application/epub+zip").is_err());
+        archive.finish().unwrap();
 
-        // Central directory size - use 0xFFFFFFFF if ZIP64
-        let cd_size = central_directory_size.min(ZIP64_THRESHOLD_OFFSET) as u32;
-        self.writer.write_all(&cd_size.to_le_bytes())?;
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        assert_eq!(entry.file_path().as_ref(), b"mimetype");
+        assert_eq!(entry.compression_method(), CompressionMethod::Store);
+        assert_eq!(entry.flags, 0);
+        assert_eq!(entry.extra_fields().count(), 0);
+
+        let local = archive.get_entry(entry.wayfinder()).unwrap();
+        assert_eq!(local.extra_fields().count(), 0);
+        assert_eq!(local.data(), b"application/vnd.oasis.opendocument.text");
+        let mut verifier = local.verifying_reader(local.data());
+        std::io::copy(&mut verifier, &mut std::io::sink()).unwrap();
+
+        // The CRC32 and sizes are in the local header
+        let local_header = ZipLocalFileHeaderFixed::parse(&output).unwrap();
+        assert_eq!(local_header.crc32, entry.crc32);
+        assert_eq!(local_header.compressed_size, 39);
+        assert_eq!(local_header.uncompressed_size, 39);
+    }
 
-        // Central directory offset - use 0xFFFFFFFF if ZIP64
-        let cd_offset = central_directory_offset.min(ZIP64_THRESHOLD_OFFSET) as u32;
-        self.writer.write_all(&cd_offset.to_le_bytes())?;
+    #[test]
+    fn test_deterministic() {
+        let write = |offset: i64| {
+            let time = |x: i64| UtcDateTime::from_unix(1_600_000_000 + offset * 1000 + x);
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = ZipArchiveWriter::builder()
+                .deterministic(UtcDateTime::from_unix(315_532_800))
+                .build(&mut output);
+            let (mut entry, config) = archive
+                .new_file("file.txt")
+                .last_modified(time(1))
+                .last_accessed(time(2))
+                .created(time(3))
+                .ntfs_times(time(4), time(5), time(6))
+                .start()
+                .unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"data").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.new_dir("dir/").create().unwrap();
+            archive.finish().unwrap();
+            output.into_inner()
+        };
 
-        // Comment length
-        self.writer.write_all(&0u16.to_le_bytes())?;
+        let output = write(0);
+        assert_eq!(output, write(1));
 
-        self.writer.flush()?;
-        Ok(self.writer.writer)
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        for entry in archive.entries() {
+            let entry = entry.unwrap();
+            let expected = UtcDateTime::from_unix(315_532_800);
+            assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(expected));
+            let local = archive.get_entry(entry.wayfinder()).unwrap();
+            let (_, extended) = local
+                .extra_fields()
+                .find(|(id, _)| *id == ExtraFieldId::EXTENDED_TIMESTAMP)
+                .unwrap_or((ExtraFieldId::EXTENDED_TIMESTAMP, &[]));
+            assert!(extended.is_empty());
+        }
     }
-}
 
-/// A writer for a file in a ZIP archive.
-///
-/// This writer is created by `ZipArchiveWriter::new_file`.
-/// Data written to this writer is compressed and written to the underlying archive.
-///
-/// After writing all data, call `finish` to complete the entry.
-#[derive(Debug)]
-pub struct ZipEntryWriter<'a, W> {
-    inner: &'a mut ZipArchiveWriter<W>,
-    compressed_bytes: u64,
-    name_len: u16,
-    local_header_offset: u64,
-    compression_method: CompressionMethod,
-    flags: u16,
-    modification_time: Option<UtcDateTime>,
-    unix_permissions: Option<u32>,
-    extra_fields: ExtraFieldsContainer,
-}
+    #[test]
+    fn test_jar_marker() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .jar_marker(true)
+            .build(&mut output);
+        archive
+            .new_dir("META-INF/")
+            .last_modified(UtcDateTime::from_components(2024, 1, 1, 0, 0, 0, 0).unwrap())
+            .create()
+            .unwrap();
+        archive.new_dir("lib/").create().unwrap();
+        archive.finish().unwrap();
 
-/// Configuration for creating data writers that handle uncompressed data and CRC32 calculation.
-#[derive(Debug)]
-pub struct ZipDataWriterConfig {
-    crc32_option: Crc32Option,
-}
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        let entry = entries.next_entry().unwrap().unwrap();
+        let fields = entry.extra_fields().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [ExtraFieldId::JAVA_JAR, ExtraFieldId::EXTENDED_TIMESTAMP]
+        );
+        let local = archive.get_entry(entry.wayfinder()).unwrap();
+        let (id, data) = local.extra_fields().next().unwrap();
+        assert_eq!((id, data), (ExtraFieldId::JAVA_JAR, &[][..]));
 
-impl ZipDataWriterConfig {
-    /// Wraps an encoder with a data writer configured with this builder's options.
-    pub fn wrap<E>(self, encoder: E) -> ZipDataWriter<E> {
-        ZipDataWriter::with_crc32(encoder, self.crc32_option)
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert!(!entry
+            .extra_fields()
+            .any(|(id, _)| id == ExtraFieldId::JAVA_JAR));
     }
-}
 
-impl<'a, W> ZipEntryWriter<'a, W> {
-    /// Returns the total number of bytes successfully written (bytes out).
-    pub fn compressed_bytes(&self) -> u64 {
-        self.compressed_bytes
-    }
+    #[test]
+    fn test_headers_written_at_once() {
+        /// Records the length of each write.
+        #[derive(Default)]
+        struct Writes(Vec<usize>);
+
+        impl Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
 
-    /// Returns the current offset in the output stream.
-    ///
-    /// See [`ZipArchiveWriter::stream_offset`] for more information.
-    pub fn stream_offset(&self) -> u64 {
-        self.inner.stream_offset()
-    }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
 
-    /// Finishes writing the file entry.
-    ///
-    /// This writes the data descriptor if necessary and adds the file entry to the central directory.
-    pub fn finish(self, mut output: DataDescriptorOutput) -> Result<u64, Error>
-    where
-        W: Write,
-    {
-        output.compressed_size = self.compressed_bytes;
-        let mut buffer = [0u8; 24];
-        buffer[0..4].copy_from_slice(&DataDescriptor::SIGNATURE.to_le_bytes());
-        buffer[4..8].copy_from_slice(&output.crc.to_le_bytes());
+        let mut archive = ZipArchiveWriter::builder()
+            .force_zip64(true)
+            .build(Writes::default());
+        archive
+            .new_dir("dir/")
+            .last_modified(UtcDateTime::from_unix(1_700_000_000))
+            .create()
+            .unwrap();
+        assert_eq!(archive.writer.writer.0.len(), 1);
+        archive
+            .new_dir("other/")
+            .comment("comment")
+            .create()
+            .unwrap();
+        archive.set_comment(b"archive").unwrap();
+        let writes = archive.finish().unwrap().0;
 
-        let out_data = if output.compressed_size >= ZIP64_THRESHOLD_FILE_SIZE
-            || output.uncompressed_size >= ZIP64_THRESHOLD_FILE_SIZE
-        {
-            // Use 64-bit sizes for ZIP64
-            buffer[8..16].copy_from_slice(&output.compressed_size.to_le_bytes());
-            buffer[16..24].copy_from_slice(&output.uncompressed_size.to_le_bytes());
-            &buffer[..]
-        } else {
-            // Use 32-bit sizes for standard ZIP
-            buffer[8..12].copy_from_slice(&(output.compressed_size as u32).to_le_bytes());
-            buffer[12..16].copy_from_slice(&(output.uncompressed_size as u32).to_le_bytes());
-            &buffer[..16]
-        };
+        // Two local headers, two central directory records, and the end of
+        // central directory records
+        assert_eq!(writes.len(), 5);
+        assert_eq!(
+            writes[4],
+            ZIP64_EOCD_SIZE + ZIP64_EOCD_LOCATOR_SIZE + EOCD_SIZE + 7
+        );
+    }
 
-        self.inner.writer.write_all(out_data)?;
-
-        let mut file_header = FileHeader {
-            name_len: self.name_len,
-            compression_method: self.compression_method,
-            local_header_offset: self.local_header_offset,
-            compressed_size: output.compressed_size,
-            uncompressed_size: output.uncompressed_size,
-            crc: output.crc,
-            flags: self.flags,
-            modification_time: self.modification_time,
-            unix_permissions: self.unix_permissions,
-            extra_fields: self.extra_fields,
+    #[test]
+    fn test_preserve_subsecond_times() {
+        let precise = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 123_456_700).unwrap();
+        let whole = UtcDateTime::from_unix(1_700_000_001);
+        let write = |enabled: bool| {
+            let mut output = Vec::new();
+            let mut archive = ZipArchiveWriter::builder()
+                .preserve_subsecond_times(enabled)
+                .build(&mut output);
+            archive
+                .new_dir("precise/")
+                .last_modified(precise)
+                .create()
+                .unwrap();
+            archive
+                .new_dir("whole/")
+                .last_modified(whole)
+                .create()
+                .unwrap();
+            archive.finish().unwrap();
+            output
         };
-        file_header.finalize_extra_fields()?;
-        self.inner.files.push(file_header);
-
-        Ok(self.compressed_bytes)
-    }
-}
 
-impl<W> Write for ZipEntryWriter<'_, W>
-where
-    W: Write,
-{
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let bytes_written = self.inner.writer.write(buf)?;
-        self.compressed_bytes += bytes_written as u64;
-        Ok(bytes_written)
-    }
+        let output = write(true);
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(precise));
+        assert!(entry.extra_fields().any(|(id, _)| id == ExtraFieldId::NTFS));
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(whole));
+        assert!(!entry.extra_fields().any(|(id, _)| id == ExtraFieldId::NTFS));
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.inner.writer.flush()
+        let output = write(false);
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        let truncated = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 0).unwrap();
+        assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(truncated));
     }
-}
 
-/// A writer for the uncompressed data of a Zip file entry.
-///
-/// This writer will keep track of the data necessary to write the data
-/// descriptor (ie: number of bytes written and the CRC32 checksum).
-///
-/// Once all the data has been written, invoke the `finish` method to receive the
-/// `DataDescriptorOutput` necessary to finalize the entry.
-#[derive(Debug)]
-pub struct ZipDataWriter<W> {
-    inner: W,
-    uncompressed_bytes: u64,
-    crc: u32,
-    crc32_option: Crc32Option,
-}
+    #[test]
+    fn test_seekable_backpatch() {
+        let prefix = b"prefix";
+        let mut output = Cursor::new(Vec::new());
+        output.write_all(prefix).unwrap();
+        let mut archive = ZipArchiveWriter::builder()
+            .with_offset(prefix.len() as u64)
+            .build_seekable(&mut output);
 
-impl<W> ZipDataWriter<W> {
-    /// Creates a new `ZipDataWriter` that writes to an underlying writer.
-    #[deprecated(
-        since = "0.4.0",
-        note = "Use the tuple-based API: `ZipFileBuilder::start()` returns `(writer, builder)` which can propagate the CRC32 option"
-    )]
-    pub fn new(inner: W) -> Self {
-        Self::with_crc32_option(inner, Crc32Option::default())
-    }
+        let contents: [&[u8]; 3] = [b"first", b"", b"third entry"];
+        for (i, data) in contents.iter().enumerate() {
+            let (mut entry, config) = archive
+                .new_file(&format!("file{}.txt", i))
+                .alignment(4)
+                .start()
+                .unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(data).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+        }
+        archive.new_dir("dir/").create().unwrap();
+        archive.finish().unwrap();
 
-    /// Creates a new `ZipDataWriter` with the specified CRC32 option.
-    ///
-    /// This is an internal method. Use the tuple-based API via
-    /// `ZipFileBuilder::start()` instead.
-    pub(crate) fn with_crc32(inner: W, crc32_option: Crc32Option) -> Self {
-        Self::with_crc32_option(inner, crc32_option)
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        for data in contents {
+            let entry = entries.next_entry().unwrap().unwrap();
+            assert!(!entry.has_data_descriptor());
+
+            let offset = entry.local_header_offset() as usize;
+            let local_header = ZipLocalFileHeaderFixed::parse(&output[offset..]).unwrap();
+            assert_eq!(local_header.flags, entry.flags);
+            assert_eq!(local_header.crc32, crc::crc32(data));
+            assert_eq!(local_header.compressed_size, data.len() as u32);
+            assert_eq!(local_header.uncompressed_size, data.len() as u32);
+
+            let local = archive.get_entry(entry.wayfinder()).unwrap();
+            assert_eq!(local.data(), data);
+            let mut verifier = local.verifying_reader(local.data());
+            std::io::copy(&mut verifier, &mut std::io::sink()).unwrap();
+        }
     }
 
-    /// Creates a new `ZipDataWriter` with a specific CRC32 calculation option.
-    fn with_crc32_option(inner: W, crc32_option: Crc32Option) -> Self {
-        let crc = crc32_option.initial_value();
-        ZipDataWriter {
-            inner,
-            uncompressed_bytes: 0,
-            crc,
-            crc32_option,
+    #[test]
+    fn test_predeclared_entries() {
+        let data = b"content addressed";
+        let mut compressed = Vec::new();
+        {
+            let mut encoder =
+                flate2::write::DeflateEncoder::new(&mut compressed, flate2::Compression::best());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap();
         }
-    }
 
-    /// Gets a mutable reference to the underlying writer.
-    pub fn get_mut(&mut self) -> &mut W {
-        &mut self.inner
-    }
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("stored.txt")
+            .predeclare(crc::crc32(data), data.len() as u64, data.len() as u64)
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(data).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
 
-    /// Consumes self and returns the inner writer and the data descriptor to be
-    /// passed to a `ZipEntryWriter`.
-    ///
-    /// The writer is returned to facilitate situations where the underlying
-    /// compressor needs to be notified that no more data will be written so it
-    /// can write any sort of necessary epilogue (think zstd).
-    ///
-    /// The `DataDescriptorOutput` contains the CRC32 checksum and uncompressed size,
-    /// which is needed by `ZipEntryWriter::finish`.
-    pub fn finish(mut self) -> Result<(W, DataDescriptorOutput), Error>
-    where
-        W: Write,
-    {
-        self.flush()?;
-        let output = DataDescriptorOutput {
-            crc: self.crc,
-            compressed_size: 0,
-            uncompressed_size: self.uncompressed_bytes,
-        };
+        let mut writer = archive
+            .new_file("deflated.txt")
+            .compression_method(CompressionMethod::Deflate)
+            .predeclare(crc::crc32(data), compressed.len() as u64, data.len() as u64)
+            .start_raw(crc::crc32(data), data.len() as u64)
+            .unwrap();
+        writer.write_all(&compressed).unwrap();
+        writer.finish().unwrap();
+        archive.finish().unwrap();
 
-        Ok((self.inner, output))
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        for entry in archive.entries() {
+            let entry = entry.unwrap();
+            assert!(!entry.has_data_descriptor());
+            let offset = entry.local_header_offset() as usize;
+            let local_header = ZipLocalFileHeaderFixed::parse(&output[offset..]).unwrap();
+            assert_eq!(local_header.crc32, crc::crc32(data));
+            assert_eq!(
+                u64::from(local_header.compressed_size),
+                entry.compressed_size_hint()
+            );
+            assert_eq!(local_header.uncompressed_size, data.len() as u32);
+        }
     }
-}
 
-impl<W> Write for ZipDataWriter<W>
-where
-    W: Write,
-{
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let bytes_written = self.inner.write(buf)?;
-        self.uncompressed_bytes += bytes_written as u64;
+    #[test]
+    fn test_force_zip64() {
+        for seekable in [false, true] {
+            let builder = ZipArchiveWriter::builder().force_zip64(true);
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = if seekable {
+                builder.build_seekable(&mut output)
+            } else {
+                builder.build(&mut output)
+            };
 
-        // Only calculate CRC32 if the option is Calculate
-        if matches!(self.crc32_option, Crc32Option::Calculate) {
-            self.crc = crc::crc32_chunk(&buf[..bytes_written], self.crc);
-        }
+            archive.new_dir("dir/").create().unwrap();
+            let (mut entry, config) = archive.new_file("dir/file.txt").start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"Hello, world!").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
 
-        Ok(bytes_written)
-    }
+            let output = output.into_inner();
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            assert_eq!(archive.entries_hint(), 2);
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.inner.flush()
-    }
-}
+            let mut entries = archive.entries();
+            let dir = entries.next_entry().unwrap().unwrap();
+            let file = entries.next_entry().unwrap().unwrap();
+            assert_eq!(file.has_data_descriptor(), !seekable);
+            assert_eq!(file.uncompressed_size_hint(), 13);
+            assert!(file.local_header_offset() > dir.local_header_offset());
+            for entry in [&dir, &file] {
+                assert!(entry.version_needed >= ZIP64_VERSION_NEEDED);
+                let (_, field) = entry
+                    .extra_fields()
+                    .find(|(id, _)| *id == ExtraFieldId::ZIP64)
+                    .unwrap();
+                assert_eq!(field.len(), 24);
+            }
 
-/// Contains information written in the data descriptor after the file data.
-#[derive(Debug, Clone)]
-pub struct DataDescriptorOutput {
-    crc: u32,
-    compressed_size: u64,
-    uncompressed_size: u64,
-}
+            let offset = file.local_header_offset() as usize;
+            let local_header = ZipLocalFileHeaderFixed::parse(&output[offset..]).unwrap();
+            assert_eq!(local_header.compressed_size, u32::MAX);
+            assert_eq!(local_header.uncompressed_size, u32::MAX);
+            let extra_start = offset + ZipLocalFileHeaderFixed::SIZE + 12;
+            let extra_end = extra_start + local_header.extra_field_len as usize;
+            let (_, field) = crate::extra_fields::ExtraFields::new(&output[extra_start..extra_end])
+                .find(|(id, _)| *id == ExtraFieldId::ZIP64)
+                .unwrap();
+            let expected_size = if seekable { 13u64 } else { 0 };
+            assert_eq!(&field[..8], &expected_size.to_le_bytes());
+            assert_eq!(&field[8..], &expected_size.to_le_bytes());
 
-impl DataDescriptorOutput {
-    /// Returns the CRC32 checksum of the uncompressed data.
-    pub fn crc(&self) -> u32 {
-        self.crc
+            let wayfinder = file.wayfinder();
+            let entry = archive.get_entry(wayfinder).unwrap();
+            assert_eq!(entry.data(), b"Hello, world!");
+            if !seekable {
+                // Data descriptors have 64-bit sizes
+                let (_, end) = entry.compressed_data_range();
+                let descriptor = &output[end as usize..end as usize + 24];
+                assert_eq!(&descriptor[8..16], &13u64.to_le_bytes());
+                assert_eq!(&descriptor[16..24], &13u64.to_le_bytes());
+            }
+        }
     }
 
-    /// Returns the uncompressed size of the data.
-    pub fn uncompressed_size(&self) -> u64 {
-        self.uncompressed_size
-    }
-}
+    #[test]
+    fn test_data_descriptor_width() {
+        let cases = [
+            (DataDescriptorWidth::Auto, 10, 16),
+            (DataDescriptorWidth::Auto, u64::from(u32::MAX) + 1, 24),
+            (DataDescriptorWidth::Bits64, 10, 24),
+            (DataDescriptorWidth::Bits32, 10, 16),
+            (DataDescriptorWidth::Bits32, u64::from(u32::MAX), 16),
+        ];
+
+        for (width, uncompressed_size, descriptor_len) in cases {
+            let mut output = Vec::new();
+            let mut archive = ZipArchiveWriter::builder()
+                .with_data_descriptor_width(width)
+                .build(&mut output);
+            let mut writer = archive
+                .new_file("file.txt")
+                .compression_method(CompressionMethod::Deflate)
+                .start_raw(0, uncompressed_size)
+                .unwrap();
+            writer.write_all(b"compressed").unwrap();
+            let data_end = writer.entry.stream_offset();
+            writer.finish().unwrap();
+            assert_eq!(archive.stream_offset(), data_end + descriptor_len);
+            archive.finish().unwrap();
 
-#[derive(Debug)]
-struct FileHeader {
-    name_len: u16,
-    compression_method: CompressionMethod,
-    local_header_offset: u64,
-    compressed_size: u64,
-    uncompressed_size: u64,
-    crc: u32,
-    flags: u16,
-    modification_time: Option<UtcDateTime>,
-    unix_permissions: Option<u32>,
-    extra_fields: ExtraFieldsContainer,
-}
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            let entry = archive.entries().next_entry().unwrap().unwrap();
+            assert_eq!(entry.uncompressed_size_hint(), uncompressed_size);
+        }
 
-impl FileHeader {
-    fn needs_zip64(&self) -> bool {
-        self.compressed_size >= ZIP64_THRESHOLD_FILE_SIZE
-            || self.uncompressed_size >= ZIP64_THRESHOLD_FILE_SIZE
-            || self.local_header_offset >= ZIP64_THRESHOLD_OFFSET
+        // A lowered threshold puts no ZIP64 field in the local header of a
+        // streamed entry, so its descriptor stays 32-bit
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .with_zip64_threshold(1)
+            .build(&mut output);
+        let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        let data_end = entry.stream_offset();
+        entry.finish(descriptor).unwrap();
+        assert_eq!(archive.stream_offset(), data_end + 16);
+        archive.finish().unwrap();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        let local = archive.get_entry(entry.wayfinder()).unwrap();
+        assert_eq!(local.data(), b"Hello");
+
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .with_data_descriptor_width(DataDescriptorWidth::Bits32)
+            .build(&mut output);
+        let mut writer = archive
+            .new_file("file.txt")
+            .compression_method(CompressionMethod::Deflate)
+            .start_raw(0, u64::from(u32::MAX) + 1)
+            .unwrap();
+        writer.write_all(b"compressed").unwrap();
+        let err = writer.finish().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
     }
 
-    fn finalize_extra_fields(&mut self) -> Result<(), Error> {
-        if self.needs_zip64() {
-            let mut sink = [0u8; 24];
-            let mut pos = 0;
-            if self.uncompressed_size >= ZIP64_THRESHOLD_FILE_SIZE {
-                sink[pos..pos + 8].copy_from_slice(&self.uncompressed_size.to_le_bytes());
-                pos += 8;
-            }
-            if self.compressed_size >= ZIP64_THRESHOLD_FILE_SIZE {
-                sink[pos..pos + 8].copy_from_slice(&self.compressed_size.to_le_bytes());
-                pos += 8;
-            }
-            if self.local_header_offset >= ZIP64_THRESHOLD_OFFSET {
-                sink[pos..pos + 8].copy_from_slice(&self.local_header_offset.to_le_bytes());
-                pos += 8;
-            }
-            self.extra_fields
-                .add_field(ExtraFieldId::ZIP64, &sink[..pos], Header::CENTRAL)?;
+    #[test]
+    fn test_prefix() {
+        let stub = b"#!/bin/sh\nexit 0\n";
+        for adjust_offsets in [true, false] {
+            let mut output = Vec::new();
+            let mut archive = ZipArchiveWriter::builder()
+                .with_prefix(stub, adjust_offsets)
+                .build(&mut output);
+            let expected_offset = if adjust_offsets { stub.len() as u64 } else { 0 };
+            assert_eq!(archive.stream_offset(), expected_offset);
+
+            let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"Hello, world!").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+
+            assert!(output.starts_with(stub));
+            let eocd = &output[output.len() - 22..];
+            let directory_offset = u32::from_le_bytes(eocd[16..20].try_into().unwrap());
+            let directory_size = u32::from_le_bytes(eocd[12..16].try_into().unwrap());
+            assert_eq!(
+                u64::from(directory_offset + directory_size),
+                (output.len() - 22) as u64 - (stub.len() as u64 - expected_offset)
+            );
+
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            let entry = archive.entries().next_entry().unwrap().unwrap();
+            assert_eq!(entry.local_header_offset(), stub.len() as u64);
+            let entry = archive.get_entry(entry.wayfinder()).unwrap();
+            assert_eq!(entry.data(), b"Hello, world!");
         }
 
-        Ok(())
+        // The prefix is written even without any entries
+        let mut output = Vec::new();
+        let archive = ZipArchiveWriter::builder()
+            .with_prefix(stub, true)
+            .build(&mut output);
+        archive.finish().unwrap();
+        assert!(output.starts_with(stub));
+        assert_eq!(output.len(), stub.len() + 22);
     }
-}
-
-/// Writes the ZIP64 End of Central Directory Record
-fn write_zip64_eocd<W>(
-    writer: &mut W,
-    total_entries: u64,
-    central_directory_size: u64,
-    central_directory_offset: u64,
-) -> Result<(), Error>
-where
-    W: Write,
-{
-    // ZIP64 End of Central Directory Record signature
-    writer.write_all(&END_OF_CENTRAL_DIR_SIGNATURE64.to_le_bytes())?;
 
-    // Size of ZIP64 end of central directory record (excluding signature and this field)
-    let record_size = (ZIP64_EOCD_SIZE - 12) as u64;
-    writer.write_all(&record_size.to_le_bytes())?;
+    #[test]
+    fn test_finish_with_summary() {
+        for force_zip64 in [false, true] {
+            let mut output = Vec::new();
+            let mut archive = ZipArchiveWriter::builder()
+                .force_zip64(force_zip64)
+                .build(&mut output);
+            archive.new_dir("dir/").create().unwrap();
+            for (name, data) in [("a.txt", &b"aaaa"[..]), ("b.txt", b"bb"), ("c.txt", b"c")] {
+                let mut writer = archive
+                    .new_file(name)
+                    .compression_method(CompressionMethod::Deflate)
+                    .start_raw(crc::crc32(data), data.len() as u64 * 10)
+                    .unwrap();
+                writer.write_all(data).unwrap();
+                writer.finish().unwrap();
+            }
+            archive.remove("b.txt");
+            let (_, summary) = archive.finish_with_summary().unwrap();
 
-    // Version made by
-    writer.write_all(&ZIP64_VERSION_NEEDED.to_le_bytes())?;
+            assert_eq!(summary.entries(), 3);
+            assert_eq!(summary.compressed_size(), 5);
+            assert_eq!(summary.uncompressed_size(), 50);
+            assert_eq!(summary.is_zip64(), force_zip64);
 
-    // Version needed to extract
-    writer.write_all(&ZIP64_VERSION_NEEDED.to_le_bytes())?;
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            assert_eq!(
+                summary.central_directory_offset(),
+                archive.directory_offset()
+            );
+            assert_eq!(archive.entries_hint(), 3);
+            let eocd_offset = if force_zip64 {
+                archive.eocd_offset() - (ZIP64_EOCD_SIZE + ZIP64_EOCD_LOCATOR_SIZE) as u64
+            } else {
+                archive.eocd_offset()
+            };
+            assert_eq!(
+                summary.central_directory_offset() + summary.central_directory_size(),
+                eocd_offset
+            );
+        }
+    }
 
-    // Number of this disk
-    writer.write_all(&0u32.to_le_bytes())?;
+    #[test]
+    fn test_recover_writer() {
+        let mut archive = ZipArchiveWriter::new(Cursor::new(Vec::new()));
+        archive.new_dir("dir/").create().unwrap();
+        assert_eq!(archive.get_ref().position(), archive.stream_offset());
+
+        let mut output = archive.finish().unwrap();
+        output.set_position(0);
+        let archive =
+            ZipArchive::from_seekable(output, &mut [0u8; RECOMMENDED_BUFFER_SIZE]).unwrap();
+        assert_eq!(archive.entries_hint(), 1);
+
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        archive.new_dir("dir/").create().unwrap();
+        let written = archive.stream_offset();
+        let output = archive.into_inner();
+        assert_eq!(output.len() as u64, written);
+    }
 
-    // Number of the disk with the start of the central directory
-    writer.write_all(&0u32.to_le_bytes())?;
+    #[test]
+    fn test_abort_entry() {
+        // Streaming outputs leave the partial data in place
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, _) = archive.new_file("partial.txt").start().unwrap();
+        entry.write_all(&[1u8; 100]).unwrap();
+        entry.abort().unwrap();
+        let (mut entry, config) = archive.new_file("full.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
 
-    // Total number of entries in the central directory on this disk
-    writer.write_all(&total_entries.to_le_bytes())?;
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        assert_eq!(archive.entries_hint(), 1);
+        let mut entries = archive.entries();
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.file_path().as_ref(), b"full.txt");
+        assert!(entry.local_header_offset() > 100);
 
-    // Total number of entries in the central directory
-    writer.write_all(&total_entries.to_le_bytes())?;
+        // Seekable outputs are rewound, with the leftovers zeroed out
+        for next in [false, true] {
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = ZipArchiveWriterBuilder::new().build_seekable(&mut output);
+            let (mut entry, _) = archive.new_file("partial.txt").start().unwrap();
+            entry.write_all(&[1u8; 1000]).unwrap();
+            entry.abort().unwrap();
+            assert_eq!(archive.stream_offset(), 0);
+            if next {
+                archive.new_dir("dir/").create().unwrap();
+            }
+            archive.finish().unwrap();
 
-    // Size of the central directory
-    writer.write_all(&central_directory_size.to_le_bytes())?;
+            let output = output.into_inner();
+            assert!(!output.windows(16).any(|x| x == [1u8; 16]));
+            let archive = ZipArchive::from_slice(&output).unwrap();
+            assert_eq!(archive.entries_hint(), u64::from(next));
+            let mut entries = archive.entries();
+            if next {
+                let entry = entries.next_entry().unwrap().unwrap();
+                assert_eq!(entry.file_path().as_ref(), b"dir/");
+                assert_eq!(entry.local_header_offset(), 0);
+            }
+            assert!(entries.next_entry().unwrap().is_none());
+        }
+    }
 
-    // Offset of start of central directory with respect to the starting disk number
-    writer.write_all(&central_directory_offset.to_le_bytes())?;
+    #[test]
+    fn test_unfinished_entry() {
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        let (mut entry, _) = archive.new_file("dropped.txt").start().unwrap();
+        entry.write_all(b"Hello").unwrap();
+        drop(entry);
+
+        let err = archive.new_dir("dir/").create().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+        let err = archive.new_file("next.txt").start().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+        let err = archive.finish().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+
+        // Finished and aborted entries leave the archive usable
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        let (entry, _) = archive.new_file("aborted.txt").start().unwrap();
+        entry.abort().unwrap();
+        let (entry, config) = archive.new_file("empty.txt").start().unwrap();
+        let (entry, descriptor) = config.wrap(entry).finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
+    }
 
-    Ok(())
-}
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "without calling finish")]
+    fn test_unfinished_archive() {
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        archive.new_dir("dir/").create().unwrap();
+    }
 
-/// Writes the ZIP64 End of Central Directory Locator
-fn write_zip64_eocd_locator<W>(writer: &mut W, zip64_eocd_offset: u64) -> Result<(), Error>
-where
-    W: Write,
-{
-    // ZIP64 End of Central Directory Locator signature
-    writer.write_all(&END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE.to_le_bytes())?;
+    #[test]
+    fn test_abandoned_archive() {
+        // Nothing to lose before the first entry is completed
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        let err = archive.new_dir("dir").create().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+        drop(archive);
+
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        archive.new_dir("dir/").create().unwrap();
+        assert!(!archive.into_inner().is_empty());
+    }
 
-    // Number of the disk with the start of the ZIP64 end of central directory
-    writer.write_all(&0u32.to_le_bytes())?;
+    #[test]
+    fn test_progress_updates() {
+        // A sender isn't `Sync` (before Rust 1.72), which the callback mustn't require
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        archive.on_progress(move |progress| sender.send(*progress).unwrap());
+
+        archive.new_dir("dir/").create().unwrap();
+        let (mut entry, config) = archive.new_file("dir/file.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello").unwrap();
+        writer.write_all(b", world!").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        let (entry, _) = archive.new_file("aborted.txt").start().unwrap();
+        entry.abort().unwrap();
+        archive.finish().unwrap();
 
-    // Relative offset of the ZIP64 end of central directory record
-    writer.write_all(&zip64_eocd_offset.to_le_bytes())?;
+        let updates = receiver.try_iter().collect::<Vec<_>>();
+        let summary = updates
+            .iter()
+            .map(|p| {
+                (
+                    p.entries_completed(),
+                    p.entry_consumed(),
+                    p.entry_produced(),
+                    p.entry_completed(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 0, 0, true),
+                (1, 5, 5, false),
+                (1, 13, 13, false),
+                (2, 13, 13, true),
+            ]
+        );
+        assert!(updates
+            .windows(2)
+            .all(|w| w[0].stream_offset() < w[1].stream_offset()));
+    }
 
-    // Total number of disks
-    writer.write_all(&1u32.to_le_bytes())?;
+    #[test]
+    fn test_entry_summary() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .with_prefix(b"prefix", true)
+            .build(&mut output);
+        archive.new_dir("dir/").create().unwrap();
 
-    Ok(())
-}
+        let mut summaries = Vec::new();
+        for (name, alignment) in [("dir/a.txt", 1), ("dir/b.bin", 4096)] {
+            let (mut entry, config) = archive.new_file(name).alignment(alignment).start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(name.as_bytes()).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            summaries.push(entry.finish_with_summary(descriptor).unwrap());
+        }
 
-#[derive(Debug, Clone)]
-struct ZipEntryOptions {
-    compression_method: CompressionMethod,
-    modification_time: Option<UtcDateTime>,
-    unix_permissions: Option<u32>,
-    extra_fields: ExtraFieldsContainer,
-}
+        let mut raw = archive
+            .new_file("dir/c.txt")
+            .predeclare(crc::crc32(b"raw"), 3, 3)
+            .start_raw(crc::crc32(b"raw"), 3)
+            .unwrap();
+        raw.write_all(b"raw").unwrap();
+        summaries.push(raw.finish_with_summary().unwrap());
+        archive.finish().unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::ZipArchive;
-    use std::io::Cursor;
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        entries.next_entry().unwrap().unwrap();
+        for summary in summaries {
+            let record = entries.next_entry().unwrap().unwrap();
+            assert_eq!(summary.local_header_offset(), record.local_header_offset());
+            assert_eq!(summary.compressed_size(), record.compressed_size_hint());
+            assert_eq!(summary.uncompressed_size(), record.uncompressed_size_hint());
+            assert_eq!(summary.crc32(), record.crc32());
+
+            let entry = archive.get_entry(record.wayfinder()).unwrap();
+            let range = summary.data_range();
+            assert_eq!(
+                &output[range.start as usize..range.end as usize],
+                entry.data()
+            );
+        }
+    }
 
     #[test]
-    fn test_name_lifetime_independence() {
-        let mut output = Cursor::new(Vec::new());
+    fn test_windows_separators() {
+        let mut output = Vec::new();
         let mut archive = ZipArchiveWriter::new(&mut output);
+        archive.new_dir(".\\dir\\").create().unwrap();
+        archive.new_dir("dir\\sub\\").create().unwrap();
+        let (mut entry, config) = archive.new_file(".\\dir\\sub\\file.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
 
-        // Test file builder with temporary name
-        {
-            let (mut entry, config) = {
-                let temp_name = format!("temp-{}.txt", 42);
-                archive.new_file(&temp_name).start().unwrap()
-            };
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let names = archive
+            .entries()
+            .map(|entry| entry.unwrap().file_path().as_ref().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                b"dir/".to_vec(),
+                b"dir/sub/".to_vec(),
+                b"dir/sub/file.txt".to_vec()
+            ]
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(ZipArchiveWriter::builder())]
+    #[case(ZipArchiveWriter::builder().force_zip64(true))]
+    #[case(ZipArchiveWriter::builder().with_zip64_threshold(10))]
+    #[case(ZipArchiveWriter::builder().with_unicode_path_field(true))]
+    #[case(ZipArchiveWriter::builder().with_prefix(b"#!/bin/sh\n", true))]
+    fn test_size_planner(#[case] builder: ZipArchiveWriterBuilder) {
+        let contents: [&[u8]; 3] = [b"", b"Hello, world!", &[b'a'; 100]];
+
+        let mut planner = builder.build(SizePlanner::new());
+        planner.new_dir("dir/").create().unwrap();
+        for (i, data) in contents.iter().enumerate() {
+            let len = data.len() as u64;
+            planner
+                .new_file(&format!("dir/f\u{e9}-{i}"))
+                .plan(len, len)
+                .unwrap();
+        }
+        planner
+            .new_file("declared.txt")
+            .predeclare(0, 5, 5)
+            .plan(5, 5)
+            .unwrap();
+        let (planner, planned) = planner.finish_with_summary().unwrap();
+
+        let mut output = Vec::new();
+        let mut archive = builder.build(&mut output);
+        archive.new_dir("dir/").create().unwrap();
+        for (i, data) in contents.iter().enumerate() {
+            let (mut entry, config) = archive
+                .new_file(&format!("dir/f\u{e9}-{i}"))
+                .start()
+                .unwrap();
             let mut writer = config.wrap(&mut entry);
-            writer.write_all(b"test").unwrap();
-            let (_, desc) = writer.finish().unwrap();
-            entry.finish(desc).unwrap();
+            writer.write_all(data).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
         }
+        let mut writer = archive
+            .new_file("declared.txt")
+            .predeclare(crate::crc32(b"12345"), 5, 5)
+            .start_raw(crate::crc32(b"12345"), 5)
+            .unwrap();
+        writer.write_all(b"12345").unwrap();
+        writer.finish().unwrap();
+        let (_, summary) = archive.finish_with_summary().unwrap();
 
-        archive.finish().unwrap();
+        assert_eq!(planner.size(), output.len() as u64);
+        assert_eq!(
+            planned.central_directory_size(),
+            summary.central_directory_size()
+        );
+        assert_eq!(planned.is_zip64(), summary.is_zip64());
     }
 
-    #[test]
-    fn test_builder_with_offset_and_capacity() {
+    #[rstest::rstest]
+    #[case(ZipArchiveWriter::builder())]
+    #[case(ZipArchiveWriter::builder().force_zip64(true))]
+    #[case(ZipArchiveWriter::builder().with_zip64_threshold(16))]
+    fn test_reserved_entries(#[case] builder: ZipArchiveWriterBuilder) {
         let mut output = Cursor::new(Vec::new());
+        let mut archive = builder.build_seekable(&mut output);
+        let filled = archive.new_file("filled.bin").reserve(32).unwrap();
+        let exact = archive.new_file("exact.bin").reserve(4).unwrap();
+        let aborted = archive.new_file("aborted.bin").reserve(8).unwrap();
+        let end = archive.stream_offset();
 
-        output.write_all(b"PREFIX DATA").unwrap();
-        let offset = output.position();
+        let (mut entry, config) = archive.new_file("after.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"after").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        assert!(archive.stream_offset() > end);
+        let end = archive.stream_offset();
 
-        let mut archive = ZipArchiveWriterBuilder::new()
-            .with_capacity(5)
-            .with_offset(offset)
-            .build(&mut output);
+        let (mut entry, config) = archive.fill(filled).unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello, world!").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        assert_eq!(entry.finish(descriptor).unwrap(), 13);
 
-        let (mut entry, config) = archive.new_file("test.txt").start().unwrap();
+        let (mut entry, config) = archive.fill(exact).unwrap();
         let mut writer = config.wrap(&mut entry);
-        writer.write_all(b"Hello World").unwrap();
-        let (_, desc) = writer.finish().unwrap();
-        entry.finish(desc).unwrap();
+        let err = writer.write_all(b"12345").unwrap_err();
+        assert_eq!(err.to_string(), "data exceeds the reserved size");
+        let (_, descriptor) = writer.into_parts();
+        entry.finish(descriptor).unwrap();
 
+        let (mut entry, _) = archive.fill(aborted).unwrap();
+        entry.write_all(b"partial").unwrap();
+        entry.abort().unwrap();
+        assert_eq!(archive.stream_offset(), end);
         archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = Vec::new();
+        for record in archive.entries() {
+            let record = record.unwrap();
+            let name = String::from_utf8(record.file_path().as_ref().to_vec()).unwrap();
+            let offset = record.local_header_offset() as usize;
+            let local_header = ZipLocalFileHeaderFixed::parse(&output[offset..]).unwrap();
+            assert_eq!(local_header.crc32, record.crc32());
+            let entry = archive.get_entry(record.wayfinder()).unwrap();
+            let mut contents = Vec::new();
+            entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents)
+                .unwrap();
+            entries.push((name, contents));
+        }
+
+        let expected: [(&str, &[u8]); 4] = [
+            ("filled.bin", b"Hello, world!"),
+            ("exact.bin", b"1234"),
+            ("aborted.bin", b""),
+            ("after.txt", b"after"),
+        ];
+        let expected = expected
+            .iter()
+            .map(|(name, data)| (name.to_string(), data.to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, expected);
     }
 
     #[test]
-    fn test_stream_offset_methods() {
-        let mut output = Cursor::new(Vec::new());
-        let mut archive = ZipArchiveWriter::new(&mut output);
+    fn test_fill_foreign_reservation() {
+        let mut other = ZipArchiveWriter::builder().build_seekable(Cursor::new(Vec::new()));
+        other.new_dir("dir/").create().unwrap();
+        let foreign = other.new_file("other.bin").reserve(8).unwrap();
+        let missing = other.new_file("missing.bin").reserve(8).unwrap();
 
-        // Test case 1: Get local header offset
-        let local_header_offset = archive.stream_offset();
-        let (mut file, config) = archive.new_file("test.txt").start().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        let mut archive = ZipArchiveWriter::builder().build_seekable(&mut output);
+        let _empty = archive.new_file("a.bin").reserve(16).unwrap();
+        archive.new_dir("b/").create().unwrap();
+        archive.new_dir("c/").create().unwrap();
+        for reservation in [foreign, missing] {
+            let err = archive.fill(reservation).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+        }
+        archive.finish().unwrap();
+        drop(other.into_inner());
+    }
 
-        // Test case 2: Get start of data offset
-        let data_start_offset = file.stream_offset();
+    #[test]
+    fn test_reserve_requires_seekable_output() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let err = archive.new_file("file.bin").reserve(16).unwrap_err();
+        assert!(err.to_string().contains("requires a seekable output"));
+        archive.finish().unwrap();
+    }
 
-        // Write some data
-        let mut writer = config.wrap(&mut file);
-        writer.write_all(b"Hello World").unwrap();
-        let (_, desc) = writer.finish().unwrap();
+    #[test]
+    fn test_sort_central_directory() {
+        let names = ["b.txt", "dir/z.txt", "a.txt", "dir/", "b.txt", "A.txt"];
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .sort_central_directory(true)
+            .build(&mut output);
+        for (i, name) in names.iter().enumerate() {
+            if name.ends_with('/') {
+                archive.new_dir(name).create().unwrap();
+            } else {
+                let (mut entry, config) = archive.new_file(name).start().unwrap();
+                let mut writer = config.wrap(&mut entry);
+                writer.write_all(&[b'0' + i as u8]).unwrap();
+                let (_, descriptor) = writer.finish().unwrap();
+                entry.finish(descriptor).unwrap();
+            }
+        }
+        assert!(archive.remove("dir/z.txt"));
+        archive.finish().unwrap();
 
-        // Test case 3: Get end of compressed data offset
-        let end_data_offset = file.stream_offset();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = Vec::new();
+        let mut offsets = Vec::new();
+        for record in archive.entries() {
+            let record = record.unwrap();
+            let name = String::from_utf8(record.file_path().as_ref().to_vec()).unwrap();
+            let entry = archive.get_entry(record.wayfinder()).unwrap();
+            let mut contents = Vec::new();
+            entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents)
+                .unwrap();
+            offsets.push(record.local_header_offset());
+            entries.push((name, String::from_utf8(contents).unwrap()));
+        }
 
-        let compressed_bytes = file.finish(desc).unwrap();
+        let expected = [
+            ("A.txt", "5"),
+            ("a.txt", "2"),
+            ("b.txt", "0"),
+            ("b.txt", "4"),
+            ("dir/", ""),
+        ];
+        let expected = expected
+            .iter()
+            .map(|(name, data)| (name.to_string(), data.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, expected);
+        assert!(offsets.windows(2).any(|pair| pair[0] > pair[1]));
+    }
 
-        // Test case 4: Get end of data descriptor offset (next file's local header offset)
-        let end_descriptor_offset = archive.stream_offset();
+    #[test]
+    fn test_create_parent_directories() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .create_parent_directories(true)
+            .build(&mut output);
+        archive.new_dir("a/").create().unwrap();
+        for name in ["a/b/c.txt", "a/b/d.txt", "./x/y/", "top.txt"] {
+            if name.ends_with('/') {
+                archive.new_dir(name).create().unwrap();
+            } else {
+                let (mut entry, config) = archive.new_file(name).start().unwrap();
+                let mut writer = config.wrap(&mut entry);
+                writer.write_all(name.as_bytes()).unwrap();
+                let (_, descriptor) = writer.finish().unwrap();
+                entry.finish(descriptor).unwrap();
+            }
+        }
+        let (mut entry, config) = archive.new_file_bytes(b"r/s/t.bin").start().unwrap();
+        let writer = config.wrap(&mut entry);
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+        archive.finish().unwrap();
 
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let names = archive
+            .entries()
+            .map(|record| {
+                let record = record.unwrap();
+                String::from_utf8(record.file_path().as_ref().to_vec()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            "a/",
+            "a/b/",
+            "a/b/c.txt",
+            "a/b/d.txt",
+            "x/",
+            "x/y/",
+            "top.txt",
+            "r/",
+            "r/s/",
+            "r/s/t.bin",
+        ];
+        assert_eq!(names, expected);
+
+        // A rejected duplicate doesn't leave its parents behind
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .create_parent_directories(true)
+            .duplicate_names(DuplicateNamePolicy::Error)
+            .build(&mut output);
+        archive.add_bytes("a/b.txt", b"b").unwrap();
+        assert!(archive.remove("a/"));
+        let err = archive.add_bytes("a/b.txt", b"b").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DuplicateName { .. }));
         archive.finish().unwrap();
 
-        // Verify the offsets make sense
-        assert_eq!(local_header_offset, 0);
-        assert!(data_start_offset > local_header_offset);
-        assert_eq!(
-            end_data_offset,
-            data_start_offset + b"Hello World".len() as u64
-        );
-        assert_eq!(end_descriptor_offset, end_data_offset + 16); // 16 bytes for data descriptor
-        assert_eq!(compressed_bytes, end_data_offset - data_start_offset);
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let names = archive
+            .entries()
+            .map(|record| {
+                let record = record.unwrap();
+                String::from_utf8(record.file_path().as_ref().to_vec()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a/b.txt"]);
     }
 
     #[test]
-    fn test_crc32_options() {
-        use std::io::Write;
-
-        let data = b"Hello, world!";
-        let correct_crc = crate::crc32(data);
-        let incorrect_crc = 0x12345678u32;
-
-        // Test with default CRC calculation
-        {
-            let mut output = Cursor::new(Vec::new());
-            let mut archive = ZipArchiveWriter::new(&mut output);
-            let (mut entry, config) = archive.new_file("normal.txt").start().unwrap();
+    fn test_duplicate_name_policy() {
+        fn write(archive: &mut ZipArchiveWriter<&mut Vec<u8>>, name: &str, data: &[u8]) {
+            let (mut entry, config) = archive.new_file(name).start().unwrap();
             let mut writer = config.wrap(&mut entry);
             writer.write_all(data).unwrap();
             let (_, descriptor) = writer.finish().unwrap();
             entry.finish(descriptor).unwrap();
-            archive.finish().unwrap();
         }
 
-        // Test with correct custom CRC - should succeed
-        {
-            let mut output = Cursor::new(Vec::new());
-            let mut archive = ZipArchiveWriter::new(&mut output);
-            let (mut entry, config) = archive
-                .new_file("correct.txt")
-                .crc32(Crc32Option::Custom(correct_crc))
-                .start()
-                .unwrap();
-            let mut writer = config.wrap(&mut entry);
-            writer.write_all(data).unwrap();
-            let (_, descriptor) = writer.finish().unwrap();
-            entry.finish(descriptor).unwrap();
-            archive.finish().unwrap();
-
-            // Verify the archive can be read
-            let output = output.into_inner();
-            let archive = ZipArchive::from_slice(&output).unwrap();
-            let mut entries = archive.entries();
-            let entry = entries.next_entry().unwrap().unwrap();
-            let wayfinder = entry.wayfinder();
-            let entry = archive.get_entry(wayfinder).unwrap();
-            let mut verifier = entry.verifying_reader(entry.data());
-            let mut actual = Vec::new();
-            std::io::copy(&mut verifier, &mut actual).unwrap();
-            assert_eq!(&actual, data);
+        fn entries(output: &[u8]) -> Vec<(String, Vec<u8>)> {
+            let archive = ZipArchive::from_slice(output).unwrap();
+            archive
+                .entries()
+                .map(|record| {
+                    let record = record.unwrap();
+                    let name = String::from_utf8(record.file_path().as_ref().to_vec()).unwrap();
+                    let entry = archive.get_entry(record.wayfinder()).unwrap();
+                    (name, entry.data().to_vec())
+                })
+                .collect()
         }
 
-        // Test with incorrect custom CRC - verification should fail
-        {
-            let mut output = Cursor::new(Vec::new());
-            let mut archive = ZipArchiveWriter::new(&mut output);
-            let (mut entry, config) = archive
-                .new_file("incorrect.txt")
-                .crc32(Crc32Option::Custom(incorrect_crc))
-                .start()
-                .unwrap();
-            let mut writer = config.wrap(&mut entry);
-            writer.write_all(data).unwrap();
-            let (_, descriptor) = writer.finish().unwrap();
-            entry.finish(descriptor).unwrap();
-            archive.finish().unwrap();
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        write(&mut archive, "a.txt", b"1");
+        write(&mut archive, "a.txt", b"2");
+        archive.finish().unwrap();
+        assert_eq!(entries(&output).len(), 2);
 
-            // Verify the archive fails verification
-            let output = output.into_inner();
-            let archive = ZipArchive::from_slice(&output).unwrap();
-            let mut entries = archive.entries();
-            let entry = entries.next_entry().unwrap().unwrap();
-            let wayfinder = entry.wayfinder();
-            let entry = archive.get_entry(wayfinder).unwrap();
-            let mut verifier = entry.verifying_reader(entry.data());
-            let mut actual = Vec::new();
-            let result = std::io::copy(&mut verifier, &mut actual);
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .duplicate_names(DuplicateNamePolicy::Error)
+            .build(&mut output);
+        write(&mut archive, "a.txt", b"1");
+        let err = archive.new_file("./a.txt").start().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DuplicateName { name } if name == "a.txt"));
+        assert!(archive.remove("a.txt"));
+        write(&mut archive, "a.txt", b"2");
+        archive.finish().unwrap();
+        assert_eq!(entries(&output), [(String::from("a.txt"), b"2".to_vec())]);
 
-            // Verification should fail with InvalidChecksum error
-            assert!(result.is_err());
-            let err = result.unwrap_err();
-            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
-            let source = err.into_inner().unwrap();
-            let zip_error = source.downcast::<crate::Error>().unwrap();
-            match zip_error.kind() {
-                ErrorKind::InvalidChecksum { expected, actual } => {
-                    assert_eq!(*expected, incorrect_crc);
-                    assert_eq!(*actual, correct_crc);
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .duplicate_names(DuplicateNamePolicy::Overwrite)
+            .build(&mut output);
+        write(&mut archive, "a.txt", b"1");
+        write(&mut archive, "b.txt", b"2");
+        let (entry, _) = archive.new_file("a.txt").start().unwrap();
+        entry.abort().unwrap();
+        write(&mut archive, "b.txt", b"3");
+        archive.finish().unwrap();
+        let expected = [
+            (String::from("a.txt"), b"1".to_vec()),
+            (String::from("b.txt"), b"3".to_vec()),
+        ];
+        assert_eq!(entries(&output), expected);
+    }
+
+    #[rstest::rstest]
+    #[case("/etc/passwd", Some("is an absolute path"))]
+    #[case("\\windows\\system32", Some("is an absolute path"))]
+    #[case("C:/file.txt", Some("contains a drive or device letter"))]
+    #[case("dir\\d:file.txt", Some("contains a drive or device letter"))]
+    #[case("../file.txt", Some("contains a parent directory component"))]
+    #[case("dir\\..\\..\\file.txt", Some("contains a parent directory component"))]
+    #[case("file\0.txt", Some("contains a NUL byte"))]
+    #[case("dir/file.txt", None)]
+    #[case("dir/..file.txt", None)]
+    #[case("./file.txt", None)]
+    #[case("logs/12:30.txt", None)]
+    fn test_validate_names(#[case] name: &str, #[case] expected: Option<&str>) {
+        let mut archive = ZipArchiveWriter::builder()
+            .validate_names(true)
+            .build(Vec::new());
+        let result = archive.new_file(name).start().map(|_| ());
+
+        match (result, expected) {
+            (Ok(_), None) => {}
+            (Err(err), Some(expected)) => match err.kind() {
+                ErrorKind::InvalidPath { path, msg } => {
+                    assert_eq!(path, name);
+                    assert_eq!(msg, expected);
                 }
-                _ => panic!("Expected InvalidChecksum error, got {:?}", zip_error.kind()),
-            }
+                kind => panic!("unexpected error: {kind:?}"),
+            },
+            (result, _) => panic!("unexpected result for {name:?}: {result:?}"),
         }
 
-        // Test with skipped CRC - should have CRC of 0, and should validate fine
-        {
-            let mut output = Cursor::new(Vec::new());
-            let mut archive = ZipArchiveWriter::new(&mut output);
-            let (mut entry, config) = archive
-                .new_file("skipped.txt")
-                .crc32(Crc32Option::Skip)
-                .start()
-                .unwrap();
-            let mut writer = config.wrap(&mut entry);
-            writer.write_all(data).unwrap();
-            let (_, descriptor) = writer.finish().unwrap();
-            entry.finish(descriptor).unwrap();
-            archive.finish().unwrap();
-
-            // Verify the archive can be read
-            let output = output.into_inner();
-            let archive = ZipArchive::from_slice(&output).unwrap();
-            let mut entries = archive.entries();
-            let entry = entries.next_entry().unwrap().unwrap();
-            let wayfinder = entry.wayfinder();
-            let entry = archive.get_entry(wayfinder).unwrap();
-            let mut verifier = entry.verifying_reader(entry.data());
-            let mut actual = Vec::new();
-            std::io::copy(&mut verifier, &mut actual).unwrap();
-            assert_eq!(&actual, data);
-        }
+        // Without validation, the name is normalized instead
+        let mut archive = ZipArchiveWriter::new(Vec::new());
+        archive.new_file(name).start().unwrap();
     }
 
     #[test]
-    fn test_tuple_api() {
-        use std::io::Write;
+    fn test_predeclared_mismatch() {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("short.txt")
+            .predeclare(crc::crc32(b"abc"), 4, 4)
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"abc").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        let err = entry.finish(descriptor).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::InvalidSize {
+                expected: 4,
+                actual: 3
+            }
+        ));
 
-        let data = b"Hello, world!";
-        let custom_crc = 0x12345678u32;
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("crc.txt")
+            .predeclare(0, 3, 3)
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"abc").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        let err = entry.finish(descriptor).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidChecksum { .. }));
 
-        // Test the new tuple-based API with custom CRC
+        // The mismatched header and data are already out, so the archive
+        // can't be continued
+        assert!(archive.add_bytes("next.txt", b"next").is_err());
+        assert!(archive.finish().is_err());
+    }
+
+    #[test]
+    fn test_entry_comments() {
         let mut output = Cursor::new(Vec::new());
         let mut archive = ZipArchiveWriter::new(&mut output);
+        archive
+            .new_dir("docs/")
+            .comment("directory comment")
+            .create()
+            .unwrap();
+
+        let long_comment = "a".repeat(65536);
+        assert!(archive
+            .new_file("too-long.txt")
+            .comment(&long_comment)
+            .start()
+            .is_err());
+
         let (mut entry, config) = archive
-            .new_file("test.txt")
-            .crc32(Crc32Option::Custom(custom_crc))
+            .new_file("docs/readme.txt")
+            .comment("built from 1a2b3c")
             .start()
             .unwrap();
-
-        // Using the new unified API - the CRC option is automatically configured
         let mut writer = config.wrap(&mut entry);
-        writer.write_all(data).unwrap();
+        writer.write_all(b"hello").unwrap();
         let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
 
-        // Verify the CRC was correctly applied
-        assert_eq!(descriptor.crc, custom_crc);
-
+        let (mut entry, config) = archive.new_file("plain.txt").start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"plain").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
         entry.finish(descriptor).unwrap();
         archive.finish().unwrap();
+
+        let output = output.into_inner();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let mut entries = archive.entries();
+        let mut actual = Vec::new();
+        while let Some(entry) = entries.next_entry().unwrap() {
+            actual.push((
+                entry.file_path().as_ref().to_vec(),
+                entry.comment().as_bytes().to_vec(),
+            ));
+        }
+        assert_eq!(
+            actual,
+            vec![
+                (b"docs/".to_vec(), b"directory comment".to_vec()),
+                (b"docs/readme.txt".to_vec(), b"built from 1a2b3c".to_vec()),
+                (b"plain.txt".to_vec(), Vec::new()),
+            ]
+        );
     }
 
     #[test]
@@ -1450,4 +9073,90 @@ mod tests {
         std::io::copy(&mut verifier, &mut actual).unwrap();
         assert_eq!(&actual, data);
     }
+
+    #[test]
+    fn test_flush_policy() {
+        /// Records how many bytes were written by each flush.
+        #[derive(Default)]
+        struct Flushes {
+            written: usize,
+            flushes: Vec<usize>,
+        }
+
+        impl Write for Flushes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.written += buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.flushes.push(self.written);
+                Ok(())
+            }
+        }
+
+        let write = |policy: FlushPolicy| {
+            let mut archive = ZipArchiveWriter::builder()
+                .with_flush_policy(policy)
+                .build(Flushes::default());
+            let (mut entry, config) = archive.new_file("a.txt").start().unwrap();
+            let header_end = entry.inner.writer.writer.written;
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"Hello").unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.new_dir("dir/").create().unwrap();
+            (header_end, archive.finish().unwrap())
+        };
+
+        let (header_end, output) = write(FlushPolicy::Finish);
+        assert_eq!(output.flushes, [output.written]);
+
+        let (_, output) = write(FlushPolicy::Entry);
+        let [data_end, first_end, second_end, end] = output.flushes[..] else {
+            panic!("unexpected flushes: {:?}", output.flushes);
+        };
+        assert_eq!(data_end, header_end + 5);
+        assert!(first_end > data_end);
+        assert_eq!(end, output.written);
+
+        let (_, output) = write(FlushPolicy::Header);
+        assert_eq!(
+            output.flushes,
+            [header_end, data_end, first_end, second_end, second_end, end]
+        );
+    }
+
+    #[test]
+    fn test_write_buffer() {
+        let write = |buffer_size: usize, seekable: bool| {
+            let builder = ZipArchiveWriter::builder().with_write_buffer_size(buffer_size);
+            let mut output = Cursor::new(Vec::new());
+            let mut archive = if seekable {
+                builder.build_seekable(&mut output)
+            } else {
+                builder.build(&mut output)
+            };
+            archive.new_dir("dir/").create().unwrap();
+            let buffered = archive.buffered_len();
+            assert_eq!(archive.get_ref().get_ref().len() + buffered, 34);
+
+            let (mut entry, config) = archive.new_file("dir/a.txt").start().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(b"Hello").unwrap();
+            writer.write_all(&[b'!'; 100]).unwrap();
+            let (_, descriptor) = writer.finish().unwrap();
+            entry.finish(descriptor).unwrap();
+            archive.finish().unwrap();
+            (buffered, output.into_inner())
+        };
+
+        for seekable in [false, true] {
+            let (buffered, expected) = write(0, seekable);
+            assert_eq!(buffered, 0);
+            let (buffered, actual) = write(64, seekable);
+            assert_eq!(buffered, 34);
+            assert_eq!(actual, expected);
+        }
+    }
 }
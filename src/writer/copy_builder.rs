@@ -0,0 +1,171 @@
+#[cfg(feature = "_codec")]
+use super::FLAG_ENCRYPTED;
+use super::{validate_name, ZipEntryOptions, FLAG_UTF8_ENCODING};
+use crate::{
+    errors::ErrorKind, extra_fields::ExtraFieldId, path::ZipFilePath, CompressionMethod, Error,
+    Header, ZipArchiveWriter, ZipFileHeaderRecord,
+};
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// A builder for copying an entry from another archive, created by
+/// [`ZipArchiveWriter::copy_entry_with`].
+pub struct ZipCopyBuilder<'a, W> {
+    pub(super) archive: &'a mut ZipArchiveWriter<W>,
+    pub(super) record: &'a ZipFileHeaderRecord<'a>,
+    pub(super) name: Option<&'a str>,
+    pub(super) map_extra_fields: Option<Box<ExtraFieldMap<'a>>>,
+    #[cfg(feature = "_codec")]
+    pub(super) compression_method: Option<CompressionMethod>,
+    #[cfg(feature = "zstd")]
+    pub(super) zstd_dictionary: Option<&'a [u8]>,
+}
+
+type ExtraFieldMap<'a> = dyn FnMut(ExtraFieldId, &[u8]) -> Option<Vec<u8>> + 'a;
+
+impl<W: std::fmt::Debug> std::fmt::Debug for ZipCopyBuilder<'_, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZipCopyBuilder")
+            .field("archive", &self.archive)
+            .field("record", &self.record)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, W> ZipCopyBuilder<'a, W>
+where
+    W: Write,
+{
+    /// Sets the name the entry is copied under.
+    ///
+    /// The name is normalized and encoded the same way as the names given
+    /// to [`ZipArchiveWriter::new_file`]. The source's Info-ZIP Unicode Path
+    /// extra field no longer applies, so it is dropped.
+    #[must_use]
+    #[inline]
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Rewrites the entry's extra fields.
+    ///
+    /// The closure is called with each extra field of the source, other
+    /// than ZIP64, which is regenerated, and returns the data to write in its
+    /// place, or `None` to drop it.
+    #[must_use]
+    #[inline]
+    pub fn map_extra_fields(
+        mut self,
+        map: impl FnMut(ExtraFieldId, &[u8]) -> Option<Vec<u8>> + 'a,
+    ) -> Self {
+        self.map_extra_fields = Some(Box::new(map));
+        self
+    }
+
+    /// Recompresses the entry with the built-in codecs when its compression
+    /// method differs from the source's.
+    ///
+    /// The data is decompressed and compressed again as it's streamed, and
+    /// checked against the CRC32 and size of the source. Directories and
+    /// encrypted entries are copied as is. Recompressing from or to a method
+    /// without a built-in codec returns an error.
+    #[cfg(feature = "_codec")]
+    #[must_use]
+    #[inline]
+    pub fn compression_method(mut self, compression_method: CompressionMethod) -> Self {
+        self.compression_method = Some(compression_method);
+        self
+    }
+
+    /// Sets the dictionary that the source's zstd entries were compressed
+    /// against, which they are decompressed with when
+    /// [recompressed](ZipCopyBuilder::compression_method).
+    ///
+    /// See [`ZipArchive::zstd_dictionary`](crate::ZipArchive::zstd_dictionary) for reading it from the source.
+    #[cfg(feature = "zstd")]
+    #[must_use]
+    #[inline]
+    pub fn zstd_dictionary(mut self, dictionary: &'a [u8]) -> Self {
+        self.zstd_dictionary = Some(dictionary);
+        self
+    }
+
+    /// Copies the entry, with `compressed` supplying its compressed data,
+    /// and returns the number of compressed bytes written.
+    ///
+    /// See [`ZipArchiveWriter::copy_entry`] for details.
+    pub fn copy<R>(self, compressed: R) -> Result<u64, Error>
+    where
+        R: io::Read,
+    {
+        let archive = self.archive;
+        let record = self.record;
+        let mut header = archive.begin_copy(record)?;
+
+        // Scratch options for the extra fields and the name's encoding
+        let mut options = ZipEntryOptions::new(CompressionMethod::Store);
+        let renamed = self.name.is_some();
+        let mut map_extra_fields = self.map_extra_fields;
+        if renamed || map_extra_fields.is_some() {
+            for (id, data) in record.extra_fields() {
+                if id == ExtraFieldId::ZIP64 || renamed && id == ExtraFieldId::INFO_ZIP_UNICODE_PATH
+                {
+                    continue;
+                }
+
+                let data = match map_extra_fields.as_mut() {
+                    Some(map) => match map(id, data) {
+                        Some(data) => Cow::Owned(data),
+                        None => continue,
+                    },
+                    None => Cow::Borrowed(data),
+                };
+                options
+                    .extra_fields
+                    .add_field(id, &data, Header::default())?;
+            }
+        }
+
+        let file_path;
+        let name = match self.name {
+            Some(name) => {
+                if archive.options.validate_names {
+                    validate_name(name.as_bytes())?;
+                }
+
+                file_path = ZipFilePath::from_str(name);
+                let (name, flags) = archive.encode_name(file_path.as_ref(), &mut options)?;
+                header.flags = (header.flags & !FLAG_UTF8_ENCODING) | flags;
+                name
+            }
+            None => Cow::Borrowed(record.file_path().as_bytes()),
+        };
+
+        if name.len() > u16::MAX as usize {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "file name too long".to_string(),
+            }));
+        }
+
+        header.name_len = name.len() as u16;
+        if renamed || map_extra_fields.is_some() {
+            header.extra_fields = options.extra_fields;
+        }
+
+        #[cfg(feature = "_codec")]
+        if let Some(method) = self.compression_method {
+            let encrypted = header.flags & FLAG_ENCRYPTED != 0;
+            if method != record.compression_method() && !record.is_dir() && !encrypted {
+                #[cfg(feature = "zstd")]
+                let dictionary = self.zstd_dictionary;
+                #[cfg(not(feature = "zstd"))]
+                let dictionary = None;
+                return archive.recompress_data(&name, header, compressed, method, dictionary);
+            }
+        }
+
+        archive.copy_data(&name, header, compressed)
+    }
+}
@@ -0,0 +1,40 @@
+use super::{setters::entry_option_setters, EntryName, ZipEntryOptions};
+use crate::{Error, ZipArchiveWriter};
+use std::io::Write;
+
+/// A builder for creating a new directory entry in a ZIP archive.
+#[derive(Debug)]
+pub struct ZipDirBuilder<'a, W> {
+    pub(super) archive: &'a mut ZipArchiveWriter<W>,
+    pub(super) name: EntryName<'a>,
+    pub(super) options: ZipEntryOptions,
+}
+
+impl<W> ZipDirBuilder<'_, W>
+where
+    W: Write,
+{
+    entry_option_setters!(
+        "directory";
+        last_modified,
+        metadata,
+        last_accessed,
+        created,
+        ntfs_timestamps,
+        ntfs_times,
+        unix_permissions,
+        unix_owner,
+        host_system,
+        disk_number_start,
+        internal_attributes,
+        dos_attributes,
+        external_attributes,
+        comment,
+        extra_field,
+    );
+
+    /// Creates the directory entry.
+    pub fn create(self) -> Result<(), Error> {
+        self.archive.new_dir_with_options(self.name, self.options)
+    }
+}
@@ -0,0 +1,1045 @@
+use super::{
+    setters::entry_option_setters, DeclaredSizes, EntryName, ZipEntryOptions,
+    FLAG_COMPRESSION_OPTIONS,
+};
+#[cfg(feature = "_codec")]
+use super::{BufferedFileWriter, EntryQueue, TrialFileWriter, ZipFileWriterInner};
+#[cfg(feature = "deflate")]
+use super::{ParallelDeflateWriter, PARALLEL_DEFLATE_BLOCK_SIZE};
+#[cfg(any(feature = "aes", feature = "zipcrypto"))]
+use crate::encryption::{Encryption, Password};
+#[cfg(feature = "_codec")]
+use crate::{
+    codec::Encoder,
+    parallel::{CompressorPool, Job},
+    ZipDataWriter, ZipFileWriter,
+};
+use crate::{
+    errors::ErrorKind, CompressionMethod, Crc32Option, DataDescriptorOutput, Error, SizePlanner,
+    ZipArchiveWriter, ZipDataWriterConfig, ZipEntrySummary, ZipEntryWriter, ZipRawEntryWriter,
+    ZipReservation, ZipStoredWriter,
+};
+#[cfg(feature = "_codec")]
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// A builder for creating a new file entry in a ZIP archive.
+#[derive(Debug)]
+pub struct ZipFileBuilder<'archive, 'name, W> {
+    pub(super) archive: &'archive mut ZipArchiveWriter<W>,
+    pub(super) name: EntryName<'name>,
+    pub(super) options: ZipEntryOptions,
+    pub(super) compression_level: Option<i32>,
+    pub(super) crc32_option: Crc32Option,
+    pub(super) store_if_larger: bool,
+    pub(super) best_method: Option<usize>,
+}
+
+impl<'archive, W> ZipFileBuilder<'archive, '_, W>
+where
+    W: Write,
+{
+    /// Sets the compression method for the file entry.
+    #[must_use]
+    #[inline]
+    pub fn compression_method(mut self, compression_method: CompressionMethod) -> Self {
+        self.options.compression_method = compression_method;
+        self
+    }
+
+    /// Sets the compression level for the file entry.
+    ///
+    /// Rawzip does not interpret the level itself. It is handed to the
+    /// built-in codecs when the entry is started with `start_compressed`, and
+    /// custom codecs can retrieve it from
+    /// [`ZipDataWriterConfig::compression_level`].
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt")
+    ///     .compression_method(rawzip::CompressionMethod::Deflate)
+    ///     .compression_level(9)
+    ///     .start()?;
+    /// let level = config.compression_level().unwrap_or(6);
+    /// let encoder = flate2::write::DeflateEncoder::new(&mut entry, flate2::Compression::new(level as u32));
+    /// let mut writer = config.wrap(encoder);
+    /// writer.write_all(b"Hello")?;
+    /// let (encoder, output) = writer.finish()?;
+    /// encoder.finish()?;
+    /// entry.finish(output)?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn compression_level(mut self, level: i32) -> Self {
+        self.compression_level = Some(level);
+        self
+    }
+
+    entry_option_setters!(
+        "file";
+        last_modified,
+        metadata,
+        last_accessed,
+        created,
+        ntfs_timestamps,
+        ntfs_times,
+        unix_permissions,
+        unix_owner,
+        host_system,
+        disk_number_start,
+        internal_attributes,
+        dos_attributes,
+        external_attributes,
+        comment,
+        extra_field,
+    );
+
+    /// Sets whether the language encoding (EFS) flag is set, which marks the
+    /// name and comment as UTF-8.
+    ///
+    /// By default, the flag is set for names given to
+    /// [`new_file`](ZipArchiveWriter::new_file) that aren't plain ASCII,
+    /// and never for names given to
+    /// [`new_file_bytes`](ZipArchiveWriter::new_file_bytes). Clearing the
+    /// flag writes a UTF-8 name the way older tools did, leaving readers to
+    /// guess the encoding. Setting it forgoes the
+    /// [Unicode path extra field](crate::ZipArchiveWriterBuilder::with_unicode_path_field).
+    #[must_use]
+    #[inline]
+    pub fn utf8_flag(mut self, enabled: bool) -> Self {
+        self.options.utf8_flag = Some(enabled);
+        self
+    }
+
+    /// Aligns the start of the file data to a multiple of `alignment` bytes.
+    ///
+    /// The local file header's extra field is padded so that the data begins
+    /// on the requested boundary, as Android requires of stored entries in
+    /// APKs: 4 bytes for most entries and 4096 bytes for native libraries
+    /// that are loaded directly from the APK. Alignment is relative to the
+    /// start of the output, so it accounts for any
+    /// [offset](crate::ZipArchiveWriterBuilder::with_offset). An alignment of 0 or 1
+    /// disables padding.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive
+    ///     .new_file("lib/arm64-v8a/libnative.so")
+    ///     .alignment(4096)
+    ///     .start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"\x7fELF")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// let data_start = archive.get_entry(entry.wayfinder())?.compressed_data_range().0;
+    /// assert_eq!(data_start % 4096, 0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn alignment(mut self, alignment: u16) -> Self {
+        self.options.alignment = alignment;
+        self
+    }
+
+    /// Declares the CRC32 and sizes of the entry before its data is written.
+    ///
+    /// Writing a complete local file header means that no data descriptor
+    /// follows the entry's data, even when the output isn't seekable. This
+    /// suits content addressed blobs and other data whose digest and size are
+    /// already on hand. For compressed entries, the compressed size must be
+    /// known as well, as is the case for data written with
+    /// [`start_raw`](ZipFileBuilder::start_raw).
+    ///
+    /// The values are checked against the data written when the entry is
+    /// finished, so a disagreeing [`crc32`](ZipFileBuilder::crc32) option
+    /// results in an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let mut output = Vec::new();
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let data = b"Hello, world!";
+    /// let (mut entry, config) = archive
+    ///     .new_file("blob.txt")
+    ///     .predeclare(rawzip::crc32(data), data.len() as u64, data.len() as u64)
+    ///     .start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(data)?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn predeclare(mut self, crc32: u32, compressed_size: u64, uncompressed_size: u64) -> Self {
+        self.options.declared = Some(DeclaredSizes {
+            crc32,
+            compressed_size,
+            uncompressed_size,
+        });
+        self
+    }
+
+    /// Sets the CRC32 calculation option for the file entry.
+    ///
+    /// By default, CRC32 is calculated automatically from the data. Use this
+    /// method to:
+    ///
+    /// - Skip CRC32 calculation entirely (for performance or when verification
+    ///   isn't desired)
+    /// - Provide a pre-calculated CRC32 value
+    #[must_use]
+    #[inline]
+    pub fn crc32(mut self, crc32_option: Crc32Option) -> Self {
+        self.crc32_option = crc32_option;
+        self
+    }
+
+    /// Stores the entry uncompressed when compression would make it larger.
+    ///
+    /// Only applies to entries started with the built-in codecs (eg:
+    /// `start_compressed`). Since the decision can only be made once all data
+    /// has been seen, the entry is buffered in memory and written to the
+    /// archive on `finish`.
+    #[must_use]
+    #[inline]
+    pub fn store_if_larger(mut self, store_if_larger: bool) -> Self {
+        self.store_if_larger = store_if_larger;
+        self
+    }
+
+    /// Writes the entry with whichever compression method makes it the
+    /// smallest, for entries of up to `max_size` bytes.
+    ///
+    /// The candidates are storing the data, deflate and zstd when their
+    /// features are enabled, and the configured
+    /// [`compression_method`](ZipFileBuilder::compression_method), which is
+    /// the only one given the
+    /// [`compression_level`](ZipFileBuilder::compression_level). This pays
+    /// off for archives that mix text with already compressed media, at the
+    /// cost of compressing each entry several times.
+    ///
+    /// Only applies to entries started with the built-in codecs (eg:
+    /// `start_compressed`). The entry is buffered in memory until it's
+    /// finished, unless its data outgrows `max_size`, in which case it's
+    /// written with the configured method as usual and
+    /// [`store_if_larger`](ZipFileBuilder::store_if_larger) is disregarded.
+    #[must_use]
+    #[inline]
+    pub fn best_method(mut self, max_size: usize) -> Self {
+        self.best_method = Some(max_size);
+        self
+    }
+
+    /// Encrypts the entry with AES-256 using the password, in the AE-2
+    /// format that WinZip and 7-Zip read.
+    ///
+    /// The data is encrypted after it's compressed, so any compression
+    /// method works, whether the data comes from a built-in codec or is
+    /// compressed by the caller. The headers list the AES method (99), with
+    /// the actual method recorded in the AES extra field, and the CRC32 is
+    /// left out, as an authentication code follows the data instead. The
+    /// entry's compressed size includes the 28 bytes that encryption adds.
+    ///
+    /// Encrypted entries can't be [predeclared](ZipFileBuilder::predeclare)
+    /// or [reserved](ZipFileBuilder::reserve). Encryption is chosen per
+    /// entry, so encrypted entries can sit alongside plaintext ones, like a
+    /// manifest that ingestion systems read before asking for the password.
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let mut output = Vec::new();
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("secret.txt")
+    ///     .aes_password("hunter2")
+    ///     .start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.compression_method(), rawzip::CompressionMethod::Aes);
+    /// assert_eq!(entry.compressed_size_hint(), 5 + 28);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "aes")]
+    #[must_use]
+    #[inline]
+    pub fn aes_password(mut self, password: impl AsRef<[u8]>) -> Self {
+        self.options.encryption = Some(Encryption::Aes(Password::new(password.as_ref())));
+        self
+    }
+
+    /// Encrypts the entry with the traditional PKWARE encryption, also known
+    /// as ZipCrypto, which is **weak** and offers little protection: the
+    /// password can be recovered from a few bytes of known content, such as
+    /// a file's header. Prefer `aes_password` unless the archive is bound for
+    /// a system that only understands the traditional encryption.
+    ///
+    /// The data is encrypted after it's compressed, so any compression
+    /// method works. The entry's compressed size includes the 12 byte
+    /// encryption header, and the entry is always followed by a data
+    /// descriptor, which lets the header be checked against the last
+    /// modified time instead of the CRC32 that isn't known up front.
+    ///
+    /// Encrypted entries can't be [predeclared](ZipFileBuilder::predeclare)
+    /// or [reserved](ZipFileBuilder::reserve).
+    ///
+    /// ```rust
+    /// # use std::io::Write;
+    /// # let mut output = Vec::new();
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("import.csv")
+    ///     .weak_zipcrypto_password("erp")
+    ///     .start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"id,amount")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// let entry = archive.entries().next_entry()?.unwrap();
+    /// assert_eq!(entry.compressed_size_hint(), 9 + 12);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "zipcrypto")]
+    #[must_use]
+    #[inline]
+    pub fn weak_zipcrypto_password(mut self, password: impl AsRef<[u8]>) -> Self {
+        let password = Password::new(password.as_ref());
+        self.options.encryption = Some(Encryption::ZipCrypto(password));
+        self
+    }
+
+    /// Overrides the general purpose bit flags (4.4.4) selected by `mask`
+    /// with those in `flags`, after the writer has chosen its own.
+    ///
+    /// This is meant for testing how readers cope with unusual flags, such
+    /// as:
+    ///
+    /// - Bit 3 (`0x0008`): the CRC32 and sizes follow the data in a data
+    ///   descriptor. The writer follows the bit, so setting it adds a data
+    ///   descriptor, while clearing it on a streamed entry leaves the local
+    ///   file header without the CRC32 and sizes.
+    /// - Bit 11 (`0x0800`): the name and comment are UTF-8. See
+    ///   [`utf8_flag`](ZipFileBuilder::utf8_flag) for a safer alternative.
+    /// - Bits 1 and 2 (`0x0006`): the deflate compression option, from normal
+    ///   (`0x0000`) to super fast (`0x0006`), which is informational.
+    ///
+    /// Unless the entry is encrypted with `aes_password` or
+    /// `weak_zipcrypto_password`, setting bit 0 yields an entry that readers
+    /// will fail to decrypt.
+    ///
+    /// ```rust
+    /// # let mut output = Vec::new();
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// // Mark the entry as deflated with the maximum compression option
+    /// let (mut entry, config) = archive.new_file("file.txt")
+    ///     .general_purpose_flags(0x0006, 0x0002)
+    ///     .start()?;
+    /// # let (_, descriptor) = config.wrap(&mut entry).finish()?;
+    /// # entry.finish(descriptor)?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    #[inline]
+    pub fn general_purpose_flags(mut self, mask: u16, flags: u16) -> Self {
+        let (current_mask, current_flags) = self.options.flags_override;
+        self.options.flags_override = (
+            current_mask | mask,
+            (current_flags & !mask) | (flags & mask),
+        );
+        self
+    }
+
+    /// Writes `version` as the entry's "version needed to extract", instead
+    /// of the archive's
+    /// [override](crate::ZipArchiveWriterBuilder::with_version_needed) or the
+    /// version derived from the features the entry uses.
+    #[must_use]
+    #[inline]
+    pub fn version_needed(mut self, version: u16) -> Self {
+        self.options.version_needed = Some(version);
+        self
+    }
+
+    /// Marks the deflate level in general purpose bits 1 and 2 as Info-ZIP
+    /// does, unless the caller set those bits.
+    fn apply_info_zip_flags(&mut self) {
+        let (mask, flags) = self.options.flags_override;
+        if !self.archive.options.info_zip
+            || self.options.compression_method != CompressionMethod::Deflate
+            || mask & FLAG_COMPRESSION_OPTIONS != 0
+        {
+            return;
+        }
+
+        let level_flags = match self.compression_level {
+            Some(level) if level >= 8 => 0x02,
+            Some(1 | 2) => 0x04,
+            _ => 0,
+        };
+        self.options.flags_override = (mask | FLAG_COMPRESSION_OPTIONS, flags | level_flags);
+    }
+
+    /// Creates the file entry and returns a writer for the file's content.
+    #[deprecated(
+        since = "0.4.0",
+        note = "Use `start()` method instead as it allows for more flexibility (ie: CRC configuration)"
+    )]
+    pub fn create(self) -> Result<ZipEntryWriter<'archive, W>, Error> {
+        let (entry_writer, _) = self.start()?;
+        Ok(entry_writer)
+    }
+
+    /// Mark the start of file data
+    ///
+    /// Returns a tuple:
+    ///
+    /// - `entry` handles the ZIP format and writes compressed data to the archive
+    /// - `config` constructs data writers that handle uncompressed data and CRC32 calculation
+    ///
+    /// # Examples
+    ///
+    /// For stored (uncompressed) files:
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"Hello").unwrap();
+    /// let (_, output) = writer.finish().unwrap();
+    /// entry.finish(output).unwrap();
+    /// # archive.finish().unwrap();
+    /// ```
+    ///
+    /// For deflate compression:
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+    /// let encoder = flate2::write::DeflateEncoder::new(&mut entry, flate2::Compression::default());
+    /// let mut writer = config.wrap(encoder);
+    /// writer.write_all(b"Hello").unwrap();
+    /// let (encoder, output) = writer.finish().unwrap();
+    /// encoder.finish().unwrap();
+    /// entry.finish(output).unwrap();
+    /// # archive.finish().unwrap();
+    /// ```
+    pub fn start(mut self) -> Result<(ZipEntryWriter<'archive, W>, ZipDataWriterConfig), Error> {
+        self.apply_info_zip_flags();
+        let crc32_option = self.crc32_option;
+        let compression_level = self.compression_level;
+        let entry_writer = self
+            .archive
+            .new_file_with_options(&self.name, self.options)?;
+
+        let consumed = entry_writer
+            .inner
+            .progress
+            .as_ref()
+            .map(|progress| Arc::clone(&progress.consumed));
+        let data_writer_config = ZipDataWriterConfig {
+            crc32_option,
+            compression_level,
+            consumed,
+        };
+
+        Ok((entry_writer, data_writer_config))
+    }
+
+    /// Mark the start of data that has already been compressed.
+    ///
+    /// For pipelines that compress data externally or cache compressed blobs,
+    /// the CRC32 and size of the uncompressed data are supplied up front so
+    /// the compressed bytes can be written verbatim. Rawzip counts the
+    /// compressed bytes and uses the supplied values for the data descriptor
+    /// and central directory. The configured
+    /// [`crc32`](ZipFileBuilder::crc32) option is ignored.
+    ///
+    /// The compression method should be set to match the data.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let data = b"Hello";
+    /// let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    /// encoder.write_all(data)?;
+    /// let compressed = encoder.finish()?;
+    ///
+    /// let mut writer = archive.new_file("file.txt")
+    ///     .compression_method(rawzip::CompressionMethod::Deflate)
+    ///     .start_raw(rawzip::crc32(data), data.len() as u64)?;
+    /// writer.write_all(&compressed)?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn start_raw(
+        self,
+        crc32: u32,
+        uncompressed_size: u64,
+    ) -> Result<ZipRawEntryWriter<'archive, W>, Error> {
+        let (entry, _) = self.start()?;
+        Ok(ZipRawEntryWriter {
+            entry,
+            crc32,
+            uncompressed_size,
+        })
+    }
+
+    /// Mark the start of file data that is stored without compression.
+    ///
+    /// The returned writer computes the CRC32 (subject to the
+    /// [`crc32`](ZipFileBuilder::crc32) option) and counts the bytes as they
+    /// are written, so the entry is finished in one call, without wrapping
+    /// the entry writer with a [`ZipDataWriterConfig`]. Any compression
+    /// method set on the builder is replaced with
+    /// [`Store`](CompressionMethod::Store).
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_stored()?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn start_stored(self) -> Result<ZipStoredWriter<'archive, W>, Error> {
+        let (entry, config) = self.compression_method(CompressionMethod::Store).start()?;
+        Ok(ZipStoredWriter {
+            inner: config.wrap(entry),
+        })
+    }
+
+    /// Mark the start of file data that is compressed with the built-in
+    /// deflate compressor.
+    ///
+    /// The `level` ranges from 0 (fastest) to 9 (smallest), with 6 being the
+    /// customary default. Levels above 9 are clamped.
+    ///
+    /// The returned writer accepts the uncompressed data and takes care of
+    /// compression, CRC32, and the data descriptor, so there is only a single
+    /// `finish` to call.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_deflate(6)?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "deflate")]
+    pub fn start_deflate(self, level: u32) -> Result<ZipFileWriter<'archive, W>, Error> {
+        self.compression_method(CompressionMethod::Deflate)
+            .compression_level(level.min(9) as i32)
+            .start_compressed()
+    }
+
+    /// Mark the start of file data that is deflated on multiple threads, in
+    /// the manner of pigz.
+    ///
+    /// The data is split into 1 MiB blocks that are deflated independently
+    /// and joined into a single deflate stream, while the CRC32 of each block
+    /// is combined with the others. This speeds up writing large entries
+    /// several times over at the cost of a slightly larger output, as blocks
+    /// don't share a window. The number of threads is set with
+    /// [`with_compression_threads`](crate::ZipArchiveWriterBuilder::with_compression_threads).
+    ///
+    /// The returned writer is used the same way as the one from
+    /// `start_deflate`. [`store_if_larger`](ZipFileBuilder::store_if_larger)
+    /// is not supported.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("large.bin").start_deflate_parallel(6)?;
+    /// writer.write_all(&vec![b'a'; 5 * 1024 * 1024])?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "deflate")]
+    pub fn start_deflate_parallel(self, level: u32) -> Result<ZipFileWriter<'archive, W>, Error> {
+        let crc32_option = self.crc32_option;
+        let pool = CompressorPool::new(self.archive.options.compression_threads)?;
+        let (entry, config) = self
+            .compression_method(CompressionMethod::Deflate)
+            .compression_level(level.min(9) as i32)
+            .start()?;
+
+        Ok(ZipFileWriter {
+            inner: ZipFileWriterInner::Parallel(ParallelDeflateWriter {
+                entry,
+                pool,
+                level,
+                crc32_option,
+                block: Vec::with_capacity(PARALLEL_DEFLATE_BLOCK_SIZE),
+                crc: 0,
+                uncompressed_size: 0,
+                consumed: config.consumed,
+            }),
+        })
+    }
+
+    /// Mark the start of file data that is compressed with the built-in zstd
+    /// compressor.
+    ///
+    /// The `level` follows zstd conventions: 1 through 22, with 0 meaning the
+    /// zstd default (currently 3). Negative levels trade ratio for speed.
+    ///
+    /// The returned writer is used the same way as the one from
+    /// `start_deflate`.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_zstd(3)?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn start_zstd(self, level: i32) -> Result<ZipFileWriter<'archive, W>, Error> {
+        self.compression_method(CompressionMethod::Zstd)
+            .compression_level(level)
+            .start_compressed()
+    }
+
+    /// Mark the start of file data that is compressed with the built-in bzip2
+    /// compressor.
+    ///
+    /// The `level` ranges from 1 to 9 and sets the block size in units of
+    /// 100 KiB, with 9 being the customary default. Levels outside of the
+    /// range are clamped.
+    ///
+    /// The returned writer is used the same way as the one from
+    /// `start_deflate`.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_bzip2(9)?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "bzip2")]
+    pub fn start_bzip2(self, level: u32) -> Result<ZipFileWriter<'archive, W>, Error> {
+        self.compression_method(CompressionMethod::Bzip2)
+            .compression_level(level.clamp(1, 9) as i32)
+            .start_compressed()
+    }
+
+    /// Mark the start of file data that is compressed with the built-in LZMA
+    /// compressor.
+    ///
+    /// The `level` is one of liblzma's presets, from 0 to 9, with 6 being the
+    /// default. Levels above 9 are clamped. The entry's data begins with the
+    /// LZMA properties as zip requires, and the stream ends with an end
+    /// marker, which is signaled in the general purpose flags.
+    ///
+    /// The returned writer is used the same way as the one from
+    /// `start_deflate`.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_lzma(6)?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "lzma")]
+    pub fn start_lzma(self, level: u32) -> Result<ZipFileWriter<'archive, W>, Error> {
+        self.compression_method(CompressionMethod::Lzma)
+            .compression_level(level.min(9) as i32)
+            .start_compressed()
+    }
+
+    /// Mark the start of file data that is compressed with the built-in xz
+    /// compressor.
+    ///
+    /// The `level` is one of liblzma's presets, from 0 to 9, with 6 being the
+    /// default. Levels above 9 are clamped. The entry's data is a complete
+    /// `.xz` stream.
+    ///
+    /// The returned writer is used the same way as the one from
+    /// `start_deflate`.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_xz(6)?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "xz")]
+    pub fn start_xz(self, level: u32) -> Result<ZipFileWriter<'archive, W>, Error> {
+        self.compression_method(CompressionMethod::Xz)
+            .compression_level(level.min(9) as i32)
+            .start_compressed()
+    }
+
+    /// Mark the start of file data that is compressed with the built-in codec
+    /// matching the configured
+    /// [`compression_method`](ZipFileBuilder::compression_method).
+    ///
+    /// The [`compression_level`](ZipFileBuilder::compression_level) is passed
+    /// to the codec, otherwise the codec's default level is used. Deflate
+    /// levels outside of 0 to 9 are clamped.
+    ///
+    /// Returns an error if the compression method does not have a built-in
+    /// codec enabled.
+    ///
+    /// ```
+    /// # use std::io::Write;
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// # #[cfg(feature = "deflate")]
+    /// # let method = rawzip::CompressionMethod::Deflate;
+    /// # #[cfg(all(not(feature = "deflate"), feature = "zstd"))]
+    /// # let method = rawzip::CompressionMethod::Zstd;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd")), feature = "bzip2"))]
+    /// # let method = rawzip::CompressionMethod::Bzip2;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd", feature = "bzip2")), feature = "lzma"))]
+    /// # let method = rawzip::CompressionMethod::Lzma;
+    /// # #[cfg(not(any(feature = "deflate", feature = "zstd", feature = "bzip2", feature = "lzma")))]
+    /// # let method = rawzip::CompressionMethod::Xz;
+    /// let mut writer = archive.new_file("file.txt")
+    ///     .compression_method(method)
+    ///     .compression_level(9)
+    ///     .start_compressed()?;
+    /// writer.write_all(b"Hello")?;
+    /// writer.finish()?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "_codec")]
+    pub fn start_compressed(mut self) -> Result<ZipFileWriter<'archive, W>, Error> {
+        if self.best_method.is_none() {
+            self.apply_info_zip_flags();
+        }
+        let method = self.options.compression_method;
+        let level = self.compression_level;
+        if !Encoder::<ZipEntryWriter<'archive, W>>::supports(method) {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: format!("no built-in codec for compression method {:?}", method),
+            }));
+        }
+
+        if let Some(max_size) = self.best_method {
+            let data = ZipDataWriter::with_crc32(Vec::new(), self.crc32_option);
+            let buffered = BufferedFileWriter {
+                archive: self.archive,
+                name: self.name.into_owned(),
+                options: self.options,
+                level,
+                data,
+            };
+            return Ok(ZipFileWriter {
+                inner: ZipFileWriterInner::Trial(Box::new(TrialFileWriter {
+                    buffered: Some(buffered),
+                    streaming: None,
+                    max_size,
+                })),
+            });
+        }
+
+        if self.store_if_larger && method != CompressionMethod::Store {
+            let data = ZipDataWriter::with_crc32(Vec::new(), self.crc32_option);
+            let buffered = BufferedFileWriter {
+                archive: self.archive,
+                name: self.name.into_owned(),
+                options: self.options,
+                level,
+                data,
+            };
+            return Ok(ZipFileWriter {
+                inner: ZipFileWriterInner::Buffered(buffered),
+            });
+        }
+
+        let dictionary = self.archive.dictionary();
+        let (entry, config) = self.start()?;
+        let encoder = Encoder::with_dictionary(entry, method, level, dictionary.as_deref())?;
+        Ok(ZipFileWriter {
+            inner: ZipFileWriterInner::Streaming(config.wrap(encoder)),
+        })
+    }
+
+    /// Writes the file entry with the contents of a reader, compressed with
+    /// the built-in codec matching the configured
+    /// [`compression_method`](ZipFileBuilder::compression_method).
+    ///
+    /// This starts the entry with
+    /// [`start_compressed`](ZipFileBuilder::start_compressed), copies the
+    /// reader to it, and finishes it. Returns the number of compressed bytes
+    /// written. When reading fails, the entry is left unfinished and the
+    /// error is returned.
+    ///
+    /// ```
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// # #[cfg(feature = "deflate")]
+    /// # let method = rawzip::CompressionMethod::Deflate;
+    /// # #[cfg(all(not(feature = "deflate"), feature = "zstd"))]
+    /// # let method = rawzip::CompressionMethod::Zstd;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd")), feature = "bzip2"))]
+    /// # let method = rawzip::CompressionMethod::Bzip2;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd", feature = "bzip2")), feature = "lzma"))]
+    /// # let method = rawzip::CompressionMethod::Lzma;
+    /// # #[cfg(not(any(feature = "deflate", feature = "zstd", feature = "bzip2", feature = "lzma")))]
+    /// # let method = rawzip::CompressionMethod::Xz;
+    /// let data: &[u8] = b"Hello, world!";
+    /// archive.new_file("file.txt")
+    ///     .compression_method(method)
+    ///     .unix_permissions(0o644)
+    ///     .copy_from(data)?;
+    /// # archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "_codec")]
+    pub fn copy_from(self, mut reader: impl Read) -> Result<u64, Error> {
+        let mut writer = self.start_compressed()?;
+        io::copy(&mut reader, &mut writer)?;
+        writer.finish()
+    }
+
+    /// Queues the entry's data to be compressed on a background thread with
+    /// the built-in codec matching the configured
+    /// [`compression_method`](ZipFileBuilder::compression_method).
+    ///
+    /// Entries are written to the archive in the order they were queued, as
+    /// they finish compressing. Adding any other entry or finishing the
+    /// archive first waits for all queued entries to be written. Errors
+    /// concerning a queued entry, such as a name that is too long, are
+    /// returned by whichever call ends up writing it.
+    ///
+    /// The [`compression_level`](ZipFileBuilder::compression_level),
+    /// [`store_if_larger`](ZipFileBuilder::store_if_larger), and
+    /// [`crc32`](ZipFileBuilder::crc32) options are respected. The number of
+    /// threads is set with
+    /// [`with_compression_threads`](crate::ZipArchiveWriterBuilder::with_compression_threads).
+    ///
+    /// ```
+    /// # let mut output = std::io::Cursor::new(Vec::new());
+    /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// # #[cfg(feature = "deflate")]
+    /// # let method = rawzip::CompressionMethod::Deflate;
+    /// # #[cfg(all(not(feature = "deflate"), feature = "zstd"))]
+    /// # let method = rawzip::CompressionMethod::Zstd;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd")), feature = "bzip2"))]
+    /// # let method = rawzip::CompressionMethod::Bzip2;
+    /// # #[cfg(all(not(any(feature = "deflate", feature = "zstd", feature = "bzip2")), feature = "lzma"))]
+    /// # let method = rawzip::CompressionMethod::Lzma;
+    /// # #[cfg(not(any(feature = "deflate", feature = "zstd", feature = "bzip2", feature = "lzma")))]
+    /// # let method = rawzip::CompressionMethod::Xz;
+    /// for i in 0..10 {
+    ///     archive.new_file(&format!("file-{i}.txt"))
+    ///         .compression_method(method)
+    ///         .queue_compressed(vec![b'a'; 1000])?;
+    /// }
+    /// archive.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "_codec")]
+    pub fn queue_compressed(mut self, data: Vec<u8>) -> Result<(), Error> {
+        self.apply_info_zip_flags();
+        let method = self.options.compression_method;
+        if !Encoder::<Vec<u8>>::supports(method) {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: format!("no built-in codec for compression method {:?}", method),
+            }));
+        }
+
+        let archive = self.archive;
+        archive.check_open_entry()?;
+        let dictionary = archive.dictionary();
+        let queue = match archive.queue.as_mut() {
+            Some(queue) => queue,
+            None => archive.queue.insert(EntryQueue {
+                pool: CompressorPool::new(archive.options.compression_threads)?,
+                entries: VecDeque::new(),
+            }),
+        };
+
+        queue.pool.submit(Job::Entry {
+            data,
+            method,
+            level: self.compression_level,
+            store_if_larger: self.store_if_larger,
+            crc32_option: self.crc32_option,
+            dictionary,
+        })?;
+        queue
+            .entries
+            .push_back((self.name.into_owned(), self.options));
+        let capacity = queue.pool.capacity();
+
+        // The queued entry is lost if the archive isn't finished
+        archive.guard.armed = true;
+        archive.write_queued(capacity)
+    }
+    /// Reserves room for the entry's data, to be written later with
+    /// [`ZipArchiveWriter::fill`], while further entries are written after
+    /// it.
+    ///
+    /// This lays out the archive ahead of knowing every entry's contents,
+    /// such as for images where the position of each entry is fixed. The
+    /// local header is written along with `max_size` zero bytes, and the
+    /// entry is added to the central directory as an empty file until it's
+    /// filled. Data that falls short of the maximum leaves the remainder of
+    /// the region unused.
+    ///
+    /// The archive must be [seekable](crate::ZipArchiveWriterBuilder::build_seekable).
+    /// The [predeclared](ZipFileBuilder::predeclare) sizes are ignored. When
+    /// `max_size` calls for ZIP64, the local header is given a ZIP64 extra
+    /// field up front so that it can be patched in place.
+    ///
+    /// ```
+    /// # use std::io::{Cursor, Write};
+    /// let mut output = Cursor::new(Vec::new());
+    /// let mut archive = rawzip::ZipArchiveWriter::builder().build_seekable(&mut output);
+    /// let reservation = archive.new_file("manifest.txt").reserve(64)?;
+    /// let (mut entry, config) = archive.new_file("payload.bin").start()?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"payload")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    ///
+    /// let (mut entry, config) = archive.fill(reservation)?;
+    /// let mut writer = config.wrap(&mut entry);
+    /// writer.write_all(b"payload.bin: 7 bytes")?;
+    /// let (_, descriptor) = writer.finish()?;
+    /// entry.finish(descriptor)?;
+    /// archive.finish()?;
+    ///
+    /// let output = output.into_inner();
+    /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+    /// assert_eq!(archive.entries_hint(), 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reserve(self, max_size: u64) -> Result<ZipReservation, Error> {
+        let archive = self.archive;
+        if archive.seeker.is_none() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "reserving an entry requires a seekable output".to_string(),
+            }));
+        }
+
+        #[cfg(any(feature = "aes", feature = "zipcrypto"))]
+        if self.options.encryption.is_some() {
+            return Err(Error::from(ErrorKind::InvalidInput {
+                msg: "encrypted entries can't be reserved".to_string(),
+            }));
+        }
+
+        let mut options = self.options;
+        options.declared = None;
+        let alignment = options.alignment;
+        let header = archive.begin_entry(self.name.trim_dir(), 0, options, |header| {
+            if header.is_zip64_value(max_size) {
+                header.force_zip64 = true;
+                header.finalize_local_extra_fields()?;
+            }
+            header.align_local_data(alignment)
+        })?;
+
+        let data_offset = archive.writer.count();
+        io::copy(&mut io::repeat(0).take(max_size), &mut archive.writer)?;
+        let reservation = ZipReservation {
+            index: archive.files.len(),
+            data_offset,
+            max_size,
+            crc32_option: self.crc32_option,
+            compression_level: self.compression_level,
+        };
+        archive.push_entry(header)?;
+        Ok(reservation)
+    }
+}
+
+impl<'archive> ZipFileBuilder<'archive, '_, SizePlanner> {
+    /// Accounts for an entry whose data is `compressed_size` bytes, without
+    /// writing it.
+    ///
+    /// The local header, data descriptor, and central directory record are
+    /// laid out exactly as the entry would be written with the same options,
+    /// including any ZIP64 fields that the sizes call for. For stored
+    /// entries, both sizes are the length of the data.
+    ///
+    /// ```
+    /// let mut planner = rawzip::ZipArchiveWriter::new(rawzip::SizePlanner::new());
+    /// planner.new_file("file.txt").plan(5, 5)?;
+    /// let size = planner.finish()?.size();
+    ///
+    /// let mut output = Vec::new();
+    /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    /// let mut writer = archive.new_file("file.txt").start_raw(rawzip::crc32(b"Hello"), 5)?;
+    /// std::io::Write::write_all(&mut writer, b"Hello")?;
+    /// writer.finish()?;
+    /// archive.finish()?;
+    /// assert_eq!(output.len() as u64, size);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn plan(
+        self,
+        compressed_size: u64,
+        uncompressed_size: u64,
+    ) -> Result<ZipEntrySummary, Error> {
+        let (mut entry, _) = self.start()?;
+
+        // The local header may still be buffered, and has to be counted
+        // before the data that follows it
+        entry.inner.writer.inner_mut()?.size += compressed_size;
+        entry.inner.writer.count += compressed_size;
+        entry.compressed_bytes += compressed_size;
+        let output = DataDescriptorOutput {
+            crc: entry.header.crc,
+            compressed_size,
+            uncompressed_size,
+        };
+        entry.finish_with_summary(output)
+    }
+}
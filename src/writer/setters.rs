@@ -0,0 +1,456 @@
+/// Implements the setters that the file, directory, and symlink builders
+/// share, each of which writes to the builder's `options`.
+///
+/// Invoked within a builder's `impl` block with the kind of entry that the
+/// builder creates, followed by the setters to implement:
+///
+/// ```ignore
+/// entry_option_setters!("directory"; last_modified, metadata, comment);
+/// ```
+macro_rules! entry_option_setters {
+    (@last_modified $kind:literal) => {
+        #[doc = concat!("Sets the modification time for the ", $kind, " entry.")]
+        ///
+        /// Only accepts UTC timestamps to ensure Extended Timestamp fields are written correctly.
+        #[must_use]
+        #[inline]
+        pub fn last_modified(mut self, modification_time: $crate::time::UtcDateTime) -> Self {
+            self.options.modification_time = Some(modification_time);
+            self
+        }
+    };
+    (@metadata $kind:literal) => {
+        #[doc = concat!("Fills in the modification time and attributes of the ", $kind, " entry")]
+        /// from its metadata.
+        ///
+        /// On Unix, the full mode is recorded as the
+        /// [Unix permissions](Self::unix_permissions), including the
+        /// file type. On Windows, the attributes are recorded as
+        /// [MS-DOS attributes](crate::ZipFileBuilder::dos_attributes), except
+        /// for symlinks, whose metadata should come from
+        /// [`std::fs::symlink_metadata`]. Elsewhere, only the read-only
+        /// attribute is recorded. A modification time that is unavailable or
+        /// that can't be represented is left unset.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// # use std::io::Write;
+        /// # let path = std::env::temp_dir().join(format!("rawzip-doc-metadata-{}", std::process::id()));
+        /// # std::fs::write(&path, b"Hello")?;
+        /// let mut output = Vec::new();
+        /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+        /// let metadata = std::fs::metadata(&path)?;
+        /// let (mut entry, config) = archive
+        ///     .new_file("file.txt")
+        ///     .metadata(&metadata)
+        ///     .start()?;
+        /// let mut writer = config.wrap(&mut entry);
+        /// std::io::copy(&mut std::fs::File::open(&path)?, &mut writer)?;
+        /// let (_, descriptor) = writer.finish()?;
+        /// entry.finish(descriptor)?;
+        /// archive.finish()?;
+        /// # std::fs::remove_file(&path)?;
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[must_use]
+        #[inline]
+        pub fn metadata(mut self, metadata: &std::fs::Metadata) -> Self {
+            self.options.apply_metadata(metadata);
+            self
+        }
+    };
+    (@last_accessed $kind:literal) => {
+        #[doc = concat!("Sets the last access time for the ", $kind, " entry.")]
+        ///
+        /// The access time is written to the extended timestamp field of the
+        /// local file header. Like the modification time, it is stored with
+        /// second precision.
+        #[must_use]
+        #[inline]
+        pub fn last_accessed(mut self, access_time: $crate::time::UtcDateTime) -> Self {
+            self.options.access_time = Some(access_time);
+            self
+        }
+    };
+    (@created $kind:literal) => {
+        #[doc = concat!("Sets the creation time for the ", $kind, " entry.")]
+        ///
+        /// Like the [access time](Self::last_accessed), the creation time is
+        /// written to the extended timestamp field of the local file header
+        /// with second precision.
+        #[must_use]
+        #[inline]
+        pub fn created(mut self, creation_time: $crate::time::UtcDateTime) -> Self {
+            self.options.creation_time = Some(creation_time);
+            self
+        }
+    };
+    (@ntfs_timestamps $kind:literal) => {
+        /// Writes the NTFS extra field, which stores times at 100ns resolution.
+        ///
+        /// The times are taken from [`last_modified`](Self::last_modified),
+        /// [`last_accessed`](Self::last_accessed), and
+        /// [`created`](Self::created). Missing access and creation
+        /// times fall back to the modification time. Nothing is written when
+        /// there is no modification time.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// # use std::io::Write;
+        /// # use rawzip::{extra_fields::ExtraFieldId, time::UtcDateTime, ZipArchive, ZipArchiveWriter};
+        /// let mtime = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 123_456_700).unwrap();
+        /// let mut output = Vec::new();
+        /// let mut archive = ZipArchiveWriter::new(&mut output);
+        /// let (mut entry, config) = archive
+        ///     .new_file("file.txt")
+        ///     .last_modified(mtime)
+        ///     .ntfs_timestamps()
+        ///     .start()?;
+        /// let mut writer = config.wrap(&mut entry);
+        /// writer.write_all(b"Hello")?;
+        /// let (_, descriptor) = writer.finish()?;
+        /// entry.finish(descriptor)?;
+        /// archive.finish()?;
+        ///
+        /// let archive = ZipArchive::from_slice(&output)?;
+        /// let entry = archive.entries().next_entry()?.unwrap();
+        /// assert!(entry.extra_fields().any(|(id, _)| id == ExtraFieldId::NTFS));
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[must_use]
+        #[inline]
+        pub fn ntfs_timestamps(mut self) -> Self {
+            self.options
+                .ntfs_times
+                .get_or_insert_with($crate::writer::NtfsTimes::default);
+            self
+        }
+    };
+    (@ntfs_times $kind:literal) => {
+        /// Writes the NTFS extra field with explicit times.
+        ///
+        /// Unlike the extended timestamp field, these times keep their sub-second
+        /// precision down to 100ns. The times only affect the NTFS field.
+        #[must_use]
+        #[inline]
+        pub fn ntfs_times(
+            mut self,
+            modified: $crate::time::UtcDateTime,
+            accessed: $crate::time::UtcDateTime,
+            created: $crate::time::UtcDateTime,
+        ) -> Self {
+            self.options.ntfs_times = Some($crate::writer::NtfsTimes {
+                modified: Some(modified),
+                accessed: Some(accessed),
+                created: Some(created),
+            });
+            self
+        }
+    };
+    (@unix_permissions $kind:literal) => {
+        #[doc = concat!("Sets the Unix permissions for the ", $kind, " entry.")]
+        ///
+        /// Accepts either:
+        /// - Basic permission bits (e.g., 0o644 for rw-r--r--, 0o755 for rwxr-xr-x)
+        /// - Full Unix mode including file type (e.g., 0o100644 for regular file, 0o040755 for directory)
+        /// - Special permission bits are preserved (SUID: 0o4000, SGID: 0o2000, sticky: 0o1000)
+        ///
+        /// When set, the archive will be created with Unix-compatible "version made by" field
+        /// to ensure proper interpretation of the permissions by zip readers.
+        #[must_use]
+        #[inline]
+        pub fn unix_permissions(mut self, permissions: u32) -> Self {
+            self.options.unix_permissions = Some(permissions);
+            self
+        }
+    };
+    (@unix_owner $kind:literal) => {
+        #[doc = concat!("Sets the Unix user and group ID that own the ", $kind, " entry.")]
+        ///
+        /// The IDs are written to the Info-ZIP new Unix (`0x7875`) extra field of
+        /// both headers, which Info-ZIP's `unzip -X` and other archivers use to
+        /// restore ownership.
+        #[must_use]
+        #[inline]
+        pub fn unix_owner(mut self, uid: u32, gid: u32) -> Self {
+            self.options.unix_owner = Some((uid, gid));
+            self
+        }
+    };
+    (@host_system $kind:literal) => {
+        #[doc = concat!("Sets the host system recorded for the ", $kind, " entry.")]
+        ///
+        /// Overrides the archive's
+        /// [host system](crate::ZipArchiveWriterBuilder::with_host_system) for this
+        /// entry.
+        #[must_use]
+        #[inline]
+        pub fn host_system(mut self, host_system: $crate::HostSystem) -> Self {
+            self.options.host_system = Some(host_system);
+            self
+        }
+    };
+    (@disk_number_start $kind:literal) => {
+        #[doc = concat!("Sets the \"disk number start\" recorded for the ", $kind, " entry in the")]
+        /// central directory, instead of the volume that it's written to, which
+        /// is 0 unless the archive is [split](crate::SplitZipWriter).
+        ///
+        /// This is for tools that go on to split the archive themselves, and for
+        /// matching the output of another producer byte for byte. Readers that
+        /// honor the field look for the entry on that volume.
+        ///
+        /// Disk numbers of 65,535 or more are recorded in the ZIP64 extra field.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// let mut output = Vec::new();
+        /// let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+        /// let (mut entry, config) = archive.new_file("file.txt").disk_number_start(2).start()?;
+        /// # let (_, descriptor) = config.wrap(&mut entry).finish()?;
+        /// # entry.finish(descriptor)?;
+        /// archive.finish()?;
+        ///
+        /// let archive = rawzip::ZipArchive::from_slice(&output)?;
+        /// let entry = archive.entries().next_entry()?.unwrap();
+        /// assert_eq!(entry.disk_number_start(), 2);
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[must_use]
+        #[inline]
+        pub fn disk_number_start(mut self, disk: u32) -> Self {
+            self.options.disk_number = Some(disk);
+            self
+        }
+    };
+    (@internal_attributes $kind:literal) => {
+        #[doc = concat!("Sets the internal file attributes of the ", $kind, " entry.")]
+        ///
+        /// Bit 0 marks the entry as apparently being a text file, which some
+        /// extractors use to decide on line ending conversion. Bit 1 marks a
+        /// mainframe record length control field before each logical record.
+        /// The remaining bits are reserved. Defaults to zero.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// # use std::io::Write;
+        /// # let mut output = Vec::new();
+        /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+        /// let (mut entry, config) = archive
+        ///     .new_file("README.txt")
+        ///     .internal_attributes(0x0001)
+        ///     .start()?;
+        /// let mut writer = config.wrap(&mut entry);
+        /// writer.write_all(b"plain text\n")?;
+        /// let (_, descriptor) = writer.finish()?;
+        /// entry.finish(descriptor)?;
+        /// # archive.finish()?;
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[must_use]
+        #[inline]
+        pub fn internal_attributes(mut self, attributes: u16) -> Self {
+            self.options.internal_attrs = attributes;
+            self
+        }
+    };
+    (@dos_attributes $kind:literal) => {
+        #[doc = concat!("Sets the MS-DOS attributes of the ", $kind, " entry.")]
+        ///
+        /// The attributes occupy the low byte of the external file attributes and
+        /// may be combined with [`unix_permissions`](Self::unix_permissions),
+        /// which occupy the high bytes. Readers only consult the attributes that
+        /// match the [host system](Self::host_system), so Windows
+        /// targeted archives should leave the host system as MS-DOS or NTFS.
+        /// Directory entries always include the directory attribute.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// # use std::io::Write;
+        /// # use rawzip::DosAttributes;
+        /// # let mut output = Vec::new();
+        /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+        /// let (mut entry, config) = archive
+        ///     .new_file("desktop.ini")
+        ///     .dos_attributes(DosAttributes::HIDDEN | DosAttributes::SYSTEM)
+        ///     .start()?;
+        /// let mut writer = config.wrap(&mut entry);
+        /// writer.write_all(b"[.ShellClassInfo]\n")?;
+        /// let (_, descriptor) = writer.finish()?;
+        /// entry.finish(descriptor)?;
+        /// # archive.finish()?;
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[must_use]
+        #[inline]
+        pub fn dos_attributes(mut self, attributes: $crate::DosAttributes) -> Self {
+            self.options.dos_attributes = Some(attributes);
+            self
+        }
+    };
+    (@external_attributes $kind:literal) => {
+        #[doc = concat!("Sets the external file attributes of the ", $kind, " entry verbatim.")]
+        ///
+        /// This is an escape hatch for attribute encodings that have no dedicated
+        /// builder method, and it takes precedence over
+        /// [`unix_permissions`](Self::unix_permissions) and
+        /// [`dos_attributes`](Self::dos_attributes). As the meaning of
+        /// the attributes depends on the host system, consider setting the
+        /// [host system](Self::host_system) as well.
+        ///
+        /// # Example
+        ///
+        /// ```rust
+        /// # use std::io::Write;
+        /// # use rawzip::HostSystem;
+        /// # let mut output = Vec::new();
+        /// # let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+        /// // A Unix character device node
+        /// let (mut entry, config) = archive
+        ///     .new_file("dev/null")
+        ///     .host_system(HostSystem::UNIX)
+        ///     .external_attributes(0o020666 << 16)
+        ///     .start()?;
+        /// let (_, descriptor) = config.wrap(&mut entry).finish()?;
+        /// entry.finish(descriptor)?;
+        /// # archive.finish()?;
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        #[must_use]
+        #[inline]
+        pub fn external_attributes(mut self, attributes: u32) -> Self {
+            self.options.external_attrs = Some(attributes);
+            self
+        }
+    };
+    (@comment $kind:literal) => {
+        /// Sets the comment stored in the central directory record of the entry.
+        ///
+        /// Adding the entry fails if the comment is longer than 65,535 bytes.
+        #[must_use]
+        #[inline]
+        pub fn comment(mut self, comment: &str) -> Self {
+            self.options.comment = comment.as_bytes().to_vec();
+            self
+        }
+    };
+    (@extra_field $kind:literal) => {
+        #[doc = concat!("Adds an extra field to this ", $kind, " entry.")]
+        ///
+        /// Extra fields contain additional metadata about files in ZIP archives,
+        /// such as timestamps, alignment information, and platform-specific data.
+        ///
+        /// No deduplication is performed - duplicate field IDs will result in
+        /// multiple entries
+        ///
+        /// The ID may be an [`ExtraFieldId`](crate::extra_fields::ExtraFieldId)
+        /// or a raw `u16`, which is handy for vendor specific fields that rawzip
+        /// has no constant for.
+        ///
+        /// Will return an error if the total size exceeds 65,535 bytes for the
+        /// specified headers.
+        ///
+        /// Rawzip will automatically add extra fields:
+        ///
+        /// - `EXTENDED_TIMESTAMP` when `last_modified()`, `last_accessed()`, or
+        ///   `created()` is set
+        /// - `NTFS` when `ntfs_timestamps()` or `ntfs_times()` is set
+        /// - `INFO_ZIP_UNIX_UID_GID` when `unix_owner()` is set
+        /// - `ZIP64` when 32-bit thresholds are met. Supplying a `ZIP64` field
+        ///   yourself is an error.
+        ///
+        /// # Examples
+        ///
+        /// Create files with different extra field headers and verify the
+        /// behavior. Only the central directory is checked. To check the local
+        /// extra fields, see
+        /// [`ZipEntry::local_header`](crate::ZipEntry::local_header)
+        ///
+        /// ```rust
+        /// # use std::io::{Cursor, Write};
+        /// # use rawzip::{ZipArchive, ZipArchiveWriter, ZipDataWriter, extra_fields::ExtraFieldId, Header};
+        /// let mut output = Cursor::new(Vec::new());
+        /// let mut archive = ZipArchiveWriter::new(&mut output);
+        ///
+        /// let my_custom_field = ExtraFieldId::new(0x6666);
+        ///
+        /// // File with extra fields only in the local file header
+        /// let mut local_file = archive.new_file("video.mp4")
+        ///     .extra_field(my_custom_field, b"field1", Header::LOCAL)?
+        ///     .create()?;
+        /// let mut writer = ZipDataWriter::new(&mut local_file);
+        /// writer.write_all(b"video data")?;
+        /// let (_, desc) = writer.finish()?;
+        /// local_file.finish(desc)?;
+        ///
+        /// // File with extra fields only in the central directory
+        /// let mut central_file = archive.new_file("document.pdf")
+        ///     .extra_field(my_custom_field, b"field2", Header::CENTRAL)?
+        ///     .create()?;
+        /// let mut writer = ZipDataWriter::new(&mut central_file);
+        /// writer.write_all(b"PDF content")?;
+        /// let (_, desc) = writer.finish()?;
+        /// central_file.finish(desc)?;
+        ///
+        /// // File with extra fields in both headers for maximum compatibility
+        /// assert_eq!(Header::default(), Header::LOCAL | Header::CENTRAL);
+        /// let mut both_file = archive.new_file("important.dat")
+        ///     .extra_field(my_custom_field, b"field3", Header::default())?
+        ///     .create()?;
+        /// let mut writer = ZipDataWriter::new(&mut both_file);
+        /// writer.write_all(b"important data")?;
+        /// let (_, desc) = writer.finish()?;
+        /// both_file.finish(desc)?;
+        ///
+        /// archive.finish()?;
+        ///
+        /// // Verify the behavior when reading back the central directory
+        /// let zip_data = output.into_inner();
+        /// let archive = ZipArchive::from_slice(&zip_data)?;
+        ///
+        /// for entry_result in archive.entries() {
+        ///     let entry = entry_result?;
+        ///
+        ///     // Find our custom field in the central directory
+        ///     let custom_field_data = entry.extra_fields()
+        ///         .find(|(id, _)| *id == my_custom_field)
+        ///         .map(|(_, data)| data);
+        ///
+        ///     match entry.file_path().as_ref() {
+        ///         b"video.mp4" => {
+        ///             // local only field should not be in central directory
+        ///             assert_eq!(custom_field_data, None);
+        ///         }
+        ///         b"document.pdf" => {
+        ///             // central only field should be in central directory
+        ///             assert_eq!(custom_field_data, Some(b"field2".as_slice()));
+        ///         }
+        ///         b"important.dat" => {
+        ///             // both location field should be in central directory
+        ///             assert_eq!(custom_field_data, Some(b"field3".as_slice()));
+        ///         }
+        ///         _ => {}
+        ///     }
+        /// }
+        /// # Ok::<(), Box<dyn std::error::Error>>(())
+        /// ```
+        pub fn extra_field(
+            mut self,
+            id: impl Into<$crate::extra_fields::ExtraFieldId>,
+            data: &[u8],
+            location: $crate::Header,
+        ) -> Result<Self, $crate::Error> {
+            self.options.add_extra_field(id.into(), data, location)?;
+            Ok(self)
+        }
+    };
+    ($kind:literal; $($setter:ident),+ $(,)?) => {
+        $(entry_option_setters!(@$setter $kind);)+
+    };
+}
+
+pub(super) use entry_option_setters;
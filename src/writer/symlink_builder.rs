@@ -0,0 +1,45 @@
+use super::{setters::entry_option_setters, ZipEntryOptions};
+use crate::{mode::S_IFLNK, Error, ZipArchiveWriter};
+use std::io::Write;
+
+/// A builder for creating a new symbolic link entry in a ZIP archive.
+///
+/// Symlinks are encoded the way Info-ZIP does: the link target is the stored
+/// content of the entry and the Unix mode carries the symlink file type.
+#[derive(Debug)]
+pub struct ZipSymlinkBuilder<'a, W> {
+    pub(super) archive: &'a mut ZipArchiveWriter<W>,
+    pub(super) name: &'a str,
+    pub(super) target: &'a str,
+    pub(super) options: ZipEntryOptions,
+}
+
+impl<W> ZipSymlinkBuilder<'_, W>
+where
+    W: Write,
+{
+    entry_option_setters!("symlink"; last_modified, metadata, unix_owner, comment);
+
+    /// Sets the permission bits for the symlink entry.
+    ///
+    /// Defaults to `0o777`. Any file type bits are replaced with the symlink
+    /// file type.
+    #[must_use]
+    #[inline]
+    pub fn unix_permissions(mut self, permissions: u32) -> Self {
+        self.options.unix_permissions = Some(permissions);
+        self
+    }
+
+    /// Creates the symlink entry.
+    pub fn create(self) -> Result<(), Error> {
+        let mut options = self.options;
+        let permissions = options.unix_permissions.unwrap_or(0o777) & 0o7777;
+        options.unix_permissions = Some(S_IFLNK | permissions);
+        // Attributes filled in from the metadata aren't recorded for symlinks
+        options.dos_attributes = None;
+
+        self.archive
+            .new_stored_file_with_options(self.name, self.target.as_bytes(), options)
+    }
+}
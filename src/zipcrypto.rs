@@ -0,0 +1,70 @@
+//! Traditional PKWARE encryption, also known as ZipCrypto (6.1).
+//!
+//! The cipher is a stream cipher keyed by three 32-bit registers that are
+//! stirred with the password and then with every byte of plaintext. It is
+//! broken: a dozen bytes of known plaintext are enough to recover the keys.
+
+use crate::{crc::crc32_update, encryption::Password, Error};
+use std::io;
+
+/// The encryption header is 12 bytes, the last of which is checked when
+/// decrypting to tell whether the password is right (6.1.3).
+const HEADER_LEN: usize = 12;
+
+/// Encrypts an entry's data with the traditional PKWARE cipher.
+pub(crate) struct ZipCryptoEncryptor {
+    keys: [u32; 3],
+}
+
+impl ZipCryptoEncryptor {
+    /// Initializes the keys with the password. Returns the encryptor along
+    /// with the encrypted header that precedes the encrypted data.
+    ///
+    /// The last byte of the header is the `check` byte, which readers
+    /// compare against the high byte of the CRC32, or the high byte of the
+    /// last modified time when the entry has a data descriptor.
+    pub(crate) fn new(password: &Password, check: u8) -> Result<(Self, Vec<u8>), Error> {
+        let mut encryptor = ZipCryptoEncryptor {
+            keys: [0x12345678, 0x23456789, 0x34567890],
+        };
+
+        for &byte in password.as_bytes() {
+            encryptor.update_keys(byte);
+        }
+
+        let mut header = vec![0u8; HEADER_LEN];
+        getrandom::getrandom(&mut header[..HEADER_LEN - 1]).map_err(io::Error::from)?;
+        header[HEADER_LEN - 1] = check;
+        encryptor.encrypt(&mut header);
+        Ok((encryptor, header))
+    }
+
+    /// Encrypts the data in place.
+    pub(crate) fn encrypt(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            let plain = *byte;
+            *byte ^= self.keystream_byte();
+            self.update_keys(plain);
+        }
+    }
+
+    fn keystream_byte(&self) -> u8 {
+        let temp = (self.keys[2] | 2) as u16;
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+
+    fn update_keys(&mut self, byte: u8) {
+        self.keys[0] = crc32_update(self.keys[0], byte);
+        self.keys[1] = self.keys[1]
+            .wrapping_add(self.keys[0] & 0xff)
+            .wrapping_mul(134775813)
+            .wrapping_add(1);
+        self.keys[2] = crc32_update(self.keys[2], (self.keys[1] >> 24) as u8);
+    }
+}
+
+impl std::fmt::Debug for ZipCryptoEncryptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZipCryptoEncryptor").finish_non_exhaustive()
+    }
+}
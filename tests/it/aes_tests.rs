@@ -0,0 +1,208 @@
+#![cfg(feature = "aes")]
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use hmac::Mac;
+use rawzip::{extra_fields::ExtraFieldId, CompressionMethod, ZipArchive, ZipArchiveWriter};
+use std::io::{Cursor, Write};
+
+/// Decrypts an AE-2 entry's data, checking the password verification value
+/// and authentication code along the way. Returns `None` for a wrong
+/// password.
+fn decrypt(data: &[u8], password: &[u8]) -> Option<Vec<u8>> {
+    let (salt, rest) = data.split_at(16);
+    let (verifier, rest) = rest.split_at(2);
+    let (encrypted, auth_code) = rest.split_at(rest.len() - 10);
+
+    let mut keys = [0u8; 66];
+    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(password, salt, 1000, &mut keys);
+    if &keys[64..] != verifier {
+        return None;
+    }
+
+    let mut mac = <hmac::Hmac<sha1::Sha1> as Mac>::new_from_slice(&keys[32..64]).unwrap();
+    mac.update(encrypted);
+    assert_eq!(&mac.finalize().into_bytes()[..10], auth_code);
+
+    let cipher = aes::Aes256::new_from_slice(&keys[..32]).unwrap();
+    let mut plaintext = encrypted.to_vec();
+    for (i, chunk) in plaintext.chunks_mut(16).enumerate() {
+        let mut block = (i as u128 + 1).to_le_bytes();
+        cipher.encrypt_block((&mut block).into());
+        for (byte, key) in chunk.iter_mut().zip(block) {
+            *byte ^= key;
+        }
+    }
+
+    Some(plaintext)
+}
+
+fn write_entry(archive: &mut ZipArchiveWriter<impl Write>, name: &str, data: &[u8]) {
+    let (mut entry, config) = archive
+        .new_file(name)
+        .aes_password("hunter2")
+        .start()
+        .unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(data).unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+}
+
+fn assert_encrypted(output: &[u8], expected: &[(&str, &[u8])]) {
+    let archive = ZipArchive::from_slice(output).unwrap();
+    let mut entries = archive.entries();
+    for (name, data) in expected {
+        let record = entries.next_entry().unwrap().unwrap();
+        assert_eq!(record.file_path().as_ref(), name.as_bytes());
+        assert_eq!(record.compression_method(), CompressionMethod::Aes);
+        assert_eq!(record.crc32(), 0);
+        assert_eq!(record.compressed_size_hint(), data.len() as u64 + 28);
+
+        let field = record
+            .extra_fields()
+            .find(|(id, _)| *id == ExtraFieldId::WINZIP_AES)
+            .map(|(_, data)| data.to_vec());
+        assert_eq!(field, Some(vec![2, 0, b'A', b'E', 3, 0, 0]));
+
+        let offset = record.local_header_offset() as usize;
+        let flags = u16::from_le_bytes([output[offset + 6], output[offset + 7]]);
+        assert_eq!(flags & 0x01, 0x01);
+        assert_eq!(
+            u16::from_le_bytes([output[offset + 4], output[offset + 5]]),
+            51
+        );
+
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        assert_eq!(decrypt(entry.data(), b"hunter2").as_deref(), Some(*data));
+        assert_eq!(decrypt(entry.data(), b"hunter3"), None);
+    }
+    assert!(entries.next_entry().unwrap().is_none());
+}
+
+#[test]
+fn test_aes_roundtrip() {
+    let large = (0..10_000u32).map(|x| x as u8).collect::<Vec<_>>();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    write_entry(&mut archive, "empty.txt", b"");
+    write_entry(&mut archive, "small.txt", b"Hello, world!");
+    write_entry(&mut archive, "large.bin", &large);
+    archive.finish().unwrap();
+
+    assert_encrypted(
+        &output,
+        &[
+            ("empty.txt", b""),
+            ("small.txt", b"Hello, world!"),
+            ("large.bin", &large),
+        ],
+    );
+}
+
+#[test]
+fn test_aes_seekable() {
+    let mut output = Cursor::new(Vec::new());
+    let mut archive = ZipArchiveWriter::builder().build_seekable(&mut output);
+    write_entry(&mut archive, "file.txt", b"Hello, world!");
+    archive.finish().unwrap();
+
+    let output = output.into_inner();
+    assert_encrypted(&output, &[("file.txt", b"Hello, world!")]);
+}
+
+#[test]
+fn test_aes_mixed_archive() {
+    let manifest = b"Manifest-Version: 1.0\n";
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut writer = archive.new_file("MANIFEST.MF").start_stored().unwrap();
+    writer.write_all(manifest).unwrap();
+    writer.finish().unwrap();
+    write_entry(&mut archive, "payload.bin", b"Hello, world!");
+    archive.finish().unwrap();
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut entries = archive.entries();
+    let record = entries.next_entry().unwrap().unwrap();
+    assert_eq!(record.file_path().as_ref(), b"MANIFEST.MF");
+    assert_eq!(record.compression_method(), CompressionMethod::Store);
+    let offset = record.local_header_offset() as usize;
+    assert_eq!(output[offset + 6] & 0x01, 0);
+    assert!(!record
+        .extra_fields()
+        .any(|(id, _)| id == ExtraFieldId::WINZIP_AES));
+    let entry = archive.get_entry(record.wayfinder()).unwrap();
+    let mut contents = Vec::new();
+    std::io::copy(&mut entry.verifying_reader(entry.data()), &mut contents).unwrap();
+    assert_eq!(contents, manifest);
+
+    let record = entries.next_entry().unwrap().unwrap();
+    assert_eq!(record.compression_method(), CompressionMethod::Aes);
+    let entry = archive.get_entry(record.wayfinder()).unwrap();
+    assert_eq!(
+        decrypt(entry.data(), b"hunter2").as_deref(),
+        Some(&b"Hello, world!"[..])
+    );
+}
+
+#[test]
+fn test_aes_salt_is_random() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    write_entry(&mut archive, "a.txt", b"same");
+    write_entry(&mut archive, "b.txt", b"same");
+    archive.finish().unwrap();
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut entries = archive.entries();
+    let mut data = Vec::new();
+    while let Some(record) = entries.next_entry().unwrap() {
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        data.push(entry.data().to_vec());
+    }
+    assert_ne!(data[0][..16], data[1][..16]);
+}
+
+#[test]
+fn test_aes_predeclare_rejected() {
+    let mut archive = ZipArchiveWriter::new(Vec::new());
+    let err = archive
+        .new_file("file.txt")
+        .aes_password("hunter2")
+        .predeclare(0, 5, 5)
+        .start()
+        .unwrap_err();
+    assert!(matches!(err.kind(), rawzip::ErrorKind::InvalidInput { .. }));
+    archive.finish().unwrap();
+}
+
+#[test]
+#[cfg(feature = "deflate")]
+fn test_aes_deflate() {
+    let data = b"Hello, world! ".repeat(100);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut writer = archive
+        .new_file("file.txt")
+        .aes_password("hunter2")
+        .start_deflate(6)
+        .unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let record = archive.entries().next_entry().unwrap().unwrap();
+    let field = record
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::WINZIP_AES)
+        .map(|(_, data)| data.to_vec());
+    assert_eq!(field, Some(vec![2, 0, b'A', b'E', 3, 8, 0]));
+
+    let entry = archive.get_entry(record.wayfinder()).unwrap();
+    let compressed = decrypt(entry.data(), b"hunter2").unwrap();
+    let mut decoder = flate2::read::DeflateDecoder::new(&compressed[..]);
+    let mut contents = Vec::new();
+    std::io::Read::read_to_end(&mut decoder, &mut contents).unwrap();
+    assert_eq!(contents, data);
+}
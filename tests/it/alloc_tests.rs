@@ -0,0 +1,99 @@
+use rawzip::{ZipArchive, ZipArchiveWriter};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::Write;
+
+/// Counts the allocations made on each thread, so that tests running in
+/// parallel don't see each other's.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+#[test]
+fn test_small_entries_are_allocation_free() {
+    const ENTRIES: usize = 1000;
+    let names = (0..ENTRIES)
+        .map(|i| format!("dir/file-{i:04}.txt"))
+        .collect::<Vec<_>>();
+
+    let mut archive = ZipArchiveWriter::new(std::io::sink());
+    let before = allocations();
+    for name in &names {
+        let (mut entry, config) = archive.new_file(name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(name.as_bytes()).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+    }
+    let allocated = allocations() - before;
+    archive.finish().unwrap();
+
+    // Only the buffers that keep track of the entries grow, and they double
+    // in size when they do
+    assert!(
+        allocated < 64,
+        "{allocated} allocations for {ENTRIES} entries"
+    );
+}
+
+#[test]
+fn test_slice_archive_is_allocation_free() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for i in 0..100 {
+        let name = format!("dir/file-{i:04}.txt");
+        let (mut entry, config) = archive.new_file(&name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(name.as_bytes()).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+    }
+    archive.finish().unwrap();
+
+    let before = allocations();
+    let archive = ZipArchive::from_slice(output.as_slice()).unwrap();
+    let mut count = 0;
+    for record in archive.entries() {
+        let record = record.unwrap();
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        let local = entry.local_header();
+        assert_eq!(local.file_path().as_ref(), record.file_path().as_ref());
+        assert_eq!(local.extra_fields().count(), record.extra_fields().count());
+        assert_eq!(entry.data(), record.file_path().as_ref());
+        entry
+            .claim_verifier()
+            .valid(rawzip::ZipVerification {
+                crc: rawzip::crc32(entry.data()),
+                uncompressed_size: entry.data().len() as u64,
+            })
+            .unwrap();
+        count += 1;
+    }
+    assert_eq!(allocations() - before, 0);
+    assert_eq!(count, 100);
+}
@@ -0,0 +1,632 @@
+use rawzip::{
+    extra_fields::ExtraFieldId,
+    time::{UtcDateTime, ZipDateTimeKind},
+    CompressionMethod, ZipArchive, ZipArchiveWriter,
+};
+use rstest::rstest;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+fn temp_copy(asset: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rawzip-append-{}-{}", std::process::id(), asset));
+    std::fs::copy(format!("assets/{}", asset), &path).unwrap();
+    path
+}
+
+fn read_all(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = entry
+            .file_path()
+            .try_normalize()
+            .unwrap()
+            .as_ref()
+            .to_string();
+        let method = entry.compression_method();
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents),
+            CompressionMethod::Deflate => entry
+                .verifying_reader(flate2::read::DeflateDecoder::new(entry.data()))
+                .read_to_end(&mut contents),
+            _ => panic!("unexpected compression method"),
+        }
+        .unwrap();
+        result.push((name, contents));
+    }
+    result
+}
+
+#[rstest]
+#[case("test.zip")]
+#[case("test-prefix.zip")]
+#[case("winxp.zip")]
+#[case("zip64.zip")]
+fn test_append_to_existing_archive(#[case] asset: &str) {
+    let path = temp_copy(asset);
+    let mut expected = read_all(&std::fs::read(&path).unwrap());
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut archive = ZipArchiveWriter::append(file).unwrap();
+    let (mut entry, config) = archive.new_file("appended.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"appended data").unwrap();
+    let (_, output) = writer.finish().unwrap();
+    entry.finish(output).unwrap();
+    archive.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let original = std::fs::read(format!("assets/{}", asset)).unwrap();
+    let original = ZipArchive::from_slice(&original).unwrap();
+    let appended = ZipArchive::from_slice(&data).unwrap();
+    assert_eq!(original.comment(), appended.comment());
+
+    expected.push((String::from("appended.txt"), b"appended data".to_vec()));
+    assert_eq!(read_all(&data), expected);
+}
+
+#[test]
+fn test_append_without_changes_preserves_entries() {
+    let path = temp_copy("unix.zip");
+    let original = std::fs::read(&path).unwrap();
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    ZipArchiveWriter::append(file).unwrap().finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let original = ZipArchive::from_slice(&original).unwrap();
+    let appended = ZipArchive::from_slice(&data).unwrap();
+    assert_eq!(original.entries_hint(), appended.entries_hint());
+    for (a, b) in original.entries().zip(appended.entries()) {
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.file_path(), b.file_path());
+        assert_eq!(a.mode(), b.mode());
+        assert_eq!(a.last_modified(), b.last_modified());
+        assert_eq!(a.crc32(), b.crc32());
+        assert_eq!(a.local_header_offset(), b.local_header_offset());
+    }
+}
+
+#[test]
+fn test_append_abandoned_keeps_archive() {
+    let path = temp_copy("test.zip");
+    let original = std::fs::read(&path).unwrap();
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let archive = ZipArchiveWriter::builder()
+        .sort_central_directory(true)
+        .append(file)
+        .unwrap();
+    drop(archive.into_inner());
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(data, original);
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+fn test_append_remove_and_replace(#[case] compact: bool) {
+    let path = std::env::temp_dir().join(format!(
+        "rawzip-remove-{}-{}.zip",
+        std::process::id(),
+        compact
+    ));
+
+    let mut archive = ZipArchiveWriter::new(std::fs::File::create(&path).unwrap());
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        let (mut entry, config) = archive.new_file(name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(name.repeat(1000).as_bytes()).unwrap();
+        let (_, output) = writer.finish().unwrap();
+        entry.finish(output).unwrap();
+    }
+    archive.finish().unwrap();
+    let original_len = std::fs::metadata(&path).unwrap().len();
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut archive = ZipArchiveWriter::append(file).unwrap();
+    assert!(archive.remove("a.txt"));
+    assert!(archive.remove(b"b.txt"));
+    assert!(!archive.remove("b.txt"));
+
+    // Replace b.txt with new contents
+    let (mut entry, config) = archive.new_file("b.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"replaced").unwrap();
+    let (_, output) = writer.finish().unwrap();
+    entry.finish(output).unwrap();
+
+    if compact {
+        archive.compact().unwrap();
+    }
+    archive.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let expected = vec![
+        (String::from("c.txt"), "c.txt".repeat(1000).into_bytes()),
+        (String::from("b.txt"), b"replaced".to_vec()),
+    ];
+    assert_eq!(read_all(&data), expected);
+
+    if compact {
+        assert!((data.len() as u64) < original_len - 9000);
+        let archive = ZipArchive::from_slice(&data).unwrap();
+        let first = archive.entries().next_entry().unwrap().unwrap();
+        assert_eq!(first.local_header_offset(), 0);
+    } else {
+        assert!(data.len() as u64 > original_len);
+    }
+}
+
+#[test]
+fn test_resume_from_checkpoint() {
+    let path = std::env::temp_dir().join(format!("rawzip-resume-{}.zip", std::process::id()));
+    let write = |archive: &mut ZipArchiveWriter<std::fs::File>, name: &str| {
+        let (mut entry, config) = archive.new_file(name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(name.as_bytes()).unwrap();
+        let (_, output) = writer.finish().unwrap();
+        entry.finish(output).unwrap();
+    };
+
+    let mut archive = ZipArchiveWriter::new(std::fs::File::create(&path).unwrap());
+    archive.set_comment(b"comment").unwrap();
+    write(&mut archive, "first.txt");
+    write(&mut archive, "second.txt");
+    let checkpoint = archive.checkpoint().unwrap();
+
+    // Neither the finished nor the partial entry after the checkpoint survives
+    write(&mut archive, "lost.txt");
+    let (mut entry, _) = archive.new_file("partial.txt").start().unwrap();
+    entry.write_all(b"partial").unwrap();
+    drop(entry);
+    drop(archive.into_inner());
+
+    let bytes = checkpoint.to_bytes();
+    let checkpoint = rawzip::ZipCheckpoint::from_bytes(&bytes).unwrap();
+    assert!(rawzip::ZipCheckpoint::from_bytes(&bytes[..8]).is_err());
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut archive = ZipArchiveWriter::resume(file, &checkpoint).unwrap();
+    write(&mut archive, "third.txt");
+    archive.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let expected = ["first.txt", "second.txt", "third.txt"]
+        .map(|name| (name.to_string(), name.as_bytes().to_vec()));
+    assert_eq!(read_all(&data), expected);
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    assert_eq!(archive.comment().as_bytes(), b"comment");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_checkpoint_sorts_central_directory() {
+    let path =
+        std::env::temp_dir().join(format!("rawzip-resume-sorted-{}.zip", std::process::id()));
+    let mut archive = ZipArchiveWriter::builder()
+        .sort_central_directory(true)
+        .build(std::fs::File::create(&path).unwrap());
+    archive.add_bytes("b.txt", b"b").unwrap();
+    archive.add_bytes("a.txt", b"a").unwrap();
+    let checkpoint = archive.checkpoint().unwrap();
+    drop(archive.into_inner());
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    ZipArchiveWriter::resume(file, &checkpoint)
+        .unwrap()
+        .finish()
+        .unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let names: Vec<_> = read_all(&data).into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["a.txt", "b.txt"]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_archive_extra_data_roundtrip() {
+    let path = std::env::temp_dir().join(format!("rawzip-extra-data-{}.zip", std::process::id()));
+    let mut archive = ZipArchiveWriter::new(std::fs::File::create(&path).unwrap());
+    archive.set_archive_extra_data(b"manifest").unwrap();
+    let (mut entry, config) = archive.new_file("first.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"first").unwrap();
+    let (_, output) = writer.finish().unwrap();
+    entry.finish(output).unwrap();
+    archive.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    assert_eq!(
+        archive.archive_extra_data().unwrap(),
+        Some(&b"manifest"[..])
+    );
+
+    let mut buffer = vec![0u8; rawzip::RECOMMENDED_BUFFER_SIZE];
+    let archive = ZipArchive::from_file(std::fs::File::open(&path).unwrap(), &mut buffer).unwrap();
+    let mut extra_data = Vec::new();
+    archive
+        .archive_extra_data(&mut buffer)
+        .unwrap()
+        .unwrap()
+        .read_to_end(&mut extra_data)
+        .unwrap();
+    assert_eq!(extra_data, b"manifest");
+
+    // Appended entries go ahead of the record, which is kept
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut archive = ZipArchiveWriter::append(file).unwrap();
+    let (mut entry, config) = archive.new_file("second.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"second").unwrap();
+    let (_, output) = writer.finish().unwrap();
+    entry.finish(output).unwrap();
+    archive.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    let expected = [("first.txt", "first"), ("second.txt", "second")]
+        .map(|(name, data)| (name.to_string(), data.as_bytes().to_vec()));
+    assert_eq!(read_all(&data), expected);
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    assert_eq!(
+        archive.archive_extra_data().unwrap(),
+        Some(&b"manifest"[..])
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_archive_extra_data_absent() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    archive.new_dir("dir/").create().unwrap();
+    archive.finish().unwrap();
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    assert_eq!(archive.archive_extra_data().unwrap(), None);
+
+    // The record may be the only thing ahead of the central directory
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    archive.set_archive_extra_data(b"data").unwrap();
+    archive.finish().unwrap();
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    assert_eq!(archive.archive_extra_data().unwrap(), Some(&b"data"[..]));
+
+    // Entries that end with a data descriptor are skipped over
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    archive.set_archive_extra_data(b"data").unwrap();
+    let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"contents").unwrap();
+    let (_, output_descriptor) = writer.finish().unwrap();
+    entry.finish(output_descriptor).unwrap();
+    archive.finish().unwrap();
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    assert_eq!(archive.archive_extra_data().unwrap(), Some(&b"data"[..]));
+}
+
+#[rstest]
+#[case("test.zip")]
+#[case("winxp.zip")]
+fn test_edit_metadata_in_place(#[case] asset: &str) {
+    let path = temp_copy(asset);
+    let data = std::fs::read(&path).unwrap();
+    let expected = read_all(&data);
+    let name = ZipArchive::from_slice(&data)
+        .unwrap()
+        .entries()
+        .next_entry()
+        .unwrap()
+        .unwrap()
+        .file_path()
+        .as_ref()
+        .to_vec();
+
+    let mtime = UtcDateTime::from_components(2020, 6, 15, 12, 34, 56, 0).unwrap();
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut archive = ZipArchiveWriter::append(file).unwrap();
+    assert!(archive.edit("missing").is_none());
+    let mut entry = archive.edit(&name).unwrap();
+    entry.set_last_modified(mtime);
+    entry.set_unix_permissions(0o100600);
+    entry.set_comment("edited").unwrap();
+    archive.update_local_headers().unwrap();
+    archive.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read_all(&data), expected);
+
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    let entry = archive.entries().next_entry().unwrap().unwrap();
+    assert_eq!(entry.comment().as_bytes(), b"edited");
+    assert_eq!(entry.mode().value(), 0o100600);
+    match entry.last_modified() {
+        ZipDateTimeKind::Utc(time) => assert_eq!(time, mtime),
+        ZipDateTimeKind::Local(time) => {
+            assert_eq!((time.year(), time.month(), time.day()), (2020, 6, 15));
+            assert_eq!((time.hour(), time.minute(), time.second()), (12, 34, 56));
+        }
+    }
+
+    let dos = entry.dos_last_modified();
+    let local = archive.get_entry(entry.wayfinder()).unwrap();
+    let local_offset = entry.local_header_offset() as usize;
+    assert_eq!(
+        data[local_offset + 10..local_offset + 14],
+        [
+            dos.packed_time().to_le_bytes(),
+            dos.packed_date().to_le_bytes()
+        ]
+        .concat()
+    );
+    for (id, field) in local.extra_fields() {
+        if id == ExtraFieldId::EXTENDED_TIMESTAMP && field[0] & 0x01 != 0 {
+            assert_eq!(field[1..5], (mtime.to_unix() as u32).to_le_bytes());
+        }
+    }
+}
+
+#[test]
+fn test_replace_entry() {
+    let path = std::env::temp_dir().join(format!("rawzip-replace-{}.zip", std::process::id()));
+    let mut archive = ZipArchiveWriter::new(std::fs::File::create(&path).unwrap());
+    archive.add_bytes("a.txt", &[b'a'; 100]).unwrap();
+    archive.add_bytes("b.txt", &[b'b'; 100]).unwrap();
+    archive.add_bytes("c.txt", &[b'c'; 100]).unwrap();
+    archive.finish().unwrap();
+    let original = std::fs::read(&path).unwrap();
+
+    let replace = |name: &str, data: &[u8]| {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let mut archive = ZipArchiveWriter::append(file).unwrap();
+        let in_place = archive
+            .replace(name, |archive| {
+                archive.add_bytes(name, data)?;
+                Ok(())
+            })
+            .unwrap();
+        archive.finish().unwrap();
+        in_place
+    };
+
+    // A smaller entry fits where the old one was
+    assert!(replace("a.txt", b"short"));
+    let data = std::fs::read(&path).unwrap();
+    let b_start = original.windows(5).position(|w| w == b"b.txt").unwrap() - 30;
+    let directory = original
+        .windows(4)
+        .position(|w| w == b"PK\x01\x02")
+        .unwrap();
+    assert_eq!(data[b_start..directory], original[b_start..directory]);
+
+    // A larger one moves after the last entry
+    assert!(!replace("b.txt", &[b'B'; 500]));
+    // The last entry is rewritten where it is, whatever its size
+    let large = (0..3000u32)
+        .map(|i| (i * 7919 % 251) as u8)
+        .collect::<Vec<_>>();
+    assert!(replace("b.txt", &large));
+
+    let expected = vec![
+        ("a.txt".to_string(), b"short".to_vec()),
+        ("c.txt".to_string(), vec![b'c'; 100]),
+        ("b.txt".to_string(), large),
+    ];
+    assert_eq!(read_all(&std::fs::read(&path).unwrap()), expected);
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut archive = ZipArchiveWriter::append(file).unwrap();
+    let err = archive
+        .replace("a.txt", |archive| {
+            archive.add_bytes("other.txt", b"")?;
+            Ok(())
+        })
+        .unwrap_err();
+    assert!(err.to_string().contains("same name"), "{}", err);
+    assert!(archive.replace("missing.txt", |_| Ok(())).is_err());
+    archive.finish().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Appends to the archive at `path` with the given builder, replacing `name`
+fn replace_with(
+    path: &std::path::Path,
+    builder: rawzip::ZipArchiveWriterBuilder,
+    setup: impl FnOnce(&mut ZipArchiveWriter<std::fs::File>),
+    write: impl FnOnce(&mut ZipArchiveWriter<Vec<u8>>) -> Result<(), rawzip::Error>,
+    name: &str,
+) {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+    let mut archive = builder.append(file).unwrap();
+    setup(&mut archive);
+    archive.replace(name, write).unwrap();
+    archive.finish().unwrap();
+}
+
+#[test]
+fn test_replace_keeps_archive_options() {
+    let path = std::env::temp_dir().join(format!("rawzip-replace-opts-{}.zip", std::process::id()));
+    let jar = || ZipArchiveWriter::builder().jar_marker(true);
+    let mut archive = jar().build(std::fs::File::create(&path).unwrap());
+    archive.add_bytes("a.txt", b"a.txt").unwrap();
+    archive.add_bytes("b.txt", b"b.txt").unwrap();
+    archive.finish().unwrap();
+
+    // Only the first entry is marked as a JAR, whichever is replaced
+    for name in ["b.txt", "a.txt"] {
+        let write = |archive: &mut ZipArchiveWriter<Vec<u8>>| {
+            archive.add_bytes(name, name.to_uppercase().as_bytes())?;
+            Ok(())
+        };
+        replace_with(&path, jar(), |_| {}, write, name);
+    }
+    let data = std::fs::read(&path).unwrap();
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    let markers = archive
+        .entries()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let cafe = entry
+                .extra_fields()
+                .any(|(id, _)| id == ExtraFieldId::JAVA_JAR);
+            (
+                String::from_utf8(entry.file_path().as_ref().to_vec()).unwrap(),
+                cafe,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        markers,
+        vec![("a.txt".to_string(), true), ("b.txt".to_string(), false)]
+    );
+
+    // The parent directories of the replaced entry are already there
+    let parents = || ZipArchiveWriter::builder().create_parent_directories(true);
+    let mut archive = parents().build(std::fs::File::create(&path).unwrap());
+    archive.add_bytes("a/b.txt", b"old").unwrap();
+    archive.finish().unwrap();
+    let write = |archive: &mut ZipArchiveWriter<Vec<u8>>| {
+        archive.add_bytes("a/b.txt", b"new")?;
+        Ok(())
+    };
+    replace_with(&path, parents(), |_| {}, write, "a/b.txt");
+    let expected = vec![
+        ("a/".to_string(), Vec::new()),
+        ("a/b.txt".to_string(), b"new".to_vec()),
+    ];
+    assert_eq!(read_all(&std::fs::read(&path).unwrap()), expected);
+
+    // The header hook sees the replacement
+    let epoch = UtcDateTime::from_components(1980, 1, 1, 0, 0, 0, 0).unwrap();
+    let hook = |archive: &mut ZipArchiveWriter<std::fs::File>| {
+        archive.on_header(move |header| {
+            header.set_dos_last_modified(epoch);
+            Ok(())
+        })
+    };
+    let write = |archive: &mut ZipArchiveWriter<Vec<u8>>| {
+        let now = UtcDateTime::from_components(2024, 5, 6, 7, 8, 9, 0).unwrap();
+        let (mut entry, config) = archive.new_file("a/b.txt").last_modified(now).start()?;
+        let (_, descriptor) = config.wrap(&mut entry).finish()?;
+        entry.finish(descriptor)?;
+        Ok(())
+    };
+    replace_with(&path, ZipArchiveWriter::builder(), hook, write, "a/b.txt");
+    let data = std::fs::read(&path).unwrap();
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    let entry = archive.entries().nth(1).unwrap().unwrap();
+    assert_eq!(entry.file_path().as_ref(), b"a/b.txt");
+    assert_eq!(entry.dos_last_modified().year(), 1980);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_replace_with_zstd_dictionary() {
+    let path = std::env::temp_dir().join(format!("rawzip-replace-zstd-{}.zip", std::process::id()));
+    let mut archive = ZipArchiveWriter::new(std::fs::File::create(&path).unwrap());
+    archive.add_bytes("a.json", b"{}").unwrap();
+    archive.finish().unwrap();
+
+    let samples = (0..200)
+        .map(|i| format!(r#"{{"id": {i}, "kind": "sample", "tags": ["a", "b"]}}"#))
+        .collect::<Vec<_>>();
+    let dictionary = rawzip::train_zstd_dictionary(&samples, 4096).unwrap();
+    let setup = |archive: &mut ZipArchiveWriter<std::fs::File>| {
+        archive.set_zstd_dictionary(&dictionary).unwrap();
+    };
+    let write = |archive: &mut ZipArchiveWriter<Vec<u8>>| {
+        let mut writer = archive.new_file("a.json").start_zstd(3)?;
+        writer.write_all(samples[1].as_bytes())?;
+        writer.finish()?;
+        Ok(())
+    };
+    replace_with(&path, ZipArchiveWriter::builder(), setup, write, "a.json");
+
+    let data = std::fs::read(&path).unwrap();
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    let dictionary = archive.zstd_dictionary().unwrap().unwrap();
+    let entry = archive
+        .entries()
+        .map(|entry| entry.unwrap())
+        .find(|entry| entry.file_path().as_ref() == b"a.json")
+        .unwrap();
+    assert_eq!(entry.compression_method(), CompressionMethod::Zstd);
+    let entry = archive.get_entry(entry.wayfinder()).unwrap();
+
+    // The data can only be decompressed with the dictionary
+    let mut data = String::new();
+    assert!(zstd::Decoder::new(entry.data())
+        .unwrap()
+        .read_to_string(&mut data)
+        .is_err());
+    let decoder = zstd::Decoder::with_dictionary(entry.data(), dictionary).unwrap();
+    let mut data = String::new();
+    entry
+        .verifying_reader(decoder)
+        .read_to_string(&mut data)
+        .unwrap();
+    assert_eq!(data, samples[1]);
+    std::fs::remove_file(&path).unwrap();
+}
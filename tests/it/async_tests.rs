@@ -0,0 +1,311 @@
+#![cfg(any(feature = "tokio", feature = "futures-io"))]
+
+use rawzip::{AsyncZipArchiveWriter, ZipArchive};
+use std::io::Read;
+
+fn read_entries(output: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(output).unwrap();
+    let mut result = Vec::new();
+    for record in archive.entries() {
+        let record = record.unwrap();
+        let name = String::from_utf8(record.file_path().as_ref().to_vec()).unwrap();
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        entry
+            .verifying_reader(entry.data())
+            .read_to_end(&mut contents)
+            .unwrap();
+        result.push((name, contents));
+    }
+    result
+}
+
+fn sync_archive() -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    std::io::Write::write_all(&mut writer, b"Hello, world!").unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+    archive.finish().unwrap();
+    output
+}
+
+#[cfg(feature = "tokio")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+#[cfg(feature = "tokio")]
+fn test_tokio_writer_roundtrip() {
+    use tokio::io::AsyncWriteExt;
+
+    let output = block_on(async {
+        let mut archive = AsyncZipArchiveWriter::new(Vec::new());
+        archive.new_dir("dir/").create().unwrap();
+        for i in 0..3 {
+            let name = format!("dir/file-{i}.txt");
+            let (mut entry, config) = archive.new_file(&name).start_async().unwrap();
+            let mut writer = config.wrap(&mut entry);
+            for _ in 0..=i {
+                writer.write_all(name.as_bytes()).await.unwrap();
+            }
+            let (_, descriptor) = writer.into_parts();
+            entry.finish(descriptor).await.unwrap();
+        }
+
+        let (entry, _) = archive.new_file("aborted.txt").start_async().unwrap();
+        entry.abort().await.unwrap();
+        archive.flush().await.unwrap();
+        archive.finish().await.unwrap()
+    });
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0], (String::from("dir/"), Vec::new()));
+    for i in 0..3 {
+        let name = format!("dir/file-{i}.txt");
+        let contents = name.repeat(i + 1).into_bytes();
+        assert_eq!(entries[i + 1], (name, contents));
+    }
+}
+
+#[test]
+#[cfg(feature = "tokio")]
+fn test_tokio_writer_matches_sync_writer() {
+    use tokio::io::AsyncWriteExt;
+
+    let output = block_on(async {
+        let mut archive = AsyncZipArchiveWriter::new(Vec::new());
+        let (mut entry, config) = archive.new_file("file.txt").start_async().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello, world!").await.unwrap();
+        let (_, descriptor) = writer.into_parts();
+        entry.finish(descriptor).await.unwrap();
+        archive.finish().await.unwrap()
+    });
+
+    assert_eq!(output, sync_archive());
+}
+
+#[test]
+#[cfg(feature = "futures-io")]
+fn test_futures_writer_matches_sync_writer() {
+    use futures::io::AsyncWriteExt;
+    use rawzip::FuturesAsyncWriter;
+
+    let output = futures::executor::block_on(async {
+        let output = FuturesAsyncWriter::new(futures::io::Cursor::new(Vec::new()));
+        let mut archive = AsyncZipArchiveWriter::new(output);
+        let (mut entry, config) = archive.new_file("file.txt").start_async().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello, world!").await.unwrap();
+        let (_, descriptor) = writer.into_parts();
+        entry.finish(descriptor).await.unwrap();
+        archive.finish().await.unwrap()
+    });
+
+    let output = output.into_inner().into_inner();
+    assert_eq!(output, sync_archive());
+    assert_eq!(
+        read_entries(&output),
+        vec![(String::from("file.txt"), b"Hello, world!".to_vec())]
+    );
+}
+
+/// Reads each entry's name, compressed data, and verification through the
+/// async reader, which is expected to match the sync one.
+#[cfg(feature = "tokio")]
+fn compare_async_reader(data: Vec<u8>) {
+    use rawzip::AsyncZipArchive;
+    use tokio::io::AsyncReadExt;
+
+    let expected = ZipArchive::from_slice(&data).unwrap();
+    let expected_entries = expected
+        .entries()
+        .map(|record| {
+            let record = record.unwrap();
+            let entry = expected.get_entry(record.wayfinder()).unwrap();
+            (
+                record.file_path().as_ref().to_vec(),
+                entry.data().to_vec(),
+                entry.claim_verifier(),
+                entry.compressed_data_range(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let entries = block_on(async {
+        let mut archive = AsyncZipArchive::from_reader(std::io::Cursor::new(data.clone()))
+            .await
+            .unwrap();
+        assert_eq!(archive.entries_hint(), expected.entries_hint());
+        assert_eq!(archive.comment().as_bytes(), expected.comment().as_bytes());
+        assert_eq!(archive.directory_offset(), expected.directory_offset());
+        assert_eq!(archive.eocd_offset(), expected.eocd_offset());
+
+        let records = archive
+            .entries()
+            .map(|record| {
+                let record = record.unwrap();
+                (record.file_path().as_ref().to_vec(), record.wayfinder())
+            })
+            .collect::<Vec<_>>();
+
+        let mut entries = Vec::new();
+        for (name, wayfinder) in records {
+            let mut entry = archive.get_entry(wayfinder).await.unwrap();
+            let verification = entry.claim_verifier();
+            let range = entry.compressed_data_range();
+            let mut compressed = Vec::new();
+            entry.read_to_end(&mut compressed).await.unwrap();
+            entries.push((name, compressed, verification, range));
+        }
+        entries
+    });
+
+    assert!(!entries.is_empty());
+    assert_eq!(entries, expected_entries);
+}
+
+#[rstest::rstest]
+#[case("test.zip")]
+#[case("zip64.zip")]
+#[case("test-prefix.zip")]
+#[case("go-with-datadesc-sig.zip")]
+#[case("time-winzip.zip")]
+#[cfg(feature = "tokio")]
+fn test_tokio_reader_matches_sync(#[case] asset: &str) {
+    compare_async_reader(std::fs::read(format!("assets/{}", asset)).unwrap());
+}
+
+#[test]
+#[cfg(feature = "tokio")]
+fn test_tokio_reader_large_directory() {
+    // A directory that doesn't fit in what is first read of the end, and
+    // trailing data that pushes the end of central directory further back
+    let mut output = Vec::new();
+    let mut archive = rawzip::ZipArchiveWriter::new(&mut output);
+    for i in 0..2000 {
+        let name = format!("{}/file-{:04}.txt", "dir".repeat(20), i);
+        let (mut entry, config) = archive.new_file(&name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        std::io::Write::write_all(&mut writer, name.as_bytes()).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+    }
+    archive.finish().unwrap();
+    output.extend_from_slice(&[0u8; 100_000]);
+    compare_async_reader(output);
+}
+
+#[test]
+#[cfg(feature = "tokio")]
+fn test_tokio_verifying_reader() {
+    use rawzip::AsyncZipArchive;
+    use tokio::io::AsyncReadExt;
+
+    let mut output = sync_archive();
+    let read = |data: Vec<u8>| {
+        block_on(async {
+            let mut archive = AsyncZipArchive::from_reader(std::io::Cursor::new(data))
+                .await
+                .unwrap();
+            let wayfinder = archive.entries().next_entry().unwrap().unwrap().wayfinder();
+            let entry = archive.get_entry(wayfinder).await.unwrap();
+            let mut contents = Vec::new();
+            entry
+                .verifying_reader(|data| data)
+                .read_to_end(&mut contents)
+                .await
+                .map(|_| contents)
+        })
+    };
+
+    assert_eq!(read(output.clone()).unwrap(), b"Hello, world!");
+
+    let position = output.windows(5).position(|x| x == b"Hello").unwrap();
+    output[position] = b'J';
+    let err = read(output).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[rstest::rstest]
+#[case("test.zip")]
+#[case("zip64.zip")]
+#[case("test-prefix.zip")]
+#[cfg(feature = "futures-io")]
+fn test_futures_reader_matches_sync(#[case] asset: &str) {
+    use futures::io::AsyncReadExt;
+    use rawzip::{AsyncZipArchive, FuturesAsyncReader};
+
+    let data = std::fs::read(format!("assets/{}", asset)).unwrap();
+    let expected = ZipArchive::from_slice(&data).unwrap();
+    let expected_entries = expected
+        .entries()
+        .map(|record| {
+            let record = record.unwrap();
+            let entry = expected.get_entry(record.wayfinder()).unwrap();
+            (entry.data().to_vec(), entry.claim_verifier())
+        })
+        .collect::<Vec<_>>();
+
+    let entries = futures::executor::block_on(async {
+        let input = FuturesAsyncReader::new(futures::io::Cursor::new(data.clone()));
+        let mut archive = AsyncZipArchive::from_reader(input).await.unwrap();
+        assert_eq!(archive.comment().as_bytes(), expected.comment().as_bytes());
+
+        let wayfinders = archive
+            .entries()
+            .map(|record| record.unwrap().wayfinder())
+            .collect::<Vec<_>>();
+
+        let mut entries = Vec::new();
+        for wayfinder in wayfinders {
+            let mut entry = archive.get_entry(wayfinder).await.unwrap();
+            let verification = entry.claim_verifier();
+            let mut compressed = Vec::new();
+            entry.read_to_end(&mut compressed).await.unwrap();
+            entries.push((compressed, verification));
+        }
+        entries
+    });
+
+    assert!(!entries.is_empty());
+    assert_eq!(entries, expected_entries);
+}
+
+#[test]
+#[cfg(feature = "futures-io")]
+fn test_futures_verifying_reader() {
+    use futures::io::AsyncReadExt;
+    use rawzip::{AsyncZipArchive, FuturesAsyncReader};
+
+    let mut output = sync_archive();
+    let read = |data: Vec<u8>| {
+        futures::executor::block_on(async {
+            let input = FuturesAsyncReader::new(futures::io::Cursor::new(data));
+            let mut archive = AsyncZipArchive::from_reader(input).await.unwrap();
+            let wayfinder = archive.entries().next_entry().unwrap().unwrap().wayfinder();
+            let entry = archive.get_entry(wayfinder).await.unwrap();
+            let mut contents = Vec::new();
+            entry
+                .verifying_reader(|data| data)
+                .read_to_end(&mut contents)
+                .await
+                .map(|_| contents)
+        })
+    };
+
+    assert_eq!(read(output.clone()).unwrap(), b"Hello, world!");
+
+    let position = output.windows(5).position(|x| x == b"Hello").unwrap();
+    output[position] = b'J';
+    let err = read(output).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "bzip2")]
+
+use rawzip::{CompressionMethod, ZipArchive, ZipArchiveWriter};
+use std::io::{Read, Write};
+
+fn read_entries(data: &[u8]) -> Vec<(String, CompressionMethod, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = String::from_utf8(entry.file_path().as_ref().to_vec()).unwrap();
+        let method = entry.compression_method();
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents)
+                .unwrap(),
+            CompressionMethod::Bzip2 => entry
+                .verifying_reader(bzip2::read::BzDecoder::new(entry.data()))
+                .read_to_end(&mut contents)
+                .unwrap(),
+            _ => panic!("unexpected compression method: {:?}", method),
+        };
+        result.push((name, method, contents));
+    }
+    result
+}
+
+#[test]
+fn test_bzip2_roundtrip() {
+    let data = b"Hello, bzip2! ".repeat(1000);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut writer = archive.new_file("started.txt").start_bzip2(9).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    let mut writer = archive
+        .new_file("compressed.txt")
+        .compression_method(CompressionMethod::Bzip2)
+        .compression_level(1)
+        .start_compressed()
+        .unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    archive
+        .new_file("queued.txt")
+        .compression_method(CompressionMethod::Bzip2)
+        .queue_compressed(data.clone())
+        .unwrap();
+    archive.finish().unwrap();
+
+    // bzip2 needs version 4.6 to extract
+    assert_eq!(u16::from_le_bytes([output[4], output[5]]), 46);
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 3);
+    for (name, method, contents) in entries {
+        assert_eq!(method, CompressionMethod::Bzip2, "{name}");
+        assert_eq!(contents, data, "{name}");
+    }
+}
+
+#[test]
+fn test_bzip2_wrap_config() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let (mut entry, config) = archive
+        .new_file("file.txt")
+        .compression_method(CompressionMethod::Bzip2)
+        .compression_level(5)
+        .start()
+        .unwrap();
+    let level = config.compression_level().unwrap() as u32;
+    let encoder = bzip2::write::BzEncoder::new(&mut entry, bzip2::Compression::new(level));
+    let mut writer = config.wrap(encoder);
+    writer.write_all(b"Hello, world!").unwrap();
+    let (encoder, descriptor) = writer.finish().unwrap();
+    encoder.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(
+        entries,
+        vec![(
+            String::from("file.txt"),
+            CompressionMethod::Bzip2,
+            b"Hello, world!".to_vec()
+        )]
+    );
+}
+
+#[test]
+fn test_bzip2_transcode() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    let mut writer = archive.new_file("file.txt").start_bzip2(9).unwrap();
+    writer.write_all(b"Hello, world!").unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(source).unwrap().into_zip_archive();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::transcode(&source, &mut archive, |_| CompressionMethod::Store).unwrap();
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries[0].1, CompressionMethod::Store);
+    assert_eq!(entries[0].2, b"Hello, world!");
+    assert_eq!(u16::from_le_bytes([output[4], output[5]]), 20);
+}
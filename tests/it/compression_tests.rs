@@ -0,0 +1,469 @@
+#![cfg(any(feature = "deflate", feature = "zstd"))]
+
+use rawzip::{CompressionMethod, ZipArchive, ZipArchiveWriter};
+use std::io::{Read, Write};
+
+fn read_entries(data: &[u8]) -> Vec<(String, CompressionMethod, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = entry
+            .file_path()
+            .try_normalize()
+            .unwrap()
+            .as_ref()
+            .to_string();
+        let method = entry.compression_method();
+        let wayfinder = entry.wayfinder();
+        let entry = archive.get_entry(wayfinder).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => {
+                let mut reader = entry.verifying_reader(entry.data());
+                reader.read_to_end(&mut contents).unwrap();
+            }
+            CompressionMethod::Deflate => {
+                let decoder = flate2::read::DeflateDecoder::new(entry.data());
+                let mut reader = entry.verifying_reader(decoder);
+                reader.read_to_end(&mut contents).unwrap();
+            }
+            CompressionMethod::Zstd => {
+                let decoder = zstd::Decoder::new(entry.data()).unwrap();
+                let mut reader = entry.verifying_reader(decoder);
+                reader.read_to_end(&mut contents).unwrap();
+            }
+            _ => panic!("unexpected compression method: {:?}", method),
+        }
+        result.push((name, method, contents));
+    }
+    result
+}
+
+#[test]
+#[cfg(feature = "deflate")]
+fn test_start_deflate_roundtrip() {
+    let data = b"Hello, world! ".repeat(100);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+
+    let mut writer = archive.new_file("hello.txt").start_deflate(6).unwrap();
+    writer.write_all(&data).unwrap();
+    let compressed = writer.finish().unwrap();
+    assert!(compressed < data.len() as u64);
+
+    let mut writer = archive.new_file("fast.txt").start_deflate(0).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 2);
+    for (name, method, contents) in entries {
+        assert_eq!(method, CompressionMethod::Deflate, "{}", name);
+        assert_eq!(contents, data, "{}", name);
+    }
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_start_zstd_roundtrip() {
+    let data = b"Hello, zstd! ".repeat(100);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+
+    let mut writer = archive.new_file("hello.txt").start_zstd(3).unwrap();
+    writer.write_all(&data).unwrap();
+    let compressed = writer.finish().unwrap();
+    assert!(compressed < data.len() as u64);
+
+    let writer = archive.new_file("empty.txt").start_zstd(0).unwrap();
+    writer.finish().unwrap();
+
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].0, "hello.txt");
+    assert_eq!(entries[0].1, CompressionMethod::Zstd);
+    assert_eq!(entries[0].2, data);
+    assert_eq!(entries[1].0, "empty.txt");
+    assert_eq!(entries[1].1, CompressionMethod::Zstd);
+    assert!(entries[1].2.is_empty());
+}
+
+#[test]
+fn test_start_compressed_uses_method_and_level() {
+    let data = b"The quick brown fox jumps over the lazy dog. ".repeat(50);
+    let mut methods = vec![CompressionMethod::Store];
+    if cfg!(feature = "deflate") {
+        methods.push(CompressionMethod::Deflate);
+    }
+    if cfg!(feature = "zstd") {
+        methods.push(CompressionMethod::Zstd);
+    }
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for (i, method) in methods.iter().enumerate() {
+        let name = format!("file-{}.txt", i);
+        let mut writer = archive
+            .new_file(&name)
+            .compression_method(*method)
+            .compression_level(1)
+            .start_compressed()
+            .unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let err = archive
+        .new_file("unsupported.txt")
+        .compression_method(CompressionMethod::Ppmd)
+        .start_compressed()
+        .unwrap_err();
+    assert!(matches!(err.kind(), rawzip::ErrorKind::InvalidInput { .. }));
+
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), methods.len());
+    for ((_, method, contents), expected) in entries.iter().zip(methods.iter()) {
+        assert_eq!(method, expected);
+        assert_eq!(contents, &data);
+    }
+}
+
+#[test]
+fn test_add_file_from_reader() {
+    let data = b"The quick brown fox jumps over the lazy dog. ".repeat(50);
+    let method = if cfg!(feature = "deflate") {
+        CompressionMethod::Deflate
+    } else {
+        CompressionMethod::Zstd
+    };
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let compressed = archive
+        .add_file("file.txt", data.as_slice(), method)
+        .unwrap();
+    assert!(compressed < data.len() as u64);
+    archive
+        .new_file("dir/file.txt")
+        .compression_method(method)
+        .compression_level(1)
+        .copy_from(std::io::Cursor::new(&data))
+        .unwrap();
+
+    let err = archive
+        .add_file("unsupported.txt", data.as_slice(), CompressionMethod::Ppmd)
+        .unwrap_err();
+    assert!(matches!(err.kind(), rawzip::ErrorKind::InvalidInput { .. }));
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0], (String::from("file.txt"), method, data.clone()));
+    assert_eq!(entries[1], (String::from("dir/file.txt"), method, data));
+}
+
+#[test]
+fn test_store_if_larger() {
+    // Pseudo random bytes that no codec can shrink
+    let mut state = 0x2545_f491_u32;
+    let noise: Vec<u8> = (0..4096)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let text = b"compress me please ".repeat(200);
+
+    let method = if cfg!(feature = "deflate") {
+        CompressionMethod::Deflate
+    } else {
+        CompressionMethod::Zstd
+    };
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for (name, data) in [("noise.bin", &noise), ("text.txt", &text)] {
+        let mut writer = archive
+            .new_file(name)
+            .compression_method(method)
+            .store_if_larger(true)
+            .start_compressed()
+            .unwrap();
+        writer.write_all(data).unwrap();
+        let compressed = writer.finish().unwrap();
+        assert!(compressed <= data.len() as u64);
+    }
+
+    let writer = archive
+        .new_file("empty.txt")
+        .compression_method(method)
+        .store_if_larger(true)
+        .start_compressed()
+        .unwrap();
+    assert_eq!(writer.finish().unwrap(), 0);
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries[0].0, "noise.bin");
+    assert_eq!(entries[0].1, CompressionMethod::Store);
+    assert_eq!(entries[0].2, noise);
+    assert_eq!(entries[1].0, "text.txt");
+    assert_eq!(entries[1].1, method);
+    assert_eq!(entries[1].2, text);
+    assert_eq!(entries[2].1, CompressionMethod::Store);
+    assert!(entries[2].2.is_empty());
+}
+
+#[test]
+fn test_best_method() {
+    let mut state = 0x2545_f491_u32;
+    let noise: Vec<u8> = (0..4096)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let text = b"compress me please ".repeat(200);
+
+    let method = if cfg!(feature = "deflate") {
+        CompressionMethod::Deflate
+    } else {
+        CompressionMethod::Zstd
+    };
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for (name, method, data) in [
+        ("noise.bin", method, &noise),
+        ("text.txt", CompressionMethod::Store, &text),
+    ] {
+        let mut writer = archive
+            .new_file(name)
+            .compression_method(method)
+            .best_method(1 << 20)
+            .start_compressed()
+            .unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Data that outgrows the maximum is written with the configured method
+    let mut writer = archive
+        .new_file("large.txt")
+        .compression_method(method)
+        .best_method(1000)
+        .start_compressed()
+        .unwrap();
+    for chunk in text.chunks(300) {
+        writer.write_all(chunk).unwrap();
+    }
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries[0].1, CompressionMethod::Store);
+    assert_eq!(entries[0].2, noise);
+    assert_ne!(entries[1].1, CompressionMethod::Store);
+    assert_eq!(entries[1].2, text);
+    assert_eq!(entries[2].1, method);
+    assert_eq!(entries[2].2, text);
+}
+
+#[test]
+fn test_queue_compressed_preserves_order() {
+    let method = if cfg!(feature = "deflate") {
+        CompressionMethod::Deflate
+    } else {
+        CompressionMethod::Zstd
+    };
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::builder()
+        .with_compression_threads(3)
+        .build(&mut output);
+    let mut expected = Vec::new();
+    for i in 0..50 {
+        let name = format!("file-{i:02}.txt");
+        let data = format!("entry {i} ").repeat(i * 100).into_bytes();
+        archive
+            .new_file(&name)
+            .compression_method(method)
+            .queue_compressed(data.clone())
+            .unwrap();
+        expected.push((name, data));
+
+        // Interleave an entry that must wait for the queue
+        if i == 25 {
+            archive.new_dir("middle/").create().unwrap();
+            expected.push((String::from("middle/"), Vec::new()));
+        }
+    }
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), expected.len());
+    for ((name, method_used, data), (expected_name, expected_data)) in entries.iter().zip(&expected)
+    {
+        assert_eq!(name, expected_name);
+        assert_eq!(data, expected_data);
+        if name.starts_with("file-") && !data.is_empty() {
+            assert_eq!(*method_used, method);
+        }
+    }
+}
+
+#[test]
+fn test_queue_compressed_unsupported_method() {
+    let mut archive = ZipArchiveWriter::new(Vec::new());
+    let err = archive
+        .new_file("file.bin")
+        .compression_method(CompressionMethod::Ppmd)
+        .queue_compressed(vec![1, 2, 3])
+        .unwrap_err();
+    assert!(matches!(err.kind(), rawzip::ErrorKind::InvalidInput { .. }));
+}
+
+#[test]
+#[cfg(feature = "deflate")]
+fn test_start_deflate_parallel_roundtrip() {
+    let text: Vec<u8> = (0..3_500_000u32)
+        .map(|i| b"abcdefghij"[(i * 7 % 13 % 10) as usize])
+        .collect();
+    let exact = vec![b'z'; 2 * 1024 * 1024];
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::builder()
+        .with_compression_threads(4)
+        .build(&mut output);
+    for (name, data) in [
+        ("text.txt", &text[..]),
+        ("exact.bin", &exact),
+        ("empty", &[]),
+    ] {
+        let mut writer = archive.new_file(name).start_deflate_parallel(6).unwrap();
+        for chunk in data.chunks(100_000) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].1, CompressionMethod::Deflate);
+    assert_eq!(entries[0].2, text);
+    assert_eq!(entries[1].2, exact);
+    assert!(entries[2].2.is_empty());
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_zstd_dictionary() {
+    let samples = (0..500)
+        .map(|i| {
+            format!(
+                r#"{{"id": {i}, "name": "entry number {i}", "kind": "sample", "tags": ["alpha", "beta", "gamma"], "enabled": {}}}"#,
+                i % 2 == 0
+            )
+        })
+        .collect::<Vec<_>>();
+    let dictionary = rawzip::train_zstd_dictionary(&samples, 4096).unwrap();
+
+    let write = |dictionary: Option<&[u8]>| {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        if let Some(dictionary) = dictionary {
+            archive.set_zstd_dictionary(dictionary).unwrap();
+            assert!(archive.set_zstd_dictionary(dictionary).is_err());
+        }
+
+        for (i, sample) in samples.iter().enumerate().take(100) {
+            let name = format!("{i}.json");
+            match i % 3 {
+                0 => {
+                    let mut writer = archive.new_file(&name).start_zstd(3).unwrap();
+                    writer.write_all(sample.as_bytes()).unwrap();
+                    writer.finish().unwrap();
+                }
+                1 => archive
+                    .new_file(&name)
+                    .compression_method(CompressionMethod::Zstd)
+                    .queue_compressed(sample.as_bytes().to_vec())
+                    .unwrap(),
+                _ => {
+                    let mut writer = archive
+                        .new_file(&name)
+                        .compression_method(CompressionMethod::Zstd)
+                        .store_if_larger(true)
+                        .start_compressed()
+                        .unwrap();
+                    writer.write_all(sample.as_bytes()).unwrap();
+                    writer.finish().unwrap();
+                }
+            }
+        }
+        archive.finish().unwrap();
+        output
+    };
+
+    let plain = write(None);
+    let archive = ZipArchive::from_slice(&plain).unwrap();
+    assert_eq!(archive.zstd_dictionary().unwrap(), None);
+
+    let output = write(Some(&dictionary));
+    let compressed = |data: &[u8]| -> u64 {
+        let archive = ZipArchive::from_slice(data).unwrap();
+        archive
+            .entries()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_path().as_ref() != rawzip::ZSTD_DICTIONARY_NAME.as_bytes())
+            .map(|entry| entry.compressed_size_hint())
+            .sum()
+    };
+    assert!(compressed(&output) < compressed(&plain));
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    assert_eq!(archive.zstd_dictionary().unwrap(), Some(&dictionary[..]));
+    let mut entries = archive.entries();
+    entries.next_entry().unwrap();
+    for (i, entry) in entries.enumerate() {
+        let entry = entry.unwrap();
+        let method = entry.compression_method();
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let mut contents = String::new();
+        match method {
+            CompressionMethod::Zstd => {
+                let decoder = zstd::Decoder::with_dictionary(entry.data(), &dictionary).unwrap();
+                entry
+                    .verifying_reader(decoder)
+                    .read_to_string(&mut contents)
+                    .unwrap();
+            }
+            _ => {
+                entry
+                    .verifying_reader(entry.data())
+                    .read_to_string(&mut contents)
+                    .unwrap();
+            }
+        }
+        assert_eq!(contents, samples[i]);
+    }
+
+    let mut buffer = vec![0u8; rawzip::RECOMMENDED_BUFFER_SIZE];
+    let archive = ZipArchive::from_seekable(std::io::Cursor::new(&output), &mut buffer).unwrap();
+    assert_eq!(
+        archive.zstd_dictionary(&mut buffer).unwrap(),
+        Some(dictionary)
+    );
+}
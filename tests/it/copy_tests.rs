@@ -0,0 +1,227 @@
+use rawzip::{CompressionMethod, ZipArchive, ZipArchiveWriter, RECOMMENDED_BUFFER_SIZE};
+use rstest::rstest;
+use std::fs::File;
+use std::io::{Read, Write};
+
+fn entry_contents(data: &[u8]) -> Vec<(Vec<u8>, CompressionMethod, u32, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = entry.file_path().as_ref().to_vec();
+        let method = entry.compression_method();
+        let mode = entry.mode().value();
+        let wayfinder = entry.wayfinder();
+        let entry = archive.get_entry(wayfinder).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => {
+                entry
+                    .verifying_reader(entry.data())
+                    .read_to_end(&mut contents)
+                    .unwrap();
+            }
+            CompressionMethod::Deflate => {
+                let decoder = flate2::read::DeflateDecoder::new(entry.data());
+                entry
+                    .verifying_reader(decoder)
+                    .read_to_end(&mut contents)
+                    .unwrap();
+            }
+            _ => panic!("unexpected compression method"),
+        }
+        result.push((name, method, mode, contents));
+    }
+    result
+}
+
+#[rstest]
+#[case("test.zip")]
+#[case("winxp.zip")]
+#[case("symlink.zip")]
+#[case("unix.zip")]
+#[case("time-infozip.zip")]
+#[case("go-with-datadesc-sig.zip")]
+fn test_copy_entries_from_file(#[case] name: &str) {
+    let path = format!("assets/{}", name);
+    let mut buf = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+    let source = ZipArchive::from_file(File::open(&path).unwrap(), &mut buf).unwrap();
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut entries = source.entries(&mut buf);
+    while let Some(record) = entries.next_entry().unwrap() {
+        let entry = source.get_entry(record.wayfinder()).unwrap();
+        let copied = archive.copy_entry(&record, entry.reader()).unwrap();
+        assert_eq!(copied, record.compressed_size_hint());
+    }
+    archive.finish().unwrap();
+
+    let original = std::fs::read(&path).unwrap();
+    assert_eq!(entry_contents(&output), entry_contents(&original));
+
+    // Metadata from the central directory is preserved
+    let original = ZipArchive::from_slice(&original).unwrap();
+    let copied = ZipArchive::from_slice(&output).unwrap();
+    for (a, b) in original.entries().zip(copied.entries()) {
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.last_modified(), b.last_modified());
+        assert_eq!(a.crc32(), b.crc32());
+        assert_eq!(a.uncompressed_size_hint(), b.uncompressed_size_hint());
+        assert!(!b.has_data_descriptor());
+        let a_fields: Vec<_> = a.extra_fields().collect();
+        let b_fields: Vec<_> = b.extra_fields().collect();
+        assert_eq!(a_fields, b_fields);
+    }
+}
+
+#[test]
+fn test_copy_entry_filters_and_mixes_with_new_entries() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        let (mut entry, config) = archive
+            .new_file(name)
+            .compression_method(CompressionMethod::Deflate)
+            .unix_permissions(0o755)
+            .start()
+            .unwrap();
+        let encoder =
+            flate2::write::DeflateEncoder::new(&mut entry, flate2::Compression::default());
+        let mut writer = config.wrap(encoder);
+        writer.write_all(name.repeat(100).as_bytes()).unwrap();
+        let (encoder, output) = writer.finish().unwrap();
+        encoder.finish().unwrap();
+        entry.finish(output).unwrap();
+    }
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(&source).unwrap();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for record in source.entries() {
+        let record = record.unwrap();
+        if record.file_path().as_ref() == b"b.txt" {
+            continue;
+        }
+        let entry = source.get_entry(record.wayfinder()).unwrap();
+        archive.copy_entry(&record, entry.data()).unwrap();
+    }
+
+    let (mut entry, config) = archive.new_file("new.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"new").unwrap();
+    let (_, output_desc) = writer.finish().unwrap();
+    entry.finish(output_desc).unwrap();
+    archive.finish().unwrap();
+
+    let entries = entry_contents(&output);
+    let names: Vec<_> = entries.iter().map(|(name, ..)| name.as_slice()).collect();
+    assert_eq!(names, vec![&b"a.txt"[..], b"c.txt", b"new.txt"]);
+    assert_eq!(entries[0].1, CompressionMethod::Deflate);
+    assert_eq!(entries[0].2, 0o100755);
+    assert_eq!(entries[0].3, "a.txt".repeat(100).as_bytes());
+    assert_eq!(entries[2].3, b"new");
+}
+
+#[test]
+fn test_copy_entry_truncated_data() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    let (mut entry, config) = archive.new_file("a.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"hello world").unwrap();
+    let (_, desc) = writer.finish().unwrap();
+    entry.finish(desc).unwrap();
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(&source).unwrap();
+    let record = source.entries().next().unwrap().unwrap();
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let err = archive.copy_entry(&record, &b"hello"[..]).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        rawzip::ErrorKind::InvalidSize {
+            expected: 11,
+            actual: 5
+        }
+    ));
+}
+
+#[test]
+fn test_copy_entry_with_new_name_and_extra_fields() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::builder()
+        .with_unicode_path_field(true)
+        .build(&mut source);
+    let (mut entry, config) = archive
+        .new_file("caf\u{e9}.txt")
+        .unix_permissions(0o640)
+        .extra_field(0xcafe_u16, b"original", rawzip::Header::CENTRAL)
+        .unwrap()
+        .extra_field(0xbeef_u16, b"dropped", rawzip::Header::CENTRAL)
+        .unwrap()
+        .start()
+        .unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"contents").unwrap();
+    let (_, desc) = writer.finish().unwrap();
+    entry.finish(desc).unwrap();
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(&source).unwrap();
+    let record = source.entries().next().unwrap().unwrap();
+    let ids = record
+        .extra_fields()
+        .map(|(id, _)| id.as_u16())
+        .collect::<Vec<_>>();
+    assert!(ids.contains(&0x7075));
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let entry = source.get_entry(record.wayfinder()).unwrap();
+    let name = "vendor\\caf\u{e9}.txt";
+    archive
+        .copy_entry_with(&record)
+        .name(name)
+        .map_extra_fields(|id, data| match id.as_u16() {
+            0xcafe => Some(b"rewritten".to_vec()),
+            0xbeef => None,
+            _ => Some(data.to_vec()),
+        })
+        .copy(entry.data())
+        .unwrap();
+    archive.copy_entry(&record, entry.data()).unwrap();
+    archive.finish().unwrap();
+
+    let entries = entry_contents(&output);
+    assert_eq!(entries[0].0, "vendor/caf\u{e9}.txt".as_bytes());
+    assert_eq!(entries[0].2, 0o100640);
+    assert_eq!(entries[0].3, b"contents");
+    assert_eq!(entries[1].3, b"contents");
+
+    let copied = ZipArchive::from_slice(&output).unwrap();
+    let mut records = copied.entries();
+    let renamed = records.next_entry().unwrap().unwrap();
+    assert_eq!(
+        renamed.file_path().try_normalize().unwrap().as_ref(),
+        "vendor/caf\u{e9}.txt"
+    );
+    let fields = renamed
+        .extra_fields()
+        .map(|(id, data)| (id.as_u16(), data.to_vec()))
+        .collect::<Vec<_>>();
+    assert!(fields.contains(&(0xcafe, b"rewritten".to_vec())));
+    assert!(!fields.iter().any(|(id, _)| *id == 0xbeef || *id == 0x7075));
+
+    let unchanged = records.next_entry().unwrap().unwrap();
+    assert_eq!(unchanged.file_path().as_ref(), b"caf\x82.txt");
+    assert!(unchanged
+        .extra_fields()
+        .any(|(id, _)| id.as_u16() == 0x7075));
+    assert!(unchanged
+        .extra_fields()
+        .any(|(id, _)| id.as_u16() == 0xbeef));
+}
@@ -1,4 +1,8 @@
-use rawzip::{extra_fields::ExtraFieldId, Header, ZipArchive, ZipArchiveWriter, ZipLocator};
+use rawzip::{
+    extra_fields::{ExtraFieldId, NtfsTimestamps, Os2ExtendedAttributes, PkwareUnix},
+    time::{UtcDateTime, ZipDateTimeKind},
+    Header, ZipArchive, ZipArchiveWriter, ZipLocator,
+};
 use std::io::{Cursor, Write};
 
 #[test]
@@ -204,3 +208,64 @@ fn test_extra_field_deduplication_behavior() {
     assert_eq!(central_instances[1], b"second");
     assert_eq!(central_instances[2], b"third");
 }
+
+#[test]
+fn test_legacy_metadata_fields() {
+    let modified = UtcDateTime::from_components(2001, 2, 3, 4, 5, 6, 700_000_000).unwrap();
+    let accessed = UtcDateTime::from_unix(1_000_000_000);
+    let attributes = b"\x00\x00\x00\x00\x06\x00\x0c\x00.LONGNAMEreadme";
+    let unix = PkwareUnix::new(accessed, accessed, 500, 20).with_data(b"target");
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    archive
+        .new_dir("os2/")
+        .extra_field(
+            ExtraFieldId::OS2,
+            &Os2ExtendedAttributes::stored(attributes).to_bytes(),
+            Header::default(),
+        )
+        .unwrap()
+        .ntfs_times(modified, accessed, accessed)
+        .create()
+        .unwrap();
+    archive
+        .new_dir("unix/")
+        .extra_field(ExtraFieldId::UNIX, &unix.to_bytes(), Header::default())
+        .unwrap()
+        .create()
+        .unwrap();
+    archive.finish().unwrap();
+
+    // Repacking keeps the fields
+    let source = ZipArchive::from_slice(&output).unwrap();
+    let mut repacked = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut repacked);
+    for entry in source.entries() {
+        archive
+            .copy_entry(&entry.unwrap(), std::io::empty())
+            .unwrap();
+    }
+    archive.finish().unwrap();
+
+    let archive = ZipArchive::from_slice(&repacked).unwrap();
+    let mut entries = archive.entries();
+    let entry = entries.next_entry().unwrap().unwrap();
+    let field = |id| entry.extra_fields().find(|(i, _)| *i == id).unwrap().1;
+    let ea = Os2ExtendedAttributes::parse(field(ExtraFieldId::OS2)).unwrap();
+    assert_eq!(ea.attributes(), Some(&attributes[..]));
+    let times = NtfsTimestamps::parse(field(ExtraFieldId::NTFS)).unwrap();
+    assert_eq!(times.modified(), modified);
+    assert_eq!(times.accessed(), accessed);
+    assert_eq!(times.created(), accessed);
+    assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(modified));
+
+    let entry = entries.next_entry().unwrap().unwrap();
+    let field = entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::UNIX)
+        .unwrap()
+        .1;
+    assert_eq!(PkwareUnix::parse(field), Some(unix));
+    assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(accessed));
+}
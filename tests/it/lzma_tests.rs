@@ -0,0 +1,119 @@
+#![cfg(feature = "lzma")]
+
+use rawzip::{CompressionMethod, ZipArchive, ZipArchiveWriter};
+use std::io::{Read, Write};
+
+/// Decodes the zip flavor of LZMA by swapping its header for the `.lzma`
+/// one that liblzma understands.
+fn lzma_reader(data: &[u8]) -> impl Read + '_ {
+    assert_eq!(&data[2..4], &5u16.to_le_bytes(), "properties size");
+    let mut header = data[4..9].to_vec();
+    header.extend_from_slice(&u64::MAX.to_le_bytes());
+    let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX).unwrap();
+    xz2::read::XzDecoder::new_stream(std::io::Cursor::new(header).chain(&data[9..]), stream)
+}
+
+fn read_entries(data: &[u8]) -> Vec<(String, CompressionMethod, u16, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = String::from_utf8(entry.file_path().as_ref().to_vec()).unwrap();
+        let method = entry.compression_method();
+        let offset = entry.local_header_offset() as usize;
+        let flags = u16::from_le_bytes([data[offset + 6], data[offset + 7]]);
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents)
+                .unwrap(),
+            CompressionMethod::Lzma => entry
+                .verifying_reader(lzma_reader(entry.data()))
+                .read_to_end(&mut contents)
+                .unwrap(),
+            _ => panic!("unexpected compression method: {:?}", method),
+        };
+        result.push((name, method, flags, contents));
+    }
+    result
+}
+
+#[test]
+fn test_lzma_roundtrip() {
+    let data = b"Hello, lzma! ".repeat(1000);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut writer = archive.new_file("started.txt").start_lzma(9).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.flush().unwrap();
+    writer.finish().unwrap();
+
+    archive
+        .new_file("queued.txt")
+        .compression_method(CompressionMethod::Lzma)
+        .queue_compressed(data.clone())
+        .unwrap();
+
+    let writer = archive
+        .new_file("empty.txt")
+        .compression_method(CompressionMethod::Lzma)
+        .start_compressed()
+        .unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    // LZMA needs version 6.3 to extract
+    assert_eq!(u16::from_le_bytes([output[4], output[5]]), 63);
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 3);
+    for (name, method, flags, contents) in entries {
+        assert_eq!(method, CompressionMethod::Lzma, "{name}");
+        assert_eq!(flags & 0x02, 0x02, "{name} end marker flag");
+        let expected = if name == "empty.txt" { &[][..] } else { &data };
+        assert_eq!(contents, expected, "{name}");
+    }
+}
+
+#[test]
+fn test_lzma_store_if_larger() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut writer = archive
+        .new_file("tiny.txt")
+        .compression_method(CompressionMethod::Lzma)
+        .store_if_larger(true)
+        .start_compressed()
+        .unwrap();
+    writer.write_all(b"a").unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries[0].1, CompressionMethod::Store);
+    assert_eq!(entries[0].2 & 0x02, 0);
+    assert_eq!(entries[0].3, b"a");
+}
+
+#[test]
+fn test_lzma_transcode() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    let mut writer = archive.new_file("file.txt").start_lzma(6).unwrap();
+    writer.write_all(b"Hello, world!").unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(source).unwrap().into_zip_archive();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::transcode(&source, &mut archive, |_| CompressionMethod::Store).unwrap();
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries[0].1, CompressionMethod::Store);
+    assert_eq!(entries[0].2 & 0x02, 0);
+    assert_eq!(entries[0].3, b"Hello, world!");
+}
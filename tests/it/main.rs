@@ -6,13 +6,31 @@ use std::fs::File;
 use std::io::{Cursor, Read};
 use std::path::Path;
 
+mod aes_tests;
+mod alloc_tests;
+mod append_tests;
+mod async_tests;
+mod bzip2_tests;
+mod compression_tests;
+mod copy_tests;
 mod extra_data_zip_tests;
 mod extra_fields_test;
 mod false_signature_tests;
+mod lzma_tests;
+mod merge_tests;
+mod mmap_tests;
 mod modification_time_tests;
 mod permission_tests;
+mod plan_tests;
+mod rewrite_tests;
+mod sink_tests;
+mod split_tests;
+mod stream_tests;
+mod transcode_tests;
 mod utf8_tests;
+mod xz_tests;
 mod zip64_tests;
+mod zipcrypto_tests;
 
 macro_rules! zip_test_case {
     ($name:expr, $case:expr) => {
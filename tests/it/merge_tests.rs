@@ -0,0 +1,99 @@
+use rawzip::{CollisionPolicy, ErrorKind, ZipArchive, ZipArchiveWriter};
+use rstest::rstest;
+use std::io::{Cursor, Read, Write};
+
+fn archive(entries: &[(&str, &[u8])]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for (name, data) in entries {
+        if name.ends_with('/') {
+            archive.new_dir(name).create().unwrap();
+            continue;
+        }
+
+        let (mut entry, config) = archive.new_file(name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(data).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+    }
+    archive.finish().unwrap();
+    ZipArchive::from_slice(output).unwrap().into_zip_archive()
+}
+
+fn read_entries(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for record in archive.entries() {
+        let record = record.unwrap();
+        let name = String::from_utf8(record.file_path().as_ref().to_vec()).unwrap();
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        entry
+            .verifying_reader(entry.data())
+            .read_to_end(&mut contents)
+            .unwrap();
+        result.push((name, contents));
+    }
+    result
+}
+
+#[rstest]
+#[case(CollisionPolicy::Skip, &[("dir/", ""), ("dir/a.txt", "first"), ("b.txt", "first"), ("c.txt", "second")])]
+#[case(CollisionPolicy::LastWins, &[("dir/", ""), ("b.txt", "first"), ("dir/a.txt", "second"), ("c.txt", "second")])]
+fn test_merge_collisions(#[case] policy: CollisionPolicy, #[case] expected: &[(&str, &str)]) {
+    let first = archive(&[("dir/", b""), ("dir/a.txt", b"first"), ("b.txt", b"first")]);
+    let second = archive(&[
+        ("dir/", b""),
+        ("dir/a.txt", b"second"),
+        ("c.txt", b"second"),
+    ]);
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::merge([&first, &second], &mut archive, policy).unwrap();
+    archive.finish().unwrap();
+
+    let expected = expected
+        .iter()
+        .map(|(name, data)| (name.to_string(), data.as_bytes().to_vec()))
+        .collect::<Vec<_>>();
+    assert_eq!(read_entries(&output), expected);
+}
+
+#[test]
+fn test_merge_collision_error() {
+    let first = archive(&[("dir/", b""), ("a.txt", b"first")]);
+    let second = archive(&[("dir/", b""), ("a.txt", b"second")]);
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let err = rawzip::merge([&first, &second], &mut archive, CollisionPolicy::Error).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::DuplicateName { name } if name == "a.txt"));
+
+    // What was merged before the collision is still intact
+    archive.finish().unwrap();
+    assert_eq!(read_entries(&output).len(), 2);
+}
+
+#[test]
+fn test_merge_into_existing_entries() {
+    let source = archive(&[("a.txt", b"merged"), ("b.txt", b"merged")]);
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let (mut entry, config) = archive.new_file("a.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"existing").unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+
+    rawzip::merge([&source], &mut archive, CollisionPolicy::Skip).unwrap();
+    archive.finish().unwrap();
+
+    let expected = vec![
+        (String::from("a.txt"), b"existing".to_vec()),
+        (String::from("b.txt"), b"merged".to_vec()),
+    ];
+    assert_eq!(read_entries(&output), expected);
+}
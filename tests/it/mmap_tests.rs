@@ -0,0 +1,53 @@
+#![cfg(feature = "mmap")]
+
+use rawzip::{MmapAdvice, ZipArchive};
+use rstest::rstest;
+
+fn map(path: impl AsRef<std::path::Path>) -> memmap2::Mmap {
+    let file = std::fs::File::open(path).unwrap();
+    // SAFETY: test files aren't modified while they're mapped
+    unsafe { memmap2::Mmap::map(&file).unwrap() }
+}
+
+#[rstest]
+#[case("test.zip")]
+#[case("zip64.zip")]
+#[case("test-prefix.zip")]
+fn test_mmap_matches_slice(#[case] asset: &str) {
+    let path = format!("assets/{}", asset);
+    let data = std::fs::read(&path).unwrap();
+    let expected = ZipArchive::from_slice(&data).unwrap();
+    let archive = ZipArchive::from_mmap(map(&path)).unwrap();
+    for advice in [
+        MmapAdvice::Sequential,
+        MmapAdvice::Random,
+        MmapAdvice::Normal,
+    ] {
+        archive.advise(advice).unwrap();
+    }
+
+    assert_eq!(archive.as_bytes(), &data[..]);
+    assert_eq!(archive.entries_hint(), expected.entries_hint());
+    let mut entries = archive.entries();
+    let mut expected_entries = expected.entries();
+    while let Some(entry) = entries.next_entry().unwrap() {
+        let expected_entry = expected_entries.next_entry().unwrap().unwrap();
+        assert_eq!(
+            entry.file_path().as_ref(),
+            expected_entry.file_path().as_ref()
+        );
+        let data = archive.get_entry(entry.wayfinder()).unwrap();
+        let expected_data = expected.get_entry(expected_entry.wayfinder()).unwrap();
+        assert_eq!(data.data(), expected_data.data());
+    }
+    assert!(expected_entries.next_entry().unwrap().is_none());
+}
+
+#[test]
+fn test_mmap_empty_file() {
+    let path = std::env::temp_dir().join(format!("rawzip-mmap-{}.zip", std::process::id()));
+    std::fs::write(&path, b"").unwrap();
+    let result = ZipArchive::from_mmap(map(&path));
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
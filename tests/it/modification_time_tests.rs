@@ -1,7 +1,7 @@
 use rawzip::{
     extra_fields::{ExtraFieldId, ExtraFields},
     time::{LocalDateTime, UtcDateTime, ZipDateTimeKind},
-    ZipArchive, ZipArchiveWriter,
+    DosTimestampPolicy, ZipArchive, ZipArchiveWriter,
 };
 use std::io::Write;
 
@@ -397,3 +397,245 @@ fn assert_extend_time_extra_field_difference(mut central: ExtraFields, mut local
     );
     assert_eq!(local_data.len(), 9, "Local header should have 9 bytes (mod + access times) and have richer timestamp data than central directory");
 }
+
+/// Test that access and creation times are written to the local extended
+/// timestamp field while the central directory keeps the modification time
+#[test]
+fn test_extended_timestamp_access_and_creation_times() {
+    let mtime = UtcDateTime::from_components(2023, 6, 15, 14, 30, 45, 0).unwrap();
+    let atime = UtcDateTime::from_components(2024, 1, 2, 3, 4, 5, 0).unwrap();
+    let ctime = UtcDateTime::from_components(2022, 12, 31, 23, 59, 59, 0).unwrap();
+    let mut output = Vec::new();
+
+    {
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("test.txt")
+            .last_modified(mtime)
+            .last_accessed(atime)
+            .created(ctime)
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello, world!").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+
+        archive
+            .new_dir("accessed/")
+            .last_accessed(atime)
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+    }
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut entries = archive.entries();
+
+    let entry = entries.next_entry().unwrap().unwrap();
+    assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(mtime));
+    let (_, central) = entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::EXTENDED_TIMESTAMP)
+        .unwrap();
+    let mut expected_central = vec![0b111];
+    expected_central.extend_from_slice(&(mtime.to_unix() as u32).to_le_bytes());
+    assert_eq!(central, expected_central.as_slice());
+
+    let local_entry = archive.get_entry(entry.wayfinder()).unwrap();
+    let (_, local) = local_entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::EXTENDED_TIMESTAMP)
+        .unwrap();
+    let mut expected_local = expected_central.clone();
+    expected_local.extend_from_slice(&(atime.to_unix() as u32).to_le_bytes());
+    expected_local.extend_from_slice(&(ctime.to_unix() as u32).to_le_bytes());
+    assert_eq!(local, expected_local.as_slice());
+
+    // Without a modification time, the central field only has the flags
+    let entry = entries.next_entry().unwrap().unwrap();
+    let (_, central) = entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::EXTENDED_TIMESTAMP)
+        .unwrap();
+    assert_eq!(central, &[0b010]);
+    let local_entry = archive.get_entry(entry.wayfinder()).unwrap();
+    let (_, local) = local_entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::EXTENDED_TIMESTAMP)
+        .unwrap();
+    assert_eq!(&local[1..], &(atime.to_unix() as u32).to_le_bytes());
+}
+
+/// Test that the NTFS extra field preserves sub-second modification times
+#[test]
+fn test_ntfs_timestamp_roundtrip() {
+    let mtime = UtcDateTime::from_components(2023, 6, 15, 14, 30, 45, 123_456_700).unwrap();
+    let atime = UtcDateTime::from_components(2024, 1, 2, 3, 4, 5, 900).unwrap();
+    let ctime = UtcDateTime::from_components(1999, 12, 31, 23, 59, 59, 0).unwrap();
+    let mut output = Vec::new();
+
+    {
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        let (mut entry, config) = archive
+            .new_file("derived.txt")
+            .last_modified(mtime)
+            .ntfs_timestamps()
+            .start()
+            .unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(b"Hello, world!").unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+
+        archive
+            .new_dir("explicit/")
+            .ntfs_times(mtime, atime, ctime)
+            .create()
+            .unwrap();
+
+        // Without a modification time, there is nothing to write
+        archive
+            .new_dir("nothing/")
+            .ntfs_timestamps()
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+    }
+
+    let ntfs_ticks = |data: &[u8], index: usize| {
+        let start = 8 + index * 8;
+        u64::from_le_bytes(data[start..start + 8].try_into().unwrap())
+    };
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut entries = archive.entries();
+
+    let entry = entries.next_entry().unwrap().unwrap();
+    assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(mtime));
+    let (_, ntfs) = entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::NTFS)
+        .unwrap();
+    assert_eq!(ntfs.len(), 32);
+    assert_eq!(ntfs_ticks(ntfs, 0), ntfs_ticks(ntfs, 1));
+    assert_eq!(ntfs_ticks(ntfs, 0), ntfs_ticks(ntfs, 2));
+    let local_entry = archive.get_entry(entry.wayfinder()).unwrap();
+    assert!(local_entry
+        .extra_fields()
+        .any(|(id, _)| id == ExtraFieldId::NTFS));
+
+    let entry = entries.next_entry().unwrap().unwrap();
+    assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(mtime));
+    let (_, ntfs) = entry
+        .extra_fields()
+        .find(|(id, _)| *id == ExtraFieldId::NTFS)
+        .unwrap();
+    // 100ns ticks between the access time and the Unix epoch
+    assert_eq!(
+        ntfs_ticks(ntfs, 1) - 116_444_736_000_000_000,
+        atime.to_unix() as u64 * 10_000_000 + 9
+    );
+    assert_eq!(
+        ntfs_ticks(ntfs, 2) - 116_444_736_000_000_000,
+        ctime.to_unix() as u64 * 10_000_000
+    );
+
+    let entry = entries.next_entry().unwrap().unwrap();
+    assert!(entry.extra_fields().all(|(id, _)| id != ExtraFieldId::NTFS));
+}
+
+/// Test each policy for times outside of the MS-DOS range
+#[test]
+fn test_dos_timestamp_policy() {
+    let write = |policy: DosTimestampPolicy, datetime: UtcDateTime| {
+        let mut output = Vec::new();
+        let mut archive = ZipArchiveWriter::builder()
+            .dos_timestamp_policy(policy)
+            .build(&mut output);
+        let result = archive.new_dir("dir/").last_modified(datetime).create();
+        archive.finish().unwrap();
+        result.map(|_| output)
+    };
+
+    let before = UtcDateTime::from_components(1970, 6, 15, 14, 30, 46, 0).unwrap();
+    let before_epoch = UtcDateTime::from_components(1900, 6, 15, 14, 30, 46, 0).unwrap();
+    let after = UtcDateTime::from_components(2200, 6, 15, 14, 30, 46, 0).unwrap();
+    let within = UtcDateTime::from_components(2000, 6, 15, 14, 30, 46, 0).unwrap();
+    for (datetime, year) in [
+        (before, 1980),
+        (before_epoch, 1980),
+        (after, 2107),
+        (within, 2000),
+    ] {
+        let output = write(DosTimestampPolicy::Clamp, datetime).unwrap();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        let dos = entry.dos_last_modified();
+        assert_eq!((dos.year(), dos.month(), dos.second()), (year, 6, 46));
+
+        let output = write(DosTimestampPolicy::ExtendedOnly, datetime).unwrap();
+        let archive = ZipArchive::from_slice(&output).unwrap();
+        let entry = archive.entries().next_entry().unwrap().unwrap();
+        let dos = entry.dos_last_modified();
+        assert_eq!(dos.packed_date() == 0, datetime != within);
+        assert_eq!(entry.last_modified(), ZipDateTimeKind::Utc(datetime));
+        let has_ntfs = entry.extra_fields().any(|(id, _)| id == ExtraFieldId::NTFS);
+        assert_eq!(has_ntfs, !matches!(datetime.year(), 1970..=2106));
+
+        let result = write(DosTimestampPolicy::Error, datetime);
+        assert_eq!(result.is_ok(), datetime == within);
+    }
+}
+
+/// Test that entries pick up the modification time and permissions of files
+#[test]
+fn test_entry_metadata() {
+    let dir = std::env::temp_dir().join(format!("rawzip-metadata-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("file.txt");
+    std::fs::write(&path, b"Hello").unwrap();
+    let mtime = filetime::FileTime::from_unix_time(1_700_000_000, 0);
+    filetime::set_file_mtime(&path, mtime).unwrap();
+    filetime::set_file_mtime(&dir, mtime).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    }
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let metadata = std::fs::metadata(&dir).unwrap();
+    archive
+        .new_dir("dir/")
+        .metadata(&metadata)
+        .create()
+        .unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+    let (mut entry, config) = archive
+        .new_file("dir/file.txt")
+        .metadata(&metadata)
+        .start()
+        .unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"Hello").unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+    archive.finish().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let expected = UtcDateTime::from_unix(1_700_000_000);
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut entries = archive.entries();
+    let dir = entries.next_entry().unwrap().unwrap();
+    assert!(dir.is_dir());
+    assert_eq!(dir.last_modified(), ZipDateTimeKind::Utc(expected));
+    let file = entries.next_entry().unwrap().unwrap();
+    assert_eq!(file.last_modified(), ZipDateTimeKind::Utc(expected));
+    #[cfg(unix)]
+    {
+        assert_eq!(dir.mode().value() & 0o170000, 0o040000);
+        assert_eq!(file.mode().value(), 0o100640);
+    }
+}
@@ -119,3 +119,42 @@ fn test_permissions_without_unix_permissions() {
         actual_mode
     );
 }
+
+#[test]
+fn test_symlink_roundtrip() {
+    let mut output = Vec::new();
+
+    {
+        let mut archive = ZipArchiveWriter::new(&mut output);
+        archive
+            .new_symlink("bin/tool", "../lib/tool-1.0")
+            .create()
+            .unwrap();
+        archive
+            .new_symlink("private", "secret/")
+            .unix_permissions(0o100700)
+            .create()
+            .unwrap();
+        archive.finish().unwrap();
+    }
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut entries = archive.entries();
+    let expected = [
+        ("bin/tool", "../lib/tool-1.0", 0o120777),
+        ("private", "secret/", 0o120700),
+    ];
+
+    for (name, target, mode) in expected {
+        let entry = entries.next_entry().unwrap().unwrap();
+        assert_eq!(entry.file_path().try_normalize().unwrap().as_ref(), name);
+        assert_eq!(entry.compression_method(), rawzip::CompressionMethod::Store);
+        assert!(entry.mode().is_symlink());
+        assert_eq!(entry.mode().value(), mode);
+
+        let zip_entry = archive.get_entry(entry.wayfinder()).unwrap();
+        assert_eq!(zip_entry.data(), target.as_bytes());
+        let mut verifier = zip_entry.verifying_reader(zip_entry.data());
+        std::io::copy(&mut verifier, &mut std::io::sink()).unwrap();
+    }
+}
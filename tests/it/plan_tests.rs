@@ -0,0 +1,183 @@
+use rawzip::time::UtcDateTime;
+use rawzip::{
+    CompressionMethod, ErrorKind, ZipArchive, ZipArchiveWriter, ZipPlan, ZipPlannedEntry,
+};
+use std::io::Read;
+
+#[test]
+fn test_plan_size_matches_output() {
+    let text = b"Hello, world! ".repeat(50);
+    let compressed = {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &text).unwrap();
+        encoder.finish().unwrap()
+    };
+    let time = UtcDateTime::from_components(2024, 1, 2, 3, 4, 5, 0).unwrap();
+
+    let builder = ZipArchiveWriter::builder().with_unicode_path_field(true);
+    let mut plan = ZipPlan::with_builder(builder);
+    plan.push(ZipPlannedEntry::stored("empty.txt", 0, 0));
+    plan.push(
+        ZipPlannedEntry::stored("dir/stored.txt", rawzip::crc32(&text), text.len() as u64)
+            .last_modified(time)
+            .unix_permissions(0o644),
+    );
+    plan.push(ZipPlannedEntry::precompressed(
+        "dir/compressed.txt",
+        CompressionMethod::Deflate,
+        rawzip::crc32(&text),
+        compressed.len() as u64,
+        text.len() as u64,
+    ));
+
+    let size = plan.size().unwrap();
+    let output = plan
+        .write(Vec::new(), |entry| {
+            Ok(match entry.name() {
+                "empty.txt" => &[][..],
+                "dir/stored.txt" => &text[..],
+                _ => &compressed[..],
+            })
+        })
+        .unwrap();
+    assert_eq!(output.len() as u64, size);
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let entries = archive
+        .entries()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            assert!(!entry.has_data_descriptor());
+            let method = entry.compression_method();
+            let name = entry.file_path().as_ref().to_vec();
+            let entry = archive.get_entry(entry.wayfinder()).unwrap();
+            let mut contents = Vec::new();
+            match method {
+                CompressionMethod::Store => entry
+                    .verifying_reader(entry.data())
+                    .read_to_end(&mut contents),
+                _ => entry
+                    .verifying_reader(flate2::read::DeflateDecoder::new(entry.data()))
+                    .read_to_end(&mut contents),
+            }
+            .unwrap();
+            (name, contents)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![
+            (b"empty.txt".to_vec(), Vec::new()),
+            (b"dir/stored.txt".to_vec(), text.clone()),
+            (b"dir/compressed.txt".to_vec(), text),
+        ]
+    );
+}
+
+#[test]
+fn test_plan_short_data() {
+    let mut plan = ZipPlan::new();
+    plan.push(ZipPlannedEntry::stored(
+        "file.txt",
+        rawzip::crc32(b"Hello"),
+        5,
+    ));
+    let err = plan.write(Vec::new(), |_| Ok(&b"Hell"[..])).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidSize {
+            expected: 5,
+            actual: 4
+        }
+    ));
+}
+
+#[rstest::rstest]
+fn test_plan_layout_ranges(#[values(0, 4096)] buffer_size: usize) {
+    let files: [(&str, Vec<u8>); 3] = [
+        ("a.txt", b"Hello".to_vec()),
+        ("empty.txt", Vec::new()),
+        ("dir/b.txt", b"world! ".repeat(20)),
+    ];
+    let builder = rawzip::ZipArchiveWriter::builder().with_write_buffer_size(buffer_size);
+    let mut plan = ZipPlan::with_builder(builder);
+    for (name, data) in &files {
+        plan.push(ZipPlannedEntry::stored(
+            *name,
+            rawzip::crc32(data),
+            data.len() as u64,
+        ));
+    }
+    let data = |entry: &ZipPlannedEntry, skip: u64| {
+        let (_, data) = files
+            .iter()
+            .find(|(name, _)| *name == entry.name())
+            .unwrap();
+        Ok(&data[skip as usize..])
+    };
+    let expected = plan.write(Vec::new(), |entry| data(entry, 0)).unwrap();
+
+    let layout = plan.layout().unwrap();
+    assert_eq!(layout.size(), expected.len() as u64);
+    assert_eq!(layout.etag(), plan.layout().unwrap().etag());
+    for (index, (_, contents)) in files.iter().enumerate() {
+        let range = layout.data_range(index);
+        assert_eq!(
+            &expected[range.start as usize..range.end as usize],
+            &contents[..]
+        );
+    }
+
+    // Every way of splitting the archive in two, and in pieces of 7 bytes
+    for middle in 0..=layout.size() {
+        let output = layout.write_range(0..middle, Vec::new(), data).unwrap();
+        let output = layout
+            .write_range(middle..layout.size(), output, data)
+            .unwrap();
+        assert_eq!(output, expected);
+    }
+    let mut output = Vec::new();
+    for start in (0..layout.size()).step_by(7) {
+        output = layout.write_range(start..start + 7, output, data).unwrap();
+    }
+    assert_eq!(output, expected);
+
+    // Only the entries within the range are asked for
+    let range = layout.data_range(2);
+    let output = layout
+        .write_range(
+            range.start + 3..range.start + 10,
+            Vec::new(),
+            |entry, skip| {
+                assert_eq!((entry.name(), skip), ("dir/b.txt", 3));
+                data(entry, skip)
+            },
+        )
+        .unwrap();
+    assert_eq!(output, &files[2].1[3..10]);
+
+    // A change to the contents changes the ETag
+    let mut changed = ZipPlan::new();
+    changed.push(ZipPlannedEntry::stored("a.txt", rawzip::crc32(b"Hellp"), 5));
+    let mut same = ZipPlan::new();
+    same.push(ZipPlannedEntry::stored("a.txt", rawzip::crc32(b"Hello"), 5));
+    assert_ne!(
+        changed.layout().unwrap().etag(),
+        same.layout().unwrap().etag()
+    );
+
+    // A short read is measured against the part of the entry in the range
+    let err = layout
+        .write_range(range.start + 3..range.start + 10, Vec::new(), |_, _| {
+            Ok(&b"short"[..])
+        })
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidSize {
+            expected: 7,
+            actual: 5
+        }
+    ));
+}
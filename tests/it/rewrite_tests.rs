@@ -0,0 +1,156 @@
+use rawzip::{EntryRewrite, ZipArchive, ZipArchiveWriter};
+use std::io::{Cursor, Read, Write};
+
+fn source(entries: &[(&str, &[u8])]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    for (name, data) in entries {
+        let (mut entry, config) = archive.new_file(name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(data).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+    }
+    archive.finish().unwrap();
+    ZipArchive::from_slice(output).unwrap().into_zip_archive()
+}
+
+fn stored_entries(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for record in archive.entries() {
+        let record = record.unwrap();
+        assert_eq!(
+            record.compression_method(),
+            rawzip::CompressionMethod::Store
+        );
+        let name = String::from_utf8(record.file_path().as_ref().to_vec()).unwrap();
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        entry
+            .verifying_reader(entry.data())
+            .read_to_end(&mut contents)
+            .unwrap();
+        result.push((name, contents));
+    }
+    result
+}
+
+#[test]
+fn test_rewrite_keep_remove_rename() {
+    let source = source(&[
+        ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0"),
+        ("META-INF/CERT.RSA", b"signature"),
+        ("com/example/Main.class", b"class"),
+        ("README", b"readme"),
+    ]);
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut seen = Vec::new();
+    rawzip::rewrite(&source, &mut archive, |record| {
+        let name = record
+            .file_path()
+            .try_normalize()
+            .unwrap()
+            .as_ref()
+            .to_string();
+        seen.push(name.clone());
+        match name.as_str() {
+            "META-INF/CERT.RSA" => EntryRewrite::remove(),
+            "README" => EntryRewrite::keep(),
+            "META-INF/MANIFEST.MF" => EntryRewrite::keep().rename("META-INF/MANIFEST.MF"),
+            _ => EntryRewrite::keep().rename(format!("shaded/{name}")),
+        }
+    })
+    .unwrap();
+    archive.finish().unwrap();
+
+    assert_eq!(seen.len(), 4);
+    let expected = vec![
+        (
+            String::from("META-INF/MANIFEST.MF"),
+            b"Manifest-Version: 1.0".to_vec(),
+        ),
+        (
+            String::from("shaded/com/example/Main.class"),
+            b"class".to_vec(),
+        ),
+        (String::from("README"), b"readme".to_vec()),
+    ];
+    assert_eq!(stored_entries(&output), expected);
+}
+
+#[test]
+#[cfg(feature = "deflate")]
+fn test_rewrite_recompress() {
+    use rawzip::CompressionMethod;
+
+    let mut data = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut data);
+    let mut writer = archive.new_file("image.png").start_deflate(6).unwrap();
+    writer.write_all(&[7u8; 1000]).unwrap();
+    writer.finish().unwrap();
+    let mut writer = archive.new_file("text.txt").start_deflate(6).unwrap();
+    writer.write_all(b"text").unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+    let source = ZipArchive::from_slice(data).unwrap().into_zip_archive();
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::rewrite(&source, &mut archive, |_| {
+        EntryRewrite::keep()
+            .rename("renamed")
+            .compression_method(CompressionMethod::Store)
+    })
+    .unwrap();
+    archive.finish().unwrap();
+
+    let expected = vec![
+        (String::from("renamed"), vec![7u8; 1000]),
+        (String::from("renamed"), b"text".to_vec()),
+    ];
+    assert_eq!(stored_entries(&output), expected);
+}
+
+#[test]
+#[cfg(feature = "deflate")]
+fn test_rewrite_recompress_checks_crc() {
+    use rawzip::{CompressionMethod, ErrorKind};
+
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"Hello").unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut data = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut data);
+    let mut writer = archive
+        .new_file("corrupt.txt")
+        .compression_method(CompressionMethod::Deflate)
+        .start_raw(0xdeadbeef, 5)
+        .unwrap();
+    writer.write_all(&compressed).unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+    let source = ZipArchive::from_slice(data).unwrap().into_zip_archive();
+
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let err = rawzip::rewrite(&source, &mut archive, |_| {
+        EntryRewrite::keep().compression_method(CompressionMethod::Store)
+    })
+    .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidChecksum {
+            expected: 0xdeadbeef,
+            ..
+        }
+    ));
+
+    // The corrupt entry is left out
+    archive.finish().unwrap();
+    assert!(stored_entries(&output).is_empty());
+}
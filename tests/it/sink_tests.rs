@@ -0,0 +1,91 @@
+use rawzip::{ErrorKind, SinkOrder, ZipArchive, ZipArchiveWriter, ZipPlannedEntry, ZipSink};
+
+fn stored(name: &str) -> (ZipPlannedEntry, Vec<u8>) {
+    let data = name.as_bytes().to_vec();
+    let entry = ZipPlannedEntry::stored(name, rawzip::crc32(&data), data.len() as u64);
+    (entry, data)
+}
+
+fn names(output: &[u8]) -> Vec<String> {
+    let archive = ZipArchive::from_slice(output).unwrap();
+    archive
+        .entries()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            String::from_utf8(entry.file_path().as_ref().to_vec()).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_sink_submission_order() {
+    let archive = ZipArchiveWriter::new(Vec::new());
+    let (sink, consumer) = ZipSink::spawn(archive, SinkOrder::Submission);
+    let first = sink.reserve();
+    let skipped = sink.reserve();
+    let third = sink.reserve();
+
+    let (entry, data) = stored("d.txt");
+    sink.submit(entry, data).unwrap();
+    let producer = std::thread::spawn(move || {
+        let (entry, data) = stored("c.txt");
+        third.submit(entry, data).unwrap();
+        let (entry, data) = stored("a.txt");
+        first.submit(entry, data).unwrap();
+    });
+    producer.join().unwrap();
+    drop(skipped);
+    drop(sink);
+
+    let output = consumer.finish().unwrap();
+    assert_eq!(names(&output), ["a.txt", "c.txt", "d.txt"]);
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let entry = archive.entries().next_entry().unwrap().unwrap();
+    let entry = archive.get_entry(entry.wayfinder()).unwrap();
+    assert_eq!(entry.data(), b"a.txt");
+}
+
+#[test]
+fn test_sink_many_producers() {
+    let archive = ZipArchiveWriter::new(Vec::new());
+    let (sink, consumer) = ZipSink::spawn(archive, SinkOrder::Arrival);
+    let producers = (0..8)
+        .map(|i| {
+            let sink = sink.clone();
+            std::thread::spawn(move || {
+                for j in 0..16 {
+                    let (entry, data) = stored(&format!("{}-{}.txt", i, j));
+                    sink.submit(entry, data).unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sink);
+    for producer in producers {
+        producer.join().unwrap();
+    }
+
+    let mut archive = consumer.join().unwrap();
+    archive.new_dir("last/").create().unwrap();
+    let output = archive.finish().unwrap();
+    let names = names(&output);
+    assert_eq!(names.len(), 8 * 16 + 1);
+    assert_eq!(names.last().unwrap(), "last/");
+}
+
+#[test]
+fn test_sink_size_mismatch() {
+    let archive = ZipArchiveWriter::new(Vec::new());
+    let (sink, consumer) = ZipSink::spawn(archive, SinkOrder::Submission);
+    let (entry, _) = stored("a.txt");
+    let err = sink.submit(entry, b"a.".to_vec()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidSize { .. }));
+
+    // The failed submission doesn't hold back the next one
+    let (entry, data) = stored("b.txt");
+    sink.submit(entry, data).unwrap();
+    drop(sink);
+    let output = consumer.finish().unwrap();
+    assert_eq!(names(&output), ["b.txt"]);
+}
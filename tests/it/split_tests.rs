@@ -0,0 +1,135 @@
+use rawzip::{ErrorKind, SplitZipWriter, ZipArchive, ZipArchiveWriter};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const VOLUME_SIZE: u64 = 64 * 1024;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rawzip-split-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn le_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes(data[pos..pos + 2].try_into().unwrap())
+}
+
+fn le_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+}
+
+fn volume_paths(path: &Path, count: u32) -> Vec<PathBuf> {
+    (1..count)
+        .map(|disk| path.with_extension(format!("z{:02}", disk)))
+        .chain(std::iter::once(path.to_path_buf()))
+        .collect()
+}
+
+fn write_entries(archive: &mut ZipArchiveWriter<SplitZipWriter>, entries: &[(String, Vec<u8>)]) {
+    for (name, data) in entries {
+        let (mut entry, config) = archive.new_file(name).start().unwrap();
+        let mut writer = config.wrap(&mut entry);
+        writer.write_all(data).unwrap();
+        let (_, descriptor) = writer.finish().unwrap();
+        entry.finish(descriptor).unwrap();
+    }
+}
+
+#[test]
+fn test_split_archive() {
+    let dir = temp_dir("archive");
+    let path = dir.join("archive.zip");
+    let entries = (0..8)
+        .map(|i| (format!("file-{}.bin", i), vec![i as u8; 30_000 + i * 1000]))
+        .collect::<Vec<_>>();
+
+    let volumes = SplitZipWriter::create(&path, VOLUME_SIZE).unwrap();
+    let mut archive = ZipArchiveWriter::builder().build_split(volumes);
+    write_entries(&mut archive, &entries);
+    let volumes = archive.finish().unwrap();
+
+    let volume_count = volumes.volume_count();
+    assert!(volume_count > 3);
+    let volumes = volume_paths(&path, volume_count)
+        .iter()
+        .map(|path| std::fs::read(path).unwrap())
+        .collect::<Vec<_>>();
+    assert!(volumes.iter().all(|x| x.len() as u64 <= VOLUME_SIZE));
+    assert_eq!(le_u32(&volumes[0], 0), 0x08074b50);
+
+    // The end of central directory is at the end of the last volume
+    let last = volumes.last().unwrap();
+    let eocd = &last[last.len() - 22..];
+    assert_eq!(le_u32(eocd, 0), 0x06054b50);
+    assert_eq!(u32::from(le_u16(eocd, 4)), volume_count - 1);
+    let directory_disk = le_u16(eocd, 6) as usize;
+    assert_eq!(le_u16(eocd, 10) as usize, entries.len());
+    let directory_offset = le_u32(eocd, 16) as usize;
+
+    // Reassemble the central directory from the volumes it spans
+    let directory = volumes[directory_disk..].concat();
+    let mut pos = directory_offset;
+    let mut disk_entries = 0;
+    for (name, data) in &entries {
+        assert_eq!(le_u32(&directory, pos), 0x02014b50);
+        let compressed_size = le_u32(&directory, pos + 20) as usize;
+        let name_len = le_u16(&directory, pos + 28) as usize;
+        let extra_len = le_u16(&directory, pos + 30) as usize;
+        let comment_len = le_u16(&directory, pos + 32) as usize;
+        let disk = le_u16(&directory, pos + 34) as usize;
+        let offset = le_u32(&directory, pos + 42) as usize;
+        assert_eq!(&directory[pos + 46..pos + 46 + name_len], name.as_bytes());
+        assert_eq!(compressed_size, data.len());
+
+        // Local headers are not split across volumes
+        let volume = &volumes[disk];
+        assert_eq!(le_u32(volume, offset), 0x04034b50);
+        assert_eq!(
+            &volume[offset + 30..offset + 30 + name_len],
+            name.as_bytes()
+        );
+
+        // The entry data may be split
+        let data_start = offset + 30 + name_len + le_u16(volume, offset + 28) as usize;
+        let contents = volumes[disk..].concat();
+        assert_eq!(&contents[data_start..data_start + data.len()], &data[..]);
+
+        if pos >= directory.len() - last.len() {
+            disk_entries += 1;
+        }
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+
+    assert_eq!(le_u16(eocd, 8), disk_entries);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_split_archive_single_volume() {
+    let dir = temp_dir("single");
+    let path = dir.join("archive.zip");
+    let entries = vec![("file.txt".to_string(), b"Hello, world!".to_vec())];
+
+    let volumes = SplitZipWriter::create(&path, VOLUME_SIZE).unwrap();
+    let mut archive = ZipArchiveWriter::builder().build_split(volumes);
+    write_entries(&mut archive, &entries);
+    let volumes = archive.finish().unwrap();
+    assert_eq!(volumes.volume_count(), 1);
+
+    let data = std::fs::read(&path).unwrap();
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    let entry = archive.entries().next_entry().unwrap().unwrap();
+    assert_eq!(entry.local_header_offset(), 4);
+    let entry = archive.get_entry(entry.wayfinder()).unwrap();
+    assert_eq!(entry.data(), b"Hello, world!");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_split_archive_volume_size_too_small() {
+    let dir = temp_dir("small");
+    let err = SplitZipWriter::create(dir.join("archive.zip"), 1024).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidInput { .. }));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,173 @@
+use rawzip::{
+    CompressionMethod, DataDescriptorWidth, ErrorKind, ZipArchive, ZipArchiveWriter,
+    ZipStreamReader, ZipVerification,
+};
+use rstest::rstest;
+use std::io::{Read, Write};
+
+/// Yields its input a byte at a time.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+fn decompress(method: CompressionMethod, data: impl Read) -> Vec<u8> {
+    let mut contents = Vec::new();
+    match method {
+        CompressionMethod::Store => { data }.read_to_end(&mut contents),
+        CompressionMethod::Deflate => {
+            flate2::read::DeflateDecoder::new(data).read_to_end(&mut contents)
+        }
+        _ => panic!("unexpected compression method {:?}", method),
+    }
+    .unwrap();
+    contents
+}
+
+/// Reads every entry through the central directory.
+fn read_archive(data: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    archive
+        .entries()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let name = entry.file_path().as_ref().to_vec();
+            let method = entry.compression_method();
+            let entry = archive.get_entry(entry.wayfinder()).unwrap();
+            (name, decompress(method, entry.data()))
+        })
+        .collect()
+}
+
+fn read_stream(reader: impl Read) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut reader = ZipStreamReader::new(reader);
+    let mut entries = Vec::new();
+    while let Some(mut entry) = reader.next_entry().unwrap() {
+        let name = entry.file_path().as_ref().to_vec();
+        let contents = decompress(entry.compression_method(), &mut entry);
+        entry
+            .claim_verifier()
+            .unwrap()
+            .valid(ZipVerification {
+                crc: rawzip::crc32(&contents),
+                uncompressed_size: contents.len() as u64,
+            })
+            .unwrap();
+        entries.push((name, contents));
+    }
+    assert!(reader.next_entry().unwrap().is_none());
+    entries
+}
+
+#[rstest]
+#[case("test.zip")]
+#[case("go-with-datadesc-sig.zip")]
+#[case("crc32-not-streamed.zip")]
+#[case("zip64.zip")]
+#[case("unix.zip")]
+#[case("winxp.zip")]
+fn test_stream_matches_archive(#[case] name: &str) {
+    let data = std::fs::read(format!("assets/{}", name)).unwrap();
+    let expected = read_archive(&data);
+    assert!(!expected.is_empty());
+    assert_eq!(read_stream(data.as_slice()), expected);
+    assert_eq!(read_stream(Trickle(&data)), expected);
+}
+
+#[rstest]
+#[case(DataDescriptorWidth::Auto)]
+#[case(DataDescriptorWidth::Bits64)]
+fn test_stream_data_descriptors(#[case] width: DataDescriptorWidth) {
+    let text = b"Hello, world! ".repeat(1000);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::builder()
+        .with_data_descriptor_width(width)
+        .build(&mut output);
+    archive.new_dir("dir/").create().unwrap();
+    for (name, method) in [
+        ("dir/deflated.txt", CompressionMethod::Deflate),
+        ("dir/stored.txt", CompressionMethod::Store),
+        ("dir/empty.txt", CompressionMethod::Store),
+    ] {
+        let data = if name == "dir/empty.txt" {
+            &[][..]
+        } else {
+            &text[..]
+        };
+        let (mut entry, config) = archive
+            .new_file(name)
+            .compression_method(method)
+            .start()
+            .unwrap();
+        let descriptor = if method == CompressionMethod::Deflate {
+            let encoder =
+                flate2::write::DeflateEncoder::new(&mut entry, flate2::Compression::default());
+            let mut writer = config.wrap(encoder);
+            writer.write_all(data).unwrap();
+            let (encoder, descriptor) = writer.finish().unwrap();
+            encoder.finish().unwrap();
+            descriptor
+        } else {
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(data).unwrap();
+            writer.finish().unwrap().1
+        };
+        entry.finish(descriptor).unwrap();
+    }
+    archive.finish().unwrap();
+
+    let expected = read_archive(&output);
+    assert_eq!(expected.len(), 4);
+    assert_eq!(read_stream(output.as_slice()), expected);
+    assert_eq!(read_stream(Trickle(&output)), expected);
+
+    // Entries that aren't read are skipped
+    let mut reader = ZipStreamReader::new(output.as_slice());
+    let mut names = Vec::new();
+    while let Some(entry) = reader.next_entry().unwrap() {
+        assert!(entry.is_dir() || entry.has_data_descriptor());
+        names.push(entry.file_path().as_ref().to_vec());
+    }
+    let expected_names = expected
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, expected_names);
+}
+
+#[test]
+fn test_stream_truncated() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"Hello, world!").unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+    archive.finish().unwrap();
+
+    // Cut off in the data, and before the central directory
+    let directory = ZipArchive::from_slice(&output).unwrap().directory_offset() as usize;
+    for len in [40, directory] {
+        let mut reader = ZipStreamReader::new(&output[..len]);
+        let err = loop {
+            match reader.next_entry() {
+                Ok(Some(mut entry)) => {
+                    if let Err(err) = entry.read_to_end(&mut Vec::new()) {
+                        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+                        break reader.next_entry().unwrap_err();
+                    }
+                }
+                Ok(None) => panic!("the archive was cut short"),
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(err.kind(), ErrorKind::Eof), "{:?}", err);
+    }
+}
@@ -0,0 +1,211 @@
+#![cfg(feature = "deflate")]
+
+use rawzip::time::UtcDateTime;
+use rawzip::{CompressionMethod, ZipArchive, ZipArchiveWriter, RECOMMENDED_BUFFER_SIZE};
+use rstest::rstest;
+use std::fs::File;
+use std::io::{Read, Write};
+
+type Entry = (Vec<u8>, CompressionMethod, u32, String, Vec<u8>);
+
+fn read_entries(data: &[u8]) -> Vec<Entry> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = entry.file_path().as_ref().to_vec();
+        let method = entry.compression_method();
+        let mode = entry.mode().value();
+        let modified = format!("{:?}", entry.last_modified());
+        let wayfinder = entry.wayfinder();
+        let entry = archive.get_entry(wayfinder).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => {
+                entry
+                    .verifying_reader(entry.data())
+                    .read_to_end(&mut contents)
+                    .unwrap();
+            }
+            CompressionMethod::Deflate => {
+                let decoder = flate2::read::DeflateDecoder::new(entry.data());
+                entry
+                    .verifying_reader(decoder)
+                    .read_to_end(&mut contents)
+                    .unwrap();
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let decoder = zstd::Decoder::new(entry.data()).unwrap();
+                entry
+                    .verifying_reader(decoder)
+                    .read_to_end(&mut contents)
+                    .unwrap();
+            }
+            _ => panic!("unexpected compression method"),
+        }
+        result.push((name, method, mode, modified, contents));
+    }
+    result
+}
+
+fn transcoded(source: &[u8], method: CompressionMethod) -> Vec<u8> {
+    let source = ZipArchive::from_slice(source).unwrap().into_zip_archive();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::transcode(&source, &mut archive, |record| {
+        if record.is_dir() {
+            record.compression_method()
+        } else {
+            method
+        }
+    })
+    .unwrap();
+    archive.finish().unwrap();
+    output
+}
+
+#[rstest]
+#[case("test.zip")]
+#[case("unix.zip")]
+#[case("time-infozip.zip")]
+#[case("go-with-datadesc-sig.zip")]
+fn test_transcode_assets_to_store(#[case] name: &str) {
+    let path = format!("assets/{}", name);
+    let original = std::fs::read(&path).unwrap();
+    let output = transcoded(&original, CompressionMethod::Store);
+
+    let expected = read_entries(&original)
+        .into_iter()
+        .map(|(name, _, mode, modified, contents)| {
+            (name, CompressionMethod::Store, mode, modified, contents)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(read_entries(&output), expected);
+
+    // And back again
+    let output = transcoded(&output, CompressionMethod::Deflate);
+    let entries = read_entries(&output);
+    for (name, method, ..) in &entries {
+        if !name.ends_with(b"/") {
+            assert_eq!(*method, CompressionMethod::Deflate);
+        }
+    }
+    assert_eq!(entries.len(), expected.len());
+}
+
+#[test]
+fn test_transcode_preserves_metadata() {
+    let modified = UtcDateTime::from_components(2021, 3, 4, 5, 6, 7, 0).unwrap();
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    archive
+        .new_dir("dir/")
+        .unix_permissions(0o755)
+        .create()
+        .unwrap();
+    let mut writer = archive
+        .new_file("dir/file.txt")
+        .last_modified(modified)
+        .unix_permissions(0o640)
+        .comment("a comment")
+        .extra_field(0xcafe_u16, b"extra", rawzip::Header::CENTRAL)
+        .unwrap()
+        .start_deflate(9)
+        .unwrap();
+    writer.write_all(&b"Hello, world! ".repeat(100)).unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let mut buf = vec![0u8; RECOMMENDED_BUFFER_SIZE];
+    let path = std::env::temp_dir().join("rawzip-transcode-metadata.zip");
+    std::fs::write(&path, &source).unwrap();
+    let source = ZipArchive::from_file(File::open(&path).unwrap(), &mut buf).unwrap();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::transcode(&source, &mut archive, |_| CompressionMethod::Store).unwrap();
+    archive.finish().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].0, b"dir/");
+    assert_eq!(entries[0].2 & 0o777, 0o755);
+    assert_eq!(entries[1].0, b"dir/file.txt");
+    assert_eq!(entries[1].1, CompressionMethod::Store);
+    assert_eq!(entries[1].2 & 0o777, 0o640);
+    assert_eq!(entries[1].4, b"Hello, world! ".repeat(100));
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let mut records = archive.entries();
+    records.next_entry().unwrap();
+    let record = records.next_entry().unwrap().unwrap();
+    assert_eq!(record.comment().as_bytes(), b"a comment");
+    assert_eq!(
+        format!("{:?}", record.last_modified()),
+        format!("{:?}", rawzip::time::ZipDateTimeKind::Utc(modified))
+    );
+    let extra = record
+        .extra_fields()
+        .find(|(id, _)| id.as_u16() == 0xcafe)
+        .unwrap();
+    assert_eq!(extra.1, b"extra");
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_transcode_deflate_to_zstd() {
+    let original = std::fs::read("assets/test.zip").unwrap();
+    let output = transcoded(&original, CompressionMethod::Zstd);
+
+    let expected = read_entries(&original)
+        .into_iter()
+        .map(|(name, _, mode, modified, contents)| {
+            (name, CompressionMethod::Zstd, mode, modified, contents)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(read_entries(&output), expected);
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_transcode_zstd_dictionary_to_store() {
+    let samples = (0..200)
+        .map(|i| format!(r#"{{"id": {i}, "kind": "sample", "tags": ["a", "b"]}}"#))
+        .collect::<Vec<_>>();
+    let dictionary = rawzip::train_zstd_dictionary(&samples, 4096).unwrap();
+
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    archive.set_zstd_dictionary(&dictionary).unwrap();
+    let mut writer = archive.new_file("1.json").start_zstd(3).unwrap();
+    writer.write_all(samples[1].as_bytes()).unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let output = transcoded(&source, CompressionMethod::Store);
+    let entries = read_entries(&output);
+    assert_eq!(entries[0].0, rawzip::ZSTD_DICTIONARY_NAME.as_bytes());
+    assert_eq!(entries[1].1, CompressionMethod::Store);
+    assert_eq!(entries[1].4, samples[1].as_bytes());
+}
+
+#[test]
+fn test_transcode_unsupported_method() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    let mut writer = archive
+        .new_file("file.ppmd")
+        .compression_method(CompressionMethod::Ppmd)
+        .start_raw(0, 0)
+        .unwrap();
+    writer.write_all(b"PPMD").unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(&source).unwrap().into_zip_archive();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let err = rawzip::transcode(&source, &mut archive, |_| CompressionMethod::Store).unwrap_err();
+    assert!(err.to_string().contains("no built-in codec"));
+}
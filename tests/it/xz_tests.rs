@@ -0,0 +1,84 @@
+#![cfg(feature = "xz")]
+
+use rawzip::{CompressionMethod, ZipArchive, ZipArchiveWriter};
+use std::io::{Read, Write};
+
+fn read_entries(data: &[u8]) -> Vec<(String, CompressionMethod, Vec<u8>)> {
+    let archive = ZipArchive::from_slice(data).unwrap();
+    let mut result = Vec::new();
+    for entry in archive.entries() {
+        let entry = entry.unwrap();
+        let name = String::from_utf8(entry.file_path().as_ref().to_vec()).unwrap();
+        let method = entry.compression_method();
+        let entry = archive.get_entry(entry.wayfinder()).unwrap();
+        let mut contents = Vec::new();
+        match method {
+            CompressionMethod::Store => entry
+                .verifying_reader(entry.data())
+                .read_to_end(&mut contents)
+                .unwrap(),
+            CompressionMethod::Xz => entry
+                .verifying_reader(xz2::read::XzDecoder::new(entry.data()))
+                .read_to_end(&mut contents)
+                .unwrap(),
+            _ => panic!("unexpected compression method: {:?}", method),
+        };
+        result.push((name, method, contents));
+    }
+    result
+}
+
+#[test]
+fn test_xz_roundtrip() {
+    let data = b"Hello, xz! ".repeat(1000);
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    let mut writer = archive.new_file("started.txt").start_xz(9).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    archive
+        .new_file("queued.txt")
+        .compression_method(CompressionMethod::Xz)
+        .compression_level(1)
+        .queue_compressed(data.clone())
+        .unwrap();
+    archive.finish().unwrap();
+
+    // xz needs version 6.3 to extract
+    assert_eq!(u16::from_le_bytes([output[4], output[5]]), 63);
+
+    let entries = read_entries(&output);
+    assert_eq!(entries.len(), 2);
+    for (name, method, contents) in entries {
+        assert_eq!(method, CompressionMethod::Xz, "{name}");
+        assert_eq!(contents, data, "{name}");
+    }
+}
+
+#[test]
+fn test_xz_transcode() {
+    let mut source = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut source);
+    let mut writer = archive.new_file("file.txt").start_xz(6).unwrap();
+    writer.write_all(b"Hello, world!").unwrap();
+    writer.finish().unwrap();
+    archive.finish().unwrap();
+
+    let source = ZipArchive::from_slice(source).unwrap().into_zip_archive();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    rawzip::transcode(&source, &mut archive, |_| CompressionMethod::Store).unwrap();
+    archive.finish().unwrap();
+
+    let entries = read_entries(&output);
+    assert_eq!(
+        entries,
+        vec![(
+            String::from("file.txt"),
+            CompressionMethod::Store,
+            b"Hello, world!".to_vec()
+        )]
+    );
+    assert_eq!(u16::from_le_bytes([output[4], output[5]]), 20);
+}
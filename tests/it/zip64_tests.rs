@@ -84,3 +84,60 @@ fn test_zip64_threshold_entries(#[case] entry_count: usize, #[case] should_be_zi
 
     verify_expected_entries(&data, entry_count as u64);
 }
+
+/// Test ZIP64 transition with a lowered entry count threshold
+#[rstest]
+#[case(9, false)]
+#[case(10, true)]
+#[case(11, true)]
+fn test_zip64_entry_threshold_override(#[case] entry_count: usize, #[case] should_be_zip64: bool) {
+    let mut archive = ZipArchiveWriter::builder()
+        .with_zip64_entry_threshold(10)
+        .build(Vec::new());
+
+    for i in 0..entry_count {
+        archive.new_dir(&format!("dir_{}/", i)).create().unwrap();
+    }
+
+    let data = archive.finish().unwrap();
+    assert_eq!(contains_zip64_signatures(&data), should_be_zip64);
+    verify_expected_entries(&data, entry_count as u64);
+}
+
+/// Test ZIP64 transition of sizes and offsets with a lowered threshold
+#[rstest]
+#[case(99, false)]
+#[case(100, true)]
+fn test_zip64_size_threshold_override(#[case] size: usize, #[case] should_be_zip64: bool) {
+    let contents = vec![b'a'; size];
+    let mut archive = ZipArchiveWriter::builder()
+        .with_zip64_threshold(100)
+        .build(Vec::new());
+
+    let (mut entry, config) = archive.new_file("file.txt").start().unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(&contents).unwrap();
+    let (_, descriptor_output) = writer.finish().unwrap();
+    entry.finish(descriptor_output).unwrap();
+
+    let data = archive.finish().unwrap();
+
+    // The central directory always starts past the threshold
+    assert!(contains_zip64_signatures(&data));
+    verify_expected_entries(&data, 1);
+
+    let archive = ZipArchive::from_slice(&data).unwrap();
+    let entry = archive.entries().next_entry().unwrap().unwrap();
+    let has_zip64_field = entry
+        .extra_fields()
+        .any(|(id, _)| id == rawzip::extra_fields::ExtraFieldId::ZIP64);
+    assert_eq!(has_zip64_field, should_be_zip64);
+    assert_eq!(entry.uncompressed_size_hint(), size as u64);
+    assert_eq!(entry.compressed_size_hint(), size as u64);
+
+    let wayfinder = entry.wayfinder();
+    let entry = archive.get_entry(wayfinder).unwrap();
+    assert_eq!(entry.data(), &contents[..]);
+    let mut verifier = entry.verifying_reader(entry.data());
+    std::io::copy(&mut verifier, &mut std::io::sink()).unwrap();
+}
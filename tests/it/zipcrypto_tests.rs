@@ -0,0 +1,142 @@
+#![cfg(feature = "zipcrypto")]
+
+use rawzip::{ZipArchive, ZipArchiveWriter};
+use std::io::{Cursor, Write};
+
+fn crc32_update(crc: u32, byte: u8) -> u32 {
+    let mut crc = crc ^ u32::from(byte);
+    for _ in 0..8 {
+        crc = if crc & 1 != 0 {
+            (crc >> 1) ^ 0xEDB88320
+        } else {
+            crc >> 1
+        };
+    }
+    crc
+}
+
+struct Keys([u32; 3]);
+
+impl Keys {
+    fn new(password: &[u8]) -> Self {
+        let mut keys = Keys([0x12345678, 0x23456789, 0x34567890]);
+        for &byte in password {
+            keys.update(byte);
+        }
+        keys
+    }
+
+    fn update(&mut self, byte: u8) {
+        self.0[0] = crc32_update(self.0[0], byte);
+        self.0[1] = (self.0[1].wrapping_add(self.0[0] & 0xff))
+            .wrapping_mul(134775813)
+            .wrapping_add(1);
+        self.0[2] = crc32_update(self.0[2], (self.0[1] >> 24) as u8);
+    }
+
+    fn decrypt(&mut self, byte: u8) -> u8 {
+        let temp = (self.0[2] | 2) as u16;
+        let plain = byte ^ (temp.wrapping_mul(temp ^ 1) >> 8) as u8;
+        self.update(plain);
+        plain
+    }
+}
+
+/// Decrypts the entry's data, returning the check byte of the header along
+/// with the plaintext.
+fn decrypt(data: &[u8], password: &[u8]) -> (u8, Vec<u8>) {
+    let mut keys = Keys::new(password);
+    let mut plain = data.iter().map(|&x| keys.decrypt(x)).collect::<Vec<_>>();
+    let body = plain.split_off(12);
+    (plain[11], body)
+}
+
+fn write_entry(archive: &mut ZipArchiveWriter<impl Write>, name: &str, data: &[u8]) {
+    let (mut entry, config) = archive
+        .new_file(name)
+        .weak_zipcrypto_password("erp")
+        .start()
+        .unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(data).unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+}
+
+fn assert_encrypted(output: &[u8], expected: &[(&str, &[u8])]) {
+    let archive = ZipArchive::from_slice(output).unwrap();
+    let mut entries = archive.entries();
+    for (name, data) in expected {
+        let record = entries.next_entry().unwrap().unwrap();
+        assert_eq!(record.file_path().as_ref(), name.as_bytes());
+        assert_eq!(record.crc32(), rawzip::crc32(data));
+        assert_eq!(record.compressed_size_hint(), data.len() as u64 + 12);
+        assert!(record.has_data_descriptor());
+
+        let offset = record.local_header_offset() as usize;
+        let flags = u16::from_le_bytes([output[offset + 6], output[offset + 7]]);
+        assert_eq!(flags & 0x01, 0x01);
+        let time = u16::from_le_bytes([output[offset + 10], output[offset + 11]]);
+
+        let entry = archive.get_entry(record.wayfinder()).unwrap();
+        let (check, plain) = decrypt(entry.data(), b"erp");
+        assert_eq!(check, (time >> 8) as u8);
+        assert_eq!(&plain, data);
+    }
+    assert!(entries.next_entry().unwrap().is_none());
+}
+
+#[test]
+fn test_zipcrypto_roundtrip() {
+    let large = (0..10_000u32).map(|x| x as u8).collect::<Vec<_>>();
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    write_entry(&mut archive, "empty.txt", b"");
+    write_entry(&mut archive, "import.csv", b"id,amount\n1,100\n");
+    write_entry(&mut archive, "large.bin", &large);
+    archive.finish().unwrap();
+
+    assert_encrypted(
+        &output,
+        &[
+            ("empty.txt", b""),
+            ("import.csv", b"id,amount\n1,100\n"),
+            ("large.bin", &large),
+        ],
+    );
+}
+
+#[test]
+fn test_zipcrypto_seekable_keeps_data_descriptor() {
+    let modified = rawzip::time::UtcDateTime::from_components(2024, 5, 6, 13, 45, 10, 0).unwrap();
+    let mut output = Cursor::new(Vec::new());
+    let mut archive = ZipArchiveWriter::builder().build_seekable(&mut output);
+    let (mut entry, config) = archive
+        .new_file("import.csv")
+        .weak_zipcrypto_password("erp")
+        .last_modified(modified)
+        .start()
+        .unwrap();
+    let mut writer = config.wrap(&mut entry);
+    writer.write_all(b"id,amount").unwrap();
+    let (_, descriptor) = writer.finish().unwrap();
+    entry.finish(descriptor).unwrap();
+    archive.finish().unwrap();
+
+    let output = output.into_inner();
+    assert_encrypted(&output, &[("import.csv", b"id,amount")]);
+}
+
+#[test]
+fn test_zipcrypto_wrong_password() {
+    let mut output = Vec::new();
+    let mut archive = ZipArchiveWriter::new(&mut output);
+    write_entry(&mut archive, "import.csv", b"id,amount");
+    archive.finish().unwrap();
+
+    let archive = ZipArchive::from_slice(&output).unwrap();
+    let record = archive.entries().next_entry().unwrap().unwrap();
+    let entry = archive.get_entry(record.wayfinder()).unwrap();
+    let (_, plain) = decrypt(entry.data(), b"wrong");
+    assert_ne!(plain, b"id,amount");
+}